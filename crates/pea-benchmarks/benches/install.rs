@@ -177,6 +177,7 @@ fn create_test_config(dep_count: usize) -> PeaToml {
             keywords: Vec::new(),
             authors: Vec::new(),
             homepage: None,
+            files: Vec::new(),
         },
         dependencies,
         dev_dependencies: HashMap::new(),
@@ -184,6 +185,8 @@ fn create_test_config(dep_count: usize) -> PeaToml {
         optional_dependencies: HashMap::new(),
         workspace: None,
         profile: HashMap::new(),
+        install: None,
+        overrides: HashMap::new(),
         scripts: HashMap::new(),
         features: HashMap::new(),
     }
@@ -337,6 +337,7 @@ fn create_config_for_validation(complexity: &str) -> PeaToml {
             keywords: vec!["benchmark".to_string(), complexity.to_string()],
             authors: Vec::new(),
             homepage: None,
+            files: Vec::new(),
         },
         dependencies,
         dev_dependencies: HashMap::new(),
@@ -344,6 +345,8 @@ fn create_config_for_validation(complexity: &str) -> PeaToml {
         optional_dependencies: HashMap::new(),
         workspace: None,
         profile: HashMap::new(),
+        install: None,
+        overrides: HashMap::new(),
         scripts: HashMap::new(),
         features: HashMap::new(),
     }
@@ -402,6 +405,7 @@ fn create_pea_toml_struct(dep_count: usize) -> PeaToml {
             keywords: vec!["benchmark".to_string()],
             authors: Vec::new(),
             homepage: None,
+            files: Vec::new(),
         },
         dependencies,
         dev_dependencies: HashMap::new(),
@@ -409,6 +413,8 @@ fn create_pea_toml_struct(dep_count: usize) -> PeaToml {
         optional_dependencies: HashMap::new(),
         workspace: None,
         profile: HashMap::new(),
+        install: None,
+        overrides: HashMap::new(),
         scripts: HashMap::new(),
         features: HashMap::new(),
     }
@@ -451,6 +457,8 @@ fn create_package_json_struct(dep_count: usize) -> PackageJson {
         exports: None,
         types: None,
         typings: None,
+        overrides: None,
+        resolutions: HashMap::new(),
     }
 }
 
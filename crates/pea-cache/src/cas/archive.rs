@@ -0,0 +1,172 @@
+//! Store export and import
+//!
+//! Moves a set of blobs between machines as a single gzipped tarball,
+//! e.g. to seed the store on an air-gapped host. The archive holds a
+//! `manifest.json` with the index entries followed by `blobs/<hex>` files.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use pea_core::error::PeaError;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use tar::{Archive, Builder, Header};
+
+use super::hash::compute_hash;
+use super::{CacheEntry, CasStore, ContentHash};
+use crate::CacheResult;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const BLOB_PREFIX: &str = "blobs/";
+
+/// Summary of an export or import
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveSummary {
+    /// Number of blobs written or imported
+    pub blobs: usize,
+    /// Total blob bytes
+    pub bytes: u64,
+}
+
+impl CasStore {
+    /// Write the blobs for `keys` into a gzipped archive
+    pub fn export_archive<W: Write>(&self, writer: W, keys: &[String]) -> CacheResult<ArchiveSummary> {
+        let mut indexed: HashMap<String, CacheEntry> = self.index().entries().into_iter().collect();
+        let mut entries: Vec<(String, CacheEntry)> = Vec::new();
+        for key in keys {
+            let hash = ContentHash::from_hex(key)?;
+            let entry = indexed.remove(key).unwrap_or_else(|| CacheEntry::new(hash, 0));
+            entries.push((key.clone(), entry));
+        }
+        
+        let mut builder = Builder::new(GzEncoder::new(writer, Compression::default()));
+        let manifest = serde_json::to_vec_pretty(&entries)
+            .map_err(|e| PeaError::JsonParse { message: format!("Failed to serialize manifest: {}", e) })?;
+        append_file(&mut builder, MANIFEST_NAME, &manifest)?;
+        
+        let mut summary = ArchiveSummary::default();
+        for (key, entry) in &entries {
+            let content = std::fs::read(self.hash_to_path(&entry.hash))
+                .map_err(|e| PeaError::io(format!("Failed to read blob {}", key), e))?;
+            append_file(&mut builder, &format!("{}{}", BLOB_PREFIX, key), &content)?;
+            summary.blobs += 1;
+            summary.bytes += content.len() as u64;
+        }
+        
+        builder.into_inner()
+            .and_then(|encoder| encoder.finish())
+            .map_err(|e| PeaError::io("Failed to finish cache archive".to_string(), e))?;
+        
+        Ok(summary)
+    }
+
+    /// Import every blob from an archive produced by `export_archive`,
+    /// rejecting any blob whose content doesn't match its name
+    pub fn import_archive<R: Read>(&self, reader: R) -> CacheResult<ArchiveSummary> {
+        let mut archive = Archive::new(GzDecoder::new(reader));
+        let mut labels: HashMap<String, String> = HashMap::new();
+        let mut summary = ArchiveSummary::default();
+        
+        let entries = archive.entries()
+            .map_err(|e| PeaError::io("Failed to read cache archive".to_string(), e))?;
+        for entry in entries {
+            let mut entry = entry
+                .map_err(|e| PeaError::io("Failed to read cache archive entry".to_string(), e))?;
+            let name = entry.path()
+                .map_err(|e| PeaError::io("Invalid path in cache archive".to_string(), e))?
+                .to_string_lossy()
+                .into_owned();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)
+                .map_err(|e| PeaError::io(format!("Failed to read {} from cache archive", name), e))?;
+            
+            if name == MANIFEST_NAME {
+                let manifest: Vec<(String, CacheEntry)> = serde_json::from_slice(&content)
+                    .map_err(|e| PeaError::JsonParse { message: format!("Invalid cache archive manifest: {}", e) })?;
+                labels.extend(manifest.into_iter()
+                    .filter_map(|(key, entry)| entry.package.map(|package| (key, package))));
+            } else if let Some(key) = name.strip_prefix(BLOB_PREFIX) {
+                let actual = compute_hash(&content);
+                if actual.to_hex() != key {
+                    return Err(PeaError::IntegrityFailure {
+                        package: key.to_string(),
+                        expected: key.to_string(),
+                        actual: actual.to_hex(),
+                    });
+                }
+                self.store(&content)?;
+                summary.blobs += 1;
+                summary.bytes += content.len() as u64;
+            }
+        }
+        
+        for (key, package) in labels {
+            let hash = ContentHash::from_hex(&key)?;
+            if self.contains(&hash) {
+                self.label(&hash, &package);
+            }
+        }
+        
+        self.save_index()?;
+        Ok(summary)
+    }
+}
+
+/// Append an in-memory file to a tar builder
+fn append_file<W: Write>(builder: &mut Builder<W>, path: &str, content: &[u8]) -> CacheResult<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, path, content)
+        .map_err(|e| PeaError::io(format!("Failed to write {} to cache archive", path), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino::Utf8PathBuf;
+    use tempfile::tempdir;
+
+    fn temp_store(dir: &tempfile::TempDir) -> CasStore {
+        CasStore::new(Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let source_dir = tempdir().unwrap();
+        let source = temp_store(&source_dir);
+        let wanted = source.store(b"tarball one").unwrap();
+        let _other = source.store(b"tarball two").unwrap();
+        source.label(&wanted, "left-pad@1.3.0");
+        
+        let mut archive = Vec::new();
+        let exported = source.export_archive(&mut archive, &[wanted.to_hex()]).unwrap();
+        assert_eq!(exported, ArchiveSummary { blobs: 1, bytes: 11 });
+        
+        let target_dir = tempdir().unwrap();
+        let target = temp_store(&target_dir);
+        let imported = target.import_archive(archive.as_slice()).unwrap();
+        assert_eq!(imported, exported);
+        assert_eq!(target.get(&wanted).unwrap(), b"tarball one");
+        assert_eq!(target.index().len(), 1);
+        assert_eq!(target.index().entries()[0].1.package.as_deref(), Some("left-pad@1.3.0"));
+    }
+
+    #[test]
+    fn test_import_rejects_mismatched_blob() {
+        let hash = compute_hash(b"expected");
+        let mut archive = Vec::new();
+        {
+            let mut builder = Builder::new(GzEncoder::new(&mut archive, Compression::default()));
+            append_file(&mut builder, &format!("{}{}", BLOB_PREFIX, hash.to_hex()), b"forged").unwrap();
+            builder.into_inner().unwrap().finish().unwrap();
+        }
+        
+        let target_dir = tempdir().unwrap();
+        let target = temp_store(&target_dir);
+        let result = target.import_archive(archive.as_slice());
+        assert!(matches!(result, Err(PeaError::IntegrityFailure { .. })));
+        assert!(!target.contains(&hash));
+    }
+}
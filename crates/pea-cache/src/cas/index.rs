@@ -28,6 +28,9 @@ pub struct CacheEntry {
     pub stored_at: i64,
     /// When the entry was last accessed (as timestamp)
     pub last_accessed: i64,
+    /// Package this blob belongs to (`name@version`), if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
 }
 
 impl CacheEntry {
@@ -39,9 +42,16 @@ impl CacheEntry {
             size,
             stored_at: now,
             last_accessed: now,
+            package: None,
         }
     }
 
+    /// Attach the package label this blob was stored for
+    pub fn with_package(mut self, package: impl Into<String>) -> Self {
+        self.package = Some(package.into());
+        self
+    }
+
     /// Update last accessed time
     pub fn touch(&mut self) {
         self.last_accessed = Utc::now().timestamp();
@@ -52,6 +62,11 @@ impl CacheEntry {
         Utc::now().timestamp() - self.stored_at
     }
 
+    /// Get seconds since the entry was last accessed
+    pub fn idle_seconds(&self) -> i64 {
+        Utc::now().timestamp() - self.last_accessed
+    }
+
     /// Get stored_at as DateTime
     pub fn stored_at_datetime(&self) -> DateTime<Utc> {
         DateTime::from_timestamp(self.stored_at, 0).unwrap_or_else(|| Utc::now())
@@ -193,6 +208,27 @@ mod tests {
             assert_eq!(retrieved.size, 1024);
         }
     }
+
+    #[test]
+    fn test_package_label_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let index_path = temp_dir.path().join("index.json");
+        let hash = ContentHash::from_hex("0000000000000000000000000000000000000000000000000000000000000000").unwrap();
+
+        // Entries written before labels existed have no `package` key and must still load
+        let legacy = serde_json::json!([[hash.to_hex(), {
+            "hash": hash, "size": 3, "stored_at": 0, "last_accessed": 0
+        }]]);
+        std::fs::write(&index_path, legacy.to_string()).unwrap();
+        let index = CasIndex::load_or_create(&index_path).unwrap();
+        assert_eq!(index.entries()[0].1.package, None);
+
+        index.insert(hash.to_hex(), CacheEntry::new(hash, 3).with_package("left-pad@1.3.0"));
+        index.save().unwrap();
+
+        let reloaded = CasIndex::load_or_create(&index_path).unwrap();
+        assert_eq!(reloaded.entries()[0].1.package.as_deref(), Some("left-pad@1.3.0"));
+    }
 }
 impl CasIndex {
     /// Remove an entry by key
@@ -205,6 +241,14 @@ impl CasIndex {
         self.entries.iter().map(|entry| entry.key().clone()).collect()
     }
 
+    /// Snapshot all entries without touching their access times
+    pub fn entries(&self) -> Vec<(String, CacheEntry)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
     /// Get entry count
     pub fn len(&self) -> usize {
         self.entries.len()
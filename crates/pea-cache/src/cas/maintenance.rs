@@ -0,0 +1,192 @@
+//! Store maintenance operations
+//!
+//! Integrity sweeps over every blob on disk. Corrupted content is moved
+//! into `store/quarantine/` rather than deleted so it can be inspected.
+
+use camino::Utf8PathBuf;
+use pea_core::error::PeaError;
+use rayon::prelude::*;
+use std::fs;
+use walkdir::WalkDir;
+
+use super::{CasStore, ContentHash};
+use crate::CacheResult;
+
+/// Outcome of a full store verification
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Number of blobs re-hashed
+    pub checked: usize,
+    /// Hashes whose content no longer matched and were quarantined
+    pub corrupted: Vec<String>,
+    /// Index entries whose blob was missing from disk
+    pub missing: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Whether the store was found fully intact
+    pub fn is_clean(&self) -> bool {
+        self.corrupted.is_empty() && self.missing.is_empty()
+    }
+}
+
+impl CasStore {
+    /// Directory holding quarantined blobs
+    pub fn quarantine_dir(&self) -> Utf8PathBuf {
+        self.root_path().join("quarantine")
+    }
+
+    /// Re-hash every blob in the store, quarantining corrupted ones and
+    /// dropping index entries whose blob has disappeared
+    pub fn verify_all(&self) -> CacheResult<VerifyReport> {
+        let blobs = self.blob_hashes();
+        
+        let results: Vec<(ContentHash, bool)> = blobs
+            .par_iter()
+            .map(|hash| (*hash, self.verify(hash).unwrap_or(false)))
+            .collect();
+        
+        let mut report = VerifyReport {
+            checked: results.len(),
+            ..Default::default()
+        };
+        
+        for (hash, ok) in results {
+            if !ok {
+                self.quarantine(&hash)?;
+                report.corrupted.push(hash.to_hex());
+            }
+        }
+        
+        for (key, entry) in self.index().entries() {
+            if !report.corrupted.contains(&key) && !self.contains(&entry.hash) {
+                self.index().remove(&key);
+                report.missing.push(key);
+            }
+        }
+        
+        report.corrupted.sort();
+        report.missing.sort();
+        self.save_index()?;
+        Ok(report)
+    }
+
+    /// Move a blob out of the store into the quarantine directory, removing
+    /// its unpacked tree so `unpack` can't keep serving the bad content
+    pub fn quarantine(&self, hash: &ContentHash) -> CacheResult<Utf8PathBuf> {
+        let source = self.hash_to_path(hash);
+        let quarantine_dir = self.quarantine_dir();
+        fs::create_dir_all(&quarantine_dir)
+            .map_err(|e| PeaError::io("Failed to create quarantine directory".to_string(), e))?;
+        
+        let target = quarantine_dir.join(hash.to_hex());
+        fs::rename(&source, &target)
+            .map_err(|e| PeaError::io(format!("Failed to quarantine {}", source), e))?;
+        self.index().remove(&hash.to_hex());
+        
+        let unpacked = self.unpacked_path(hash);
+        if unpacked.exists() {
+            fs::remove_dir_all(&unpacked)
+                .map_err(|e| PeaError::io(format!("Failed to remove {}", unpacked), e))?;
+        }
+        
+        Ok(target)
+    }
+
+    /// Collect the hashes of all blobs laid out as `ab/cd/abcd...`
+    fn blob_hashes(&self) -> Vec<ContentHash> {
+        WalkDir::new(self.root_path())
+            .min_depth(3)
+            .max_depth(3)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?;
+                let hash = ContentHash::from_hex(name).ok()?;
+                // Only accept files sitting at their canonical location
+                (self.hash_to_path(&hash).as_std_path() == entry.path()).then_some(hash)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_verify_all_clean_store() {
+        let temp_dir = tempdir().unwrap();
+        let store_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let store = CasStore::new(&store_path).unwrap();
+        
+        store.store(b"one").unwrap();
+        store.store(b"two").unwrap();
+        
+        let report = store.verify_all().unwrap();
+        assert_eq!(report.checked, 2);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_verify_all_quarantines_corruption() {
+        let temp_dir = tempdir().unwrap();
+        let store_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let store = CasStore::new(&store_path).unwrap();
+        
+        let good = store.store(b"good").unwrap();
+        let bad = store.store(b"bad").unwrap();
+        fs::write(store.hash_to_path(&bad), b"tampered").unwrap();
+        
+        let report = store.verify_all().unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.corrupted, vec![bad.to_hex()]);
+        assert!(!store.contains(&bad));
+        assert!(store.contains(&good));
+        assert!(store.quarantine_dir().join(bad.to_hex()).exists());
+        assert!(store.index().get(&bad.to_hex()).is_none());
+    }
+
+    #[test]
+    fn test_quarantine_removes_unpacked_tree() {
+        use crate::tarball::create::create_tarball_bytes;
+        
+        let temp_dir = tempdir().unwrap();
+        let store_path = Utf8PathBuf::from_path_buf(temp_dir.path().join("store")).unwrap();
+        let store = CasStore::new(&store_path).unwrap();
+        
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("index.js"), "module.exports = 1;").unwrap();
+        let tarball = create_tarball_bytes(&source).unwrap();
+        let hash = store.store(&tarball).unwrap();
+        let unpacked = store.unpack(&hash).unwrap();
+        fs::write(unpacked.join("package/index.js"), "module.exports = 'tampered';").unwrap();
+        
+        store.quarantine(&hash).unwrap();
+        assert!(!unpacked.exists());
+        assert!(store.unpack(&hash).is_err());
+        
+        // Storing the content again unpacks it afresh
+        assert_eq!(store.store(&tarball).unwrap(), hash);
+        let unpacked = store.unpack(&hash).unwrap();
+        assert_eq!(fs::read_to_string(unpacked.join("package/index.js")).unwrap(), "module.exports = 1;");
+    }
+
+    #[test]
+    fn test_verify_all_drops_missing_entries() {
+        let temp_dir = tempdir().unwrap();
+        let store_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let store = CasStore::new(&store_path).unwrap();
+        
+        let gone = store.store(b"gone").unwrap();
+        fs::remove_file(store.hash_to_path(&gone)).unwrap();
+        
+        let report = store.verify_all().unwrap();
+        assert_eq!(report.checked, 0);
+        assert_eq!(report.missing, vec![gone.to_hex()]);
+        assert!(store.index().is_empty());
+    }
+}
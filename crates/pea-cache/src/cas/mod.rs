@@ -19,8 +19,13 @@ use crate::CacheResult;
 pub mod store;
 pub mod hash;
 pub mod index;
+pub mod maintenance;
+pub mod archive;
+pub mod unpack;
 
 // Re-export main types
-pub use store::CasStore;
+pub use store::{CasStore, GcResult};
 pub use hash::ContentHash;
-pub use index::{CasIndex, CacheEntry};
\ No newline at end of file
+pub use index::{CasIndex, CacheEntry};
+pub use maintenance::VerifyReport;
+pub use archive::ArchiveSummary;
\ No newline at end of file
@@ -10,6 +10,7 @@ use std::sync::Arc;
 
 use super::{ContentHash, CasIndex, CacheEntry};
 use super::hash::compute_hash;
+use crate::tarball::ExtractLimits;
 use crate::CacheResult;
 
 /// Content-addressable storage
@@ -19,6 +20,8 @@ pub struct CasStore {
     root_path: Utf8PathBuf,
     /// Index for metadata
     index: Arc<CasIndex>,
+    /// Limits applied when unpacking tarballs
+    pub(super) extract_limits: ExtractLimits,
 }
 
 impl CasStore {
@@ -37,11 +40,12 @@ impl CasStore {
         Ok(Self {
             root_path,
             index,
+            extract_limits: ExtractLimits::default(),
         })
     }
 
     /// Get the storage path for a hash
-    fn hash_to_path(&self, hash: &ContentHash) -> Utf8PathBuf {
+    pub fn hash_to_path(&self, hash: &ContentHash) -> Utf8PathBuf {
         let hex = hash.to_hex();
         // Store as store/ab/cd/abcd...
         let prefix1 = &hex[0..2];
@@ -59,6 +63,11 @@ impl CasStore {
     pub fn root_path(&self) -> &Utf8Path {
         &self.root_path
     }
+
+    /// Get the metadata index
+    pub fn index(&self) -> &CasIndex {
+        &self.index
+    }
 }
 impl CasStore {
     /// Store content and return its hash
@@ -123,8 +132,11 @@ impl CasStore {
     }
 
     /// Verify content integrity
+    ///
+    /// Reads the blob directly rather than through `get`, so integrity
+    /// sweeps don't count as an access and keep stale entries collectable.
     pub fn verify(&self, hash: &ContentHash) -> CacheResult<bool> {
-        match self.get(hash) {
+        match fs::read(self.hash_to_path(hash)) {
             Ok(content) => {
                 let computed_hash = compute_hash(&content);
                 Ok(computed_hash == *hash)
@@ -137,6 +149,14 @@ impl CasStore {
     pub fn save_index(&self) -> CacheResult<()> {
         self.index.save()
     }
+
+    /// Record which package a stored blob belongs to
+    pub fn label(&self, hash: &ContentHash, package: &str) {
+        let key = hash.to_hex();
+        let entry = self.index.get(&key)
+            .unwrap_or_else(|| CacheEntry::new(*hash, fs::metadata(self.hash_to_path(hash)).map(|m| m.len()).unwrap_or(0)));
+        self.index.insert(key, entry.with_package(package));
+    }
 }
 
 #[cfg(test)]
@@ -236,13 +256,19 @@ use chrono::Utc;
 
 impl CasStore {
     /// Find unreferenced entries older than threshold
+    ///
+    /// An entry is unreferenced once it has gone more than `max_age_days`
+    /// whole days without being stored or read.
     pub fn find_unreferenced_entries(&self, max_age_days: i64) -> CacheResult<Vec<String>> {
-        let threshold = Utc::now().timestamp() - (max_age_days * 24 * 60 * 60);
-        let mut unreferenced = Vec::new();
+        const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+        let now = Utc::now().timestamp();
         
-        // We need to add a method to CasIndex to iterate entries
-        // For now, return empty list as this is a placeholder
-        // TODO: Add proper iteration method to CasIndex
+        let mut unreferenced: Vec<String> = self.index.entries()
+            .into_iter()
+            .filter(|(_, entry)| (now - entry.last_accessed) / SECONDS_PER_DAY > max_age_days)
+            .map(|(key, _)| key)
+            .collect();
+        unreferenced.sort();
         
         Ok(unreferenced)
     }
@@ -252,7 +278,7 @@ impl CasStore {
         let mut freed_space = 0u64;
         
         for key in keys {
-            if let Some(entry) = self.index.get(key) {
+            if let Some(entry) = self.index.remove(key) {
                 let hash = &entry.hash;
                 let path = self.hash_to_path(hash);
                 
@@ -276,8 +302,13 @@ impl CasStore {
                     }
                 }
                 
-                // Remove from index (we need to add a remove method)
-                // TODO: Add remove method to CasIndex
+                // Remove the unpacked tree, if any
+                let unpacked = self.unpacked_path(hash);
+                if unpacked.exists() {
+                    if let Err(e) = fs::remove_dir_all(&unpacked) {
+                        eprintln!("Warning: Failed to remove {}: {}", unpacked, e);
+                    }
+                }
             }
         }
         
@@ -355,6 +386,31 @@ impl GcResult {
         assert!(store.contains(&hash2));
     }
 
+    #[test]
+    fn test_garbage_collection_removes_stale_entries() {
+        use tempfile::tempdir;
+        let temp_dir = tempdir().unwrap();
+        let store_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let store = CasStore::new(&store_path).unwrap();
+        
+        let stale = store.store(b"stale").unwrap();
+        let fresh = store.store(b"fresh").unwrap();
+        
+        // Backdate the stale entry by 40 days
+        let mut entry = store.index().remove(&stale.to_hex()).unwrap();
+        entry.last_accessed -= 40 * 24 * 60 * 60;
+        store.index().insert(stale.to_hex(), entry);
+        
+        assert_eq!(store.find_unreferenced_entries(30).unwrap(), vec![stale.to_hex()]);
+        
+        let result = store.garbage_collect(30).unwrap();
+        assert_eq!(result.entries_removed, 1);
+        assert_eq!(result.freed_space, 5);
+        assert!(!store.contains(&stale));
+        assert!(store.contains(&fresh));
+        assert_eq!(store.index().len(), 1);
+    }
+
     #[test]
     fn test_gc_result_formatting() {
         let result = GcResult {
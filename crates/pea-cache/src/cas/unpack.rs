@@ -0,0 +1,117 @@
+//! Unpacked package trees
+//!
+//! Tarballs are extracted once into `store/packages/<hash>` and reused by
+//! every install, so node_modules can be reflinked or hardlinked from a
+//! stable location instead of a throwaway temp directory. Extraction is
+//! bounded by the store's `ExtractLimits`, which are checked again against
+//! an existing copy before it is reused.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_core::error::PeaError;
+use std::fs;
+use std::io;
+use walkdir::WalkDir;
+
+use super::{CasStore, ContentHash};
+use crate::tarball::{extract_tarball_with_limits, ExtractLimits};
+use crate::CacheResult;
+
+impl CasStore {
+    /// Set the limits applied when unpacking tarballs
+    pub fn with_extract_limits(mut self, limits: ExtractLimits) -> Self {
+        self.extract_limits = limits;
+        self
+    }
+
+    /// Directory a blob's tarball is unpacked into
+    pub fn unpacked_path(&self, hash: &ContentHash) -> Utf8PathBuf {
+        self.root_path().join("packages").join(hash.to_hex())
+    }
+
+    /// Unpack a stored tarball, reusing a previous extraction if present
+    pub fn unpack(&self, hash: &ContentHash) -> CacheResult<Utf8PathBuf> {
+        let target = self.unpacked_path(hash);
+        if target.exists() {
+            // The limits may be stricter than when this copy was unpacked
+            let (entries, bytes) = tree_size(&target)?;
+            let compressed = fs::metadata(self.hash_to_path(hash)).map_or(0, |metadata| metadata.len());
+            if let Some(error) = self.extract_limits.exceeded(entries, bytes, compressed) {
+                return Err(error);
+            }
+            return Ok(target);
+        }
+        
+        let content = self.get(hash)?;
+        
+        // Extract beside the target and rename so readers never see a partial tree
+        let staging = target.with_file_name(format!(".{}.{}.tmp", hash.to_hex(), std::process::id()));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)
+                .map_err(|e| PeaError::io("Failed to clear stale unpack directory".to_string(), e))?;
+        }
+        if let Err(e) = extract_tarball_with_limits(content.as_slice(), staging.as_std_path(), &self.extract_limits) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        
+        if let Err(e) = fs::rename(&staging, &target) {
+            let _ = fs::remove_dir_all(&staging);
+            // Another process may have finished the same extraction first
+            if !target.exists() {
+                return Err(PeaError::io(format!("Failed to move unpacked package into {}", target), e));
+            }
+        }
+        
+        Ok(target)
+    }
+}
+
+/// Number of entries under `dir` and the total size of its files
+fn tree_size(dir: &Utf8Path) -> CacheResult<(usize, u64)> {
+    let mut entries = 0;
+    let mut bytes = 0;
+    for entry in WalkDir::new(dir).min_depth(1) {
+        let entry = entry.map_err(|e| PeaError::io(format!("Failed to walk {}", dir), io::Error::other(e.to_string())))?;
+        entries += 1;
+        if entry.file_type().is_file() {
+            bytes += entry.metadata().map_or(0, |metadata| metadata.len());
+        }
+    }
+    Ok((entries, bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarball::create::create_tarball_bytes;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_unpack_is_persistent_and_reused() {
+        let temp_dir = tempdir().unwrap();
+        let store_path = Utf8PathBuf::from_path_buf(temp_dir.path().join("store")).unwrap();
+        let store = CasStore::new(&store_path).unwrap();
+        
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("index.js"), "module.exports = 1;").unwrap();
+        let hash = store.store(&create_tarball_bytes(&source).unwrap()).unwrap();
+        
+        let unpacked = store.unpack(&hash).unwrap();
+        assert_eq!(unpacked, store.unpacked_path(&hash));
+        assert!(unpacked.join("package/index.js").exists());
+        
+        // A second unpack reuses the existing tree
+        fs::write(unpacked.join("package/marker"), "").unwrap();
+        assert!(store.unpack(&hash).unwrap().join("package/marker").exists());
+        
+        // ...unless it breaks limits set since it was unpacked
+        let strict = CasStore::new(&store_path).unwrap()
+            .with_extract_limits(ExtractLimits::default().with_max_entries(2));
+        assert!(matches!(strict.unpack(&hash), Err(PeaError::ExtractLimitExceeded { .. })));
+        
+        // Garbage collection removes the unpacked tree with its blob
+        store.remove_entries(&[hash.to_hex()]).unwrap();
+        assert!(!unpacked.exists());
+    }
+}
@@ -0,0 +1,249 @@
+//! Git repository cache for git dependencies
+//!
+//! Repositories are mirrored once per URL with the `git` CLI and each
+//! resolved commit is checked out into its own directory, so a URL+SHA pair
+//! is only ever fetched and checked out once.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_core::error::PeaError;
+use pea_core::types::{Version, VersionReq};
+use std::process::Command;
+
+use crate::CacheResult;
+
+/// Mirrors and checkouts of git dependencies
+///
+/// Layout: `db/<url key>` holds a bare mirror of each repository and
+/// `checkouts/<url key>/<sha>` a working tree per commit.
+#[derive(Debug, Clone)]
+pub struct GitCache {
+    root: Utf8PathBuf,
+}
+
+impl GitCache {
+    /// Cache rooted at `root` (created on first use)
+    pub fn new(root: impl Into<Utf8PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Full commit SHA that `committish` names in the repository at `url`
+    ///
+    /// `None` means the default branch; `semver:<range>` picks the highest
+    /// tag matching the range. A full SHA already in the mirror is used
+    /// without fetching.
+    pub fn resolve(&self, url: &str, committish: Option<&str>) -> CacheResult<String> {
+        let mirror = self.mirror_dir(url);
+        let known_sha = committish
+            .filter(|committish| is_full_sha(committish))
+            .filter(|sha| mirror.is_dir() && git(&mirror, &["cat-file", "-e", &format!("{}^{{commit}}", sha)]).is_ok());
+        if let Some(sha) = known_sha {
+            return Ok(sha.to_string());
+        }
+        self.fetch(url)?;
+
+        let reference = match committish {
+            Some(committish) => match committish.strip_prefix("semver:") {
+                Some(range) => highest_tag(&mirror, url, range)?,
+                None => committish.to_string(),
+            },
+            None => "HEAD".to_string(),
+        };
+        git(&mirror, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", reference)])
+            .map(|sha| sha.trim().to_string())
+            .map_err(|_| PeaError::ConfigValidation {
+                field: url.to_string(),
+                reason: format!("No commit named '{}' in {}", reference, url),
+            })
+    }
+
+    /// Working tree of the repository at `url` at commit `sha`
+    ///
+    /// Checked out once; later calls return the same directory.
+    pub fn checkout(&self, url: &str, sha: &str) -> CacheResult<Utf8PathBuf> {
+        let dir = self.root.join("checkouts").join(url_key(url)).join(sha);
+        if dir.join(".git").exists() {
+            return Ok(dir);
+        }
+        self.resolve(url, Some(sha))?;
+
+        // Check out beside the final directory so a failure never leaves half a tree
+        let parent = dir.parent().unwrap_or(&self.root);
+        std::fs::create_dir_all(parent)
+            .map_err(|e| PeaError::io(format!("Failed to create {}", parent), e))?;
+        let staging = parent.join(format!(".{}.tmp", sha));
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)
+                .map_err(|e| PeaError::io(format!("Failed to remove {}", staging), e))?;
+        }
+        let mirror = self.mirror_dir(url);
+        git(parent, &["clone", "--quiet", "--shared", "--no-checkout", "--", mirror.as_str(), staging.as_str()])?;
+        git(&staging, &["checkout", "--quiet", "--detach", sha])?;
+        if let Err(e) = std::fs::rename(&staging, &dir) {
+            // Another install may have finished the same checkout first
+            let _ = std::fs::remove_dir_all(&staging);
+            if !dir.join(".git").exists() {
+                return Err(PeaError::io(format!("Failed to move checkout to {}", dir), e));
+            }
+        }
+        Ok(dir)
+    }
+
+    /// Bare mirror of `url`, cloned or updated from the remote
+    fn fetch(&self, url: &str) -> CacheResult<Utf8PathBuf> {
+        if url.starts_with('-') {
+            return Err(PeaError::ConfigValidation {
+                field: url.to_string(),
+                reason: "A git URL can't start with '-'".to_string(),
+            });
+        }
+        let mirror = self.mirror_dir(url);
+        let result = if mirror.is_dir() {
+            git(&mirror, &["fetch", "--quiet", "--prune", "--tags", "origin"])
+        } else {
+            let parent = mirror.parent().unwrap_or(&self.root);
+            std::fs::create_dir_all(parent)
+                .map_err(|e| PeaError::io(format!("Failed to create {}", parent), e))?;
+            git(parent, &["clone", "--quiet", "--mirror", "--", url, mirror.as_str()])
+        };
+        result.map_err(|e| PeaError::Network {
+            message: format!("Failed to fetch {}: {}", url, e),
+            source: None,
+        })?;
+        Ok(mirror)
+    }
+
+    fn mirror_dir(&self, url: &str) -> Utf8PathBuf {
+        self.root.join("db").join(url_key(url))
+    }
+}
+
+/// Split a locked `git+<url>#<sha>` resolution into URL and SHA
+pub fn parse_resolved(resolved: &str) -> Option<(&str, &str)> {
+    let (url, sha) = resolved.strip_prefix("git+")?.rsplit_once('#')?;
+    is_full_sha(sha).then_some((url, sha))
+}
+
+/// Directory name for a repository URL
+fn url_key(url: &str) -> String {
+    blake3::hash(url.as_bytes()).to_hex()[..16].to_string()
+}
+
+fn is_full_sha(committish: &str) -> bool {
+    committish.len() == 40 && committish.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Highest tag in `mirror` (with or without a `v` prefix) matching `range`
+fn highest_tag(mirror: &Utf8Path, url: &str, range: &str) -> CacheResult<String> {
+    let req = VersionReq::parse(range).map_err(|e| PeaError::ConfigValidation {
+        field: url.to_string(),
+        reason: format!("Invalid range 'semver:{}': {}", range, e),
+    })?;
+    let tags = git(mirror, &["tag", "--list"])?;
+    tags.lines()
+        .filter_map(|tag| Some((tag.trim_start_matches('v').parse::<Version>().ok()?, tag)))
+        .filter(|(version, _)| req.matches(version))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, tag)| format!("refs/tags/{}", tag))
+        .ok_or_else(|| PeaError::ConfigValidation {
+            field: url.to_string(),
+            reason: format!("No tag of {} matches semver:{}", url, range),
+        })
+}
+
+/// Run `git` in `cwd` without prompting, returning its stdout
+///
+/// Only the usual transports are allowed, so a dependency URL can't use
+/// `ext::` or another helper to run commands.
+fn git(cwd: &Utf8Path, args: &[&str]) -> CacheResult<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(cwd)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_PROTOCOL_FROM_USER", "0")
+        .env("GIT_ALLOW_PROTOCOL", "file:git:http:https:ssh")
+        .output()
+        .map_err(|e| PeaError::io("Failed to run git; is it installed?".to_string(), e))?;
+    if !output.status.success() {
+        return Err(PeaError::ConfigValidation {
+            field: "git".to_string(),
+            reason: format!("git {} failed: {}", args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A bare repository with a tagged 1.0.0 commit and a 1.1.0 commit on `main`
+    fn bare_repo(temp: &TempDir) -> (String, String, String) {
+        let work = Utf8PathBuf::from_path_buf(temp.path().join("work")).unwrap();
+        std::fs::create_dir_all(&work).unwrap();
+        let run = |args: &[&str]| git(&work, args).unwrap();
+        run(&["init", "--quiet", "--initial-branch=main"]);
+        run(&["config", "user.email", "dev@example.com"]);
+        run(&["config", "user.name", "Dev"]);
+        let mut shas = Vec::new();
+        for version in ["1.0.0", "1.1.0"] {
+            std::fs::write(work.join("package.json"),
+                format!(r#"{{"name": "lib", "version": "{}"}}"#, version)).unwrap();
+            run(&["add", "."]);
+            run(&["commit", "--quiet", "-m", version]);
+            shas.push(run(&["rev-parse", "HEAD"]).trim().to_string());
+        }
+        run(&["tag", "v1.0.0", &shas[0]]);
+        let bare = temp.path().join("lib.git");
+        run(&["clone", "--quiet", "--bare", work.as_str(), bare.to_str().unwrap()]);
+        (format!("file://{}", bare.display()), shas[0].clone(), shas[1].clone())
+    }
+
+    #[test]
+    fn test_resolve_refs_to_commits() {
+        let temp = TempDir::new().unwrap();
+        let (url, first, second) = bare_repo(&temp);
+        let cache = GitCache::new(Utf8PathBuf::from_path_buf(temp.path().join("cache")).unwrap());
+
+        assert_eq!(cache.resolve(&url, None).unwrap(), second);
+        assert_eq!(cache.resolve(&url, Some("main")).unwrap(), second);
+        assert_eq!(cache.resolve(&url, Some("v1.0.0")).unwrap(), first);
+        assert_eq!(cache.resolve(&url, Some(&first[..8])).unwrap(), first);
+        assert_eq!(cache.resolve(&url, Some("semver:^1.0.0")).unwrap(), first);
+        assert!(cache.resolve(&url, Some("no-such-branch")).is_err());
+        assert!(cache.resolve(&url, Some("semver:^2.0.0")).is_err());
+    }
+
+    #[test]
+    fn test_checkout_is_keyed_by_commit() {
+        let temp = TempDir::new().unwrap();
+        let (url, first, second) = bare_repo(&temp);
+        let cache = GitCache::new(Utf8PathBuf::from_path_buf(temp.path().join("cache")).unwrap());
+
+        let old = cache.checkout(&url, &first).unwrap();
+        let new = cache.checkout(&url, &second).unwrap();
+        assert_ne!(old, new);
+        assert!(std::fs::read_to_string(old.join("package.json")).unwrap().contains("1.0.0"));
+        assert!(std::fs::read_to_string(new.join("package.json")).unwrap().contains("1.1.0"));
+        assert_eq!(cache.checkout(&url, &first).unwrap(), old);
+
+        let resolved = format!("git+{}#{}", url, first);
+        assert_eq!(parse_resolved(&resolved), Some((url.as_str(), first.as_str())));
+        assert_eq!(parse_resolved("git+https://example.com/repo.git#main"), None);
+        assert_eq!(parse_resolved("https://example.com/pkg.tgz"), None);
+    }
+
+    #[test]
+    fn test_urls_cannot_run_commands() {
+        let temp = TempDir::new().unwrap();
+        let cache = GitCache::new(Utf8PathBuf::from_path_buf(temp.path().join("cache")).unwrap());
+        let marker = temp.path().join("ran");
+
+        let option = format!("--upload-pack=touch {}", marker.display());
+        assert!(cache.resolve(&option, None).unwrap_err().to_string().contains("can't start with '-'"));
+        let ext = format!("ext::sh -c touch% {}", marker.display());
+        assert!(cache.resolve(&ext, None).is_err());
+        assert!(!marker.exists());
+    }
+}
@@ -5,13 +5,15 @@
 //! and supports tarball extraction/creation.
 
 pub mod cas;
+pub mod git;
 pub mod tarball;
 pub mod link;
 
 // Re-export main types
-pub use cas::{CasStore, ContentHash, CasIndex, CacheEntry};
+pub use cas::{CasStore, ContentHash, CasIndex, CacheEntry, GcResult, VerifyReport, ArchiveSummary};
 pub use tarball::{extract_tarball, create_tarball};
 pub use link::Linker;
+pub use git::GitCache;
 
 use pea_core::error::PeaError;
 
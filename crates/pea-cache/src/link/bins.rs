@@ -0,0 +1,432 @@
+//! Package executables (`.bin` entries)
+//!
+//! Reads the `bin` and `directories.bin` declarations from package.json,
+//! decides which package owns a name when several declare it, and creates
+//! the `.bin` entries: relative symlinks on Unix, and `.cmd` shims that
+//! honour the script's shebang on Windows.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_core::error::PeaError;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use walkdir::WalkDir;
+
+use super::linker::{Linker, NodeModulesResult, PackageInfo};
+use super::relink::Relink;
+use crate::CacheResult;
+
+/// Two packages declared the same executable name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinConflict {
+    /// Executable name
+    pub bin: String,
+    /// Package whose executable was linked (`name@version`)
+    pub winner: String,
+    /// Package whose executable was skipped (`name@version`)
+    pub loser: String,
+}
+
+/// Interpreter line at the top of a script (`#!/usr/bin/env node --flag`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Shebang {
+    /// Program to run, with any `/usr/bin/env` indirection removed
+    program: String,
+    /// Extra arguments for the program
+    args: Vec<String>,
+}
+
+/// Read a package's executables from its package.json
+///
+/// A string `bin` is named after the package, without its scope. When there
+/// is no `bin`, every file under `directories.bin` is exposed. Names and
+/// paths that would escape `.bin` or the package are dropped.
+pub fn read_bin_entries(package_dir: &Utf8Path) -> CacheResult<HashMap<String, String>> {
+    let manifest_path = package_dir.join("package.json");
+    let content = match fs::read_to_string(&manifest_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(PeaError::io(format!("Failed to read {}", manifest_path), e)),
+    };
+    let manifest: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| PeaError::JsonParse { message: format!("Failed to parse {}: {}", manifest_path, e) })?;
+
+    let mut entries = HashMap::new();
+    match manifest.get("bin") {
+        Some(serde_json::Value::String(path)) => {
+            let name = manifest.get("name").and_then(|name| name.as_str()).and_then(clean_bin_name);
+            if let (Some(name), Some(path)) = (name, clean_bin_path(path)) {
+                entries.insert(name, path);
+            }
+        }
+        Some(serde_json::Value::Object(map)) => {
+            for (name, path) in map {
+                let path = path.as_str().and_then(clean_bin_path);
+                if let (Some(name), Some(path)) = (clean_bin_name(name), path) {
+                    entries.insert(name, path);
+                }
+            }
+        }
+        _ => {
+            let directory = manifest.get("directories")
+                .and_then(|directories| directories.get("bin"))
+                .and_then(|dir| dir.as_str())
+                .and_then(clean_bin_path);
+            if let Some(directory) = directory {
+                entries.extend(directory_bins(package_dir, &directory));
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Parse the shebang on the first line of a script, if any
+#[cfg_attr(unix, allow(dead_code))]
+fn parse_shebang(content: &str) -> Option<Shebang> {
+    let line = content.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?;
+
+    // `#!/usr/bin/env [-S] node` runs `node`
+    if program.rsplit('/').next() == Some("env") {
+        program = words.next()?;
+        if program == "-S" {
+            program = words.next()?;
+        }
+    }
+
+    Some(Shebang {
+        program: program.to_string(),
+        args: words.map(str::to_string).collect(),
+    })
+}
+
+/// Executables offered for one `.bin` directory, keyed by name
+#[derive(Default)]
+pub(crate) struct BinSet<'a> {
+    owners: BTreeMap<String, (&'a PackageInfo, Utf8PathBuf)>,
+    conflicts: Vec<BinConflict>,
+}
+
+impl<'a> BinSet<'a> {
+    /// Offer every executable `package` declares, installed at `package_dir`
+    ///
+    /// Direct dependencies beat transitive ones, then a package named like
+    /// the executable beats one that isn't, then the alphabetically first wins.
+    pub(crate) fn offer(&mut self, package: &'a PackageInfo, package_dir: &Utf8Path) {
+        for (bin, path) in &package.bin_entries {
+            let target = package_dir.join(path);
+            if !target.exists() {
+                continue;
+            }
+
+            let Some((owner, _)) = self.owners.get(bin) else {
+                self.owners.insert(bin.clone(), (package, target));
+                continue;
+            };
+            if owner.name == package.name {
+                continue;
+            }
+
+            let rank = |p: &PackageInfo| (p.is_direct, &p.name == bin, std::cmp::Reverse(p.name.clone()));
+            let (winner, loser) = if rank(package) > rank(owner) { (package, *owner) } else { (*owner, package) };
+            self.conflicts.push(BinConflict {
+                bin: bin.clone(),
+                winner: format!("{}@{}", winner.name, winner.version),
+                loser: format!("{}@{}", loser.name, loser.version),
+            });
+            if std::ptr::eq(winner, package) {
+                self.owners.insert(bin.clone(), (package, target));
+            }
+        }
+    }
+
+    /// Create the winning entries in `bin_dir`
+    pub(crate) fn place(
+        self,
+        linker: &Linker,
+        relink: &mut Relink,
+        bin_dir: &Utf8Path,
+        result: &mut NodeModulesResult,
+    ) -> CacheResult<()> {
+        for (bin, (_, target)) in &self.owners {
+            let created = linker.create_bin_entry(target, &bin_dir.join(bin))?;
+            result.bin_links_created += 1;
+            relink.record_link(&created);
+        }
+        result.bin_conflicts.extend(self.conflicts);
+        Ok(())
+    }
+}
+
+impl Linker {
+    /// Create a `.bin` entry for `target` and return the path written
+    ///
+    /// Symlinks are relative to `.bin` so the tree can be built in a staging
+    /// directory and renamed into place.
+    fn create_bin_entry(&self, target: &Utf8Path, link: &Utf8Path) -> CacheResult<Utf8PathBuf> {
+        let relative = link.parent()
+            .and_then(|parent| pathdiff::diff_paths(target, parent))
+            .unwrap_or_else(|| target.as_std_path().to_path_buf());
+        let relative = relative.to_string_lossy().replace('\\', "/");
+
+        #[cfg(unix)]
+        {
+            if link.exists() || link.is_symlink() {
+                fs::remove_file(link)
+                    .map_err(|e| PeaError::io("Failed to remove existing bin link".to_string(), e))?;
+            }
+            std::os::unix::fs::symlink(&relative, link)
+                .map_err(|e| PeaError::io("Failed to create bin symlink".to_string(), e))?;
+
+            // Scripts are often published without the executable bit
+            make_executable(target);
+            Ok(link.to_path_buf())
+        }
+
+        #[cfg(not(unix))]
+        {
+            let shebang = fs::read_to_string(target).ok().and_then(|content| parse_shebang(&content));
+            let shim = Utf8PathBuf::from(format!("{}.cmd", link));
+            fs::write(&shim, cmd_shim(&relative, shebang.as_ref()))
+                .map_err(|e| PeaError::io("Failed to create bin wrapper".to_string(), e))?;
+            Ok(shim)
+        }
+    }
+}
+
+/// Set the executable bits on a script that starts with a shebang
+///
+/// A file with other hardlinks (to the store, or to the previous tree) is
+/// replaced by a private copy first, so the mode change stays in this tree.
+/// Failures leave the file as it was.
+#[cfg(unix)]
+fn make_executable(target: &Utf8Path) {
+    use std::io::Read;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let Ok(metadata) = fs::metadata(target) else {
+        return;
+    };
+    let mode = metadata.permissions().mode();
+    if !metadata.is_file() || mode & 0o111 == 0o111 {
+        return;
+    }
+    let mut magic = [0u8; 2];
+    let has_shebang = fs::File::open(target).and_then(|mut file| file.read_exact(&mut magic)).is_ok();
+    if !has_shebang || &magic != b"#!" {
+        return;
+    }
+
+    if metadata.nlink() > 1 {
+        let copy = Utf8PathBuf::from(format!("{}.pea-copy", target));
+        if fs::copy(target, &copy).and_then(|_| fs::rename(&copy, target)).is_err() {
+            let _ = fs::remove_file(&copy);
+            return;
+        }
+    }
+    let _ = fs::set_permissions(target, fs::Permissions::from_mode(mode | 0o111));
+}
+
+/// Contents of a Windows `.cmd` shim running `relative` (from `.bin`)
+#[cfg_attr(unix, allow(dead_code))]
+fn cmd_shim(relative: &str, shebang: Option<&Shebang>) -> String {
+    let script = format!("\"%~dp0\\{}\"", relative.replace('/', "\\"));
+    let command = match shebang {
+        Some(shebang) => {
+            // Interpreters are looked up on PATH; /bin/sh and friends don't exist on Windows
+            let program = shebang.program.rsplit('/').next().unwrap_or(&shebang.program);
+            let mut parts = vec![format!("\"{}\"", program)];
+            parts.extend(shebang.args.iter().cloned());
+            parts.push(script);
+            parts.join(" ")
+        }
+        None if relative.ends_with(".js") || relative.ends_with(".cjs") || relative.ends_with(".mjs") => {
+            format!("\"node\" {}", script)
+        }
+        None => script,
+    };
+    format!("@echo off\r\n{} %*\r\n", command)
+}
+
+/// Every file under `directory` (relative to the package), named by file name
+fn directory_bins(package_dir: &Utf8Path, directory: &str) -> HashMap<String, String> {
+    WalkDir::new(package_dir.join(directory))
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?;
+            if name.starts_with('.') {
+                return None;
+            }
+            let relative = entry.path().strip_prefix(package_dir.as_std_path()).ok()?;
+            Some((name.to_string(), clean_bin_path(relative.to_str()?)?))
+        })
+        .collect()
+}
+
+/// Executable name without scope or directories; None if nothing usable is left
+fn clean_bin_name(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next()?.trim();
+    (!base.is_empty() && base != "." && base != "..").then(|| base.to_string())
+}
+
+/// Normalised path inside the package; None if it escapes the package
+fn clean_bin_path(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn package_dir(manifest: serde_json::Value) -> (tempfile::TempDir, Utf8PathBuf) {
+        let temp_dir = tempdir().unwrap();
+        let dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        fs::write(dir.join("package.json"), manifest.to_string()).unwrap();
+        (temp_dir, dir)
+    }
+
+    #[test]
+    fn test_string_bin_uses_unscoped_name() {
+        let (_temp_dir, dir) = package_dir(serde_json::json!({
+            "name": "@scope/tool",
+            "bin": "./cli.js",
+        }));
+
+        let entries = read_bin_entries(&dir).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries["tool"], "cli.js");
+    }
+
+    #[test]
+    fn test_bin_names_and_paths_are_sanitised() {
+        let (_temp_dir, dir) = package_dir(serde_json::json!({
+            "name": "tool",
+            "bin": {
+                "@scope/scoped": "bin/scoped.js",
+                "../escape": "bin/escape.js",
+                "outside": "../../etc/passwd",
+                "nested": "./lib/../bin/nested.js",
+            },
+        }));
+
+        let entries = read_bin_entries(&dir).unwrap();
+        assert_eq!(entries["scoped"], "bin/scoped.js");
+        assert_eq!(entries["escape"], "bin/escape.js");
+        assert_eq!(entries["nested"], "bin/nested.js");
+        assert!(!entries.contains_key("outside"));
+    }
+
+    #[test]
+    fn test_directories_bin() {
+        let (_temp_dir, dir) = package_dir(serde_json::json!({
+            "name": "tool",
+            "directories": { "bin": "./scripts" },
+        }));
+        fs::create_dir_all(dir.join("scripts/sub")).unwrap();
+        fs::write(dir.join("scripts/one"), "#!/bin/sh\n").unwrap();
+        fs::write(dir.join("scripts/sub/two.js"), "").unwrap();
+        fs::write(dir.join("scripts/.hidden"), "").unwrap();
+
+        let entries = read_bin_entries(&dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries["one"], "scripts/one");
+        assert_eq!(entries["two.js"], "scripts/sub/two.js");
+    }
+
+    #[test]
+    fn test_parse_shebang() {
+        assert_eq!(parse_shebang("#!/usr/bin/env node\nconsole.log(1)"), Some(Shebang {
+            program: "node".to_string(),
+            args: vec![],
+        }));
+        assert_eq!(parse_shebang("#!/usr/bin/env -S node --experimental-vm-modules"), Some(Shebang {
+            program: "node".to_string(),
+            args: vec!["--experimental-vm-modules".to_string()],
+        }));
+        assert_eq!(parse_shebang("#!/bin/sh\n").unwrap().program, "/bin/sh");
+        assert_eq!(parse_shebang("console.log(1)"), None);
+    }
+
+    #[test]
+    fn test_cmd_shim() {
+        let shebang = parse_shebang("#!/bin/sh -e").unwrap();
+        assert_eq!(cmd_shim("../tool/run.sh", Some(&shebang)),
+            "@echo off\r\n\"sh\" -e \"%~dp0\\..\\tool\\run.sh\" %*\r\n");
+        assert_eq!(cmd_shim("../tool/cli.js", None),
+            "@echo off\r\n\"node\" \"%~dp0\\..\\tool\\cli.js\" %*\r\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_only_scripts_become_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let linker = Linker::new(std::sync::Arc::new(crate::cas::CasStore::new(root.join("store")).unwrap()));
+        let mode = |path: &Utf8Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        fs::create_dir_all(root.join("tool")).unwrap();
+        fs::create_dir_all(root.join(".bin")).unwrap();
+
+        // A script hardlinked from the store gets its own executable copy
+        fs::write(root.join("store-file"), "#!/usr/bin/env node\n").unwrap();
+        fs::set_permissions(root.join("store-file"), fs::Permissions::from_mode(0o644)).unwrap();
+        fs::hard_link(root.join("store-file"), root.join("tool/cli.js")).unwrap();
+        linker.create_bin_entry(&root.join("tool/cli.js"), &root.join(".bin/cli")).unwrap();
+        assert_eq!(mode(&root.join("tool/cli.js")), 0o755);
+        assert_eq!(mode(&root.join("store-file")), 0o644);
+        assert_eq!(fs::read_to_string(root.join("tool/cli.js")).unwrap(), "#!/usr/bin/env node\n");
+
+        // Anything else keeps its mode
+        fs::write(root.join("tool/data.json"), "{}").unwrap();
+        fs::set_permissions(root.join("tool/data.json"), fs::Permissions::from_mode(0o644)).unwrap();
+        linker.create_bin_entry(&root.join("tool/data.json"), &root.join(".bin/data")).unwrap();
+        assert_eq!(mode(&root.join("tool/data.json")), 0o644);
+    }
+
+    #[test]
+    fn test_bin_collisions_prefer_direct_then_own_name() {
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        for name in ["tsc", "typescript", "other"] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            fs::write(root.join(name).join("cli.js"), "").unwrap();
+        }
+        let package = |name: &str| PackageInfo::new(name.into(), "1.0.0".into(), root.join(name))
+            .with_bin("tsc".into(), "cli.js".into());
+
+        let typescript = package("typescript");
+        let tsc = package("tsc");
+        let other = package("other").as_direct();
+
+        // The package named after the bin beats an unrelated one...
+        let mut bins = BinSet::default();
+        bins.offer(&typescript, &root.join("typescript"));
+        bins.offer(&tsc, &root.join("tsc"));
+        assert_eq!(bins.owners["tsc"].0.name, "tsc");
+
+        // ...but a direct dependency beats both
+        bins.offer(&other, &root.join("other"));
+        assert_eq!(bins.owners["tsc"].0.name, "other");
+        assert_eq!(bins.conflicts.len(), 2);
+        assert_eq!(bins.conflicts[1], BinConflict {
+            bin: "tsc".to_string(),
+            winner: "other@1.0.0".to_string(),
+            loser: "tsc@1.0.0".to_string(),
+        });
+    }
+}
@@ -0,0 +1,332 @@
+//! Hoisting for node_modules layouts
+//!
+//! Plans an npm-compatible tree: direct dependencies and the most-shared
+//! version of every other package sit at the top of node_modules, and a
+//! version that conflicts with what a dependent would find by walking up
+//! the tree is nested under that dependent's own node_modules.
+
+use camino::Utf8PathBuf;
+use pea_core::types::Version;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::str::FromStr;
+
+use super::linker::PackageInfo;
+
+/// Which packages may be hoisted, as `hoist-pattern` / `public-hoist-pattern` globs
+///
+/// Patterns support `*` wildcards; a leading `!` excludes matching names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoistPatterns {
+    /// Packages hoisted where only other dependencies can see them
+    pub private: Vec<String>,
+    /// Packages hoisted to the top of node_modules where the project can see them
+    pub public: Vec<String>,
+}
+
+impl Default for HoistPatterns {
+    fn default() -> Self {
+        Self {
+            private: vec!["*".to_string()],
+            public: Vec::new(),
+        }
+    }
+}
+
+impl HoistPatterns {
+    /// Whether a package may be hoisted at all
+    pub fn hoists(&self, name: &str) -> bool {
+        matches_patterns(&self.private, name) || self.hoists_publicly(name)
+    }
+
+    /// Whether a package is hoisted to the project-visible top level
+    pub fn hoists_publicly(&self, name: &str) -> bool {
+        matches_patterns(&self.public, name)
+    }
+}
+
+/// Where one package goes in a hoisted tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    /// Path relative to node_modules, e.g. `a/node_modules/b`
+    pub path: Utf8PathBuf,
+    /// Index of the package in the planned slice
+    pub package: usize,
+    /// Whether the package sits directly under node_modules
+    pub top_level: bool,
+}
+
+/// A directory in the planned tree; slot 0 is the project itself
+struct Slot {
+    package: Option<usize>,
+    parent: Option<usize>,
+    children: BTreeMap<String, usize>,
+}
+
+/// Plan a hoisted node_modules tree, parents always before their nested children
+pub(crate) fn plan_hoisted(packages: &[PackageInfo], patterns: &HoistPatterns) -> Vec<Placement> {
+    let index = package_index(packages);
+    let dependents = count_dependents(packages, &index);
+    let mut slots = vec![Slot { package: None, parent: None, children: BTreeMap::new() }];
+    
+    // Direct dependencies, and anything nothing else depends on, anchor the root
+    let anchors: Vec<usize> = (0..packages.len())
+        .filter(|&i| packages[i].is_direct || dependents[i] == 0)
+        .collect();
+    for i in best_per_name(packages, &dependents, anchors) {
+        add_slot(&mut slots, 0, &packages[i].name, i);
+    }
+    
+    // Hoist the most-shared version of every other eligible package
+    let candidates: Vec<usize> = (0..packages.len())
+        .filter(|&i| !slots[0].children.contains_key(&packages[i].name) && patterns.hoists(&packages[i].name))
+        .collect();
+    for i in best_per_name(packages, &dependents, candidates) {
+        add_slot(&mut slots, 0, &packages[i].name, i);
+    }
+    
+    // Nest whatever a dependent can't find by walking up the tree
+    let mut queue: VecDeque<usize> = slots[0].children.values().copied().collect();
+    while let Some(slot) = queue.pop_front() {
+        let package = &packages[slots[slot].package.expect("non-root slot")];
+        let mut deps: Vec<(&String, &String)> = package.dependencies.iter().collect();
+        deps.sort();
+        
+        for (dep_name, dep_version) in deps {
+            let Some(&target) = index.get(&(dep_name.as_str(), dep_version.as_str())) else {
+                continue;
+            };
+            if resolve(&slots, slot, dep_name) == Some(target) {
+                continue;
+            }
+            // A cycle back to an ancestor can't be fixed by nesting deeper
+            if ancestors(&slots, slot).any(|s| slots[s].package == Some(target)) {
+                continue;
+            }
+            queue.push_back(add_slot(&mut slots, slot, dep_name, target));
+        }
+    }
+    
+    placements(&slots)
+}
+
+/// Most-shared version of each package name among the packages `candidate`
+/// accepts, by index (ties go to the highest version)
+pub(crate) fn most_shared(packages: &[PackageInfo], candidate: impl Fn(&PackageInfo) -> bool) -> Vec<usize> {
+    let dependents = count_dependents(packages, &package_index(packages));
+    let candidates = (0..packages.len()).filter(|&i| candidate(&packages[i])).collect();
+    best_per_name(packages, &dependents, candidates)
+}
+
+/// Pick the most-shared version of each package name among `candidates`
+/// (ties go to the highest version)
+fn best_per_name(packages: &[PackageInfo], dependents: &[usize], candidates: Vec<usize>) -> Vec<usize> {
+    let mut best: BTreeMap<&str, usize> = BTreeMap::new();
+    for i in candidates {
+        let name = packages[i].name.as_str();
+        let better = match best.get(name) {
+            Some(&current) => {
+                (dependents[i], parse_version(&packages[i].version))
+                    > (dependents[current], parse_version(&packages[current].version))
+            }
+            None => true,
+        };
+        if better {
+            best.insert(name, i);
+        }
+    }
+    best.into_values().collect()
+}
+
+/// Number of packages depending on each package
+fn count_dependents(packages: &[PackageInfo], index: &HashMap<(&str, &str), usize>) -> Vec<usize> {
+    let mut counts = vec![0; packages.len()];
+    for package in packages {
+        for (name, version) in &package.dependencies {
+            if let Some(&i) = index.get(&(name.as_str(), version.as_str())) {
+                counts[i] += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Map `(name, version)` to the package's index
+fn package_index(packages: &[PackageInfo]) -> HashMap<(&str, &str), usize> {
+    packages
+        .iter()
+        .enumerate()
+        .map(|(i, package)| ((package.name.as_str(), package.version.as_str()), i))
+        .collect()
+}
+
+fn add_slot(slots: &mut Vec<Slot>, parent: usize, name: &str, package: usize) -> usize {
+    let id = slots.len();
+    slots.push(Slot { package: Some(package), parent: Some(parent), children: BTreeMap::new() });
+    slots[parent].children.insert(name.to_string(), id);
+    id
+}
+
+/// Node's lookup: own node_modules first, then each ancestor's
+fn resolve(slots: &[Slot], from: usize, name: &str) -> Option<usize> {
+    std::iter::once(from)
+        .chain(ancestors(slots, from))
+        .find_map(|slot| slots[slot].children.get(name))
+        .and_then(|&found| slots[found].package)
+}
+
+fn ancestors(slots: &[Slot], from: usize) -> impl Iterator<Item = usize> + '_ {
+    std::iter::successors(slots[from].parent, move |&slot| slots[slot].parent)
+}
+
+fn placements(slots: &[Slot]) -> Vec<Placement> {
+    let mut result = Vec::new();
+    let mut queue: VecDeque<(usize, Utf8PathBuf)> = VecDeque::from([(0, Utf8PathBuf::new())]);
+    while let Some((slot, path)) = queue.pop_front() {
+        for (name, &child) in &slots[slot].children {
+            let child_path = if slot == 0 {
+                Utf8PathBuf::from(name)
+            } else {
+                path.join("node_modules").join(name)
+            };
+            result.push(Placement {
+                path: child_path.clone(),
+                package: slots[child].package.expect("non-root slot"),
+                top_level: slot == 0,
+            });
+            queue.push_back((child, child_path));
+        }
+    }
+    result
+}
+
+/// Version of a package, ignoring the peers a copy was resolved with (`1.0.0(react@18.2.0)`)
+fn parse_version(version: &str) -> Option<Version> {
+    Version::from_str(version.split('(').next().unwrap_or(version)).ok()
+}
+
+fn matches_patterns(patterns: &[String], name: &str) -> bool {
+    let (excludes, includes): (Vec<&String>, Vec<&String>) =
+        patterns.iter().partition(|pattern| pattern.starts_with('!'));
+    includes.iter().any(|pattern| wildcard_match(pattern, name))
+        && !excludes.iter().any(|pattern| wildcard_match(&pattern[1..], name))
+}
+
+/// Glob match where `*` matches any run of characters, including `/`
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkg(name: &str, version: &str, deps: &[(&str, &str)]) -> PackageInfo {
+        deps.iter().fold(
+            PackageInfo::new(name.into(), version.into(), Utf8PathBuf::from("/unused")),
+            |p, (n, v)| p.with_dependency(n.to_string(), v.to_string()),
+        )
+    }
+
+    fn layout(packages: &[PackageInfo], patterns: &HoistPatterns) -> Vec<String> {
+        plan_hoisted(packages, patterns)
+            .into_iter()
+            .map(|p| format!("{}@{}", p.path, packages[p.package].version))
+            .collect()
+    }
+
+    #[test]
+    fn test_most_shared_version_hoisted() {
+        let packages = vec![
+            pkg("a", "1.0.0", &[("c", "1.0.0")]).as_direct(),
+            pkg("b", "1.0.0", &[("c", "2.0.0"), ("d", "1.0.0")]).as_direct(),
+            pkg("d", "1.0.0", &[("c", "2.0.0")]),
+            pkg("c", "1.0.0", &[]),
+            pkg("c", "2.0.0", &[]),
+        ];
+        
+        assert_eq!(layout(&packages, &HoistPatterns::default()), vec![
+            "a@1.0.0", "b@1.0.0", "c@2.0.0", "d@1.0.0",
+            "a/node_modules/c@1.0.0",
+        ]);
+    }
+
+    #[test]
+    fn test_direct_dependency_wins_root() {
+        // The project pins c@1 even though c@2 is more shared
+        let packages = vec![
+            pkg("c", "1.0.0", &[]).as_direct(),
+            pkg("a", "1.0.0", &[("c", "2.0.0")]).as_direct(),
+            pkg("b", "1.0.0", &[("c", "2.0.0")]).as_direct(),
+            pkg("c", "2.0.0", &[]),
+        ];
+        
+        assert_eq!(layout(&packages, &HoistPatterns::default()), vec![
+            "a@1.0.0", "b@1.0.0", "c@1.0.0",
+            "a/node_modules/c@2.0.0", "b/node_modules/c@2.0.0",
+        ]);
+    }
+
+    #[test]
+    fn test_hoist_pattern_excludes() {
+        let packages = vec![
+            pkg("a", "1.0.0", &[("internal", "1.0.0"), ("util", "1.0.0")]).as_direct(),
+            pkg("internal", "1.0.0", &[]),
+            pkg("util", "1.0.0", &[]),
+        ];
+        let patterns = HoistPatterns {
+            private: vec!["*".to_string(), "!intern*".to_string()],
+            public: Vec::new(),
+        };
+        
+        assert_eq!(layout(&packages, &patterns), vec![
+            "a@1.0.0", "util@1.0.0", "a/node_modules/internal@1.0.0",
+        ]);
+    }
+
+    #[test]
+    fn test_cycles_terminate() {
+        let packages = vec![
+            pkg("a", "1.0.0", &[("b", "1.0.0")]).as_direct(),
+            pkg("b", "1.0.0", &[("a", "2.0.0")]),
+            pkg("a", "2.0.0", &[("b", "2.0.0")]),
+            pkg("b", "2.0.0", &[("a", "1.0.0")]),
+        ];
+        
+        let planned = layout(&packages, &HoistPatterns::default());
+        assert_eq!(planned[0], "a@1.0.0");
+        assert!(planned.len() <= 5, "unexpected layout: {:?}", planned);
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(matches_patterns(&["*".to_string()], "@scope/pkg"));
+        assert!(matches_patterns(&["*eslint*".to_string()], "@typescript-eslint/parser"));
+        assert!(!matches_patterns(&["*".to_string(), "!@types/*".to_string()], "@types/node"));
+        assert!(!matches_patterns(&[], "anything"));
+        
+        let patterns = HoistPatterns { private: Vec::new(), public: vec!["prettier".to_string()] };
+        assert!(patterns.hoists("prettier"));
+        assert!(patterns.hoists_publicly("prettier"));
+        assert!(!patterns.hoists("react"));
+    }
+}
@@ -0,0 +1,312 @@
+//! Package file import strategies
+//!
+//! Implements the `package-import-method` setting: copy-on-write reflinks
+//! (FICLONE on Linux, clonefile on macOS), hardlinks and plain copies.
+//! Filesystem support is probed on the first file imported between a
+//! given pair of devices and remembered for the rest of the install. Only
+//! errors that mean the filesystem can't do it are remembered; anything
+//! else (a full disk, a file in the way) only affects the file at hand.
+
+use dashmap::DashMap;
+use pea_core::error::PeaError;
+use pea_core::types::PackageImportMethod;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::CacheResult;
+
+/// What a source/destination filesystem pair supports, learnt lazily
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FsCapabilities {
+    /// Whether reflinks work (None until first attempted)
+    pub reflink: Option<bool>,
+    /// Whether hardlinks work (None until first attempted)
+    pub hardlink: Option<bool>,
+}
+
+/// How a single file ended up in node_modules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportOutcome {
+    /// Cloned copy-on-write, sharing blocks with the store
+    Reflinked,
+    /// Hardlinked to the store's file
+    Hardlinked,
+    /// Copied byte for byte
+    Copied,
+}
+
+/// Per-device-pair capability cache
+#[derive(Debug, Default)]
+pub struct CapabilityCache {
+    probed: DashMap<(u64, u64), FsCapabilities>,
+}
+
+impl CapabilityCache {
+    /// Capabilities learnt so far for the filesystems holding `source_dir` and `dest_dir`
+    pub fn get(&self, source_dir: &Path, dest_dir: &Path) -> FsCapabilities {
+        self.probed.get(&device_pair(source_dir, dest_dir)).map(|c| *c).unwrap_or_default()
+    }
+
+    /// Import one file using `method`, consulting and updating the cache
+    ///
+    /// `devices` is the key returned by [`device_pair`] for the directories
+    /// the file is moving between.
+    pub fn import_file(
+        &self,
+        method: PackageImportMethod,
+        devices: (u64, u64),
+        source: &Path,
+        dest: &Path,
+    ) -> CacheResult<ImportOutcome> {
+        let caps = self.probed.get(&devices).map(|c| *c).unwrap_or_default();
+        
+        let try_reflink = matches!(method, PackageImportMethod::Auto | PackageImportMethod::Reflink | PackageImportMethod::CloneOrCopy);
+        if try_reflink && caps.reflink != Some(false) {
+            match reflink_file(source, dest) {
+                Ok(()) => {
+                    self.record(devices, |c| c.reflink = Some(true));
+                    return Ok(ImportOutcome::Reflinked);
+                }
+                Err(e) => {
+                    if is_unsupported(&e, false) {
+                        self.record(devices, |c| c.reflink = Some(false));
+                    }
+                    if method == PackageImportMethod::Reflink {
+                        return Err(PeaError::io(
+                            format!("Reflink of {} failed; the filesystem may not support copy-on-write clones (set package-import-method to \"auto\" or \"clone-or-copy\")", source.display()),
+                            e,
+                        ));
+                    }
+                }
+            }
+        } else if method == PackageImportMethod::Reflink {
+            return Err(PeaError::io(
+                "Filesystem does not support reflinks (set package-import-method to \"auto\" or \"clone-or-copy\")".to_string(),
+                io::Error::from(io::ErrorKind::Unsupported),
+            ));
+        }
+        
+        let try_hardlink = matches!(method, PackageImportMethod::Auto | PackageImportMethod::Hardlink);
+        if try_hardlink && caps.hardlink != Some(false) {
+            match fs::hard_link(source, dest) {
+                Ok(()) => {
+                    self.record(devices, |c| c.hardlink = Some(true));
+                    return Ok(ImportOutcome::Hardlinked);
+                }
+                Err(e) => {
+                    if is_unsupported(&e, true) {
+                        self.record(devices, |c| c.hardlink = Some(false));
+                    }
+                    if method == PackageImportMethod::Hardlink {
+                        return Err(PeaError::io(
+                            format!("Hardlink of {} failed; the store and project may be on different filesystems", source.display()),
+                            e,
+                        ));
+                    }
+                }
+            }
+        } else if method == PackageImportMethod::Hardlink {
+            return Err(PeaError::io(
+                "Filesystem does not support hardlinks between the store and project".to_string(),
+                io::Error::from(io::ErrorKind::Unsupported),
+            ));
+        }
+        
+        fs::copy(source, dest)
+            .map_err(|e| PeaError::io("Failed to copy file".to_string(), e))?;
+        Ok(ImportOutcome::Copied)
+    }
+
+    fn record(&self, devices: (u64, u64), update: impl FnOnce(&mut FsCapabilities)) {
+        update(&mut self.probed.entry(devices).or_default());
+    }
+}
+
+/// Whether a failed reflink (or hardlink, with `hardlink`) means the
+/// filesystems can't do it at all, rather than that this one file failed
+#[cfg(unix)]
+fn is_unsupported(error: &io::Error, hardlink: bool) -> bool {
+    match error.raw_os_error() {
+        Some(code) => {
+            [libc::EOPNOTSUPP, libc::ENOTSUP, libc::EXDEV, libc::EINVAL, libc::ENOTTY].contains(&code)
+                || (hardlink && code == libc::EMLINK)
+        }
+        None => error.kind() == io::ErrorKind::Unsupported,
+    }
+}
+
+/// Whether a failed reflink (or hardlink, with `hardlink`) means the
+/// filesystems can't do it at all, rather than that this one file failed
+#[cfg(not(unix))]
+fn is_unsupported(error: &io::Error, _hardlink: bool) -> bool {
+    // ERROR_NOT_SAME_DEVICE on Windows
+    error.kind() == io::ErrorKind::Unsupported || error.raw_os_error() == Some(17)
+}
+
+/// Key identifying the filesystems two directories live on
+pub(crate) fn device_pair(source_dir: &Path, dest_dir: &Path) -> (u64, u64) {
+    (device_id(source_dir), device_id(dest_dir))
+}
+
+#[cfg(unix)]
+fn device_id(path: &Path) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).map(|m| m.dev()).unwrap_or(0)
+}
+
+#[cfg(not(unix))]
+fn device_id(_path: &Path) -> u64 {
+    0
+}
+
+/// Create `dest` as a copy-on-write clone of `source`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn reflink_file(source: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    
+    let src = fs::File::open(source)?;
+    let permissions = src.metadata()?.permissions();
+    let dst = fs::OpenOptions::new().write(true).create_new(true).open(dest)?;
+    
+    // SAFETY: both descriptors are open for the duration of the call
+    let ret = unsafe { libc::ioctl(dst.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) };
+    if ret == -1 {
+        let err = io::Error::last_os_error();
+        drop(dst);
+        let _ = fs::remove_file(dest);
+        return Err(err);
+    }
+    
+    dst.set_permissions(permissions)
+}
+
+/// Create `dest` as a copy-on-write clone of `source`
+#[cfg(target_os = "macos")]
+pub fn reflink_file(source: &Path, dest: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    
+    let src = CString::new(source.as_os_str().as_bytes())?;
+    let dst = CString::new(dest.as_os_str().as_bytes())?;
+    // SAFETY: both pointers are valid NUL-terminated strings
+    let ret = unsafe { libc::clonefile(src.as_ptr(), dst.as_ptr(), 0) };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Create `dest` as a copy-on-write clone of `source`
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+pub fn reflink_file(_source: &Path, _dest: &Path) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn setup() -> (tempfile::TempDir, std::path::PathBuf, std::path::PathBuf) {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source.txt");
+        let dest_dir = temp_dir.path().join("dest");
+        fs::write(&source, "original").unwrap();
+        fs::create_dir_all(&dest_dir).unwrap();
+        (temp_dir, source, dest_dir)
+    }
+
+    #[test]
+    fn test_copy_is_independent() {
+        let (_temp, source, dest_dir) = setup();
+        let cache = CapabilityCache::default();
+        let dest = dest_dir.join("file.txt");
+        let devices = device_pair(source.parent().unwrap(), &dest_dir);
+        
+        let outcome = cache.import_file(PackageImportMethod::Copy, devices, &source, &dest).unwrap();
+        assert_eq!(outcome, ImportOutcome::Copied);
+        
+        fs::write(&dest, "patched").unwrap();
+        assert_eq!(fs::read_to_string(&source).unwrap(), "original");
+    }
+
+    #[test]
+    fn test_clone_or_copy_never_hardlinks() {
+        let (_temp, source, dest_dir) = setup();
+        let cache = CapabilityCache::default();
+        let devices = device_pair(source.parent().unwrap(), &dest_dir);
+        
+        for i in 0..3 {
+            let dest = dest_dir.join(format!("file{}.txt", i));
+            let outcome = cache.import_file(PackageImportMethod::CloneOrCopy, devices, &source, &dest).unwrap();
+            assert_ne!(outcome, ImportOutcome::Hardlinked);
+            
+            // Patching the imported file must never reach the source
+            fs::write(&dest, "patched").unwrap();
+            assert_eq!(fs::read_to_string(&source).unwrap(), "original");
+        }
+        
+        // Reflink support was probed exactly once and remembered
+        assert!(cache.get(source.parent().unwrap(), &dest_dir).reflink.is_some());
+        assert_eq!(cache.get(source.parent().unwrap(), &dest_dir).hardlink, None);
+    }
+
+    #[test]
+    fn test_reflink_errors_when_unsupported() {
+        let (_temp, source, dest_dir) = setup();
+        let cache = CapabilityCache::default();
+        let devices = device_pair(source.parent().unwrap(), &dest_dir);
+        
+        let result = cache.import_file(PackageImportMethod::Reflink, devices, &source, &dest_dir.join("a.txt"));
+        match cache.get(source.parent().unwrap(), &dest_dir).reflink {
+            Some(true) => assert_eq!(result.unwrap(), ImportOutcome::Reflinked),
+            _ => {
+                assert!(result.is_err());
+                // Later files fail fast from the cached probe result
+                assert!(cache.import_file(PackageImportMethod::Reflink, devices, &source, &dest_dir.join("b.txt")).is_err());
+                assert!(!dest_dir.join("a.txt").exists());
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_only_capability_errors_are_remembered() {
+        let (_temp, source, dest_dir) = setup();
+        let cache = CapabilityCache::default();
+        let devices = device_pair(source.parent().unwrap(), &dest_dir);
+
+        // A file in the way fails both links, but says nothing about the filesystem
+        fs::write(dest_dir.join("taken.txt"), "old").unwrap();
+        let outcome = cache.import_file(PackageImportMethod::Auto, devices, &source, &dest_dir.join("taken.txt")).unwrap();
+        assert_eq!(outcome, ImportOutcome::Copied);
+        assert_eq!(fs::read_to_string(dest_dir.join("taken.txt")).unwrap(), "original");
+        let caps = cache.get(source.parent().unwrap(), &dest_dir);
+        assert_eq!(caps.hardlink, None);
+        if cfg!(any(target_os = "linux", target_os = "macos")) {
+            assert_eq!(caps.reflink, None);
+        }
+        let outcome = cache.import_file(PackageImportMethod::Auto, devices, &source, &dest_dir.join("next.txt")).unwrap();
+        assert_ne!(outcome, ImportOutcome::Copied);
+
+        assert!(is_unsupported(&io::Error::from_raw_os_error(libc::EXDEV), false));
+        assert!(is_unsupported(&io::Error::from_raw_os_error(libc::EMLINK), true));
+        assert!(!is_unsupported(&io::Error::from_raw_os_error(libc::EMLINK), false));
+        for code in [libc::ENOSPC, libc::EACCES, libc::EEXIST] {
+            assert!(!is_unsupported(&io::Error::from_raw_os_error(code), true));
+        }
+    }
+
+    #[test]
+    fn test_auto_prefers_links() {
+        let (_temp, source, dest_dir) = setup();
+        let cache = CapabilityCache::default();
+        let devices = device_pair(source.parent().unwrap(), &dest_dir);
+        
+        let outcome = cache.import_file(PackageImportMethod::Auto, devices, &source, &dest_dir.join("a.txt")).unwrap();
+        // Same filesystem: either a reflink or a hardlink, never a plain copy
+        assert_ne!(outcome, ImportOutcome::Copied);
+        assert_eq!(fs::read_to_string(dest_dir.join("a.txt")).unwrap(), "original");
+    }
+}
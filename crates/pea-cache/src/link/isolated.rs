@@ -0,0 +1,320 @@
+//! Isolated (virtual store) node_modules layout
+//!
+//! Every package lives at `node_modules/.pea/<name>@<version>/node_modules/<name>`
+//! next to symlinks for exactly the dependencies it declares. Only the
+//! project's direct dependencies are linked at the top of node_modules, so
+//! packages can't import anything they didn't declare and several versions
+//! of one package can coexist.
+//!
+//! Packages matching `hoist-pattern` are additionally linked into
+//! `node_modules/.pea/node_modules`, where only packages inside the virtual
+//! store can find them, and those matching `public-hoist-pattern` are
+//! linked at the top level for tools that expect to find them there.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_core::error::PeaError;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use super::bins::BinSet;
+use super::hoist::most_shared;
+use super::linker::{Linker, NodeModulesResult, PackageInfo};
+use super::relink::Relink;
+use crate::CacheResult;
+
+/// Name of the virtual store directory inside node_modules
+pub const VIRTUAL_STORE_DIR: &str = ".pea";
+
+impl Linker {
+    /// Create node_modules with an isolated virtual store
+    pub(crate) fn create_isolated_node_modules(
+        &self,
+        packages: &[PackageInfo],
+        node_modules_dir: &Utf8Path,
+        relink: &mut Relink,
+    ) -> CacheResult<NodeModulesResult> {
+        let mut result = NodeModulesResult::default();
+        let virtual_store = node_modules_dir.join(VIRTUAL_STORE_DIR);
+        let bin_dir = node_modules_dir.join(".bin");
+        fs::create_dir_all(&virtual_store)
+            .map_err(|e| PeaError::io("Failed to create virtual store directory".to_string(), e))?;
+        fs::create_dir_all(&bin_dir)
+            .map_err(|e| PeaError::io("Failed to create .bin directory".to_string(), e))?;
+        
+        let by_id: HashMap<(&str, &str), &PackageInfo> = packages
+            .iter()
+            .map(|package| ((package.name.as_str(), package.version.as_str()), package))
+            .collect();
+        
+        // Materialise each package once in the virtual store
+        for package in packages {
+            let package_dir = virtual_package_dir(&virtual_store, &package.name, &package.version);
+            self.place_package(relink, package, &package_dir, &mut result)?;
+        }
+        
+        // Link each package's declared dependencies beside it
+        for package in packages {
+            let sibling_dir = virtual_store
+                .join(virtual_store_name(&package.name, &package.version))
+                .join("node_modules");
+            
+            for (dep_name, dep_version) in &package.dependencies {
+                // A package can't see a different version of itself by name
+                if dep_name == &package.name || !by_id.contains_key(&(dep_name.as_str(), dep_version.as_str())) {
+                    continue;
+                }
+                let target = virtual_package_dir(&virtual_store, dep_name, dep_version);
+                place_link(relink, &target, &sibling_dir.join(dep_name), &mut result)?;
+            }
+        }
+        
+        // Expose only direct dependencies at the top level
+        let mut bins = BinSet::default();
+        for package in packages.iter().filter(|package| package.is_direct) {
+            let target = virtual_package_dir(&virtual_store, &package.name, &package.version);
+            let link = node_modules_dir.join(&package.name);
+            place_link(relink, &target, &link, &mut result)?;
+            bins.offer(package, &link);
+        }
+        bins.place(self, relink, &bin_dir, &mut result)?;
+        
+        // Hoist the most-shared version of each matching package
+        let patterns = self.hoist_patterns();
+        for i in most_shared(packages, |package| patterns.hoists(&package.name)) {
+            let package = &packages[i];
+            let link = if patterns.hoists_publicly(&package.name) {
+                // Never shadow a direct dependency the project chose
+                if packages.iter().any(|p| p.is_direct && p.name == package.name) {
+                    continue;
+                }
+                node_modules_dir.join(&package.name)
+            } else {
+                virtual_store.join("node_modules").join(&package.name)
+            };
+            let target = virtual_package_dir(&virtual_store, &package.name, &package.version);
+            place_link(relink, &target, &link, &mut result)?;
+        }
+        
+        Ok(result)
+    }
+}
+
+/// Symlink a directory at `link`, recording it in the manifest
+///
+/// Only links the previous install didn't have count as created.
+fn place_link(relink: &mut Relink, target: &Utf8Path, link: &Utf8Path, result: &mut NodeModulesResult) -> CacheResult<()> {
+    create_parent(link)?;
+    if symlink_dir(target, link)? && !relink.record_link(link) {
+        result.symlinks_created += 1;
+    }
+    Ok(())
+}
+
+/// Directory name for a package in the virtual store (`@scope/name` -> `@scope+name@1.0.0`)
+///
+/// Copies resolved with different peers have them in their version
+/// (`1.0.0(@types/react@18.2.0)`), so each gets its own directory.
+fn virtual_store_name(name: &str, version: &str) -> String {
+    format!("{}@{}", name, version).replace('/', "+")
+}
+
+/// Location of a package's files inside the virtual store
+pub fn virtual_package_dir(virtual_store: &Utf8Path, name: &str, version: &str) -> Utf8PathBuf {
+    virtual_store
+        .join(virtual_store_name(name, version))
+        .join("node_modules")
+        .join(name)
+}
+
+pub(super) fn create_parent(path: &Utf8Path) -> CacheResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| PeaError::io(format!("Failed to create {}", parent), e))?;
+    }
+    Ok(())
+}
+
+/// Create a directory symlink at `link` pointing at `target`, relative when possible
+///
+/// Returns false if the link already pointed there.
+pub(super) fn symlink_dir(target: &Utf8Path, link: &Utf8Path) -> CacheResult<bool> {
+    let relative = link.parent()
+        .and_then(|parent| pathdiff::diff_paths(target, parent))
+        .unwrap_or_else(|| target.as_std_path().to_path_buf());
+    
+    if fs::read_link(link).is_ok_and(|existing| existing == relative) {
+        return Ok(false);
+    }
+    
+    if link.is_symlink() || link.exists() {
+        fs::remove_file(link)
+            .map_err(|e| PeaError::io(format!("Failed to replace {}", link), e))?;
+    }
+    
+    #[cfg(unix)]
+    let created = std::os::unix::fs::symlink(&relative, link);
+    #[cfg(windows)]
+    let created = std::os::windows::fs::symlink_dir(&relative, link);
+    #[cfg(not(any(unix, windows)))]
+    let created: io::Result<()> = Err(io::Error::from(io::ErrorKind::Unsupported));
+    
+    created.map_err(|e: io::Error| PeaError::io(format!("Failed to link {} -> {}", link, target), e))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cas::CasStore;
+    use pea_core::types::NodeLinker;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn package_source(root: &Utf8Path, name: &str, version: &str) -> Utf8PathBuf {
+        let dir = root.join("src").join(virtual_store_name(name, version));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("package.json"), format!(r#"{{"name":"{}","version":"{}"}}"#, name, version)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_isolated_layout() {
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let cas_store = Arc::new(CasStore::new(root.join("store")).unwrap());
+        let linker = Linker::new(cas_store).with_node_linker(NodeLinker::Isolated);
+        
+        // app -> a@1 -> shared@1, app -> @scope/b@1 -> shared@2
+        let packages = vec![
+            PackageInfo::new("a".into(), "1.0.0".into(), package_source(&root, "a", "1.0.0"))
+                .with_dependency("shared".into(), "1.0.0".into())
+                .as_direct(),
+            PackageInfo::new("@scope/b".into(), "1.0.0".into(), package_source(&root, "@scope/b", "1.0.0"))
+                .with_dependency("shared".into(), "2.0.0".into())
+                .as_direct(),
+            PackageInfo::new("shared".into(), "1.0.0".into(), package_source(&root, "shared", "1.0.0")),
+            PackageInfo::new("shared".into(), "2.0.0".into(), package_source(&root, "shared", "2.0.0")),
+        ];
+        
+        let node_modules = root.join("node_modules");
+        let result = linker.create_node_modules(&packages, &node_modules).unwrap();
+        assert_eq!(result.packages_linked, 4);
+        // 2 dependency edges, 2 direct links, 3 privately hoisted names
+        assert_eq!(result.symlinks_created, 7);
+        
+        // Both versions of `shared` coexist in the virtual store
+        let store = node_modules.join(VIRTUAL_STORE_DIR);
+        assert!(store.join("shared@1.0.0/node_modules/shared/package.json").exists());
+        assert!(store.join("shared@2.0.0/node_modules/shared/package.json").exists());
+        
+        // Each dependent sees the version it resolved to
+        let a_shared = fs::read_to_string(store.join("a@1.0.0/node_modules/shared/package.json")).unwrap();
+        assert!(a_shared.contains("\"1.0.0\""));
+        let b_shared = fs::read_to_string(store.join("@scope+b@1.0.0/node_modules/shared/package.json")).unwrap();
+        assert!(b_shared.contains("\"2.0.0\""));
+        
+        // Only direct dependencies are visible at the top level
+        assert!(node_modules.join("a").is_symlink());
+        assert!(node_modules.join("@scope/b/package.json").exists());
+        assert!(!node_modules.join("shared").exists());
+        
+        // Hidden hoisting lets packages in the virtual store find common deps
+        assert!(store.join("node_modules/shared/package.json").exists());
+        
+        // Links are relative so the project can be moved
+        let target = fs::read_link(node_modules.join("a")).unwrap();
+        assert!(target.is_relative());
+    }
+
+    #[test]
+    fn test_isolated_peer_copies() {
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let cas_store = Arc::new(CasStore::new(root.join("store")).unwrap());
+        let linker = Linker::new(cas_store).with_node_linker(NodeLinker::Isolated);
+        
+        // app -> ui (peer react 17), app -> widgets -> ui (peer react 18)
+        let (ui_17, ui_18) = ("1.0.0(@types/react@17.0.2)", "1.0.0(@types/react@18.2.0)");
+        let ui = package_source(&root, "ui", "1.0.0");
+        let packages = vec![
+            PackageInfo::new("ui".into(), ui_17.into(), ui.clone())
+                .with_dependency("@types/react".into(), "17.0.2".into())
+                .as_direct(),
+            PackageInfo::new("ui".into(), ui_18.into(), ui)
+                .with_dependency("@types/react".into(), "18.2.0".into()),
+            PackageInfo::new("widgets".into(), "1.0.0".into(), package_source(&root, "widgets", "1.0.0"))
+                .with_dependency("ui".into(), ui_18.into())
+                .with_dependency("@types/react".into(), "18.2.0".into())
+                .as_direct(),
+            PackageInfo::new("@types/react".into(), "17.0.2".into(), package_source(&root, "@types/react", "17.0.2"))
+                .as_direct(),
+            PackageInfo::new("@types/react".into(), "18.2.0".into(), package_source(&root, "@types/react", "18.2.0")),
+        ];
+        
+        let node_modules = root.join("node_modules");
+        linker.create_node_modules(&packages, &node_modules).unwrap();
+        
+        // Each copy of ui has its own directory and sees its own peer
+        let store = node_modules.join(VIRTUAL_STORE_DIR);
+        let peer_version = |copy: &str| {
+            fs::read_to_string(store.join(copy).join("node_modules/@types/react/package.json")).unwrap()
+        };
+        assert!(peer_version("ui@1.0.0(@types+react@17.0.2)").contains("\"17.0.2\""));
+        assert!(peer_version("ui@1.0.0(@types+react@18.2.0)").contains("\"18.2.0\""));
+        let widgets_ui = fs::read_link(store.join("widgets@1.0.0/node_modules/ui")).unwrap();
+        assert!(widgets_ui.to_string_lossy().contains("ui@1.0.0(@types+react@18.2.0)"));
+        assert!(node_modules.join("ui/package.json").exists());
+    }
+
+    #[test]
+    fn test_isolated_public_hoisting() {
+        use crate::link::hoist::HoistPatterns;
+        
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let cas_store = Arc::new(CasStore::new(root.join("store")).unwrap());
+        let linker = Linker::new(cas_store)
+            .with_node_linker(NodeLinker::Isolated)
+            .with_hoist_patterns(HoistPatterns {
+                private: Vec::new(),
+                public: vec!["*eslint*".to_string()],
+            });
+        
+        let packages = vec![
+            PackageInfo::new("eslint".into(), "8.0.0".into(), package_source(&root, "eslint", "8.0.0"))
+                .with_dependency("eslint-scope".into(), "7.0.0".into())
+                .with_dependency("esquery".into(), "1.0.0".into())
+                .as_direct(),
+            PackageInfo::new("eslint-scope".into(), "7.0.0".into(), package_source(&root, "eslint-scope", "7.0.0")),
+            PackageInfo::new("esquery".into(), "1.0.0".into(), package_source(&root, "esquery", "1.0.0")),
+        ];
+        
+        let node_modules = root.join("node_modules");
+        linker.create_node_modules(&packages, &node_modules).unwrap();
+        
+        assert!(node_modules.join("eslint-scope/package.json").exists());
+        assert!(!node_modules.join("esquery").exists());
+        assert!(!node_modules.join(VIRTUAL_STORE_DIR).join("node_modules").exists());
+    }
+
+    #[test]
+    fn test_isolated_workspace_package_is_symlinked() {
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let cas_store = Arc::new(CasStore::new(root.join("store")).unwrap());
+        let linker = Linker::new(cas_store).with_node_linker(NodeLinker::Isolated);
+        
+        let source = package_source(&root, "local", "0.0.0");
+        let packages = vec![
+            PackageInfo::new("local".into(), "0.0.0".into(), source.clone()).as_workspace().as_direct(),
+        ];
+        
+        let node_modules = root.join("node_modules");
+        linker.create_node_modules(&packages, &node_modules).unwrap();
+        
+        // Edits in the workspace are visible through node_modules
+        fs::write(source.join("index.js"), "edited").unwrap();
+        assert_eq!(fs::read_to_string(node_modules.join("local/index.js")).unwrap(), "edited");
+    }
+}
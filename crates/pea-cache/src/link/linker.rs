@@ -11,20 +11,64 @@ use std::io;
 use std::path::Path;
 use walkdir::WalkDir;
 
+use pea_core::types::{NodeLinker, PackageImportMethod};
+
 use super::super::cas::CasStore;
+use super::bins::{BinConflict, BinSet};
+use super::hoist::{plan_hoisted, HoistPatterns};
+use super::import::{device_pair, CapabilityCache, ImportOutcome};
+use super::relink::{Carried, Relink};
+use super::state::InstalledState;
 use crate::CacheResult;
 
-/// Linker for creating hardlinks with fallback to copying
+/// Linker for importing package files from the store into node_modules
 #[derive(Debug)]
 pub struct Linker {
     /// Reference to the CAS store
     cas_store: std::sync::Arc<CasStore>,
+    /// How files are imported into node_modules
+    import_method: PackageImportMethod,
+    /// node_modules layout
+    node_linker: NodeLinker,
+    /// Which packages may be hoisted
+    hoist_patterns: HoistPatterns,
+    /// Filesystem capabilities probed so far
+    capabilities: CapabilityCache,
 }
 
 impl Linker {
-    /// Create a new linker
+    /// Create a new linker using the `auto` import method
     pub fn new(cas_store: std::sync::Arc<CasStore>) -> Self {
-        Self { cas_store }
+        Self {
+            cas_store,
+            import_method: PackageImportMethod::default(),
+            node_linker: NodeLinker::default(),
+            hoist_patterns: HoistPatterns::default(),
+            capabilities: CapabilityCache::default(),
+        }
+    }
+
+    /// Use a specific node_modules layout
+    pub fn with_node_linker(mut self, node_linker: NodeLinker) -> Self {
+        self.node_linker = node_linker;
+        self
+    }
+
+    /// Use a specific package import method
+    pub fn with_import_method(mut self, import_method: PackageImportMethod) -> Self {
+        self.import_method = import_method;
+        self
+    }
+
+    /// Use specific hoist patterns
+    pub fn with_hoist_patterns(mut self, hoist_patterns: HoistPatterns) -> Self {
+        self.hoist_patterns = hoist_patterns;
+        self
+    }
+
+    /// Get the configured hoist patterns
+    pub fn hoist_patterns(&self) -> &HoistPatterns {
+        &self.hoist_patterns
     }
 
     /// Get reference to the CAS store
@@ -32,6 +76,56 @@ impl Linker {
         &self.cas_store
     }
 
+    /// Get the configured import method
+    pub fn import_method(&self) -> PackageImportMethod {
+        self.import_method
+    }
+
+    /// Get the configured node_modules layout
+    pub fn node_linker(&self) -> NodeLinker {
+        self.node_linker
+    }
+
+    /// Import a directory tree using the configured import method
+    pub fn import_recursive(&self, source_dir: &Path, dest_dir: &Path) -> CacheResult<LinkResult> {
+        let mut result = LinkResult::default();
+        
+        fs::create_dir_all(dest_dir)
+            .map_err(|e| PeaError::io("Failed to create destination directory".to_string(), e))?;
+        let devices = device_pair(source_dir, dest_dir);
+        
+        for entry in WalkDir::new(source_dir) {
+            let entry = entry.map_err(|e| PeaError::io("Failed to walk directory".to_string(),
+                io::Error::other(e.to_string())))?;
+            let source_path = entry.path();
+            
+            let relative_path = source_path.strip_prefix(source_dir)
+                .map_err(|e| PeaError::io(format!("Failed to strip prefix: {}", e),
+                    io::Error::other(e.to_string())))?;
+            
+            if relative_path.as_os_str().is_empty() {
+                continue;
+            }
+            
+            let dest_path = dest_dir.join(relative_path);
+            
+            if entry.file_type().is_dir() {
+                fs::create_dir_all(&dest_path)
+                    .map_err(|e| PeaError::io("Failed to create directory".to_string(), e))?;
+                result.directories_created += 1;
+            } else if entry.file_type().is_file() {
+                match self.capabilities.import_file(self.import_method, devices, source_path, &dest_path)? {
+                    ImportOutcome::Reflinked => result.reflinks_created += 1,
+                    ImportOutcome::Hardlinked => result.hardlinks_created += 1,
+                    ImportOutcome::Copied => result.files_copied += 1,
+                }
+            }
+        }
+        
+        Ok(result)
+    }
+
+    /// Hardlink a directory tree, copying files that can't be linked
     pub fn hardlink_recursive(&self, source_dir: &Path, dest_dir: &Path) -> CacheResult<LinkResult> {
         let mut result = LinkResult::default();
         
@@ -77,6 +171,7 @@ impl Linker {
         Ok(result)
     }
 
+    /// Copy a directory tree
     pub fn copy_recursive(&self, source_dir: &Path, dest_dir: &Path) -> CacheResult<LinkResult> {
         let mut result = LinkResult::default();
         
@@ -117,11 +212,49 @@ impl Linker {
         Ok(result)
     }
 
-    /// Create node_modules structure with hardlinks
+    /// Create node_modules structure using the configured layout and import method
     pub fn create_node_modules(
         &self,
         packages: &[PackageInfo],
         node_modules_dir: &Utf8Path,
+    ) -> CacheResult<NodeModulesResult> {
+        self.relink_node_modules(packages, node_modules_dir, None)
+            .map(|(result, _)| result)
+    }
+
+    /// Build node_modules for `packages`, reusing what `previous` recorded for the live tree at its path
+    ///
+    /// Also returns the live packages that were moved into the new tree,
+    /// which must be restored if it doesn't replace the live one; on
+    /// failure they already are.
+    pub(crate) fn relink_node_modules(
+        &self,
+        packages: &[PackageInfo],
+        node_modules_dir: &Utf8Path,
+        previous: Option<(&InstalledState, &Utf8Path)>,
+    ) -> CacheResult<(NodeModulesResult, Carried)> {
+        let mut relink = Relink::new(self, node_modules_dir, previous);
+        let built = match self.node_linker {
+            NodeLinker::Hoisted => self.create_hoisted_node_modules(packages, node_modules_dir, &mut relink),
+            NodeLinker::Isolated => self.create_isolated_node_modules(packages, node_modules_dir, &mut relink),
+        };
+        let mut result = match built {
+            Ok(result) => result,
+            Err(e) => {
+                relink.restore();
+                return Err(e);
+            }
+        };
+        let carried = self.finish_relink(relink, &mut result)?;
+        Ok((result, carried))
+    }
+
+    /// Hoist packages to the top of node_modules, nesting conflicting versions
+    fn create_hoisted_node_modules(
+        &self,
+        packages: &[PackageInfo],
+        node_modules_dir: &Utf8Path,
+        relink: &mut Relink,
     ) -> CacheResult<NodeModulesResult> {
         let mut result = NodeModulesResult::default();
         
@@ -134,87 +267,27 @@ impl Linker {
         fs::create_dir_all(&bin_dir)
             .map_err(|e| PeaError::io("Failed to create .bin directory".to_string(), e))?;
         
-        // Process each package
-        for package in packages {
-            let package_dir = if package.name.starts_with('@') {
-                // Scoped package: @org/name -> @org/name
-                node_modules_dir.join(&package.name)
-            } else {
-                // Regular package: name -> name
-                node_modules_dir.join(&package.name)
-            };
+        // Place packages as planned; parents come before anything nested in them
+        let mut bins = BinSet::default();
+        for placement in plan_hoisted(packages, &self.hoist_patterns) {
+            let package = &packages[placement.package];
+            let package_dir = node_modules_dir.join(&placement.path);
             
-            // Create scoped directory if needed
-            if package.name.starts_with('@') {
-                if let Some(scope_dir) = package_dir.parent() {
-                    fs::create_dir_all(scope_dir)
-                        .map_err(|e| PeaError::io("Failed to create scope directory".to_string(), e))?;
-                }
+            // Import package content; local directories are linked so edits show up
+            self.place_package(relink, package, &package_dir, &mut result)?;
+            if !placement.top_level {
+                result.packages_nested += 1;
+                continue;
             }
             
-            // Link package content
-            let link_result = self.hardlink_recursive(
-                package.source_path.as_std_path(),
-                package_dir.as_std_path(),
-            )?;
-            
-            result.packages_linked += 1;
-            result.hardlinks_created += link_result.hardlinks_created;
-            result.files_copied += link_result.files_copied;
-            
-            // Create .bin symlinks
-            for (bin_name, bin_path) in &package.bin_entries {
-                let bin_target = package_dir.join(bin_path);
-                let bin_link = bin_dir.join(bin_name);
-                
-                if bin_target.exists() {
-                    self.create_bin_symlink(&bin_target, &bin_link)?;
-                    result.bin_links_created += 1;
-                }
-            }
+            // Only top-level packages contribute to .bin
+            bins.offer(package, &package_dir);
         }
+        bins.place(self, relink, &bin_dir, &mut result)?;
         
         Ok(result)
     }
 
-    /// Create a .bin symlink
-    fn create_bin_symlink(&self, target: &Utf8Path, link: &Utf8Path) -> CacheResult<()> {
-        // Remove existing symlink if it exists
-        if link.exists() || link.is_symlink() {
-            fs::remove_file(link)
-                .map_err(|e| PeaError::io("Failed to remove existing bin link".to_string(), e))?;
-        }
-        
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::symlink;
-            symlink(target.as_std_path(), link.as_std_path())
-                .map_err(|e| PeaError::io("Failed to create bin symlink".to_string(), e))?;
-            
-            // Make symlink executable
-            use std::os::unix::fs::PermissionsExt;
-            if let Ok(metadata) = fs::metadata(target) {
-                let mut perms = metadata.permissions();
-                perms.set_mode(perms.mode() | 0o111); // Add execute permission
-                let _ = fs::set_permissions(target, perms); // Ignore errors
-            }
-        }
-        
-        #[cfg(windows)]
-        {
-            // On Windows, create a .cmd wrapper script
-            let cmd_content = format!(
-                "@echo off\nnode \"{}\" %*\n",
-                target.as_str().replace('/', "\\")
-            );
-            let cmd_path = link.with_extension("cmd");
-            fs::write(&cmd_path, cmd_content)
-                .map_err(|e| PeaError::io("Failed to create bin wrapper".to_string(), e))?;
-        }
-        
-        Ok(())
-    }
-
     /// Remove node_modules directory while preserving CAS entries
     pub fn cleanup_node_modules(&self, node_modules_dir: &Utf8Path) -> CacheResult<CleanupResult> {
         let mut result = CleanupResult::default();
@@ -247,12 +320,24 @@ impl Linker {
 pub struct NodeModulesResult {
     /// Number of packages linked
     pub packages_linked: usize,
+    /// Number of copy-on-write reflinks created
+    pub reflinks_created: usize,
     /// Number of hardlinks created
     pub hardlinks_created: usize,
     /// Number of files copied (fallback)
     pub files_copied: usize,
     /// Number of .bin symlinks created
     pub bin_links_created: usize,
+    /// Number of directory symlinks created (isolated layout)
+    pub symlinks_created: usize,
+    /// Number of packages nested below another package (hoisted layout)
+    pub packages_nested: usize,
+    /// Number of packages left untouched since the previous install
+    pub packages_reused: usize,
+    /// Number of packages the previous install placed that are no longer needed
+    pub packages_removed: usize,
+    /// Executable names declared by more than one package
+    pub bin_conflicts: Vec<BinConflict>,
 }
 
 /// Result of cleanup operation
@@ -267,6 +352,8 @@ pub struct CleanupResult {
 /// Result of linking operations
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct LinkResult {
+    /// Number of copy-on-write reflinks created
+    pub reflinks_created: usize,
     /// Number of hardlinks created
     pub hardlinks_created: usize,
     /// Number of files copied (fallback)
@@ -288,6 +375,12 @@ pub struct PackageInfo {
     pub bin_entries: HashMap<String, String>,
     /// Whether this is a workspace package
     pub is_workspace: bool,
+    /// Resolved dependencies (name -> version)
+    pub dependencies: HashMap<String, String>,
+    /// Whether the project depends on this package directly
+    pub is_direct: bool,
+    /// Whether the install goes on without this package when it fails
+    pub is_optional: bool,
 }
 
 impl PackageInfo {
@@ -299,9 +392,30 @@ impl PackageInfo {
             source_path,
             bin_entries: HashMap::new(),
             is_workspace: false,
+            dependencies: HashMap::new(),
+            is_direct: false,
+            is_optional: false,
         }
     }
 
+    /// Add a resolved dependency edge
+    pub fn with_dependency(mut self, name: String, version: String) -> Self {
+        self.dependencies.insert(name, version);
+        self
+    }
+
+    /// Mark as a direct dependency of the project
+    pub fn as_direct(mut self) -> Self {
+        self.is_direct = true;
+        self
+    }
+
+    /// Mark as only reachable through optional dependencies
+    pub fn as_optional(mut self) -> Self {
+        self.is_optional = true;
+        self
+    }
+
     /// Add binary entry
     pub fn with_bin(mut self, name: String, path: String) -> Self {
         self.bin_entries.insert(name, path);
@@ -369,6 +483,26 @@ mod tests {
         assert_eq!(content2, "content2");
     }
 
+    #[test]
+    fn test_import_method_copy_protects_source() {
+        let temp_dir = tempdir().unwrap();
+        let store_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let cas_store = Arc::new(CasStore::new(&store_path).unwrap());
+        let linker = Linker::new(cas_store).with_import_method(PackageImportMethod::Copy);
+        
+        let source_dir = temp_dir.path().join("source");
+        let dest_dir = temp_dir.path().join("dest");
+        fs::create_dir_all(source_dir.join("lib")).unwrap();
+        fs::write(source_dir.join("lib/index.js"), "original").unwrap();
+        
+        let result = linker.import_recursive(&source_dir, &dest_dir).unwrap();
+        assert_eq!(result.files_copied, 1);
+        assert_eq!(result.hardlinks_created + result.reflinks_created, 0);
+        
+        fs::write(dest_dir.join("lib/index.js"), "patched").unwrap();
+        assert_eq!(fs::read_to_string(source_dir.join("lib/index.js")).unwrap(), "original");
+    }
+
     #[test]
     fn test_node_modules_creation() {
         let temp_dir = tempdir().unwrap();
@@ -393,6 +527,7 @@ mod tests {
         
         assert_eq!(result.packages_linked, 1);
         assert_eq!(result.bin_links_created, 1);
+        assert_eq!(result.reflinks_created + result.hardlinks_created + result.files_copied, 2);
         
         // Verify structure
         assert!(node_modules_dir.join("test-pkg").exists());
@@ -438,6 +573,43 @@ mod tests {
         assert!(node_modules_dir.join("@org").join("scoped-pkg").join("index.js").exists());
     }
 
+    #[test]
+    fn test_conflicting_versions_are_nested() {
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let cas_store = Arc::new(CasStore::new(root.join("store")).unwrap());
+        let linker = Linker::new(cas_store);
+        
+        let source = |name: &str, version: &str| {
+            let dir = root.join("src").join(format!("{}@{}", name, version));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("version.txt"), version).unwrap();
+            dir
+        };
+        
+        // a and b share lodash@4, c needs lodash@3
+        let packages = vec![
+            PackageInfo::new("a".into(), "1.0.0".into(), source("a", "1.0.0"))
+                .with_dependency("lodash".into(), "4.0.0".into()).as_direct(),
+            PackageInfo::new("b".into(), "1.0.0".into(), source("b", "1.0.0"))
+                .with_dependency("lodash".into(), "4.0.0".into()).as_direct(),
+            PackageInfo::new("c".into(), "1.0.0".into(), source("c", "1.0.0"))
+                .with_dependency("lodash".into(), "3.0.0".into()).as_direct(),
+            PackageInfo::new("lodash".into(), "4.0.0".into(), source("lodash", "4.0.0")),
+            PackageInfo::new("lodash".into(), "3.0.0".into(), source("lodash", "3.0.0")),
+        ];
+        
+        let node_modules = root.join("node_modules");
+        let result = linker.create_node_modules(&packages, &node_modules).unwrap();
+        
+        assert_eq!(result.packages_linked, 5);
+        assert_eq!(result.packages_nested, 1);
+        let read = |path: &str| fs::read_to_string(node_modules.join(path)).unwrap();
+        assert_eq!(read("lodash/version.txt"), "4.0.0");
+        assert_eq!(read("c/node_modules/lodash/version.txt"), "3.0.0");
+        assert!(!node_modules.join("a/node_modules").exists());
+    }
+
     #[test]
     fn test_cleanup_node_modules() {
         let temp_dir = tempdir().unwrap();
@@ -1,7 +1,7 @@
 //! Hardlinking and file system operations
 //!
-//! This module provides functionality for creating hardlinks
-//! with fallback to copying when hardlinks are not supported.
+//! This module provides functionality for importing package files into
+//! node_modules via reflinks, hardlinks or copies.
 
 use camino::{Utf8Path, Utf8PathBuf};
 use pea_core::error::PeaError;
@@ -11,6 +11,18 @@ use std::io;
 use crate::CacheResult;
 
 pub mod linker;
+pub mod import;
+pub mod isolated;
+pub mod hoist;
+pub mod swap;
+pub mod state;
+mod relink;
+pub mod bins;
 
 // Re-export main types
-pub use linker::{Linker, LinkResult, PackageInfo, NodeModulesResult, CleanupResult};
\ No newline at end of file
+pub use linker::{Linker, LinkResult, PackageInfo, NodeModulesResult, CleanupResult};
+pub use import::{reflink_file, FsCapabilities};
+pub use hoist::HoistPatterns;
+pub use swap::recover_interrupted_swap;
+pub use state::InstalledState;
+pub use bins::{read_bin_entries, BinConflict};
\ No newline at end of file
@@ -0,0 +1,443 @@
+//! Incremental relinking
+//!
+//! A tree is always built in its own directory. When the live node_modules
+//! was laid out with the same settings, packages the manifest shows are
+//! unchanged are carried over from it by renaming their directories
+//! (keeping whatever their scripts built) instead of being imported again,
+//! so a re-install only touches what changed. Every move is recorded and
+//! undone if the new tree isn't swapped in, and the live manifest is
+//! removed while packages are out of the live tree, so an interrupted
+//! install is followed by a full rebuild rather than trusting it.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_core::error::PeaError;
+use std::fs;
+use std::io;
+use walkdir::WalkDir;
+
+use super::isolated::{create_parent, symlink_dir};
+use super::linker::{Linker, NodeModulesResult, PackageInfo};
+use super::state::{InstalledPackage, InstalledState, LayoutKey, STATE_FILE, STATE_VERSION};
+use crate::CacheResult;
+
+/// Diff between the previous manifest and the tree being built
+pub(crate) struct Relink<'a> {
+    root: Utf8PathBuf,
+    previous: Option<(&'a InstalledState, Utf8PathBuf)>,
+    next: InstalledState,
+    carried: Carried,
+    /// Keys of packages placed as symlinks by this install
+    linked: Vec<String>,
+}
+
+/// Live package directories moved into the tree being built, to move back
+/// if that tree doesn't replace the live one
+#[derive(Default)]
+pub(crate) struct Carried {
+    /// (from, to) renames, in the order they were made
+    moves: Vec<(Utf8PathBuf, Utf8PathBuf)>,
+    /// The live tree's manifest and path, removed while packages are out
+    manifest: Option<(InstalledState, Utf8PathBuf)>,
+}
+
+impl Carried {
+    /// Put the live tree back the way it was, as far as possible
+    pub(crate) fn restore(self) {
+        for (from, to) in self.moves.into_iter().rev() {
+            // A package whose node_modules was moved back left an empty directory
+            let _ = fs::remove_dir(&from);
+            let _ = fs::rename(&to, &from);
+        }
+        if let Some((state, live)) = self.manifest {
+            let _ = state.save(&live);
+        }
+    }
+}
+
+impl<'a> Relink<'a> {
+    /// Start a tree at `root`, reusing entries `previous` recorded for the
+    /// live tree at its path if that was laid out with the same settings
+    pub(crate) fn new(linker: &Linker, root: &Utf8Path, previous: Option<(&'a InstalledState, &Utf8Path)>) -> Self {
+        let layout = layout_key(linker);
+        Self {
+            root: root.to_path_buf(),
+            previous: previous
+                .filter(|(state, _)| state.layout == layout)
+                .map(|(state, live)| (state, live.to_path_buf())),
+            next: InstalledState {
+                version: STATE_VERSION,
+                layout,
+                ..Default::default()
+            },
+            carried: Carried::default(),
+            linked: Vec::new(),
+        }
+    }
+
+    /// Record a symlink or `.bin` entry placed at `link`; returns whether the
+    /// previous install had it too
+    pub(crate) fn record_link(&mut self, link: &Utf8Path) -> bool {
+        let key = self.relative(link);
+        let existed = self.previous.as_ref().is_some_and(|(previous, _)| previous.links.contains(&key));
+        self.next.links.insert(key);
+        existed
+    }
+
+    /// Move back whatever was carried over from the live tree
+    pub(crate) fn restore(self) {
+        self.carried.restore();
+    }
+
+    fn relative(&self, path: &Utf8Path) -> String {
+        path.strip_prefix(&self.root).unwrap_or(path).as_str().replace('\\', "/")
+    }
+
+    /// The live copy of the package at `key`, if it is still what `recorded` describes
+    ///
+    /// Packages nested below a symlinked one live in its source directory,
+    /// outside the live tree, so they are always placed again.
+    fn reusable(&self, key: &str, recorded: &InstalledPackage) -> Option<Utf8PathBuf> {
+        let (previous, live) = self.previous.as_ref()?;
+        if self.linked.iter().any(|linked| key.strip_prefix(linked.as_str()).is_some_and(|rest| rest.starts_with('/'))) {
+            return None;
+        }
+        let path = live.join(key);
+        let unchanged = previous.packages.get(key) == Some(recorded);
+        (unchanged && (path.exists() || path.is_symlink())).then_some(path)
+    }
+
+    /// Move the live package at `live` to `dest`, leaving packages nested
+    /// in its `node_modules` in the live tree to be placed on their own
+    ///
+    /// Falls back to hardlinking its files if the directory can't be moved.
+    fn carry_over(&mut self, live: &Utf8Path, dest: &Utf8Path) -> CacheResult<()> {
+        if self.carried.manifest.is_none() {
+            if let Some((previous, live_root)) = &self.previous {
+                remove_entry(&live_root.join(STATE_FILE))?;
+                self.carried.manifest = Some(((*previous).clone(), live_root.clone()));
+            }
+        }
+        if fs::rename(live, dest).is_err() {
+            return link_files(live, dest);
+        }
+        self.carried.moves.push((live.to_path_buf(), dest.to_path_buf()));
+
+        let nested = dest.join("node_modules");
+        if fs::symlink_metadata(&nested).is_ok() {
+            let live_nested = live.join("node_modules");
+            fs::create_dir(live)
+                .map_err(|e| PeaError::io(format!("Failed to create {}", live), e))?;
+            fs::rename(&nested, &live_nested)
+                .map_err(|e| PeaError::io(format!("Failed to move {}", nested), e))?;
+            self.carried.moves.push((nested, live_nested));
+        }
+        Ok(())
+    }
+}
+
+/// Settings recorded in the manifest; a mismatch forces a full rebuild
+fn layout_key(linker: &Linker) -> LayoutKey {
+    LayoutKey {
+        node_linker: linker.node_linker().to_string(),
+        import_method: linker.import_method().to_string(),
+        hoist_pattern: linker.hoist_patterns().private.clone(),
+        public_hoist_pattern: linker.hoist_patterns().public.clone(),
+    }
+}
+
+impl Linker {
+    /// Import a package at `dest`, or carry it over if the previous install already had it
+    ///
+    /// Workspace packages and local directories (`is_workspace`) are
+    /// symlinked in either layout, so edits to them show up without
+    /// reinstalling.
+    pub(crate) fn place_package(
+        &self,
+        relink: &mut Relink,
+        package: &PackageInfo,
+        dest: &Utf8Path,
+        result: &mut NodeModulesResult,
+    ) -> CacheResult<()> {
+        let key = relink.relative(dest);
+        let recorded = InstalledPackage {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            source: package.source_path.to_string(),
+        };
+
+        result.packages_linked += 1;
+        remove_entry(dest)?;
+        create_parent(dest)?;
+        let linked = package.is_workspace;
+        if linked {
+            relink.linked.push(key.clone());
+        }
+        if let Some(live) = relink.reusable(&key, &recorded) {
+            if linked {
+                symlink_dir(&package.source_path, dest)?;
+            } else {
+                relink.carry_over(&live, dest)?;
+            }
+            result.packages_reused += 1;
+            // Reused files keep whatever their scripts built
+            if relink.previous.as_ref().is_some_and(|(previous, _)| previous.is_built(&key)) {
+                relink.next.mark_built(&key);
+            }
+        } else if linked {
+            symlink_dir(&package.source_path, dest)?;
+            result.symlinks_created += 1;
+        } else {
+            let link_result = self.import_recursive(
+                package.source_path.as_std_path(),
+                dest.as_std_path(),
+            )?;
+            result.reflinks_created += link_result.reflinks_created;
+            result.hardlinks_created += link_result.hardlinks_created;
+            result.files_copied += link_result.files_copied;
+        }
+
+        relink.next.packages.insert(key, recorded);
+        Ok(())
+    }
+
+    /// Count the packages the previous install had that this one dropped,
+    /// then save the manifest; returns what was moved out of the live tree
+    ///
+    /// On failure everything carried over is moved back first.
+    pub(crate) fn finish_relink(&self, relink: Relink, result: &mut NodeModulesResult) -> CacheResult<Carried> {
+        let Relink { root, previous, next, carried, .. } = relink;
+
+        if let Some((previous, _)) = previous {
+            result.packages_removed += previous.packages.keys()
+                .filter(|key| !next.packages.contains_key(*key))
+                .count();
+        }
+
+        match next.save(&root) {
+            Ok(()) => Ok(carried),
+            Err(e) => {
+                carried.restore();
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Hardlink the files of the live package at `live` into `dest`, copying
+/// those that can't be linked
+///
+/// Packages nested in its `node_modules` are placed on their own.
+fn link_files(live: &Utf8Path, dest: &Utf8Path) -> CacheResult<()> {
+    let entries = WalkDir::new(live).min_depth(1).into_iter()
+        .filter_entry(|entry| entry.depth() > 1 || entry.file_name() != "node_modules");
+    fs::create_dir_all(dest)
+        .map_err(|e| PeaError::io(format!("Failed to create {}", dest), e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| PeaError::io(format!("Failed to walk {}", live),
+            io::Error::other(e.to_string())))?;
+        let relative = entry.path().strip_prefix(live.as_std_path())
+            .map_err(|e| PeaError::io(format!("Failed to walk {}", live), io::Error::other(e.to_string())))?;
+        let target = dest.as_std_path().join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)
+                .map_err(|e| PeaError::io(format!("Failed to create {}", target.display()), e))?;
+        } else if entry.file_type().is_file() && fs::hard_link(entry.path(), &target).is_err() {
+            fs::copy(entry.path(), &target)
+                .map_err(|e| PeaError::io(format!("Failed to copy {}", entry.path().display()), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove a package directory or symlink if present
+fn remove_entry(path: &Utf8Path) -> CacheResult<()> {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    let removed = if metadata.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    removed.map_err(|e| PeaError::io(format!("Failed to remove {}", path), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cas::CasStore;
+    use pea_core::types::NodeLinker;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn source(root: &Utf8Path, name: &str, version: &str) -> Utf8PathBuf {
+        let dir = root.join("src").join(format!("{}@{}", name.replace('/', "+"), version));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("version.txt"), version).unwrap();
+        dir
+    }
+
+    fn package(root: &Utf8Path, name: &str, version: &str) -> PackageInfo {
+        PackageInfo::new(name.to_string(), version.to_string(), source(root, name, version)).as_direct()
+    }
+
+    fn setup() -> (tempfile::TempDir, Utf8PathBuf, Linker) {
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let linker = Linker::new(Arc::new(CasStore::new(root.join("store")).unwrap()));
+        (temp_dir, root, linker)
+    }
+
+    #[test]
+    fn test_relink_only_touches_changes() {
+        let (_temp_dir, root, linker) = setup();
+        let node_modules = root.join("node_modules");
+
+        let first = vec![package(&root, "a", "1.0.0"), package(&root, "@scope/b", "1.0.0")];
+        let result = linker.install_node_modules(&first, &node_modules).unwrap();
+        assert_eq!(result.packages_reused, 0);
+
+        let state = InstalledState::load(&node_modules).unwrap();
+        assert!(state.packages.contains_key("a"));
+        assert!(state.packages.contains_key("@scope/b"));
+
+        // Keep a, drop @scope/b, add c
+        let second = vec![package(&root, "a", "1.0.0"), package(&root, "c", "1.0.0")];
+        let result = linker.install_node_modules(&second, &node_modules).unwrap();
+        assert_eq!(result.packages_linked, 2);
+        assert_eq!(result.packages_reused, 1);
+        assert_eq!(result.packages_removed, 1);
+        assert!(node_modules.join("c/version.txt").exists());
+        assert!(!node_modules.join("@scope").exists());
+
+        // Upgrading replaces the directory
+        let third = vec![package(&root, "a", "2.0.0"), package(&root, "c", "1.0.0")];
+        linker.install_node_modules(&third, &node_modules).unwrap();
+        assert_eq!(fs::read_to_string(node_modules.join("a/version.txt")).unwrap(), "2.0.0");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unchanged_packages_are_moved_whole() {
+        use std::os::unix::fs::MetadataExt;
+
+        let (_temp_dir, root, linker) = setup();
+        let node_modules = root.join("node_modules");
+        let inode = |path: &str| fs::metadata(node_modules.join(path)).unwrap().ino();
+        let packages = |nested: &str| vec![
+            package(&root, "a", "1.0.0").with_dependency("lodash".into(), "4.0.0".into()),
+            package(&root, "c", "1.0.0").with_dependency("lodash".into(), nested.into()),
+            package(&root, "lodash", "4.0.0"),
+            package(&root, "lodash", nested),
+        ];
+
+        linker.install_node_modules(&packages("3.0.0"), &node_modules).unwrap();
+        fs::write(node_modules.join("a/build.out"), "built").unwrap();
+        let before = (inode("a"), inode("a/build.out"), inode("c"));
+
+        // Only c's nested lodash changed: everything else is renamed into
+        // the new tree as is, without visiting its files
+        let result = linker.install_node_modules(&packages("3.1.0"), &node_modules).unwrap();
+        assert_eq!(result.packages_reused, 3);
+        assert_eq!(result.files_copied + result.hardlinks_created + result.reflinks_created, 1);
+        assert_eq!((inode("a"), inode("a/build.out"), inode("c")), before);
+        assert_eq!(fs::read_to_string(node_modules.join("c/node_modules/lodash/version.txt")).unwrap(), "3.1.0");
+
+        // A failed install moves them back
+        let missing = PackageInfo::new("d".into(), "1.0.0".into(), root.join("does-not-exist"));
+        let mut failing = packages("3.1.0");
+        failing.push(missing.as_direct());
+        assert!(linker.install_node_modules(&failing, &node_modules).is_err());
+        assert_eq!((inode("a"), inode("a/build.out"), inode("c")), before);
+        assert!(node_modules.join("c/node_modules/lodash/version.txt").exists());
+        assert_eq!(InstalledState::load(&node_modules).unwrap().packages.len(), 4);
+    }
+
+    #[test]
+    fn test_local_directories_are_linked_when_hoisted() {
+        let (_temp_dir, root, linker) = setup();
+        let node_modules = root.join("node_modules");
+        let lib = root.join("lib");
+        fs::create_dir_all(&lib).unwrap();
+        fs::write(lib.join("index.js"), "one").unwrap();
+        let packages = vec![
+            PackageInfo::new("lib".into(), "1.0.0".into(), lib.clone()).as_workspace().as_direct(),
+            package(&root, "a", "1.0.0"),
+        ];
+
+        linker.install_node_modules(&packages, &node_modules).unwrap();
+        assert!(node_modules.join("lib").is_symlink());
+
+        // Edits show up at once, and survive a re-install
+        fs::write(lib.join("index.js"), "two").unwrap();
+        assert_eq!(fs::read_to_string(node_modules.join("lib/index.js")).unwrap(), "two");
+        let result = linker.install_node_modules(&packages, &node_modules).unwrap();
+        assert_eq!(result.packages_reused, 2);
+        assert!(node_modules.join("lib").is_symlink());
+        assert_eq!(fs::read_to_string(node_modules.join("lib/index.js")).unwrap(), "two");
+        assert_eq!(fs::read_to_string(lib.join("index.js")).unwrap(), "two");
+    }
+
+    #[test]
+    fn test_built_survives_reuse_only() {
+        let (_temp_dir, root, linker) = setup();
+        let node_modules = root.join("node_modules");
+
+        let packages = vec![package(&root, "a", "1.0.0"), package(&root, "b", "1.0.0")];
+        linker.install_node_modules(&packages, &node_modules).unwrap();
+        fs::write(node_modules.join("a/build.out"), "built").unwrap();
+        let mut state = InstalledState::load(&node_modules).unwrap();
+        state.mark_built("a");
+        state.mark_built("b");
+        state.save(&node_modules).unwrap();
+
+        // b is replaced, so its scripts have to run again
+        let packages = vec![package(&root, "a", "1.0.0"), package(&root, "b", "2.0.0")];
+        linker.install_node_modules(&packages, &node_modules).unwrap();
+        let state = InstalledState::load(&node_modules).unwrap();
+        assert!(state.is_built("a"));
+        assert!(!state.is_built("b"));
+        assert_eq!(fs::read_to_string(node_modules.join("a/build.out")).unwrap(), "built");
+    }
+
+    #[test]
+    fn test_failed_relink_keeps_previous_tree() {
+        let (_temp_dir, root, linker) = setup();
+        let node_modules = root.join("node_modules");
+
+        let packages = vec![package(&root, "a", "1.0.0"), package(&root, "b", "1.0.0")];
+        linker.install_node_modules(&packages, &node_modules).unwrap();
+
+        // c can't be imported, whatever was placed before it
+        let missing = PackageInfo::new("c".into(), "1.0.0".into(), root.join("does-not-exist"));
+        let packages = vec![package(&root, "a", "1.0.0"), package(&root, "b", "2.0.0"), missing];
+        assert!(linker.install_node_modules(&packages, &node_modules).is_err());
+
+        assert_eq!(fs::read_to_string(node_modules.join("b/version.txt")).unwrap(), "1.0.0");
+        assert!(node_modules.join("a/version.txt").exists());
+        let state = InstalledState::load(&node_modules).unwrap();
+        assert_eq!(state.packages["b"].version, "1.0.0");
+        assert!(!node_modules.with_file_name("node_modules.pea-staging").exists());
+    }
+
+    #[test]
+    fn test_layout_change_rebuilds() {
+        let (_temp_dir, root, linker) = setup();
+        let node_modules = root.join("node_modules");
+        let packages = vec![package(&root, "a", "1.0.0")];
+
+        linker.install_node_modules(&packages, &node_modules).unwrap();
+        let isolated = linker.with_node_linker(NodeLinker::Isolated);
+        let result = isolated.install_node_modules(&packages, &node_modules).unwrap();
+
+        assert_eq!(result.packages_reused, 0);
+        assert!(node_modules.join("a").is_symlink());
+
+        // Same settings again: everything is reused, including links
+        let result = isolated.install_node_modules(&packages, &node_modules).unwrap();
+        assert_eq!(result.packages_reused, 1);
+        assert_eq!(result.symlinks_created, 0);
+    }
+}
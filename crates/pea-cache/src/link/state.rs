@@ -0,0 +1,108 @@
+//! Installed-state manifest
+//!
+//! Every install records what it placed in node_modules, and from where, in
+//! `node_modules/.pea/state`. The next install diffs the desired tree against
+//! it to reuse the entries that did not change (see `relink.rs`).
+
+use camino::Utf8Path;
+use pea_core::error::PeaError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+
+use super::isolated::create_parent;
+use crate::CacheResult;
+
+/// Manifest location relative to node_modules
+pub const STATE_FILE: &str = ".pea/state";
+
+/// Bumped whenever the manifest format changes
+pub(super) const STATE_VERSION: u32 = 1;
+
+/// What an install left in node_modules
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstalledState {
+    /// Manifest format version
+    pub version: u32,
+    /// Settings the tree was laid out with
+    pub layout: LayoutKey,
+    /// Package directories, keyed by path relative to node_modules
+    pub packages: BTreeMap<String, InstalledPackage>,
+    /// Symlinks (including `.bin` entries), relative to node_modules
+    pub links: BTreeSet<String>,
+    /// Package directories whose lifecycle scripts have been dealt with
+    #[serde(default)]
+    pub built: BTreeSet<String>,
+}
+
+/// Settings that change the shape of node_modules
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayoutKey {
+    /// `node-linker` setting (`hoisted` or `isolated`)
+    pub node_linker: String,
+    /// `package-import-method` setting
+    pub import_method: String,
+    /// `hoist-pattern` globs
+    pub hoist_pattern: Vec<String>,
+    /// `public-hoist-pattern` globs
+    pub public_hoist_pattern: Vec<String>,
+}
+
+/// A package directory recorded in the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledPackage {
+    /// Package name
+    pub name: String,
+    /// Installed version
+    pub version: String,
+    /// Where the files were imported from
+    pub source: String,
+}
+
+impl InstalledState {
+    /// Load the manifest, or None if it is missing, unreadable or outdated
+    pub fn load(node_modules_dir: &Utf8Path) -> Option<Self> {
+        let content = fs::read_to_string(node_modules_dir.join(STATE_FILE)).ok()?;
+        let state: Self = serde_json::from_str(&content).ok()?;
+        (state.version == STATE_VERSION).then_some(state)
+    }
+
+    /// Write the manifest into node_modules
+    pub fn save(&self, node_modules_dir: &Utf8Path) -> CacheResult<()> {
+        let path = node_modules_dir.join(STATE_FILE);
+        create_parent(&path)?;
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| PeaError::io("Failed to serialize install state".to_string(), io::Error::other(e)))?;
+        fs::write(&path, content)
+            .map_err(|e| PeaError::io(format!("Failed to write {}", path), e))
+    }
+
+    /// Whether lifecycle scripts for the package at `path` have run (or weren't needed)
+    pub fn is_built(&self, path: &str) -> bool {
+        self.built.contains(path)
+    }
+
+    /// Record that the package at `path` needs no further lifecycle scripts
+    pub fn mark_built(&mut self, path: &str) {
+        self.built.insert(path.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino::Utf8PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_corrupt_state_is_ignored() {
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let node_modules = root.join("node_modules");
+        fs::create_dir_all(node_modules.join(".pea")).unwrap();
+        fs::write(node_modules.join(STATE_FILE), "not json").unwrap();
+
+        assert!(InstalledState::load(&node_modules).is_none());
+    }
+}
@@ -0,0 +1,188 @@
+//! Atomic replacement of node_modules
+//!
+//! The new tree is built in a sibling staging directory and swapped in with
+//! two renames, so an interrupted install leaves either the previous tree or
+//! the complete new one. Leftovers from an interrupted swap are recovered on
+//! the next install.
+//!
+//! When the installed-state manifest matches the current layout settings,
+//! packages that did not change are moved from the live tree into the
+//! staging directory rather than imported again, and moved back if the
+//! new tree can't be swapped in.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_core::error::PeaError;
+use std::fs;
+
+use super::linker::{Linker, NodeModulesResult, PackageInfo};
+use super::state::InstalledState;
+use crate::CacheResult;
+
+/// Suffix of the directory the new tree is built in
+const STAGING_SUFFIX: &str = ".pea-staging";
+/// Suffix the previous tree is parked under while the new one is renamed in
+const PREVIOUS_SUFFIX: &str = ".pea-old";
+
+impl Linker {
+    /// Build node_modules in a staging directory, reusing unchanged packages, and swap it in
+    pub fn install_node_modules(
+        &self,
+        packages: &[PackageInfo],
+        node_modules_dir: &Utf8Path,
+    ) -> CacheResult<NodeModulesResult> {
+        recover_interrupted_swap(node_modules_dir)?;
+
+        let previous = InstalledState::load(node_modules_dir);
+
+        // Build next to node_modules so the final rename stays on one filesystem
+        let staging = sibling(node_modules_dir, STAGING_SUFFIX);
+        let built = self.relink_node_modules(packages, &staging, previous.as_ref().map(|state| (state, node_modules_dir)));
+        let (result, carried) = match built {
+            Ok(built) => built,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = swap_in(&staging, node_modules_dir) {
+            // Packages moved out of the live tree go back before the rest is dropped
+            carried.restore();
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        Ok(result)
+    }
+}
+
+/// Restore or discard what an interrupted swap left behind
+///
+/// Returns true if a previous tree had to be moved back into place.
+pub fn recover_interrupted_swap(node_modules_dir: &Utf8Path) -> CacheResult<bool> {
+    let staging = sibling(node_modules_dir, STAGING_SUFFIX);
+    let previous = sibling(node_modules_dir, PREVIOUS_SUFFIX);
+    let mut restored = false;
+
+    // Interrupted between the two renames: the old tree is the last complete one
+    if previous.exists() && !node_modules_dir.exists() {
+        fs::rename(&previous, node_modules_dir)
+            .map_err(|e| PeaError::io(format!("Failed to restore {}", node_modules_dir), e))?;
+        restored = true;
+    }
+
+    for leftover in [&staging, &previous] {
+        if leftover.exists() {
+            fs::remove_dir_all(leftover)
+                .map_err(|e| PeaError::io(format!("Failed to remove {}", leftover), e))?;
+        }
+    }
+
+    Ok(restored)
+}
+
+/// Replace `node_modules_dir` with the complete tree at `staging`, which
+/// is left for the caller to clean up if that fails
+fn swap_in(staging: &Utf8Path, node_modules_dir: &Utf8Path) -> CacheResult<()> {
+    let previous = sibling(node_modules_dir, PREVIOUS_SUFFIX);
+    let had_previous = node_modules_dir.exists();
+
+    if had_previous {
+        fs::rename(node_modules_dir, &previous)
+            .map_err(|e| PeaError::io(format!("Failed to move {} aside", node_modules_dir), e))?;
+    }
+
+    if let Err(e) = fs::rename(staging, node_modules_dir) {
+        // Put the old tree back rather than leave the project without one
+        if had_previous {
+            let _ = fs::rename(&previous, node_modules_dir);
+        }
+        return Err(PeaError::io(format!("Failed to move new tree into {}", node_modules_dir), e));
+    }
+
+    // The new tree is in place; a failure here only leaves garbage for next time
+    if had_previous {
+        let _ = fs::remove_dir_all(&previous);
+    }
+
+    Ok(())
+}
+
+/// Path next to `dir` with `suffix` appended to its name
+fn sibling(dir: &Utf8Path, suffix: &str) -> Utf8PathBuf {
+    let name = dir.file_name().unwrap_or("node_modules");
+    dir.with_file_name(format!("{}{}", name, suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cas::CasStore;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn setup() -> (tempfile::TempDir, Utf8PathBuf, Linker) {
+        let temp_dir = tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let cas_store = Arc::new(CasStore::new(root.join("store")).unwrap());
+        (temp_dir, root, Linker::new(cas_store))
+    }
+
+    fn package(root: &Utf8Path, name: &str) -> PackageInfo {
+        let dir = root.join("src").join(name);
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        fs::write(dir.join("index.js"), name).unwrap();
+        fs::write(dir.join("bin/cli.js"), "#!/usr/bin/env node\n").unwrap();
+        PackageInfo::new(name.to_string(), "1.0.0".to_string(), dir)
+            .with_bin(name.to_string(), "bin/cli.js".to_string())
+            .as_direct()
+    }
+
+    #[test]
+    fn test_swap_replaces_previous_tree() {
+        let (_temp_dir, root, linker) = setup();
+        let node_modules = root.join("node_modules");
+
+        linker.install_node_modules(&[package(&root, "old")], &node_modules).unwrap();
+        let result = linker.install_node_modules(&[package(&root, "new")], &node_modules).unwrap();
+
+        assert_eq!(result.packages_linked, 1);
+        assert!(node_modules.join("new/index.js").exists());
+        assert!(!node_modules.join("old").exists());
+        assert!(!sibling(&node_modules, STAGING_SUFFIX).exists());
+        assert!(!sibling(&node_modules, PREVIOUS_SUFFIX).exists());
+
+        // Bin links survive the rename because they are relative
+        assert!(node_modules.join(".bin/new").exists());
+    }
+
+    #[test]
+    fn test_failed_build_keeps_previous_tree() {
+        let (_temp_dir, root, linker) = setup();
+        let node_modules = root.join("node_modules");
+        linker.install_node_modules(&[package(&root, "old")], &node_modules).unwrap();
+
+        let missing = PackageInfo::new("gone".into(), "1.0.0".into(), root.join("does-not-exist"));
+        assert!(linker.install_node_modules(&[missing], &node_modules).is_err());
+
+        assert!(node_modules.join("old/index.js").exists());
+        assert!(!sibling(&node_modules, STAGING_SUFFIX).exists());
+    }
+
+    #[test]
+    fn test_recover_interrupted_swap() {
+        let (_temp_dir, root, _linker) = setup();
+        let node_modules = root.join("node_modules");
+        let previous = sibling(&node_modules, PREVIOUS_SUFFIX);
+        let staging = sibling(&node_modules, STAGING_SUFFIX);
+        fs::create_dir_all(previous.join("old")).unwrap();
+        fs::create_dir_all(staging.join("half-built")).unwrap();
+
+        assert!(recover_interrupted_swap(&node_modules).unwrap());
+        assert!(node_modules.join("old").exists());
+        assert!(!previous.exists());
+        assert!(!staging.exists());
+
+        // Nothing left to recover the second time
+        assert!(!recover_interrupted_swap(&node_modules).unwrap());
+    }
+}
@@ -1,34 +1,126 @@
 //! Tarball extraction functionality
 //!
 //! This module provides safe tarball extraction with path validation
-//! to prevent directory traversal attacks.
+//! to prevent directory traversal attacks, and limits on size, entry count
+//! and compression ratio to defend against decompression bombs.
 
 use flate2::read::GzDecoder;
 use pea_core::error::PeaError;
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use tar::Archive;
 
+use super::limits::{CompressedReader, DecompressedReader, ExtractLimits, Meter};
 use crate::CacheResult;
 
-/// Extract a gzipped tarball to a destination directory
+/// What an extraction wrote
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExtractSummary {
+    /// Number of archive entries processed
+    pub entries: usize,
+    /// Decompressed size of the archive in bytes
+    pub bytes: u64,
+    /// Entries that were not extracted (devices, FIFOs, ...)
+    pub skipped: Vec<String>,
+}
+
+/// Extract a gzipped tarball to a destination directory with default limits
 pub fn extract_tarball<R: Read>(
     reader: R,
     dest_dir: &Path,
-) -> CacheResult<()> {
-    // Decompress gzip
-    let gz_decoder = GzDecoder::new(reader);
-    let mut archive = Archive::new(gz_decoder);
+) -> CacheResult<ExtractSummary> {
+    extract_tarball_with_limits(reader, dest_dir, &ExtractLimits::default())
+}
+
+/// Extract a gzipped tarball, failing if it exceeds `limits`
+pub fn extract_tarball_with_limits<R: Read>(
+    reader: R,
+    dest_dir: &Path,
+    limits: &ExtractLimits,
+) -> CacheResult<ExtractSummary> {
+    // Decompress gzip, metering both sides of the decoder
+    let meter = Rc::new(Meter::default());
+    let compressed = CompressedReader { inner: reader, meter: meter.clone() };
+    let decompressed = DecompressedReader {
+        inner: GzDecoder::new(compressed),
+        meter: meter.clone(),
+        limits: *limits,
+    };
+    let mut archive = Archive::new(decompressed);
     
     // Create destination directory
     fs::create_dir_all(dest_dir)
         .map_err(|e| PeaError::io("IO operation failed".to_string(), e))?;
     
-    // Extract entries
+    let mut summary = ExtractSummary::default();
+    let extracted = extract_entries(&mut archive, dest_dir, limits, &meter, &mut summary);
+    
+    // Report the limit that was hit rather than the IO error it surfaced as
+    if let Some(error) = meter.violation.take() {
+        return Err(error);
+    }
+    extracted?;
+    
+    summary.bytes = meter.decompressed.get();
+    Ok(summary)
+}
+
+/// The directory holding a package's files inside an extracted tarball
+///
+/// npm tarballs put everything under a single top-level folder, usually
+/// `package/` but not always (`@types/*` and older packages differ). If the
+/// extraction holds exactly one directory and nothing else, that is the root.
+pub fn package_root(extracted_dir: &Path) -> CacheResult<PathBuf> {
+    let mut entries = fs::read_dir(extracted_dir)
+        .map_err(|e| PeaError::io(format!("Failed to read {}", extracted_dir.display()), e))?
+        .filter_map(Result::ok);
+    
+    match (entries.next(), entries.next()) {
+        (Some(only), None) if only.file_type().is_ok_and(|kind| kind.is_dir()) => Ok(only.path()),
+        _ => Ok(extracted_dir.to_path_buf()),
+    }
+}
+
+/// Contents of the package.json in a gzipped package tarball, without
+/// extracting anything else
+///
+/// Like `package_root`, accepts the manifest at the top level or one folder down.
+pub fn package_manifest<R: Read>(reader: R) -> CacheResult<Vec<u8>> {
+    let mut archive = Archive::new(GzDecoder::new(reader));
+    let entries = archive.entries().map_err(|e| PeaError::io("Failed to read tarball".to_string(), e))?;
+    for entry_result in entries {
+        let mut entry = entry_result.map_err(|e| PeaError::io("Failed to read tarball".to_string(), e))?;
+        let is_manifest = entry.path().is_ok_and(|path| {
+            let components: Vec<_> = path.components().collect();
+            components.last().is_some_and(|last| last.as_os_str() == "package.json") && components.len() <= 2
+        });
+        if is_manifest {
+            let mut manifest = Vec::new();
+            entry.read_to_end(&mut manifest)
+                .map_err(|e| PeaError::io("Failed to read package.json from tarball".to_string(), e))?;
+            return Ok(manifest);
+        }
+    }
+    Err(PeaError::JsonParse { message: "Tarball has no package.json".to_string() })
+}
+
+fn extract_entries<R: Read>(
+    archive: &mut Archive<R>,
+    dest_dir: &Path,
+    limits: &ExtractLimits,
+    meter: &Meter,
+    summary: &mut ExtractSummary,
+) -> CacheResult<()> {
     for entry_result in archive.entries().map_err(|e| PeaError::io("IO operation failed".to_string(), e))? {
         let mut entry = entry_result.map_err(|e| PeaError::io("IO operation failed".to_string(), e))?;
         
+        summary.entries += 1;
+        if let Some(error) = limits.exceeded(summary.entries, meter.decompressed.get(), meter.compressed.get()) {
+            return Err(error);
+        }
+        
         // Get entry path and validate it
         let entry_path = entry.path().map_err(|e| PeaError::io("IO operation failed".to_string(), e))?;
         let safe_path = validate_extract_path(&entry_path, dest_dir)?;
@@ -38,7 +130,7 @@ pub fn extract_tarball<R: Read>(
         let mode = entry.header().mode().ok();
         
         match entry_type {
-            tar::EntryType::Regular => {
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
                 // Extract regular file
                 extract_regular_file(&mut entry, &safe_path)?;
             }
@@ -47,12 +139,21 @@ pub fn extract_tarball<R: Read>(
                 fs::create_dir_all(&safe_path)
                     .map_err(|e| PeaError::io("IO operation failed".to_string(), e))?;
             }
-            tar::EntryType::Symlink | tar::EntryType::Link => {
+            tar::EntryType::Symlink => {
                 // Handle symlinks safely (validate target)
                 extract_symlink(&mut entry, &safe_path, dest_dir)?;
             }
+            tar::EntryType::Link => {
+                // Hardlink targets are archive paths of earlier entries
+                extract_hardlink(&mut entry, &safe_path, dest_dir)?;
+            }
+            tar::EntryType::XGlobalHeader | tar::EntryType::XHeader => {
+                // Metadata only (e.g. the pax header `git archive` writes)
+                continue;
+            }
             _ => {
-                // Skip other entry types (char devices, block devices, etc.)
+                // Char/block devices, FIFOs and the like have no place in a package
+                summary.skipped.push(entry_path.display().to_string());
                 continue;
             }
         }
@@ -72,6 +173,7 @@ pub fn extract_tarball<R: Read>(
     
     Ok(())
 }
+
 /// Validate extraction path to prevent directory traversal
 fn validate_extract_path(entry_path: &Path, dest_dir: &Path) -> CacheResult<PathBuf> {
     // Normalize the path and check for directory traversal
@@ -136,6 +238,41 @@ fn extract_regular_file<R: Read>(
     Ok(())
 }
 
+/// Extract a hardlink entry as a copy of the earlier entry it names
+fn extract_hardlink<R: Read>(
+    entry: &mut tar::Entry<R>,
+    dest_path: &Path,
+    dest_dir: &Path,
+) -> CacheResult<()> {
+    let link_name = entry.link_name()
+        .map_err(|e| PeaError::io("IO operation failed".to_string(), e))?
+        .ok_or_else(|| PeaError::IntegrityFailure {
+            package: "tarball".to_string(),
+            expected: "hardlink target".to_string(),
+            actual: format!("hardlink {} without target", dest_path.display()),
+        })?;
+    let source = validate_extract_path(&link_name, dest_dir)?;
+    
+    // Only files extracted earlier in this archive can be linked to
+    if !source.is_file() || source.is_symlink() {
+        return Err(PeaError::IntegrityFailure {
+            package: "tarball".to_string(),
+            expected: "hardlink to an earlier file in the archive".to_string(),
+            actual: format!("hardlink to missing {}", link_name.display()),
+        });
+    }
+    
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| PeaError::io("IO operation failed".to_string(), e))?;
+    }
+    
+    // Copy rather than link so the two paths can't alias each other later
+    fs::copy(&source, dest_path)
+        .map_err(|e| PeaError::io("Failed to extract hardlink".to_string(), e))?;
+    Ok(())
+}
+
 /// Extract symlink safely (validate target doesn't escape)
 fn extract_symlink<R: Read>(
     entry: &mut tar::Entry<R>,
@@ -265,4 +402,122 @@ mod tests {
         let _result = extract_tarball(cursor, &extract_dir);
         // Test passes if we don't panic
     }
-}
\ No newline at end of file
+
+    /// Build a gzipped tarball from (path, contents) pairs
+    fn tarball(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tarball_data = Vec::new();
+        {
+            let gz_encoder = GzEncoder::new(&mut tarball_data, Compression::default());
+            let mut tar_builder = Builder::new(gz_encoder);
+            for (path, contents) in files {
+                let mut header = tar::Header::new_gnu();
+                header.set_path(path).unwrap();
+                header.set_size(contents.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                tar_builder.append(&header, *contents).unwrap();
+            }
+            tar_builder.finish().unwrap();
+        }
+        tarball_data
+    }
+
+    #[test]
+    fn test_hardlink_entries_resolve_inside_archive() {
+        let temp_dir = tempdir().unwrap();
+        let extract_dir = temp_dir.path().join("extract");
+        
+        let mut tarball_data = Vec::new();
+        {
+            let gz_encoder = GzEncoder::new(&mut tarball_data, Compression::default());
+            let mut tar_builder = Builder::new(gz_encoder);
+            let mut header = tar::Header::new_gnu();
+            header.set_path("package/original.js").unwrap();
+            header.set_size(5);
+            header.set_cksum();
+            tar_builder.append(&header, "hello".as_bytes()).unwrap();
+            
+            let mut link = tar::Header::new_gnu();
+            link.set_entry_type(tar::EntryType::Link);
+            link.set_path("package/lib/alias.js").unwrap();
+            link.set_link_name("package/original.js").unwrap();
+            link.set_size(0);
+            link.set_cksum();
+            tar_builder.append(&link, std::io::empty()).unwrap();
+            tar_builder.finish().unwrap();
+        }
+        
+        extract_tarball(tarball_data.as_slice(), &extract_dir).unwrap();
+        assert_eq!(fs::read_to_string(extract_dir.join("package/lib/alias.js")).unwrap(), "hello");
+        
+        // A link to something the archive never contained is rejected
+        let mut tarball_data = Vec::new();
+        {
+            let gz_encoder = GzEncoder::new(&mut tarball_data, Compression::default());
+            let mut tar_builder = Builder::new(gz_encoder);
+            let mut link = tar::Header::new_gnu();
+            link.set_entry_type(tar::EntryType::Link);
+            link.set_path("package/alias.js").unwrap();
+            link.set_link_name("package/missing.js").unwrap();
+            link.set_size(0);
+            link.set_cksum();
+            tar_builder.append(&link, std::io::empty()).unwrap();
+            tar_builder.finish().unwrap();
+        }
+        assert!(extract_tarball(tarball_data.as_slice(), &temp_dir.path().join("other")).is_err());
+    }
+
+    #[test]
+    fn test_package_root_detection() {
+        let temp_dir = tempdir().unwrap();
+        
+        // @types/* tarballs use the package name as their root folder
+        let types_dir = temp_dir.path().join("types");
+        extract_tarball(tarball(&[("node/index.d.ts", b"export {};")]).as_slice(), &types_dir).unwrap();
+        assert_eq!(package_root(&types_dir).unwrap(), types_dir.join("node"));
+        
+        // Without a single root folder the extraction itself is the root
+        let flat_dir = temp_dir.path().join("flat");
+        extract_tarball(tarball(&[("index.js", b""), ("lib/a.js", b"")]).as_slice(), &flat_dir).unwrap();
+        assert_eq!(package_root(&flat_dir).unwrap(), flat_dir);
+    }
+
+    #[test]
+    fn test_extract_limits() {
+        let temp_dir = tempdir().unwrap();
+        let data = tarball(&[("package/a.js", &[b'a'; 1024]), ("package/b.js", b"b")]);
+        
+        let summary = extract_tarball(data.as_slice(), &temp_dir.path().join("ok")).unwrap();
+        assert_eq!(summary.entries, 2);
+        assert!(summary.bytes > 1024);
+        
+        let too_big = ExtractLimits::default().with_max_size(512);
+        let error = extract_tarball_with_limits(data.as_slice(), &temp_dir.path().join("big"), &too_big).unwrap_err();
+        assert!(matches!(error, PeaError::ExtractLimitExceeded { ref limit, .. } if limit.contains("bytes")));
+        
+        let too_many = ExtractLimits::default().with_max_entries(1);
+        let error = extract_tarball_with_limits(data.as_slice(), &temp_dir.path().join("many"), &too_many).unwrap_err();
+        assert!(matches!(error, PeaError::ExtractLimitExceeded { ref limit, .. } if limit.contains("entries")));
+    }
+
+    #[test]
+    fn test_compression_ratio_limit() {
+        let temp_dir = tempdir().unwrap();
+        let zeros = vec![0u8; 32 * 1024 * 1024];
+        let bomb = tarball(&[("package/zeros.bin", &zeros)]);
+        
+        let error = extract_tarball(bomb.as_slice(), &temp_dir.path().join("bomb")).unwrap_err();
+        assert!(matches!(error, PeaError::ExtractLimitExceeded { ref limit, .. } if limit.contains("ratio")));
+        
+        let relaxed = ExtractLimits::default().with_max_ratio(u64::MAX);
+        assert!(extract_tarball_with_limits(bomb.as_slice(), &temp_dir.path().join("ok"), &relaxed).is_ok());
+    }
+
+    #[test]
+    fn test_package_manifest_without_extracting() {
+        let nested = tarball(&[("package/index.js", b"x"), ("package/package.json", br#"{"name":"a"}"#)]);
+        assert_eq!(package_manifest(nested.as_slice()).unwrap(), br#"{"name":"a"}"#);
+        let deep = tarball(&[("package/lib/package.json", b"{}")]);
+        assert!(package_manifest(deep.as_slice()).is_err());
+    }
+}
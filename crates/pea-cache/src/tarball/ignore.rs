@@ -0,0 +1,232 @@
+//! Ignore rules for packing
+//!
+//! Implements the gitignore dialect used by `.npmignore` and `.gitignore`:
+//! `#` comments, `!` negation, a trailing `/` for directories only, and
+//! patterns containing a `/` anchored to the directory of the ignore file.
+//! Patterns without a slash match the file name at any depth.
+
+use pea_core::error::PeaError;
+use std::fs;
+use std::path::Path;
+
+use crate::CacheResult;
+
+/// Ignore files consulted in each directory, first one found wins
+const IGNORE_FILES: [&str; 2] = [".npmignore", ".gitignore"];
+
+/// One line of an ignore file
+#[derive(Debug, Clone)]
+pub(crate) struct Rule {
+    pattern: Vec<String>,
+    /// `!` rule: re-includes what it matches
+    pub(crate) negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl Rule {
+    /// Parse a line, or None for blanks and comments
+    ///
+    /// `always_anchored` anchors the pattern even without a slash, as for
+    /// `files` entries, which may also start with `./`.
+    pub(crate) fn parse(line: &str, always_anchored: bool) -> Option<Self> {
+        let line = if always_anchored { line.trim_start_matches("./") } else { line };
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, mut line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        if let Some(escaped) = line.strip_prefix('\\').filter(|rest| rest.starts_with(['!', '#'])) {
+            line = escaped;
+        }
+
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+        let anchored = always_anchored || line.contains('/');
+        let line = line.trim_start_matches('/');
+        if line.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            pattern: line.split('/').map(str::to_string).collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the rule matches `path`, relative to the rule's base directory
+    pub(crate) fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            let segments: Vec<&str> = path.split('/').collect();
+            return match_segments(&self.pattern, &segments);
+        }
+        let name = path.rsplit('/').next().unwrap_or(path);
+        match_segments(&self.pattern, &[name])
+    }
+}
+
+/// The rules of one ignore file and the directory they apply to
+#[derive(Debug)]
+pub(crate) struct IgnoreFile {
+    /// Directory of the ignore file relative to the package root ("" for the root)
+    base: String,
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    /// Load `.npmignore`, falling back to `.gitignore`, from `dir`
+    pub(crate) fn load(dir: &Path, base: &str) -> CacheResult<Option<Self>> {
+        for name in IGNORE_FILES {
+            let path = dir.join(name);
+            if !path.is_file() {
+                continue;
+            }
+            let content = fs::read_to_string(&path)
+                .map_err(|e| PeaError::io(format!("Failed to read {}", path.display()), e))?;
+            return Ok(Some(Self {
+                base: base.to_string(),
+                rules: content.lines().filter_map(|line| Rule::parse(line, false)).collect(),
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Some(true) if ignored, Some(false) if re-included, None if no rule matched
+    pub(crate) fn verdict(&self, path: &str, is_dir: bool) -> Option<bool> {
+        let relative = if self.base.is_empty() {
+            path
+        } else {
+            path.strip_prefix(&self.base)?.strip_prefix('/')?
+        };
+        self.rules.iter()
+            .rev()
+            .find(|rule| rule.matches(relative, is_dir))
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// Match pattern segments against path segments, with `**` spanning directories
+fn match_segments(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| match_segments(rest, &path[skip..]))
+        }
+        Some((first, rest)) => match path.split_first() {
+            Some((segment, path_rest)) => {
+                match_glob(first.as_bytes(), segment.as_bytes()) && match_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Match one path segment against `*`, `?` and `[...]` wildcards
+fn match_glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') => (0..=text.len()).any(|skip| match_glob(&pattern[1..], &text[skip..])),
+        Some(b'?') => !text.is_empty() && match_glob(&pattern[1..], &text[1..]),
+        Some(b'[') => match (text.first(), match_class(&pattern[1..])) {
+            (Some(&c), Some((class, negated, rest))) => {
+                class_contains(class, c) != negated && match_glob(rest, &text[1..])
+            }
+            // An unterminated class is a literal '['
+            (Some(&c), None) => c == b'[' && match_glob(&pattern[1..], &text[1..]),
+            (None, _) => false,
+        },
+        Some(b'\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && match_glob(&pattern[2..], &text[1..])
+        }
+        Some(&c) => text.first() == Some(&c) && match_glob(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Split a character class after its `[` into (members, negated, rest of pattern)
+fn match_class(pattern: &[u8]) -> Option<(&[u8], bool, &[u8])> {
+    let negated = matches!(pattern.first(), Some(b'!') | Some(b'^'));
+    let body = if negated { &pattern[1..] } else { pattern };
+    // A leading ']' is a member, not the end of the class
+    let end = body.iter().skip(1).position(|&c| c == b']')? + 1;
+    Some((&body[..end], negated, &body[end + 1..]))
+}
+
+fn class_contains(class: &[u8], c: u8) -> bool {
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == b'-' {
+            if class[i] <= c && c <= class[i + 2] {
+                return true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(line: &str) -> Rule {
+        Rule::parse(line, false).unwrap()
+    }
+
+    #[test]
+    fn test_unanchored_matches_any_depth() {
+        assert!(rule("*.log").matches("debug.log", false));
+        assert!(rule("*.log").matches("logs/deep/debug.log", false));
+        assert!(!rule("*.log").matches("debug.log.txt", false));
+        assert!(rule("test").matches("src/test", true));
+    }
+
+    #[test]
+    fn test_anchored_and_globstar() {
+        assert!(rule("/build").matches("build", true));
+        assert!(!rule("/build").matches("src/build", true));
+        assert!(rule("docs/*.md").matches("docs/a.md", false));
+        assert!(!rule("docs/*.md").matches("docs/sub/a.md", false));
+        assert!(rule("**/fixtures").matches("a/b/fixtures", true));
+        assert!(rule("src/**/*.test.js").matches("src/x.test.js", false));
+        assert!(rule("src/**/*.test.js").matches("src/a/b/x.test.js", false));
+    }
+
+    #[test]
+    fn test_dir_only_negation_and_classes() {
+        assert!(!rule("out/").matches("out", false));
+        assert!(rule("out/").matches("out", true));
+        assert!(rule("!keep.js").negated);
+        assert!(rule("file[0-9].txt").matches("file7.txt", false));
+        assert!(!rule("file[!0-9].txt").matches("file7.txt", false));
+        assert!(rule("\\#notes").matches("#notes", false));
+        assert!(Rule::parse("# comment", false).is_none());
+        assert!(Rule::parse("   ", false).is_none());
+    }
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        let file = IgnoreFile {
+            base: "lib".to_string(),
+            rules: ["*.js", "!keep.js"].into_iter().filter_map(|line| Rule::parse(line, false)).collect(),
+        };
+        assert_eq!(file.verdict("lib/drop.js", false), Some(true));
+        assert_eq!(file.verdict("lib/keep.js", false), Some(false));
+        assert_eq!(file.verdict("lib/readme.md", false), None);
+        // Rules only apply below their own directory
+        assert_eq!(file.verdict("drop.js", false), None);
+    }
+}
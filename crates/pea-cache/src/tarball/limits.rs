@@ -0,0 +1,122 @@
+//! Extraction limits
+//!
+//! Tarballs come from the network, so extraction enforces a ceiling on the
+//! unpacked size, the number of entries and the compression ratio to defend
+//! against decompression bombs. Sizes are measured on the decompressed
+//! stream as it is read, so a single oversized entry is caught mid-stream.
+
+use pea_core::error::PeaError;
+use std::cell::{Cell, RefCell};
+use std::io::{self, Read};
+use std::rc::Rc;
+
+/// Ratios are only checked once this much has been decompressed, since tar
+/// headers of tiny packages compress extremely well
+const RATIO_FLOOR: u64 = 16 * 1024 * 1024;
+
+/// Limits applied while extracting a tarball
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractLimits {
+    /// Maximum decompressed size in bytes
+    pub max_size: u64,
+    /// Maximum number of archive entries
+    pub max_entries: usize,
+    /// Maximum decompressed / compressed size ratio
+    pub max_ratio: u64,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_size: 2 * 1024 * 1024 * 1024,
+            max_entries: 200_000,
+            max_ratio: 100,
+        }
+    }
+}
+
+impl ExtractLimits {
+    /// Set the maximum decompressed size in bytes
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Set the maximum number of archive entries
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Set the maximum compression ratio
+    pub fn with_max_ratio(mut self, max_ratio: u64) -> Self {
+        self.max_ratio = max_ratio;
+        self
+    }
+}
+
+impl ExtractLimits {
+    /// The error for the first limit that `entries` entries unpacking to
+    /// `bytes` bytes from a `compressed`-byte tarball exceed, if any
+    pub(crate) fn exceeded(&self, entries: usize, bytes: u64, compressed: u64) -> Option<PeaError> {
+        let (limit, actual) = if entries > self.max_entries {
+            (format!("at most {} entries", self.max_entries), format!("more than {} entries", self.max_entries))
+        } else if bytes > self.max_size {
+            (format!("at most {} unpacked bytes", self.max_size), format!("more than {} bytes", self.max_size))
+        } else if bytes > RATIO_FLOOR && bytes / compressed.max(1) > self.max_ratio {
+            let compressed = compressed.max(1);
+            (
+                format!("compression ratio at most {}:1", self.max_ratio),
+                format!("ratio above {}:1 ({} bytes from {})", bytes / compressed, bytes, compressed),
+            )
+        } else {
+            return None;
+        };
+        Some(PeaError::ExtractLimitExceeded { limit, actual })
+    }
+}
+
+/// Byte counts shared between the compressed and decompressed readers
+#[derive(Default)]
+pub(crate) struct Meter {
+    pub(crate) compressed: Cell<u64>,
+    pub(crate) decompressed: Cell<u64>,
+    /// Limit the decompressed reader hit, to report instead of the bare IO error
+    pub(crate) violation: RefCell<Option<PeaError>>,
+}
+
+/// Counts bytes read from the compressed input
+pub(crate) struct CompressedReader<R> {
+    pub(crate) inner: R,
+    pub(crate) meter: Rc<Meter>,
+}
+
+impl<R: Read> Read for CompressedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.meter.compressed.set(self.meter.compressed.get() + read as u64);
+        Ok(read)
+    }
+}
+
+/// Counts decompressed bytes and fails once a limit is exceeded
+pub(crate) struct DecompressedReader<R> {
+    pub(crate) inner: R,
+    pub(crate) meter: Rc<Meter>,
+    pub(crate) limits: ExtractLimits,
+}
+
+impl<R: Read> Read for DecompressedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let total = self.meter.decompressed.get() + read as u64;
+        self.meter.decompressed.set(total);
+
+        if let Some(error) = self.limits.exceeded(0, total, self.meter.compressed.get()) {
+            let message = error.to_string();
+            self.meter.violation.replace(Some(error));
+            return Err(io::Error::other(message));
+        }
+        Ok(read)
+    }
+}
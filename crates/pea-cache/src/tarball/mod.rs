@@ -16,7 +16,12 @@ use crate::CacheResult;
 
 pub mod extract;
 pub mod create;
+pub mod limits;
+pub mod pack;
+mod ignore;
 
 // Re-export main functions
-pub use extract::extract_tarball;
-pub use create::create_tarball;
\ No newline at end of file
+pub use extract::{extract_tarball, extract_tarball_with_limits, package_manifest, package_root, ExtractSummary};
+pub use limits::ExtractLimits;
+pub use create::create_tarball;
+pub use pack::{integrity, pack_list, shasum, write_package_tarball, PackedFile};
\ No newline at end of file
@@ -0,0 +1,363 @@
+//! Package tarball creation for `pea pack` and publishing
+//!
+//! Selects files the way npm does (the `files` field, then `.npmignore`
+//! falling back to `.gitignore` in every directory, with package.json,
+//! README and LICENSE always included) and writes a `package/`-rooted
+//! tarball. Entries are sorted and their metadata normalized, so the same
+//! source always produces byte-identical output.
+
+use base64::{engine::general_purpose, Engine as _};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use pea_core::error::PeaError;
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tar::{Builder, EntryType, Header};
+
+use super::ignore::{IgnoreFile, Rule};
+use crate::CacheResult;
+
+/// Timestamp npm gives every entry (1985-10-26T08:15:00Z)
+const NORMALIZED_MTIME: u64 = 499_162_500;
+
+/// Names never packed, at any depth
+const ALWAYS_EXCLUDED: [&str; 19] = [
+    ".git", ".svn", ".hg", "CVS", "node_modules", ".pea", ".npmrc", ".npmignore",
+    ".gitignore", ".DS_Store", "._*", ".*.swp", "*.orig", "npm-debug.log",
+    "package-lock.json", "yarn.lock", "pnpm-lock.yaml", "bun.lockb", "pea.lock",
+];
+
+/// A file written to a package tarball
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedFile {
+    /// Path inside the package, without the `package/` prefix
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Whether the entry was marked executable
+    pub executable: bool,
+}
+
+/// List the files of the package at `package_dir` that belong in its tarball
+///
+/// `files` is the manifest's `files` field; `entry_points` are the `main`
+/// and bin paths, which are packed even when ignored. Paths are relative,
+/// `/`-separated and sorted.
+pub fn pack_list(package_dir: &Path, files: &[String], entry_points: &[String]) -> CacheResult<Vec<String>> {
+    let excluded: Vec<Rule> = ALWAYS_EXCLUDED.iter().filter_map(|pattern| Rule::parse(pattern, false)).collect();
+    let selection: Vec<Rule> = files.iter().filter_map(|pattern| Rule::parse(pattern, true)).collect();
+    let mut walker = Walker {
+        root: package_dir,
+        excluded,
+        selection: (!files.is_empty()).then_some(selection),
+        ignores: Vec::new(),
+        found: Vec::new(),
+    };
+    walker.walk(package_dir, "")?;
+
+    let mut found = walker.found;
+    for entry in entry_points {
+        let entry = entry.trim_start_matches("./").to_string();
+        if package_dir.join(&entry).is_file() {
+            found.push(entry);
+        }
+    }
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+/// Write a deterministic `package/`-rooted tarball of `paths`
+///
+/// When `manifest` is given it is packed as package.json in place of the
+/// file on disk, e.g. a manifest generated from pea.toml.
+pub fn write_package_tarball<W: Write>(
+    writer: W,
+    package_dir: &Path,
+    paths: &[String],
+    manifest: Option<&[u8]>,
+) -> CacheResult<Vec<PackedFile>> {
+    let mut paths = paths.to_vec();
+    if manifest.is_some() && !paths.iter().any(|path| path == "package.json") {
+        paths.push("package.json".to_string());
+    }
+    paths.sort();
+
+    let mut builder = Builder::new(GzEncoder::new(writer, Compression::default()));
+    let mut packed = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let (data, executable) = match manifest {
+            Some(manifest) if path == "package.json" => (manifest.to_vec(), false),
+            _ => read_packed_file(&package_dir.join(path))?,
+        };
+
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(data.len() as u64);
+        header.set_mode(if executable { 0o755 } else { 0o644 });
+        header.set_mtime(NORMALIZED_MTIME);
+        header.set_uid(0);
+        header.set_gid(0);
+        builder.append_data(&mut header, format!("package/{}", path), data.as_slice())
+            .map_err(|e| PeaError::io(format!("Failed to pack {}", path), e))?;
+
+        packed.push(PackedFile { path: path.clone(), size: data.len() as u64, executable });
+    }
+
+    let encoder = builder.into_inner()
+        .map_err(|e| PeaError::io("Failed to finish package tarball".to_string(), e))?;
+    encoder.finish()
+        .map_err(|e| PeaError::io("Failed to finish package tarball".to_string(), e))?;
+    Ok(packed)
+}
+
+/// Subresource integrity string (`sha512-<base64>`) of `bytes`
+pub fn integrity(bytes: &[u8]) -> String {
+    format!("sha512-{}", general_purpose::STANDARD.encode(Sha512::digest(bytes)))
+}
+
+/// Hex SHA-1 of `bytes`, the legacy `shasum` registries still expect
+pub fn shasum(bytes: &[u8]) -> String {
+    hex::encode(Sha1::digest(bytes))
+}
+
+/// Read a file to pack, and whether any execute bit is set
+fn read_packed_file(path: &Path) -> CacheResult<(Vec<u8>, bool)> {
+    let data = fs::read(path)
+        .map_err(|e| PeaError::io(format!("Failed to read {}", path.display()), e))?;
+
+    #[cfg(unix)]
+    let executable = {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path)
+            .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    };
+    #[cfg(not(unix))]
+    let executable = false;
+
+    Ok((data, executable))
+}
+
+/// Recursive directory walk applying the selection and ignore rules
+struct Walker<'a> {
+    root: &'a Path,
+    excluded: Vec<Rule>,
+    /// Patterns from `files`, or None to pack everything not ignored
+    selection: Option<Vec<Rule>>,
+    /// Ignore files of the directories currently being walked, outermost first
+    ignores: Vec<IgnoreFile>,
+    found: Vec<String>,
+}
+
+impl Walker<'_> {
+    fn walk(&mut self, dir: &Path, relative: &str) -> CacheResult<()> {
+        // `files` replaces the root ignore file, but nested ones still apply
+        let pushed = if relative.is_empty() && self.selection.is_some() {
+            false
+        } else {
+            match IgnoreFile::load(dir, relative)? {
+                Some(ignore) => {
+                    self.ignores.push(ignore);
+                    true
+                }
+                None => false,
+            }
+        };
+
+        let mut entries = fs::read_dir(dir)
+            .map_err(|e| PeaError::io(format!("Failed to read {}", dir.display()), e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PeaError::io(format!("Failed to read {}", dir.display()), e))?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let path = if relative.is_empty() { name.clone() } else { format!("{}/{}", relative, name) };
+            // Symlinks aren't packed, like npm
+            let file_type = entry.file_type()
+                .map_err(|e| PeaError::io(format!("Failed to stat {}", path), e))?;
+            let is_dir = file_type.is_dir();
+            if !is_dir && !file_type.is_file() {
+                continue;
+            }
+            if self.excluded.iter().any(|rule| rule.matches(&name, is_dir)) {
+                continue;
+            }
+
+            if is_dir {
+                if !self.is_ignored(&path, true) {
+                    self.walk(&self.root.join(&path), &path)?;
+                }
+            } else if (relative.is_empty() && is_always_included(&name))
+                || (self.is_selected(&path) && !self.is_ignored(&path, false))
+            {
+                self.found.push(path);
+            }
+        }
+
+        if pushed {
+            self.ignores.pop();
+        }
+        Ok(())
+    }
+
+    /// Whether the innermost matching ignore rule excludes `path`
+    fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.ignores.iter()
+            .rev()
+            .find_map(|ignore| ignore.verdict(path, is_dir))
+            .unwrap_or(false)
+    }
+
+    /// Whether `files` selects `path` itself or one of its directories
+    fn is_selected(&self, path: &str) -> bool {
+        let Some(selection) = &self.selection else {
+            return true;
+        };
+        let mut selected = false;
+        for rule in selection {
+            let mut prefixes = path.match_indices('/').map(|(i, _)| (&path[..i], true));
+            let matched = prefixes.any(|(prefix, is_dir)| rule.matches(prefix, is_dir))
+                || rule.matches(path, false);
+            if matched {
+                selected = !rule.negated;
+            }
+        }
+        selected
+    }
+}
+
+/// package.json, README and LICENSE/LICENCE at the package root
+fn is_always_included(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    lower == "package.json"
+        || ["readme", "license", "licence"].iter().any(|prefix| lower.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tarball::extract::extract_tarball;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn package(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let temp_dir = tempdir().unwrap();
+        write(temp_dir.path(), "package.json", r#"{"name":"pkg","version":"1.0.0"}"#);
+        for (path, content) in files {
+            write(temp_dir.path(), path, content);
+        }
+        temp_dir
+    }
+
+    fn list(dir: &Path, files: &[&str]) -> Vec<String> {
+        let files: Vec<String> = files.iter().map(|file| file.to_string()).collect();
+        pack_list(dir, &files, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_npmignore_beats_gitignore_and_defaults_apply() {
+        let temp_dir = package(&[
+            ("index.js", ""),
+            ("debug.log", ""),
+            ("dist/out.js", ""),
+            ("node_modules/dep/index.js", ""),
+            (".gitignore", "dist\n"),
+            (".npmignore", "*.log\n"),
+            ("yarn.lock", ""),
+        ]);
+
+        // .npmignore wins over .gitignore, so dist is packed but logs are not
+        assert_eq!(list(temp_dir.path(), &[]), ["dist/out.js", "index.js", "package.json"]);
+    }
+
+    #[test]
+    fn test_nested_ignore_and_negation() {
+        let temp_dir = package(&[
+            ("lib/a.js", ""),
+            ("lib/a.test.js", ""),
+            ("lib/keep.test.js", ""),
+            ("lib/.npmignore", "*.test.js\n!keep.test.js\n"),
+            ("fixtures/big.bin", ""),
+            (".npmignore", "/fixtures/\n"),
+        ]);
+
+        assert_eq!(list(temp_dir.path(), &[]), ["lib/a.js", "lib/keep.test.js", "package.json"]);
+    }
+
+    #[test]
+    fn test_files_field_with_always_included() {
+        let temp_dir = package(&[
+            ("README.md", ""),
+            ("LICENSE", ""),
+            ("src/index.ts", ""),
+            ("dist/index.js", ""),
+            ("dist/index.js.map", ""),
+            ("bin/cli.js", ""),
+            (".npmignore", "dist\n"),
+        ]);
+
+        let files = vec!["dist".to_string(), "!dist/*.map".to_string()];
+        let packed = pack_list(temp_dir.path(), &files, &["./bin/cli.js".to_string()]).unwrap();
+
+        // The root .npmignore doesn't apply once `files` is set
+        assert_eq!(packed, ["LICENSE", "README.md", "bin/cli.js", "dist/index.js", "package.json"]);
+    }
+
+    #[test]
+    fn test_tarball_is_reproducible() {
+        let temp_dir = package(&[("b.js", "b"), ("a/z.js", "z"), ("a/y.js", "y")]);
+        let paths = pack_list(temp_dir.path(), &[], &[]).unwrap();
+
+        let mut first = Vec::new();
+        write_package_tarball(&mut first, temp_dir.path(), &paths, None).unwrap();
+        // Touching a file must not change the output
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        write(temp_dir.path(), "b.js", "b");
+        let mut second = Vec::new();
+        write_package_tarball(&mut second, temp_dir.path(), &paths, None).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(integrity(&first), integrity(&second));
+        assert!(integrity(&first).starts_with("sha512-"));
+        assert_eq!(shasum(&first).len(), 40);
+    }
+
+    #[test]
+    fn test_tarball_entries_are_normalized() {
+        let temp_dir = package(&[("z.js", "z"), ("a.js", "a")]);
+        let paths = pack_list(temp_dir.path(), &[], &[]).unwrap();
+        let manifest = br#"{"name":"generated","version":"2.0.0"}"#;
+
+        let mut bytes = Vec::new();
+        let packed = write_package_tarball(&mut bytes, temp_dir.path(), &paths, Some(manifest)).unwrap();
+        assert_eq!(packed.iter().map(|file| file.path.as_str()).collect::<Vec<_>>(), ["a.js", "package.json", "z.js"]);
+
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(Cursor::new(&bytes)));
+        for entry in archive.entries().unwrap() {
+            let entry = entry.unwrap();
+            let header = entry.header();
+            assert!(entry.path().unwrap().starts_with("package"));
+            assert_eq!(header.mtime().unwrap(), NORMALIZED_MTIME);
+            assert_eq!((header.uid().unwrap(), header.gid().unwrap()), (0, 0));
+            assert_eq!(header.mode().unwrap(), 0o644);
+        }
+
+        let extract_dir = temp_dir.path().join("extract");
+        extract_tarball(Cursor::new(bytes), &extract_dir).unwrap();
+        let content = fs::read(extract_dir.join("package/package.json")).unwrap();
+        assert_eq!(content, manifest);
+    }
+}
@@ -1,18 +1,126 @@
 //! `pea add` command implementation.
 //!
-//! Adds a new dependency to the project.
+//! Adds dependencies to pea.toml (or package.json when the project has no
+//! pea.toml), keeping the file's comments, ordering and indentation, then
+//! installs and updates pea.lock.
 
-use pea_core::error::PeaResult;
-use super::CommandContext;
+use camino::Utf8PathBuf;
+use pea_config::merge::ConfigSource;
+use pea_config::{ConfigLoader, ManifestFormat};
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::{DependencyKind, Version, VersionReq};
+use pea_registry::{PackageMetadataResponse, RegistryClient, RegistryConfig};
+use pea_resolver::semver::VersionSelector;
+
+use super::{install, publish, CommandContext};
+
+/// Dist-tag used when a package is added without a version
+const DEFAULT_TAG: &str = "latest";
 
 /// Execute the `pea add` command
-pub async fn execute(package: String, dev: bool, ctx: &CommandContext) -> PeaResult<()> {
-    let dep_type = if dev { "dev dependency" } else { "dependency" };
-    ctx.output.step("➕", &format!("Adding {} {}", dep_type, package));
-    
-    // TODO: Implement actual dependency addition
-    ctx.output.warn("Dependency addition not yet implemented");
-    ctx.output.info("This will be implemented when the config parser is ready");
-    
-    Ok(())
-}
\ No newline at end of file
+///
+/// Each of `packages` is `name`, `name@tag`, `name@version` or `name@range`.
+/// Tags and versions are saved as `^version` (or the bare version when
+/// `exact`); ranges are saved as written.
+pub async fn execute(
+    packages: Vec<String>,
+    kind: DependencyKind,
+    exact: bool,
+    ctx: &CommandContext,
+) -> PeaResult<()> {
+    let (format, manifest_path) = project_manifest(ctx).await?;
+    let mut content = tokio::fs::read_to_string(&manifest_path).await
+        .map_err(|e| PeaError::io(format!("Failed to read {}", manifest_path), e))?;
+
+    let project_dir = manifest_path.parent().map(|dir| dir.as_std_path()).unwrap_or(&ctx.cwd);
+    let npmrc = publish::load_npmrc(project_dir);
+    for spec in &packages {
+        let (name, requested) = parse_spec(spec);
+        ctx.output.step("➕", &format!("Adding {}", spec));
+
+        let metadata = fetch_metadata(&npmrc, name).await?;
+        let range = save_range(&metadata, name, requested, exact)?;
+
+        content = format.add_dependency(&content, kind, name, &range)?;
+        ctx.output.info(&format!("  + {}@{}", name, range));
+    }
+
+    tokio::fs::write(&manifest_path, &content).await
+        .map_err(|e| PeaError::io(format!("Failed to write {}", manifest_path), e))?;
+    ctx.output.success(&format!("Updated {}", format.file_name()));
+
+    install::execute(false, None, ctx).await
+}
+
+/// The manifest `pea add`/`pea remove` edit: pea.toml, or package.json without one
+pub(super) async fn project_manifest(ctx: &CommandContext) -> PeaResult<(ManifestFormat, Utf8PathBuf)> {
+    let cwd = Utf8PathBuf::from_path_buf(ctx.cwd.clone())
+        .map_err(|path| PeaError::ConfigValidation {
+            field: "cwd".to_string(),
+            reason: format!("Path is not valid UTF-8: {}", path.display()),
+        })?;
+    let (_config, source) = ConfigLoader::new(cwd).load_project_config().await?;
+    match source {
+        ConfigSource::ProjectToml(path) => Ok((ManifestFormat::PeaToml, path)),
+        ConfigSource::ProjectJson(path) => Ok((ManifestFormat::PackageJson, path)),
+        _ => Err(PeaError::ConfigValidation {
+            field: "config".to_string(),
+            reason: "No pea.toml or package.json found; run pea init first".to_string(),
+        }),
+    }
+}
+
+/// Fetch the packument for `name` from the registry `.npmrc` assigns it
+pub(super) async fn fetch_metadata(npmrc: &RegistryConfig, name: &str) -> PeaResult<PackageMetadataResponse> {
+    registry_client(npmrc, name)?.fetch_metadata(name).await
+}
+
+/// A client for the registry `.npmrc` assigns to `name` (the default
+/// registry for an empty name), with its credentials
+pub(super) fn registry_client(npmrc: &RegistryConfig, name: &str) -> PeaResult<RegistryClient> {
+    let registry = npmrc.registry_for(name);
+    let client = match npmrc.auth_for(&registry) {
+        Some(auth) => RegistryClient::with_auth(auth)?,
+        None => RegistryClient::new()?,
+    };
+    Ok(client.with_registry(&registry))
+}
+
+/// Split `name@spec`, minding the `@` of scoped names
+pub(super) fn parse_spec(spec: &str) -> (&str, Option<&str>) {
+    match spec.rfind('@') {
+        Some(index) if index > 0 => (&spec[..index], Some(&spec[index + 1..])),
+        _ => (spec, None),
+    }
+}
+
+/// The range to save for `requested` (a dist-tag, version or range)
+pub(super) fn save_range(
+    metadata: &PackageMetadataResponse,
+    name: &str,
+    requested: Option<&str>,
+    exact: bool,
+) -> PeaResult<String> {
+    let requested = requested.filter(|requested| !requested.is_empty()).unwrap_or(DEFAULT_TAG);
+    let pinned = |version: &str| if exact { version.to_string() } else { format!("^{}", version) };
+
+    if let Some(version) = metadata.dist_tags.get(requested) {
+        return Ok(pinned(version));
+    }
+    if metadata.versions.contains_key(requested) {
+        return Ok(pinned(requested));
+    }
+
+    let not_found = || PeaError::ConfigValidation {
+        field: name.to_string(),
+        reason: format!("'{}' is not a dist-tag, version or range of {}", requested, name),
+    };
+    let version_req = VersionReq::parse(requested).map_err(|_| not_found())?;
+    let versions = metadata.versions.keys()
+        .filter_map(|version| version.parse::<Version>().ok())
+        .collect();
+    let best = VersionSelector::new(versions)
+        .select_preferred(&[version_req], false)
+        .ok_or_else(not_found)?;
+    Ok(if exact { best.to_string() } else { requested.to_string() })
+}
@@ -0,0 +1,189 @@
+//! `pea cache` command implementation.
+//!
+//! Inspection and maintenance of the content-addressable store:
+//! integrity verification, listing, garbage collection and moving
+//! packages between machines as a single archive.
+
+use pea_cache::{CacheEntry, CasStore};
+use pea_core::error::{PeaError, PeaResult};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use super::{get_cache_dir, CommandContext};
+use crate::CacheCommands;
+
+/// Execute a `pea cache` subcommand against the user's store
+pub async fn execute(command: CacheCommands, ctx: &CommandContext) -> PeaResult<()> {
+    let store = CasStore::new(get_cache_dir()?.join("store"))?;
+    run(command, &store, ctx)
+}
+
+/// Run a `pea cache` subcommand against the given store
+pub fn run(command: CacheCommands, store: &CasStore, ctx: &CommandContext) -> PeaResult<()> {
+    match command {
+        CacheCommands::Verify => verify(store, ctx),
+        CacheCommands::Ls => list(store, ctx),
+        CacheCommands::Info { package } => info(store, &package, ctx),
+        CacheCommands::Path => {
+            println!("{}", store.root_path());
+            Ok(())
+        }
+        CacheCommands::Prune { max_age_days } => prune(store, max_age_days, ctx),
+        CacheCommands::Export { output, packages } => export(store, &output, &packages, ctx),
+        CacheCommands::Import { archive } => import(store, &archive, ctx),
+    }
+}
+
+fn verify(store: &CasStore, ctx: &CommandContext) -> PeaResult<()> {
+    ctx.output.step("🔍", "Verifying store integrity");
+    // Capture labels up front, verification drops bad entries from the index
+    let labels: HashMap<String, CacheEntry> = store.index().entries().into_iter().collect();
+    let report = store.verify_all()?;
+    let name = |key: &String| labels.get(key)
+        .map(|entry| display_name(key, entry))
+        .unwrap_or_else(|| key.clone());
+    
+    for hash in &report.corrupted {
+        ctx.output.warn(&format!("Corrupted blob {} moved to {}", name(hash), store.quarantine_dir()));
+    }
+    for hash in &report.missing {
+        ctx.output.warn(&format!("Missing blob {} removed from index", name(hash)));
+    }
+    
+    if report.is_clean() {
+        ctx.output.success(&format!("All {} blobs verified", report.checked));
+    } else {
+        ctx.output.info(&format!(
+            "Checked {} blobs: {} corrupted, {} missing. Affected packages will be re-downloaded on next install.",
+            report.checked, report.corrupted.len(), report.missing.len()
+        ));
+    }
+    Ok(())
+}
+
+fn list(store: &CasStore, ctx: &CommandContext) -> PeaResult<()> {
+    let entries = sorted_entries(store);
+    if entries.is_empty() {
+        ctx.output.info("Cache is empty");
+        return Ok(());
+    }
+    
+    let total: u64 = entries.iter().map(|(_, entry)| entry.size).sum();
+    for (key, entry) in &entries {
+        println!("{:<40} {:>10}  stored {:>4} ago  used {:>4} ago",
+            display_name(key, entry),
+            format_bytes(entry.size),
+            format_age(entry.age_seconds()),
+            format_age(entry.idle_seconds()));
+    }
+    ctx.output.info(&format!("{} entries, {} total", entries.len(), format_bytes(total)));
+    Ok(())
+}
+
+fn info(store: &CasStore, package: &str, ctx: &CommandContext) -> PeaResult<()> {
+    let matches: Vec<(String, CacheEntry)> = sorted_entries(store)
+        .into_iter()
+        .filter(|(_, entry)| entry.package.as_deref().is_some_and(|label| matches_package(label, package)))
+        .collect();
+    
+    if matches.is_empty() {
+        return Err(PeaError::NotInCache { package: package.to_string() });
+    }
+    
+    for (key, entry) in matches {
+        ctx.output.step("📦", &display_name(&key, &entry));
+        println!("  hash:          {}", key);
+        println!("  size:          {}", format_bytes(entry.size));
+        println!("  stored:        {} ({} ago)", entry.stored_at_datetime().to_rfc3339(), format_age(entry.age_seconds()));
+        println!("  last accessed: {}", entry.last_accessed_datetime().to_rfc3339());
+        println!("  path:          {}", store.hash_to_path(&entry.hash));
+    }
+    Ok(())
+}
+
+fn prune(store: &CasStore, max_age_days: i64, ctx: &CommandContext) -> PeaResult<()> {
+    ctx.output.step("🧹", &format!("Removing entries unused for more than {} days", max_age_days));
+    let result = store.garbage_collect(max_age_days)?;
+    ctx.output.success(&format!("Removed {} entries, freed {}", result.entries_removed, result.format_freed_space()));
+    Ok(())
+}
+
+fn export(store: &CasStore, output: &Path, packages: &[String], ctx: &CommandContext) -> PeaResult<()> {
+    let entries = sorted_entries(store);
+    let mut keys = Vec::new();
+    
+    if packages.is_empty() {
+        keys.extend(entries.iter().map(|(key, _)| key.clone()));
+    } else {
+        for package in packages {
+            let before = keys.len();
+            keys.extend(entries.iter()
+                .filter(|(_, entry)| entry.package.as_deref().is_some_and(|label| matches_package(label, package)))
+                .map(|(key, _)| key.clone()));
+            if keys.len() == before {
+                return Err(PeaError::NotInCache { package: package.clone() });
+            }
+        }
+        keys.sort();
+        keys.dedup();
+    }
+    
+    ctx.output.step("📤", &format!("Exporting {} entries to {}", keys.len(), output.display()));
+    let file = File::create(output)
+        .map_err(|e| PeaError::io(format!("Failed to create {}", output.display()), e))?;
+    let summary = store.export_archive(file, &keys)?;
+    ctx.output.success(&format!("Exported {} blobs ({})", summary.blobs, format_bytes(summary.bytes)));
+    Ok(())
+}
+
+fn import(store: &CasStore, archive: &Path, ctx: &CommandContext) -> PeaResult<()> {
+    ctx.output.step("📥", &format!("Importing {}", archive.display()));
+    let file = File::open(archive)
+        .map_err(|e| PeaError::io(format!("Failed to open {}", archive.display()), e))?;
+    let summary = store.import_archive(file)?;
+    ctx.output.success(&format!("Imported {} blobs ({})", summary.blobs, format_bytes(summary.bytes)));
+    Ok(())
+}
+
+/// Index entries sorted by package label, unlabelled blobs last
+fn sorted_entries(store: &CasStore) -> Vec<(String, CacheEntry)> {
+    let mut entries = store.index().entries();
+    entries.sort_by(|(a_key, a), (b_key, b)| {
+        (a.package.is_none(), &a.package, a_key).cmp(&(b.package.is_none(), &b.package, b_key))
+    });
+    entries
+}
+
+/// Whether a `name@version` label matches a query of `name` or `name@version`
+fn matches_package(label: &str, query: &str) -> bool {
+    label == query || label.strip_prefix(query).is_some_and(|rest| rest.starts_with('@'))
+}
+
+fn display_name(key: &str, entry: &CacheEntry) -> String {
+    entry.package.clone().unwrap_or_else(|| format!("<{}>", &key[..12.min(key.len())]))
+}
+
+pub(super) fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn format_age(seconds: i64) -> String {
+    match seconds.max(0) {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
@@ -0,0 +1,169 @@
+//! `pea exec` / `pea x` command implementation.
+//!
+//! Runs a package binary like npx. Without a version, a binary already in
+//! an enclosing `node_modules/.bin` is used. Otherwise the request is
+//! resolved and the tree installed once into a prefix under `~/.pea/exec`
+//! named after the hash of the packages it resolved to, so requests that
+//! resolve alike share a prefix and a newly published version gets its
+//! own. The prefix only appears once its install is complete.
+
+use camino::Utf8Path;
+use pea_cache::cas::hash::compute_hash;
+use pea_cache::link::read_bin_entries;
+use pea_cache::GitCache;
+use pea_core::error::{PeaError, PeaResult};
+use pea_lockfile::{Lockfile, LOCKFILE_NAME};
+use pea_registry::MetadataCache;
+use pea_resolver::Resolver;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{add, get_cache_dir, install, lifecycle, lock, publish, CommandContext};
+use crate::output::OutputHandler;
+
+/// Written into a prefix once its install has completed
+const READY_MARKER: &str = ".pea-exec-ready";
+
+/// Execute the `pea exec` command
+pub async fn execute(package: String, args: Vec<String>, ctx: &CommandContext) -> PeaResult<()> {
+    let exec_root = get_cache_dir()?.join("exec");
+    run(&package, &args, exec_root.as_std_path(), ctx).await
+}
+
+/// Run the binary for `spec` (`name`, `name@version`, `name@tag` or `name@range`),
+/// installing into a prefix under `exec_root` when needed
+pub async fn run(spec: &str, args: &[String], exec_root: &Path, ctx: &CommandContext) -> PeaResult<()> {
+    let (name, requested) = add::parse_spec(spec);
+    let default_bin = name.rsplit('/').next().unwrap_or(name);
+
+    // A project's own install wins unless a specific version is asked for
+    if requested.is_none() {
+        let local = ctx.cwd.ancestors()
+            .map(|dir| dir.join("node_modules"))
+            .find(|node_modules| node_modules.join(".bin").join(default_bin).is_file());
+        if let Some(node_modules) = local {
+            return run_bin(name, default_bin, args, &node_modules, ctx).await;
+        }
+    }
+
+    let lockfile = resolve(name, requested, exec_root, ctx).await?;
+    let prefix = prefix_dir(exec_root, &lockfile);
+    if prefix.join(READY_MARKER).is_file() {
+        ctx.output.step("♻️", &format!("Using cached {}", spec));
+    } else {
+        install_prefix(name, &lockfile, &prefix, ctx).await?;
+    }
+
+    let node_modules = prefix.join("node_modules");
+    let bin_entries = Utf8Path::from_path(&node_modules.join(name))
+        .map(read_bin_entries)
+        .transpose()?
+        .unwrap_or_default();
+    let mut bins: Vec<&str> = bin_entries.keys().map(String::as_str).collect();
+    bins.sort_unstable();
+    let bin = match bins.as_slice() {
+        [only] => *only,
+        _ if bins.contains(&default_bin) => default_bin,
+        _ => return Err(PeaError::ConfigValidation {
+            field: "bin".to_string(),
+            reason: if bins.is_empty() {
+                format!("{} has no binaries to run", name)
+            } else {
+                format!("{} has several binaries ({}); none is named {}", name, bins.join(", "), default_bin)
+            },
+        }),
+    };
+    run_bin(name, bin, args, &node_modules, ctx).await
+}
+
+/// Resolve `name@requested` to the lockfile of the tree to install
+///
+/// Resolution runs from `exec_root`, so a workspace around the current
+/// directory doesn't supply any of the packages.
+pub(super) async fn resolve(name: &str, requested: Option<&str>, exec_root: &Path, ctx: &CommandContext) -> PeaResult<Lockfile> {
+    let npmrc = publish::load_npmrc(&ctx.cwd);
+    let metadata = add::fetch_metadata(&npmrc, name).await?;
+    let roots = vec![(name.to_string(), add::save_range(&metadata, name, requested, true)?)];
+
+    let resolver = Resolver::new(Arc::new(add::registry_client(&npmrc, "")?), Arc::new(MetadataCache::new()))
+        .with_git_cache(Arc::new(GitCache::new(get_cache_dir()?.join("git"))));
+    let exec_ctx = CommandContext { cwd: exec_root.to_path_buf(), output: OutputHandler::new() };
+    let resolution = install::resolve(&roots, &resolver, &exec_ctx).await?;
+    install::check_peer_issues(&resolution, false, &exec_ctx)?;
+    Ok(lock::lockfile_from_resolution(&resolution, &roots, &BTreeMap::new()))
+}
+
+/// Prefix for a resolved tree; requests that resolve to the same packages
+/// (`tool`, `tool@^1`, `tool@1.2.0`) map to the same prefix
+pub(super) fn prefix_dir(exec_root: &Path, lockfile: &Lockfile) -> PathBuf {
+    let key: String = lockfile.dependencies.iter()
+        .map(|(name, root)| format!("{}@{}\n", name, root.version))
+        .chain(lockfile.packages.iter().map(|package| format!("{}@{}\n", package.name, package.version)))
+        .collect();
+    exec_root.join(&compute_hash(key.as_bytes()).to_hex()[..16])
+}
+
+/// Install the tree `lockfile` records for `name` into `prefix`
+///
+/// The install runs in a private directory beside the prefix that is
+/// renamed into place once complete, so concurrent runs of the same request
+/// never share or clear each other's half-built prefix.
+async fn install_prefix(name: &str, lockfile: &Lockfile, prefix: &Path, ctx: &CommandContext) -> PeaResult<()> {
+    ctx.output.step("📦", &format!("Installing {} into {}", name, prefix.display()));
+
+    let nonce = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |since| since.as_nanos());
+    let staging = prefix.with_extension(format!("{}-{}.tmp", std::process::id(), nonce));
+    if let Err(e) = install_into(name, lockfile, &staging).await {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    // Clear what an interrupted install from before staged installs left
+    if prefix.exists() && !prefix.join(READY_MARKER).is_file() {
+        let _ = std::fs::remove_dir_all(prefix);
+    }
+    if let Err(e) = std::fs::rename(&staging, prefix) {
+        let _ = std::fs::remove_dir_all(&staging);
+        // Another run may have finished the same install first
+        if !prefix.join(READY_MARKER).is_file() {
+            return Err(PeaError::io(format!("Failed to move install into {}", prefix.display()), e));
+        }
+    }
+    Ok(())
+}
+
+/// Install exactly the tree `lockfile` records for `name` into the fresh
+/// directory `dir` and mark it ready
+async fn install_into(name: &str, lockfile: &Lockfile, dir: &Path) -> PeaResult<()> {
+    let version = lockfile.dependencies.get(name).map(|root| root.range.as_str()).unwrap_or_default();
+    std::fs::create_dir_all(dir)
+        .map_err(|e| PeaError::io(format!("Failed to create {}", dir.display()), e))?;
+    let manifest = serde_json::json!({
+        "name": "pea-exec",
+        "version": "0.0.0",
+        "private": true,
+        "dependencies": { name: version },
+    });
+    std::fs::write(dir.join("package.json"), manifest.to_string())
+        .map_err(|e| PeaError::io(format!("Failed to write {}", dir.display()), e))?;
+    lockfile.save(&dir.join(LOCKFILE_NAME))?;
+
+    let prefix_ctx = CommandContext { cwd: dir.to_path_buf(), output: OutputHandler::new() };
+    install::execute(true, None, &prefix_ctx).await?;
+
+    std::fs::write(dir.join(READY_MARKER), format!("{}@{}\n", name, version))
+        .map_err(|e| PeaError::io(format!("Failed to write {}", dir.display()), e))
+}
+
+/// Run `bin` from `node_modules/.bin` with `args` in the current directory
+async fn run_bin(package: &str, bin: &str, args: &[String], node_modules: &Path, ctx: &CommandContext) -> PeaResult<()> {
+    let version = std::fs::read_to_string(node_modules.join(package).join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|manifest| manifest.get("version")?.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let path = node_modules.join(".bin").join(bin);
+    let command = lifecycle::with_args(&lifecycle::quote(&path.to_string_lossy()), args);
+    lifecycle::run_script(package, &version, "exec", &command, &ctx.cwd, node_modules).await
+}
@@ -0,0 +1,108 @@
+//! Git dependency tarballs.
+//!
+//! A git dependency is locked as `git+<url>#<sha>`. Installing it checks the
+//! commit out through the git cache, runs its `prepare` script when it has
+//! one (after installing its own dependencies, like npm), and packs the
+//! checkout the way `pea pack` would. Preparing happens in a temporary copy
+//! of the checkout, so the shared checkout always stays as committed. The
+//! packed tarball is kept beside the checkout, so each URL+SHA is only
+//! prepared once.
+
+use pea_cache::git::{parse_resolved, GitCache};
+use pea_core::error::{PeaError, PeaResult};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+use super::{install, pack, CommandContext};
+use crate::output::OutputHandler;
+
+/// Tarball of the git dependency `name` locked at `resolved`
+///
+/// `prepare` only runs for packages in `trusted`; an untrusted package with
+/// one is packed as committed, with a warning, and not cached.
+pub(super) async fn tarball(
+    name: &str,
+    resolved: &str,
+    git_cache: &GitCache,
+    trusted: &[String],
+    ctx: &CommandContext,
+) -> PeaResult<Vec<u8>> {
+    let (url, sha) = parse_resolved(resolved).ok_or_else(|| PeaError::ConfigValidation {
+        field: name.to_string(),
+        reason: format!("'{}' is not a locked git dependency (git+<url>#<sha>)", resolved),
+    })?;
+    let checkout = git_cache.checkout(url, sha)?;
+    let packed_path = checkout.with_extension("tgz");
+    if let Ok(tarball) = tokio::fs::read(&packed_path).await {
+        return Ok(tarball);
+    }
+
+    let mut cacheable = true;
+    let mut prepared = None;
+    if has_prepare_script(checkout.as_std_path()) {
+        if trusted.iter().any(|trusted| trusted == name) {
+            ctx.output.info(&format!("    🛠️  Preparing {} from git", name));
+            let copy = tempfile::Builder::new()
+                .prefix(".prepare-")
+                .tempdir_in(checkout.parent().unwrap_or(&checkout))
+                .map_err(|e| PeaError::io(format!("Failed to create a directory to prepare {} in", name), e))?;
+            copy_checkout(checkout.as_std_path(), copy.path())?;
+            let prepare_ctx = CommandContext { cwd: copy.path().to_path_buf(), output: OutputHandler::new() };
+            // Installing the copy runs its prepare script once its dependencies are in place
+            let install: Pin<Box<dyn Future<Output = PeaResult<()>> + '_>> = Box::pin(install::execute(false, None, &prepare_ctx));
+            install.await?;
+            prepared = Some(copy);
+        } else {
+            ctx.output.warn(&format!(
+                "Skipped the prepare script of git dependency {}; add it to trusted-dependencies to build it", name));
+            cacheable = false;
+        }
+    }
+
+    let source = prepared.as_ref().map_or(checkout.as_std_path(), |copy| copy.path());
+    let packed = pack::pack_project(source).await?;
+    if cacheable {
+        tokio::fs::write(&packed_path, &packed.tarball).await
+            .map_err(|e| PeaError::io(format!("Failed to write {}", packed_path), e))?;
+    }
+    Ok(packed.tarball)
+}
+
+fn has_prepare_script(dir: &Path) -> bool {
+    std::fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .is_some_and(|manifest| manifest.pointer("/scripts/prepare").is_some())
+}
+
+/// Copy the working tree at `from` into `to`, leaving out `.git`
+fn copy_checkout(from: &Path, to: &Path) -> PeaResult<()> {
+    let entries = walkdir::WalkDir::new(from).min_depth(1).into_iter()
+        .filter_entry(|entry| entry.depth() > 1 || entry.file_name() != ".git");
+    for entry in entries {
+        let entry = entry.map_err(|e| PeaError::io(format!("Failed to read {}", from.display()),
+            std::io::Error::other(e.to_string())))?;
+        let target = to.join(entry.path().strip_prefix(from).unwrap_or(entry.path()));
+        let copied = if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)
+        } else if entry.file_type().is_symlink() {
+            copy_symlink(entry.path(), &target)
+        } else {
+            std::fs::copy(entry.path(), &target).map(|_| ())
+        };
+        copied.map_err(|e| PeaError::io(format!("Failed to copy {}", entry.path().display()), e))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(link: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(link)?, target)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(_link: &Path, _target: &Path) -> std::io::Result<()> {
+    // Symlinks in a checkout need privileges to recreate here; pack leaves them out anyway
+    Ok(())
+}
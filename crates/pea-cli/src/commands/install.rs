@@ -1,22 +1,29 @@
 //! `pea install` command implementation.
 //!
-//! Installs dependencies specified in pea.toml by resolving them,
-//! downloading packages, storing in CAS, and creating node_modules.
+//! Installs dependencies specified in pea.toml by resolving them (or reading
+//! an up-to-date pea.lock), downloading packages, storing in CAS, and creating
+//! node_modules. Packages already in the store aren't downloaded again.
+//! Optional dependencies are installed when the platform supports them, and
+//! failing to install one doesn't fail the install.
 
-use camino::{Utf8Path, Utf8PathBuf};
-use pea_cache::{CasStore, Linker};
+use camino::Utf8PathBuf;
+use pea_cache::{CasStore, GitCache, Linker};
+use pea_cache::tarball::{package_root, ExtractLimits};
+use pea_cache::link::{read_bin_entries, HoistPatterns};
 use pea_config::{ConfigLoader, PeaToml};
 use pea_core::error::{PeaError, PeaResult};
-use pea_registry::{RegistryClient, MetadataCache};
-use pea_resolver::Resolver;
+use pea_core::types::{Platform, Version};
+use pea_lockfile::{Lockfile, LOCKFILE_NAME};
+use pea_registry::{MetadataCache, RegistryClient};
+use pea_resolver::{PackageId, ResolutionResult, Resolver};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Instant;
-use tokio::fs;
 
-use super::CommandContext;
+use super::{add, get_cache_dir, git, lifecycle, lock, optional, publish, workspace, CommandContext};
 
-/// Execute the `pea install` command
-pub async fn execute(frozen: bool, ctx: &CommandContext) -> PeaResult<()> {
+/// Execute the `pea install` command, for `platform` or the current one
+pub async fn execute(frozen: bool, platform: Option<Platform>, ctx: &CommandContext) -> PeaResult<()> {
     let start_time = Instant::now();
     
     if frozen {
@@ -27,36 +34,93 @@ pub async fn execute(frozen: bool, ctx: &CommandContext) -> PeaResult<()> {
     
     // Parse configuration
     let cwd_utf8 = Utf8PathBuf::from_path_buf(ctx.cwd.clone()).unwrap();
-    let config_loader = ConfigLoader::new(cwd_utf8);
+    let config_loader = ConfigLoader::new(cwd_utf8.clone());
     let (config, _source) = config_loader.load_project_config().await?;
     
-    // Check for existing lockfile
-    let lockfile_path = ctx.cwd.join("pea.lock");
-    let has_lockfile = lockfile_path.exists();
+    // A lockfile whose roots and overrides match the manifest is installed as-is
+    let roots = lock::root_ranges(&config);
+    let overrides = config.resolved_overrides()?;
+    let lockfile_path = ctx.cwd.join(LOCKFILE_NAME);
+    let stored = Lockfile::load(&lockfile_path)?;
+    let lockfile = stored.as_ref()
+        .filter(|lockfile| lockfile.is_up_to_date(&roots) && lockfile.overrides == overrides)
+        .filter(|lockfile| match lock::changed_local_package(lockfile) {
+            Some(name) => {
+                ctx.output.info(&format!("  📁 {} changed on disk; resolving again", name));
+                false
+            }
+            None => true,
+        });
     
-    if frozen && !has_lockfile {
+    if frozen && lockfile.is_none() {
         return Err(PeaError::ConfigValidation {
             field: "lockfile".to_string(),
-            reason: "Frozen install requires existing lockfile".to_string(),
+            reason: format!("Frozen install requires an {} that matches the manifest", LOCKFILE_NAME),
         });
     }
     
     // Initialize components
     let cache_dir = get_cache_dir()?;
-    let cas_store = Arc::new(CasStore::new(&cache_dir.join("store"))?);
-    let registry_client = Arc::new(RegistryClient::new()?);
+    let install_config = config.install.clone().unwrap_or_default();
+    let cas_store = Arc::new(CasStore::new(&cache_dir.join("store"))?
+        .with_extract_limits(extract_limits(&install_config)));
+    let registry_client = Arc::new(add::registry_client(&publish::load_npmrc(&ctx.cwd), "")?);
     let metadata_cache = Arc::new(MetadataCache::new());
-    let resolver = Resolver::new(registry_client.clone(), metadata_cache.clone());
-    let linker = Linker::new(cas_store.clone());
+    let git_cache = Arc::new(GitCache::new(cache_dir.join("git")));
+    let resolver = Resolver::new(registry_client.clone(), metadata_cache.clone())
+        .with_git_cache(git_cache.clone())
+        .with_project_dir(cwd_utf8)
+        .with_overrides(overrides.clone())
+        .with_optional_roots(lock::optional_roots(&config))
+        // Otherwise keep what it locked where the manifest still allows it,
+        // so changing one dependency doesn't upgrade the others
+        .with_preferred_versions(stored.as_ref()
+            .map(|stored| locked_versions(stored, &BTreeSet::new()))
+            .unwrap_or_default());
+    let linker = Linker::new(cas_store.clone())
+        .with_import_method(install_config.package_import_method)
+        .with_node_linker(install_config.node_linker)
+        .with_hoist_patterns(HoistPatterns {
+            private: install_config.hoist_pattern,
+            public: install_config.public_hoist_pattern,
+        });
+    
+    let project_version = config.package.version.to_string();
+    lifecycle::run_project_script("preinstall", &config.scripts, &config.package.name,
+        &project_version, &ctx.cwd, ctx).await?;
     
-    if has_lockfile && !frozen {
-        // Cached install flow
-        ctx.output.step("⚡", "Using cached dependencies");
-        cached_install(&config, &linker, ctx).await?;
+    let resolution = match lockfile {
+        Some(lockfile) => {
+            ctx.output.step("⚡", &format!("Using {}", LOCKFILE_NAME));
+            lock::resolution_from_lockfile(lockfile)?
+        }
+        None => {
+            ctx.output.step("🔍", "Resolving dependencies");
+            let resolution = resolve(&roots, &resolver, ctx).await?;
+            check_peer_issues(&resolution, install_config.strict_peer_dependencies, ctx)?;
+            resolution
+        }
+    };
+    
+    if resolution.package_count == 0 {
+        ctx.output.info("No dependencies to install");
+        create_empty_node_modules(&linker, ctx)?;
     } else {
-        // Fresh install flow
-        ctx.output.step("🔍", "Resolving dependencies");
-        fresh_install(&config, &resolver, &cas_store, &linker, ctx).await?;
+        let platform = platform.unwrap_or_else(Platform::current);
+        let selection = optional::select(&resolution, &lock::optional_roots(&config), &platform)?;
+        install_resolution(&config, &resolution, &selection, &registry_client, &git_cache, &linker, ctx).await?;
+    }
+    
+    if !frozen {
+        let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &overrides);
+        if lockfile.save(&lockfile_path)? {
+            ctx.output.info(&format!("  📝 Wrote {}", LOCKFILE_NAME));
+        }
+    }
+    
+    for event in ["install", "postinstall", "prepare"] {
+        lifecycle::run_project_script(event, &config.scripts, &config.package.name,
+            &project_version, &ctx.cwd, ctx).await?;
     }
     
     let duration = start_time.elapsed();
@@ -65,42 +129,27 @@ pub async fn execute(frozen: bool, ctx: &CommandContext) -> PeaResult<()> {
     Ok(())
 }
 
-/// Perform a fresh install by resolving dependencies
-async fn fresh_install(
-    config: &PeaToml,
+/// Versions `lockfile` locked, by package name, leaving out the packages in `except`
+pub(super) fn locked_versions(lockfile: &Lockfile, except: &BTreeSet<String>) -> HashMap<String, Vec<Version>> {
+    let mut versions: HashMap<String, Vec<Version>> = HashMap::new();
+    for package in lockfile.packages.iter().filter(|package| !except.contains(&package.name)) {
+        if let Ok(id) = PackageId::from_name_version(&package.name, &package.version) {
+            versions.entry(package.name.clone()).or_default().push(id.version);
+        }
+    }
+    versions
+}
+
+/// Resolve the root dependencies against the registry, linking members of
+/// the enclosing workspace
+pub(super) async fn resolve(
+    roots: &[(String, String)],
     resolver: &Resolver,
-    cas_store: &Arc<CasStore>,
-    linker: &Linker,
     ctx: &CommandContext,
-) -> PeaResult<()> {
-    ctx.output.step("🧩", "Resolving dependency tree");
-    
-    // Check if we have dependencies to install
-    let dep_count = config.dependencies.len();
-    ctx.output.info(&format!("📊 Found {} dependencies to install", dep_count));
-    
-    if dep_count == 0 {
-        ctx.output.info("No dependencies to install");
-        return create_empty_node_modules(ctx).await;
-    }
-    
-    // Convert dependencies to resolver format
-    let root_dependencies: Vec<(String, String)> = config.dependencies
-        .iter()
-        .map(|(name, spec)| {
-            let version_req = match spec {
-                pea_config::DependencySpec::Simple(version) => version.clone(),
-                pea_config::DependencySpec::Detailed { version, .. } => {
-                    version.clone().unwrap_or_else(|| "*".to_string())
-                },
-            };
-            (name.clone(), version_req)
-        })
-        .collect();
-    
-    // Resolve dependencies
-    ctx.output.info(&format!("🔍 Resolving {} root dependencies", root_dependencies.len()));
-    let resolution_result = resolver.resolve(root_dependencies).await
+) -> PeaResult<ResolutionResult> {
+    ctx.output.info(&format!("🔍 Resolving {} root dependencies", roots.len()));
+    let members = workspace_members(ctx).await?;
+    let resolution_result = resolver.resolve_with_workspace(roots.to_vec(), members).await
         .map_err(|e| PeaError::VersionConflict {
             package: "resolution".to_string(),
             required: "compatible versions".to_string(),
@@ -111,213 +160,347 @@ async fn fresh_install(
     ctx.output.info(&format!("✅ Resolved {} packages in {}ms", 
         resolution_result.package_count, 
         resolution_result.resolution_time_ms));
+    for skipped in &resolution_result.skipped_optional {
+        ctx.output.warn(&format!("Skipped optional dependency {}@{}: {}", skipped.package, skipped.required, skipped.conflict));
+    }
+    Ok(resolution_result)
+}
+
+/// Warn about unmet peer dependencies, which fail the install with
+/// `strict-peer-dependencies`
+pub(super) fn check_peer_issues(resolution: &ResolutionResult, strict: bool, ctx: &CommandContext) -> PeaResult<()> {
+    for issue in &resolution.peer_issues {
+        ctx.output.warn(&format!("Unmet peer dependency {}", issue));
+    }
+    match resolution.peer_issues.first() {
+        Some(issue) if strict => Err(PeaError::VersionConflict {
+            package: issue.package.to_string(),
+            required: format!("{}@{}", issue.peer, issue.range),
+            conflicting: "strict-peer-dependencies".to_string(),
+            conflict: match &issue.found {
+                Some(found) => format!("{}@{}", issue.peer, found),
+                None => "no version of it".to_string(),
+            },
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Members of the nearest workspace at or above the project, by package name
+async fn workspace_members(ctx: &CommandContext) -> PeaResult<Option<HashMap<String, String>>> {
+    let Ok(cwd) = Utf8PathBuf::from_path_buf(ctx.cwd.clone()) else {
+        return Ok(None);
+    };
+    for dir in cwd.ancestors() {
+        let Some(section) = workspace::load_manifest(dir).await?.and_then(|config| config.workspace) else {
+            continue;
+        };
+        let members = workspace::members(dir, &section).await?;
+        return Ok(Some(members.into_iter()
+            .map(|member| (member.config.package.name, member.dir.to_string()))
+            .collect()));
+    }
+    Ok(None)
+}
+
+/// Download, link and run lifecycle scripts for the `selection` of a resolved tree
+async fn install_resolution(
+    config: &PeaToml,
+    resolution_result: &ResolutionResult,
+    selection: &optional::Selection,
+    registry_client: &RegistryClient,
+    git_cache: &GitCache,
+    linker: &Linker,
+    ctx: &CommandContext,
+) -> PeaResult<()> {
+    let trusted = config.install.as_ref()
+        .map(|install| install.trusted_dependencies.clone())
+        .unwrap_or_default();
     
     // Download and store packages in CAS
     ctx.output.step("📥", "Downloading packages");
-    let packages = download_packages(&resolution_result.graph, cas_store, ctx).await?;
+    let skipped = resolution_result.graph.package_count().saturating_sub(selection.install.len());
+    if skipped > 0 {
+        ctx.output.info(&format!("  ⏭️  Skipped {} optional packages for other platforms", skipped));
+    }
+    let packages = download_packages(&resolution_result.graph, selection, linker.cas_store(), registry_client,
+        git_cache, &trusted, ctx).await?;
+    let packages = attach_dependency_edges(packages, resolution_result);
     
     // Create node_modules structure
     ctx.output.step("🔗", "Creating node_modules");
     let node_modules_dir = Utf8PathBuf::from_path_buf(ctx.cwd.join("node_modules")).unwrap();
     
-    // Clean existing node_modules if it exists
-    if node_modules_dir.exists() {
-        linker.cleanup_node_modules(&node_modules_dir)?;
-    }
-    
-    let link_result = linker.create_node_modules(&packages, &node_modules_dir)?;
+    // Build beside the existing tree and swap it in once complete
+    let link_result = linker.install_node_modules(&packages, &node_modules_dir)?;
     
-    ctx.output.info(&format!("  📦 Linked {} packages", link_result.packages_linked));
-    ctx.output.info(&format!("  🔗 Created {} hardlinks", link_result.hardlinks_created));
+    ctx.output.info(&format!("  📦 Linked {} packages ({}, {})",
+        link_result.packages_linked, linker.node_linker(), linker.import_method()));
+    if link_result.packages_reused > 0 || link_result.packages_removed > 0 {
+        ctx.output.info(&format!("  ♻️  Reused {} unchanged packages, removed {}",
+            link_result.packages_reused, link_result.packages_removed));
+    }
+    if link_result.packages_nested > 0 {
+        ctx.output.info(&format!("  🪆 Nested {} conflicting versions", link_result.packages_nested));
+    }
+    if link_result.reflinks_created > 0 {
+        ctx.output.info(&format!("  🪞 Created {} reflinks", link_result.reflinks_created));
+    }
+    if link_result.hardlinks_created > 0 {
+        ctx.output.info(&format!("  🔗 Created {} hardlinks", link_result.hardlinks_created));
+    }
     if link_result.files_copied > 0 {
         ctx.output.info(&format!("  📄 Copied {} files (fallback)", link_result.files_copied));
     }
     if link_result.bin_links_created > 0 {
         ctx.output.info(&format!("  🔧 Created {} binary links", link_result.bin_links_created));
     }
+    for conflict in &link_result.bin_conflicts {
+        ctx.output.warn(&format!("Binary '{}' is provided by both {} and {}; using {}",
+            conflict.bin, conflict.winner, conflict.loser, conflict.winner));
+    }
     
-    // TODO: Generate lockfile
-    ctx.output.step("🔒", "Generating lockfile");
-    ctx.output.info("  📝 pea.lock (lockfile generation not yet implemented)");
+    // Run dependency lifecycle scripts, dependencies before dependents
+    ctx.output.step("⚙️", "Running lifecycle scripts");
+    let ordered = lifecycle_order(&packages, &resolution_result.graph, ctx);
+    let report = lifecycle::run_dependency_scripts(&ordered, &node_modules_dir, &trusted, ctx).await?;
+    if report.ran > 0 {
+        ctx.output.info(&format!("  ⚙️  Ran lifecycle scripts for {} packages", report.ran));
+    }
+    if !report.blocked.is_empty() {
+        let blocked: Vec<&str> = report.blocked.iter().map(String::as_str).collect();
+        ctx.output.warn(&format!("Blocked lifecycle scripts of {} untrusted packages: {}",
+            blocked.len(), blocked.join(", ")));
+        ctx.output.info("  Add them to trusted-dependencies under [install] in pea.toml to allow their scripts");
+    }
     
     Ok(())
 }
 
 /// Create empty node_modules directory for projects with no dependencies
-async fn create_empty_node_modules(ctx: &CommandContext) -> PeaResult<()> {
+///
+/// Linking an empty tree also clears packages left by a previous install.
+fn create_empty_node_modules(linker: &Linker, ctx: &CommandContext) -> PeaResult<()> {
     ctx.output.step("🔗", "Creating node_modules");
     let node_modules_dir = Utf8PathBuf::from_path_buf(ctx.cwd.join("node_modules")).unwrap();
-    fs::create_dir_all(&node_modules_dir).await
-        .map_err(|e| PeaError::io("Failed to create node_modules".to_string(), e))?;
+    linker.install_node_modules(&[], &node_modules_dir)?;
     
     ctx.output.info("  📁 Created empty node_modules directory");
     Ok(())
 }
 
-/// Download packages and store them in CAS
+/// Download the selected packages (or pack git checkouts, or read tarball
+/// files) and store them in CAS, fetching with the client `.npmrc`
+/// configures; optional packages that fail are left out
 async fn download_packages(
     graph: &pea_resolver::graph::DependencyGraph,
-    cas_store: &Arc<CasStore>,
+    selection: &optional::Selection,
+    cas_store: &CasStore,
+    registry_client: &RegistryClient,
+    git_cache: &GitCache,
+    trusted: &[String],
     ctx: &CommandContext,
 ) -> PeaResult<Vec<pea_cache::link::PackageInfo>> {
-    use pea_cache::link::PackageInfo;
-    use pea_registry::RegistryClient;
-    
     let mut packages = Vec::new();
-    let registry_client = RegistryClient::new()
-        .map_err(|e| PeaError::Network { 
-            message: format!("Failed to create registry client: {}", e),
-            source: Some(Box::new(e))
-        })?;
     
-    let total_packages = graph.package_count();
+    let total_packages = selection.install.len();
     let mut downloaded = 0;
+    let mut stored: std::collections::HashMap<String, pea_cache::ContentHash> = cas_store.index().entries()
+        .into_iter()
+        .filter_map(|(_, entry)| entry.package.map(|package| (package, entry.hash)))
+        .collect();
     
-    for package in graph.packages() {
+    for package in graph.packages().filter(|package| selection.install.contains(&package.id)) {
         downloaded += 1;
         ctx.output.info(&format!("  📦 [{}/{}] Downloading {}@{}", 
             downloaded, total_packages, package.name, package.version));
         
-        // Skip workspace packages (they have file:// URLs)
-        if package.resolved_url.starts_with("file://") {
-            ctx.output.info(&format!("    📁 Workspace package, skipping download"));
-            
-            // For workspace packages, use the local path directly
-            let workspace_path = package.resolved_url.strip_prefix("file://").unwrap();
-            let package_info = PackageInfo::new(
-                package.name.clone(),
-                package.version.to_string(),
-                Utf8PathBuf::from(workspace_path),
-            ).as_workspace();
-            
-            packages.push(package_info);
-            continue;
+        let optional = selection.optional.contains(&package.id);
+        match download_package(package, cas_store, git_cache, registry_client, &mut stored, trusted, ctx).await {
+            Ok(package_info) if optional => packages.push(package_info.as_optional()),
+            Ok(package_info) => packages.push(package_info),
+            Err(e) if optional => ctx.output.warn(&format!("Skipping optional dependency {}: {}", package.id, e)),
+            Err(e) => return Err(e),
         }
-        
-        // Download tarball
-        let tarball_bytes = registry_client.download_tarball(&pea_registry::api::DistInfo {
-            tarball: package.resolved_url.clone(),
-            shasum: extract_shasum(&package.integrity),
-            integrity: Some(package.integrity.clone()),
-            file_count: Some(0), // Not used for download
-            unpacked_size: Some(0), // Not used for download
-        }).await
-        .map_err(|e| PeaError::Network {
-            message: format!("Failed to download {}: {}", package.name, e),
-            source: Some(Box::new(e))
-        })?;
-        
-        // Store in CAS
-        let content_hash = cas_store.store(&tarball_bytes)?;
-        ctx.output.info(&format!("    💾 Stored in CAS: {}", content_hash.to_hex()[..12].to_string()));
-        
-        // Extract to temporary location for linking
-        let temp_dir = tempfile::tempdir()
-            .map_err(|e| PeaError::io("Failed to create temp directory".to_string(), e))?;
-        
-        let extract_path = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
-        let tarball_cursor = std::io::Cursor::new(&tarball_bytes);
-        pea_cache::tarball::extract_tarball(tarball_cursor, extract_path.as_std_path())?;
-        
-        // Look for package.json to extract bin entries
-        let package_json_path = extract_path.join("package").join("package.json");
-        let bin_entries = if package_json_path.exists() {
-            extract_bin_entries(&package_json_path)?
-        } else {
-            std::collections::HashMap::new()
-        };
-        
-        let package_info = PackageInfo::new(
-            package.name.clone(),
-            package.version.to_string(),
-            extract_path.join("package"), // npm tarballs have package/ prefix
-        );
-        
-        let package_info = bin_entries.into_iter().fold(package_info, |pkg, (name, path)| {
-            pkg.with_bin(name, path)
-        });
-        
-        packages.push(package_info);
     }
     
+    cas_store.save_index()?;
     ctx.output.info(&format!("✅ Downloaded and stored {} packages", packages.len()));
     Ok(packages)
 }
 
-/// Extract shasum from integrity string
-fn extract_shasum(integrity: &str) -> String {
-    // For now, return a placeholder since we're using blake3 for integrity
-    // In a real implementation, we'd parse the integrity string properly
-    integrity.chars().filter(|c| c.is_alphanumeric()).take(40).collect()
-}
-
-/// Extract binary entries from package.json
-fn extract_bin_entries(package_json_path: &Utf8Path) -> PeaResult<std::collections::HashMap<String, String>> {
-    use std::collections::HashMap;
-    
-    let content = std::fs::read_to_string(package_json_path)
-        .map_err(|e| PeaError::io("Failed to read package.json".to_string(), e))?;
-    
-    let package_json: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| PeaError::JsonParse {
-            message: format!("Failed to parse {}: {}", package_json_path, e),
-        })?;
+/// Download one package into CAS, reusing a stored tarball (`stored` maps
+/// `name@version` labels to content)
+async fn download_package(
+    package: &pea_resolver::PackageNode,
+    cas_store: &CasStore,
+    git_cache: &GitCache,
+    registry_client: &RegistryClient,
+    stored: &mut HashMap<String, pea_cache::ContentHash>,
+    trusted: &[String],
+    ctx: &CommandContext,
+) -> PeaResult<pea_cache::link::PackageInfo> {
+    use pea_cache::link::PackageInfo;
     
-    let mut bin_entries = HashMap::new();
+    // Workspace packages and path dependencies on directories are linked in place
+    let local_path = package.resolved_url.strip_prefix("file://").map(Utf8PathBuf::from);
+    if let Some(workspace_path) = local_path.clone().filter(|path| !path.is_file()) {
+        ctx.output.info("    📁 Local directory, skipping download");
+        let bin_entries = read_bin_entries(&workspace_path)?;
+        let package_info = PackageInfo::new(
+            package.name.clone(),
+            package.id.version_key(),
+            workspace_path,
+        ).as_workspace();
+        
+        return Ok(with_bins(package_info, bin_entries));
+    }
     
-    if let Some(bin) = package_json.get("bin") {
-        match bin {
-            serde_json::Value::String(path) => {
-                // Single binary with package name
-                if let Some(name) = package_json.get("name").and_then(|n| n.as_str()) {
-                    bin_entries.insert(name.to_string(), path.clone());
-                }
-            }
-            serde_json::Value::Object(map) => {
-                // Multiple binaries
-                for (name, path) in map {
-                    if let Some(path_str) = path.as_str() {
-                        bin_entries.insert(name.clone(), path_str.to_string());
-                    }
-                }
+    // Reuse a tarball already stored for this exact package
+    let label = format!("{}@{}", package.name, package.version);
+    let from_git = package.resolved_url.starts_with("git+");
+    let unlabelled = from_git || local_path.is_some();
+    let content_hash = match stored.get(&label).filter(|hash| !unlabelled && cas_store.contains(hash)) {
+        // The same name and version from git or a tarball file is different code, so isn't labelled
+        None if from_git => {
+            let tarball_bytes = git::tarball(&package.name, &package.resolved_url, git_cache, trusted, ctx).await?;
+            let content_hash = cas_store.store(&tarball_bytes)?;
+            ctx.output.info(&format!("    🌿 Packed from git: {}", content_hash.to_hex()[..12].to_string()));
+            content_hash
+        }
+        None if local_path.is_some() => {
+            let tarball_path = local_path.as_ref().expect("checked by the match guard");
+            let tarball_bytes = tokio::fs::read(tarball_path).await
+                .map_err(|e| PeaError::io(format!("Failed to read {}", tarball_path), e))?;
+            let actual = pea_cache::tarball::integrity(&tarball_bytes);
+            if actual != package.integrity {
+                return Err(PeaError::IntegrityFailure {
+                    package: package.name.clone(),
+                    expected: package.integrity.clone(),
+                    actual,
+                });
             }
-            _ => {}
+            let content_hash = cas_store.store(&tarball_bytes)?;
+            ctx.output.info(&format!("    📁 Stored from {}: {}", tarball_path, content_hash.to_hex()[..12].to_string()));
+            content_hash
         }
-    }
-    
-    Ok(bin_entries)
+        Some(hash) => {
+            ctx.output.info("    ♻️  Already in store");
+            *hash
+        }
+        None => {
+            let tarball_bytes = registry_client.download_tarball(&pea_registry::api::DistInfo {
+                tarball: package.resolved_url.clone(),
+                shasum: extract_shasum(&package.integrity),
+                integrity: Some(package.integrity.clone()),
+                file_count: Some(0), // Not used for download
+                unpacked_size: Some(0), // Not used for download
+            }).await
+            .map_err(|e| PeaError::Network {
+                message: format!("Failed to download {}: {}", package.name, e),
+                source: Some(Box::new(e))
+            })?;
+            
+            // Store in CAS; copies resolved with other peers reuse it
+            let content_hash = cas_store.store(&tarball_bytes)?;
+            cas_store.label(&content_hash, &label);
+            stored.insert(label, content_hash);
+            ctx.output.info(&format!("    💾 Stored in CAS: {}", content_hash.to_hex()[..12].to_string()));
+            content_hash
+        }
+    };
+    
+    // Unpack once into the store so node_modules can link from a stable location
+    let extract_path = cas_store.unpack(&content_hash)?;
+    
+    // Usually package/, but not for @types/* and some older packages
+    let package_dir = package_root(extract_path.as_std_path())
+        .map(|root| Utf8PathBuf::from_path_buf(root).unwrap_or_else(|_| extract_path.clone()))?;
+    let bin_entries = read_bin_entries(&package_dir)?;
+    let package_info = PackageInfo::new(
+        package.name.clone(),
+        package.id.version_key(),
+        package_dir,
+    );
+    
+    Ok(with_bins(package_info, bin_entries))
 }
 
-/// Perform a cached install using existing lockfile
-async fn cached_install(
-    config: &PeaToml,
-    linker: &Linker,
+/// Record resolved dependency edges and direct dependencies on each
+/// package, leaving out edges to packages that weren't installed
+fn attach_dependency_edges(
+    packages: Vec<pea_cache::link::PackageInfo>,
+    resolution: &pea_resolver::ResolutionResult,
+) -> Vec<pea_cache::link::PackageInfo> {
+    let installed: HashSet<(String, String)> = packages.iter()
+        .map(|package| (package.name.clone(), package.version.clone()))
+        .collect();
+    packages
+        .into_iter()
+        .map(|package| {
+            let Ok(id) = pea_resolver::PackageId::from_name_version(&package.name, &package.version) else {
+                return package;
+            };
+            
+            let is_direct = resolution.roots.contains(&id);
+            let package = resolution.graph.dependencies_of(&id)
+                .into_iter()
+                .filter(|(dep, _edge)| installed.contains(&(dep.name.clone(), dep.id.version_key())))
+                .fold(package, |pkg, (dep, _edge)| {
+                    pkg.with_dependency(dep.name.clone(), dep.id.version_key())
+                });
+            if is_direct { package.as_direct() } else { package }
+        })
+        .collect()
+}
+
+/// Packages in the order their lifecycle scripts run (dependencies first)
+fn lifecycle_order(
+    packages: &[pea_cache::link::PackageInfo],
+    graph: &pea_resolver::graph::DependencyGraph,
     ctx: &CommandContext,
-) -> PeaResult<()> {
-    ctx.output.step("📋", "Reading lockfile");
-    ctx.output.warn("Lockfile reading not yet implemented, falling back to fresh install");
+) -> Vec<pea_cache::link::PackageInfo> {
+    let order = match graph.topological_sort() {
+        Ok(order) => order,
+        Err(e) => {
+            ctx.output.warn(&format!("{}; running lifecycle scripts in install order", e));
+            return packages.to_vec();
+        }
+    };
     
-    // For now, fall back to fresh install since lockfile system isn't implemented yet
-    // This ensures we still provide working functionality
-    let cache_dir = get_cache_dir()?;
-    let cas_store = Arc::new(CasStore::new(&cache_dir.join("store"))?);
-    let registry_client = Arc::new(RegistryClient::new()?);
-    let metadata_cache = Arc::new(MetadataCache::new());
-    let resolver = Resolver::new(registry_client.clone(), metadata_cache.clone());
+    let mut by_id: std::collections::HashMap<(String, String), &pea_cache::link::PackageInfo> = packages
+        .iter()
+        .map(|package| ((package.name.clone(), package.version.clone()), package))
+        .collect();
     
-    fresh_install(config, &resolver, &cas_store, linker, ctx).await
+    // Edges point from dependents to dependencies, so reverse the sort
+    order
+        .into_iter()
+        .rev()
+        .filter_map(|id| by_id.remove(&(id.name.clone(), id.version_key())).cloned())
+        .collect()
 }
 
+/// Extract shasum from integrity string
+fn extract_shasum(integrity: &str) -> String {
+    // For now, return a placeholder since we're using blake3 for integrity
+    // In a real implementation, we'd parse the integrity string properly
+    integrity.chars().filter(|c| c.is_alphanumeric()).take(40).collect()
+}
 
+/// Tarball extraction limits, with pea.toml overrides applied
+fn extract_limits(install: &pea_config::InstallSection) -> ExtractLimits {
+    let defaults = ExtractLimits::default();
+    defaults
+        .with_max_size(install.max_unpacked_size.unwrap_or(defaults.max_size))
+        .with_max_entries(install.max_tarball_entries.unwrap_or(defaults.max_entries))
+        .with_max_ratio(install.max_compression_ratio.unwrap_or(defaults.max_ratio))
+}
 
-/// Get the cache directory path
-fn get_cache_dir() -> PeaResult<Utf8PathBuf> {
-    let home_dir = dirs::home_dir()
-        .ok_or_else(|| PeaError::ConfigValidation {
-            field: "home_directory".to_string(),
-            reason: "Could not determine home directory".to_string(),
-        })?;
-    
-    let cache_dir = home_dir.join(".pea");
-    Ok(Utf8PathBuf::from_path_buf(cache_dir).unwrap())
-}
\ No newline at end of file
+/// Attach executables read from package.json
+fn with_bins(
+    package: pea_cache::link::PackageInfo,
+    bin_entries: std::collections::HashMap<String, String>,
+) -> pea_cache::link::PackageInfo {
+    bin_entries.into_iter().fold(package, |pkg, (name, path)| pkg.with_bin(name, path))
+}
@@ -0,0 +1,315 @@
+//! Lifecycle script execution.
+//!
+//! Runs `preinstall`/`install`/`postinstall` for installed dependencies,
+//! dependencies before dependents, and only for packages listed in
+//! `trusted-dependencies`. Scripts run through the platform shell with every
+//! enclosing `node_modules/.bin` on PATH. A failing script of an optional
+//! package is reported rather than failing the install.
+
+use camino::Utf8Path;
+use pea_cache::link::{InstalledState, PackageInfo};
+use pea_core::error::{PeaError, PeaResult};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use super::CommandContext;
+
+/// Scripts run for installed dependencies, in order
+const DEPENDENCY_EVENTS: [&str; 3] = ["preinstall", "install", "postinstall"];
+
+/// Outcome of running dependency lifecycle scripts
+#[derive(Debug, Default)]
+pub struct LifecycleReport {
+    /// Packages whose scripts ran
+    pub ran: usize,
+    /// Packages with scripts that aren't trusted (`name@version`)
+    pub blocked: BTreeSet<String>,
+    /// Optional packages whose scripts failed (`name@version`); they run
+    /// again on the next install
+    pub failed: BTreeSet<String>,
+}
+
+/// Run lifecycle scripts for `packages` (dependencies first) that haven't run yet
+pub async fn run_dependency_scripts(
+    packages: &[PackageInfo],
+    node_modules_dir: &Utf8Path,
+    trusted: &[String],
+    ctx: &CommandContext,
+) -> PeaResult<LifecycleReport> {
+    let mut report = LifecycleReport::default();
+    let Some(mut state) = InstalledState::load(node_modules_dir) else {
+        return Ok(report);
+    };
+
+    // A hoisted package may be placed more than once; each copy needs building
+    let mut locations: HashMap<(String, String), Vec<String>> = HashMap::new();
+    for (path, installed) in &state.packages {
+        locations.entry((installed.name.clone(), installed.version.clone()))
+            .or_default()
+            .push(path.clone());
+    }
+
+    let mut outcome = Ok(());
+    'packages: for package in packages {
+        let paths = locations.remove(&(package.name.clone(), package.version.clone())).unwrap_or_default();
+        'paths: for path in paths {
+            if state.is_built(&path) {
+                continue;
+            }
+            // Workspace packages are the user's own code, not installed artifacts
+            let package_dir = node_modules_dir.join(&path);
+            let scripts = if package.is_workspace { Vec::new() } else { lifecycle_scripts(package_dir.as_std_path()) };
+            if scripts.is_empty() {
+                state.mark_built(&path);
+                continue;
+            }
+            if !trusted.iter().any(|name| name == &package.name) {
+                report.blocked.insert(format!("{}@{}", package.name, package.version));
+                continue;
+            }
+
+            // Copies resolved with peers have them after the version
+            let version = package.version.split('(').next().unwrap_or_default();
+            for (event, command) in &scripts {
+                ctx.output.info(&format!("  ⚙️  {}@{} {}: {}", package.name, package.version, event, command));
+                let result = run_script(&package.name, version, event, command,
+                    package_dir.as_std_path(), node_modules_dir.as_std_path()).await;
+                if let Err(e) = result {
+                    if package.is_optional {
+                        ctx.output.warn(&format!("Optional dependency {}@{} failed to build: {}", package.name, package.version, e));
+                        report.failed.insert(format!("{}@{}", package.name, package.version));
+                        continue 'paths;
+                    }
+                    outcome = Err(e);
+                    break 'packages;
+                }
+            }
+            state.mark_built(&path);
+            report.ran += 1;
+        }
+    }
+
+    // Keep progress even when a script failed, so finished packages don't rerun
+    state.save(node_modules_dir)?;
+    outcome.map(|_| report)
+}
+
+/// Run one of the project's own scripts from pea.toml, if defined
+pub async fn run_project_script(
+    event: &str,
+    scripts: &HashMap<String, String>,
+    name: &str,
+    version: &str,
+    project_dir: &Path,
+    ctx: &CommandContext,
+) -> PeaResult<()> {
+    let Some(command) = scripts.get(event) else {
+        return Ok(());
+    };
+    ctx.output.info(&format!("  ⚙️  {}: {}", event, command));
+    run_script(name, version, event, command, project_dir, &project_dir.join("node_modules")).await
+}
+
+/// Run `command` through the platform shell in `cwd`
+///
+/// Every `node_modules/.bin` from `cwd` up to the filesystem root (and the
+/// project's own) is prepended to PATH, and the usual `npm_*` variables are
+/// set. Ctrl+C reaches the script through the terminal, so pea waits for it
+/// to exit and reports the signal in the exit code rather than dying first.
+pub async fn run_script(
+    package: &str,
+    version: &str,
+    event: &str,
+    command: &str,
+    cwd: &Path,
+    node_modules_dir: &Path,
+) -> PeaResult<()> {
+    let mut shell = shell_command(command);
+    shell.current_dir(cwd)
+        .env("PATH", script_path(cwd, node_modules_dir)?)
+        .env("npm_lifecycle_event", event)
+        .env("npm_lifecycle_script", command)
+        .env("npm_package_name", package)
+        .env("npm_package_version", version)
+        .env("INIT_CWD", node_modules_dir.parent().unwrap_or(cwd));
+    let package_json = cwd.join("package.json");
+    if package_json.is_file() {
+        shell.env("npm_package_json", package_json);
+    }
+
+    let error = |e| PeaError::io(format!("Failed to run {} script of {}", event, package), e);
+    let mut child = shell.spawn().map_err(error)?;
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status.map_err(error)?,
+            _ = tokio::signal::ctrl_c() => continue,
+        }
+    };
+    if status.success() {
+        return Ok(());
+    }
+
+    Err(PeaError::ScriptFailed {
+        package: format!("{}@{}", package, version),
+        script: event.to_string(),
+        code: exit_code(&status),
+    })
+}
+
+/// `command` with `args` appended, quoted for the shell
+pub(super) fn with_args(command: &str, args: &[String]) -> String {
+    args.iter().fold(command.to_string(), |mut command, arg| {
+        command.push(' ');
+        command.push_str(&quote(arg));
+        command
+    })
+}
+
+/// `arg` quoted for the platform shell
+#[cfg(unix)]
+pub(super) fn quote(arg: &str) -> String {
+    let safe = !arg.is_empty() && arg.chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// `arg` quoted for the platform shell
+#[cfg(windows)]
+pub(super) fn quote(arg: &str) -> String {
+    cmd_quote(arg)
+}
+
+/// Characters cmd.exe acts on outside its own quotes
+#[cfg(any(windows, test))]
+const CMD_SPECIAL: &str = "()[]%!^\"`<>&|;, *?";
+
+/// `arg` quoted the way Windows programs split their command line, then
+/// with everything cmd.exe acts on escaped by `^`
+///
+/// Quotes can't protect `%VAR%` (or `!VAR!` with delayed expansion) from
+/// cmd.exe, so its quoting isn't used at all: the escaped quotes reach the
+/// program untouched.
+#[cfg(any(windows, test))]
+fn cmd_quote(arg: &str) -> String {
+    let safe = !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || CMD_SPECIAL.contains(c));
+    if safe {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+        } else {
+            // Backslashes only escape when they come before a quote
+            if c == '"' {
+                quoted.push_str(&"\\".repeat(backslashes + 1));
+            }
+            backslashes = 0;
+        }
+        quoted.push(c);
+    }
+    quoted.push_str(&"\\".repeat(backslashes));
+    quoted.push('"');
+
+    quoted.chars().fold(String::new(), |mut escaped, c| {
+        if CMD_SPECIAL.contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
+/// Lifecycle scripts a package declares, in the order they run
+fn lifecycle_scripts(package_dir: &Path) -> Vec<(&'static str, String)> {
+    let scripts = std::fs::read_to_string(package_dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| json.get("scripts").cloned())
+        .unwrap_or_default();
+
+    let mut found: Vec<(&'static str, String)> = DEPENDENCY_EVENTS.iter()
+        .filter_map(|event| Some((*event, scripts.get(*event)?.as_str()?.to_string())))
+        .collect();
+
+    // Native addons without an install step are built with node-gyp, like npm does
+    let has_install = found.iter().any(|(event, _)| *event == "install" || *event == "preinstall");
+    if !has_install && package_dir.join("binding.gyp").exists() {
+        let position = found.iter().position(|(event, _)| *event == "postinstall").unwrap_or(found.len());
+        found.insert(position, ("install", "node-gyp rebuild".to_string()));
+    }
+
+    found
+}
+
+/// PATH with every enclosing `node_modules/.bin` first, innermost first
+fn script_path(cwd: &Path, node_modules_dir: &Path) -> PeaResult<std::ffi::OsString> {
+    let mut dirs: Vec<PathBuf> = cwd.ancestors()
+        .map(|dir| dir.join("node_modules").join(".bin"))
+        .collect();
+    // Packages unpacked outside the project still see its binaries
+    let project_bin = node_modules_dir.join(".bin");
+    if !dirs.contains(&project_bin) {
+        dirs.push(project_bin);
+    }
+    dirs.retain(|dir| dir.is_dir());
+    if let Some(existing) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&existing));
+    }
+
+    std::env::join_paths(dirs)
+        .map_err(|e| PeaError::io("Failed to build PATH for script".to_string(), std::io::Error::other(e)))
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> tokio::process::Command {
+    // Passed as-is: the default quoting would backslash-escape the quotes
+    // in `command`, which cmd.exe doesn't understand
+    let mut shell = tokio::process::Command::new("cmd");
+    shell.arg("/d").arg("/s").arg("/c").raw_arg(format!("\"{}\"", command));
+    shell
+}
+
+/// Exit code as a shell would report it (128 + signal when killed)
+fn exit_code(status: &std::process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cmd_quote_escapes_expansion_and_quotes() {
+        assert_eq!(cmd_quote("--flag"), "--flag");
+        assert_eq!(cmd_quote(r"C:\dir\file.txt"), r"C:\dir\file.txt");
+        assert_eq!(cmd_quote(""), r#"^"^""#);
+        assert_eq!(cmd_quote("%PATH%"), r#"^"^%PATH^%^""#);
+        assert_eq!(cmd_quote("a^b!c"), r#"^"a^^b^!c^""#);
+        assert_eq!(cmd_quote("it's two words"), r#"^"it's^ two^ words^""#);
+        assert_eq!(cmd_quote(r#"say "hi" & exit"#), r#"^"say^ \^"hi\^"^ ^&^ exit^""#);
+        // Backslashes are doubled only before a quote, including the closing one
+        assert_eq!(cmd_quote(r#"a\"b"#), r#"^"a\\\^"b^""#);
+        assert_eq!(cmd_quote(r"C:\my dir\"), r#"^"C:\my^ dir\\^""#);
+    }
+}
@@ -0,0 +1,166 @@
+//! Conversion between resolutions and `pea.lock`.
+//!
+//! Installs resolve from the registry only when the lockfile is missing, its
+//! root ranges no longer match the manifest or a path dependency changed on
+//! disk; otherwise the dependency graph is rebuilt from the lockfile as-is.
+
+use camino::Utf8PathBuf;
+use pea_cache::GitCache;
+use pea_config::PeaToml;
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::{DependencyKind, PlatformSupport, VersionReq};
+use pea_lockfile::{LockedPackage, Lockfile, RootDependency, LOCKFILE_NAME};
+use pea_registry::MetadataCache;
+use pea_resolver::{DependencyEdge, DependencyGraph, LocalPackage, PackageId, PackageNode, ResolutionResult, Resolver};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use super::{add, get_cache_dir, install, publish, CommandContext};
+
+/// Root dependencies, dev dependencies and optional dependencies as
+/// `(name, specifier)`, sorted by name; specifiers are npm-style (`^1.0.0`,
+/// `npm:other@^2`, `workspace:*`)
+pub fn root_ranges(config: &PeaToml) -> Vec<(String, String)> {
+    let mut roots: Vec<(String, String)> = config.dependencies.iter()
+        .chain(&config.dev_dependencies)
+        .chain(&config.optional_dependencies)
+        .map(|(name, spec)| (name.clone(), spec.specifier().to_string()))
+        .collect();
+    roots.sort();
+    roots.dedup_by(|a, b| a.0 == b.0);
+    roots
+}
+
+/// Names of the root dependencies that are only optional dependencies
+pub(super) fn optional_roots(config: &PeaToml) -> HashSet<String> {
+    config.optional_dependencies.keys()
+        .filter(|name| !config.dependencies.contains_key(*name) && !config.dev_dependencies.contains_key(*name))
+        .cloned()
+        .collect()
+}
+
+/// Record a resolution of `roots` with `overrides` as a lockfile
+pub fn lockfile_from_resolution(
+    resolution: &ResolutionResult,
+    roots: &[(String, String)],
+    overrides: &BTreeMap<String, String>,
+) -> Lockfile {
+    let mut lockfile = Lockfile { overrides: overrides.clone(), ..Lockfile::default() };
+    for (name, range) in roots {
+        if let Some(root) = resolution.roots.iter().find(|root| &root.name == name) {
+            lockfile.dependencies.insert(name.clone(), RootDependency {
+                range: range.clone(),
+                version: root.version_key(),
+            });
+        }
+    }
+
+    for package in resolution.graph.packages() {
+        let edges = resolution.graph.dependencies_of(&package.id);
+        lockfile.packages.push(LockedPackage {
+            name: package.name.clone(),
+            version: package.id.version_key(),
+            resolved: package.resolved_url.clone(),
+            integrity: package.integrity.clone(),
+            os: package.platform.os.clone(),
+            cpu: package.platform.cpu.clone(),
+            libc: package.platform.libc.clone(),
+            optional: edges.iter()
+                .filter(|(_, edge)| edge.optional)
+                .map(|(dependency, _)| dependency.name.clone())
+                .collect(),
+            dependencies: edges.iter()
+                .map(|(dependency, _)| (dependency.name.clone(), dependency.id.version_key()))
+                .collect(),
+            specifiers: edges.iter()
+                .map(|(dependency, edge)| (dependency.name.clone(), edge.version_req.to_string()))
+                .collect(),
+            overrides: edges.iter()
+                .filter_map(|(dependency, edge)| Some((dependency.name.clone(), edge.overridden_by.clone()?)))
+                .collect(),
+        });
+    }
+    lockfile.packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    lockfile
+}
+
+/// Rebuild the dependency graph recorded in `lockfile`
+pub fn resolution_from_lockfile(lockfile: &Lockfile) -> PeaResult<ResolutionResult> {
+    let start_time = std::time::Instant::now();
+    let mut graph = DependencyGraph::new();
+    for package in &lockfile.packages {
+        let id = package_id(&package.name, &package.version)?;
+        let node = PackageNode::new(id.name.clone(), id.version.clone(),
+            package.resolved.clone(), package.integrity.clone());
+        let platform = PlatformSupport { os: package.os.clone(), cpu: package.cpu.clone(), libc: package.libc.clone() };
+        graph.add_package(PackageNode { id, platform, ..node });
+    }
+
+    for package in &lockfile.packages {
+        let from = package_id(&package.name, &package.version)?;
+        for (name, version) in &package.dependencies {
+            let to = package_id(name, version)?;
+            // Edges keep the declared range; without a usable one, pin the locked version
+            let version_req = package.specifiers.get(name)
+                .and_then(|range| VersionReq::parse(range).ok())
+                .map_or_else(|| VersionReq::parse(&format!("={}", to.version)), Ok)
+                .map_err(|e| corrupt(format!("{}@{}: {}", name, version, e)))?;
+            let mut edge = if package.optional.contains(name) {
+                DependencyEdge::optional(version_req, DependencyKind::Normal)
+            } else {
+                DependencyEdge::normal(version_req)
+            };
+            edge.overridden_by = package.overrides.get(name).cloned();
+            graph.add_dependency(&from, &to, edge)
+                .map_err(|e| corrupt(format!("{} depends on a package that isn't locked: {}", from, e)))?;
+        }
+    }
+
+    let roots = lockfile.dependencies.iter()
+        .map(|(name, root)| package_id(name, &root.version))
+        .collect::<PeaResult<Vec<_>>>()?;
+    Ok(ResolutionResult {
+        package_count: graph.package_count(),
+        resolution_time_ms: start_time.elapsed().as_millis() as u64,
+        graph,
+        roots,
+        peer_issues: Vec::new(),
+        skipped_optional: Vec::new(),
+    })
+}
+
+/// First locked path dependency whose tarball or package.json no longer
+/// matches the lockfile, or that is gone
+pub(super) fn changed_local_package(lockfile: &Lockfile) -> Option<&str> {
+    lockfile.packages.iter()
+        .filter(|package| package.integrity != "workspace")
+        .find(|package| LocalPackage::read_resolved(&package.resolved)
+            .is_some_and(|local| local.map_or(true, |local| local.integrity != package.integrity)))
+        .map(|package| package.name.as_str())
+}
+
+/// The project's dependency tree: pea.lock if there is one, otherwise a fresh resolution
+pub async fn installed_resolution(config: &PeaToml, ctx: &CommandContext) -> PeaResult<ResolutionResult> {
+    if let Some(lockfile) = Lockfile::load(&ctx.cwd.join(LOCKFILE_NAME))? {
+        return resolution_from_lockfile(&lockfile);
+    }
+    ctx.output.warn(&format!("No {} found; resolving from the registry", LOCKFILE_NAME));
+    let client = add::registry_client(&publish::load_npmrc(&ctx.cwd), "")?;
+    let resolver = Resolver::new(Arc::new(client), Arc::new(MetadataCache::new()))
+        .with_git_cache(Arc::new(GitCache::new(get_cache_dir()?.join("git"))))
+        .with_project_dir(Utf8PathBuf::from_path_buf(ctx.cwd.clone()).unwrap_or_default())
+        .with_overrides(config.resolved_overrides()?)
+        .with_optional_roots(optional_roots(config));
+    install::resolve(&root_ranges(config), &resolver, ctx).await
+}
+
+fn package_id(name: &str, version: &str) -> PeaResult<PackageId> {
+    PackageId::from_name_version(name, version).map_err(corrupt)
+}
+
+fn corrupt(reason: String) -> PeaError {
+    PeaError::ConfigValidation {
+        field: LOCKFILE_NAME.to_string(),
+        reason: format!("{}; delete it and run pea install again", reason),
+    }
+}
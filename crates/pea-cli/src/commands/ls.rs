@@ -0,0 +1,353 @@
+//! `pea ls` command implementation.
+//!
+//! Prints the dependency tree from pea.lock (or a fresh resolution) and
+//! checks it against node_modules: packages that should be installed but
+//! aren't, directories no dependency accounts for, and peer dependencies the
+//! tree doesn't satisfy. Like `npm ls`, any problem makes the command fail
+//! after the tree is printed.
+
+use camino::Utf8PathBuf;
+use pea_cache::link::isolated::VIRTUAL_STORE_DIR;
+use pea_config::ConfigLoader;
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::{DependencyKind, Platform, VersionReq};
+use pea_resolver::{PackageId, ResolutionResult};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use super::{add, lock, why, CommandContext};
+
+/// A package directory found in node_modules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledDir {
+    pub name: String,
+    pub version: String,
+    /// Path relative to the project, e.g. `node_modules/a/node_modules/b`
+    pub path: String,
+    /// Required (non-optional) peer dependencies as name -> range
+    pub peers: BTreeMap<String, String>,
+}
+
+/// One package in the printed tree
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeNode {
+    pub version: String,
+    /// Range the parent (or the manifest) declares
+    pub range: String,
+    /// Already shown, with its dependencies, elsewhere in the tree
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deduped: bool,
+    /// Not found in node_modules
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub missing: bool,
+    /// Peer dependencies the tree doesn't satisfy
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub peer_problems: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, TreeNode>,
+}
+
+/// The tree and everything wrong with node_modules
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub name: String,
+    pub version: String,
+    pub dependencies: BTreeMap<String, TreeNode>,
+    /// node_modules paths no locked package accounts for
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extraneous: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+/// Execute the `pea ls` command
+///
+/// `depth` is how many levels below the project's dependencies to show
+/// (0 unless `all` or a `pattern` is given); `only` keeps just the normal
+/// or just the dev dependencies; `pattern` (`name` or `name@range`) shows
+/// only the paths leading to matching packages.
+pub async fn execute(
+    pattern: Option<String>,
+    depth: Option<usize>,
+    all: bool,
+    only: Option<DependencyKind>,
+    json: bool,
+    ctx: &CommandContext,
+) -> PeaResult<()> {
+    let cwd = Utf8PathBuf::from_path_buf(ctx.cwd.clone())
+        .map_err(|path| PeaError::ConfigValidation {
+            field: "cwd".to_string(),
+            reason: format!("Path is not valid UTF-8: {}", path.display()),
+        })?;
+    let (config, _source) = ConfigLoader::new(cwd).load_project_config().await?;
+
+    let roots: Vec<(String, String)> = lock::root_ranges(&config).into_iter()
+        .filter(|(name, _)| match only {
+            Some(DependencyKind::Dev) => config.dev_dependencies.contains_key(name),
+            Some(_) => config.dependencies.contains_key(name),
+            None => true,
+        })
+        .collect();
+    let resolution = lock::installed_resolution(&config, ctx).await?;
+    let installed = scan_node_modules(&ctx.cwd)?;
+
+    let pattern = pattern.as_deref()
+        .map(|pattern| {
+            let (name, requested) = add::parse_spec(pattern);
+            let range = requested
+                .map(|range| VersionReq::parse(range).map_err(|e| PeaError::ConfigValidation {
+                    field: name.to_string(),
+                    reason: format!("Invalid range '{}': {}", range, e),
+                }))
+                .transpose()?;
+            Ok::<_, PeaError>((name, range))
+        })
+        .transpose()?;
+    let depth = if all || (pattern.is_some() && depth.is_none()) { None } else { Some(depth.unwrap_or(0)) };
+
+    let mut report = build_report(&resolution, &roots, &installed, depth,
+        pattern.as_ref().map(|(name, range)| (*name, range.as_ref())));
+    report.name = config.package.name.clone();
+    report.version = config.package.version.to_string();
+
+    if json {
+        let output = serde_json::to_string_pretty(&report)
+            .map_err(|e| PeaError::JsonParse { message: e.to_string() })?;
+        println!("{}", output);
+    } else {
+        ctx.output.info(&format!("{}@{} {}", report.name, report.version, ctx.cwd.display()));
+        let mut lines = Vec::new();
+        render(&report.dependencies, "", &mut lines);
+        for line in lines {
+            ctx.output.info(&line);
+        }
+        if report.dependencies.is_empty() {
+            ctx.output.info("└── (empty)");
+        }
+        for problem in &report.problems {
+            ctx.output.warn(problem);
+        }
+    }
+
+    match report.problems.len() {
+        0 => Ok(()),
+        count => Err(PeaError::ConfigValidation {
+            field: "node_modules".to_string(),
+            reason: format!("{} problem{} found; run pea install to fix them", count, if count == 1 { "" } else { "s" }),
+        }),
+    }
+}
+
+/// Every package directory in the project's node_modules, hoisted or isolated
+pub(super) fn scan_node_modules(project_dir: &Path) -> PeaResult<Vec<InstalledDir>> {
+    let mut found = Vec::new();
+    let node_modules = project_dir.join("node_modules");
+    scan_dir(project_dir, &node_modules, &mut found)?;
+
+    // The isolated layout keeps the real directories in the virtual store
+    let virtual_store = node_modules.join(VIRTUAL_STORE_DIR);
+    if let Ok(entries) = std::fs::read_dir(&virtual_store) {
+        for entry in entries.flatten().filter(|entry| entry.path().is_dir()) {
+            scan_dir(project_dir, &entry.path().join("node_modules"), &mut found)?;
+        }
+    }
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+
+/// Collect the packages in one node_modules directory, descending into
+/// nested node_modules of real (non-symlinked) package directories
+fn scan_dir(project_dir: &Path, dir: &Path, found: &mut Vec<InstalledDir>) -> PeaResult<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(PeaError::io(format!("Failed to read {}", dir.display()), e)),
+    };
+    let mut package_dirs = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        if name.starts_with('@') {
+            for scoped in std::fs::read_dir(entry.path()).into_iter().flatten().flatten() {
+                package_dirs.push(scoped.path());
+            }
+        } else {
+            package_dirs.push(entry.path());
+        }
+    }
+
+    for package_dir in package_dirs {
+        let Some(manifest) = std::fs::read_to_string(package_dir.join("package.json")).ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        else {
+            continue;
+        };
+        let field = |key: &str| manifest.get(key).and_then(|value| value.as_str()).unwrap_or_default().to_string();
+        let optional_peer = |name: &str| manifest.pointer(&format!("/peerDependenciesMeta/{}/optional", name.replace('/', "~1")))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        let peers = manifest.get("peerDependencies")
+            .and_then(|peers| peers.as_object())
+            .map(|peers| peers.iter()
+                .filter(|(name, _)| !optional_peer(name))
+                .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+                .collect())
+            .unwrap_or_default();
+        let path = package_dir.strip_prefix(project_dir).unwrap_or(&package_dir)
+            .to_string_lossy()
+            .replace('\\', "/");
+        found.push(InstalledDir { name: field("name"), version: field("version"), path, peers });
+
+        let is_symlink = std::fs::symlink_metadata(&package_dir).is_ok_and(|meta| meta.file_type().is_symlink());
+        if !is_symlink {
+            scan_dir(project_dir, &package_dir.join("node_modules"), found)?;
+        }
+    }
+    Ok(())
+}
+
+/// Build the tree below `roots`, marking what `installed` lacks or adds
+pub(super) fn build_report(
+    resolution: &ResolutionResult,
+    roots: &[(String, String)],
+    installed: &[InstalledDir],
+    depth: Option<usize>,
+    pattern: Option<(&str, Option<&VersionReq>)>,
+) -> Report {
+    let installed_ids: HashSet<(&str, &str)> = installed.iter()
+        .map(|dir| (dir.name.as_str(), dir.version.as_str()))
+        .collect();
+    let root_versions: HashMap<&str, &PackageId> = resolution.roots.iter()
+        .map(|root| (root.name.as_str(), root))
+        .collect();
+
+    let mut report = Report::default();
+    let mut peer_problems: HashMap<PackageId, Vec<String>> = HashMap::new();
+    let mut seen_peers = HashSet::new();
+    for dir in installed {
+        let Some(package) = resolution.graph.packages()
+            .find(|package| package.name == dir.name && package.version.to_string() == dir.version)
+        else {
+            report.extraneous.push(dir.path.clone());
+            report.problems.push(format!("extraneous: {}@{} {}", dir.name, dir.version, dir.path));
+            continue;
+        };
+        // Several directories may hold the same package; check it once
+        if !seen_peers.insert(package.id.clone()) {
+            continue;
+        }
+        let dependencies = resolution.graph.dependencies_of(&package.id);
+        for (peer, range) in &dir.peers {
+            let provided = dependencies.iter()
+                .find(|(dependency, _)| &dependency.name == peer)
+                .map(|(dependency, _)| &dependency.id)
+                .or_else(|| root_versions.get(peer.as_str()).copied());
+            let problem = match (provided, VersionReq::parse(range)) {
+                (None, _) => format!("missing peer {}@{}", peer, range),
+                (Some(id), Ok(req)) if !req.matches(&id.version) => {
+                    format!("invalid peer {}@{} (found {})", peer, range, id.version)
+                }
+                _ => continue,
+            };
+            report.problems.push(format!("{}: {}", package.id, problem));
+            peer_problems.entry(package.id.clone()).or_default().push(problem);
+        }
+    }
+
+    // Optional packages for other platforms are left out on purpose
+    let platform = Platform::current();
+    let mut missing: Vec<&PackageId> = resolution.graph.packages()
+        .filter(|package| package.platform.supports(&platform))
+        .map(|package| &package.id)
+        .filter(|id| !installed_ids.contains(&(id.name.as_str(), id.version.to_string().as_str())))
+        .collect();
+    missing.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    for id in &missing {
+        report.problems.push(format!("missing: {}", id));
+    }
+    let missing: HashSet<&PackageId> = missing.into_iter().collect();
+
+    // With a pattern, only packages that lead to a match are shown
+    let visible: Option<HashSet<PackageId>> = pattern.map(|(name, range)| {
+        resolution.graph.packages()
+            .filter(|package| package.name == name && range.map_or(true, |range| range.matches(&package.version)))
+            .flat_map(|package| why::ancestors(resolution, &package.id))
+            .collect()
+    });
+
+    let tree = Tree { resolution, depth, visible: visible.as_ref(), missing: &missing, peer_problems: &peer_problems };
+    let mut expanded = HashSet::new();
+    for (name, range) in roots {
+        let Some(root) = root_versions.get(name.as_str()) else {
+            continue;
+        };
+        if let Some(node) = tree.node(root, range, 0, &mut expanded) {
+            report.dependencies.insert(name.clone(), node);
+        }
+    }
+    report
+}
+
+/// What the tree walk needs to know about the whole graph
+struct Tree<'a> {
+    resolution: &'a ResolutionResult,
+    depth: Option<usize>,
+    visible: Option<&'a HashSet<PackageId>>,
+    missing: &'a HashSet<&'a PackageId>,
+    peer_problems: &'a HashMap<PackageId, Vec<String>>,
+}
+
+impl Tree<'_> {
+    /// The node for `id` at `level` below the roots, `None` when filtered out
+    fn node(&self, id: &PackageId, range: &str, level: usize, expanded: &mut HashSet<PackageId>) -> Option<TreeNode> {
+        if self.visible.is_some_and(|visible| !visible.contains(id)) {
+            return None;
+        }
+        let mut node = TreeNode {
+            version: id.version.to_string(),
+            range: range.to_string(),
+            missing: self.missing.contains(id),
+            peer_problems: self.peer_problems.get(id).cloned().unwrap_or_default(),
+            ..TreeNode::default()
+        };
+        if !expanded.insert(id.clone()) {
+            node.deduped = true;
+            return Some(node);
+        }
+        if self.depth.is_some_and(|depth| level >= depth) {
+            return Some(node);
+        }
+        let mut dependencies = self.resolution.graph.dependencies_of(id);
+        dependencies.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+        for (dependency, edge) in dependencies {
+            if let Some(child) = self.node(&dependency.id, &edge.version_req.to_string(), level + 1, expanded) {
+                node.dependencies.insert(dependency.name.clone(), child);
+            }
+        }
+        Some(node)
+    }
+}
+
+/// Draw `dependencies` as tree lines under `prefix`
+fn render(dependencies: &BTreeMap<String, TreeNode>, prefix: &str, lines: &mut Vec<String>) {
+    let count = dependencies.len();
+    for (i, (name, node)) in dependencies.iter().enumerate() {
+        let last = i + 1 == count;
+        let mut line = format!("{}{} {}@{}", prefix, if last { "└──" } else { "├──" }, name, node.version);
+        if node.deduped {
+            line.push_str(" deduped");
+        }
+        if node.missing {
+            line.push_str(" MISSING");
+        }
+        for problem in &node.peer_problems {
+            line.push_str(&format!(" ({})", problem));
+        }
+        lines.push(line);
+        render(&node.dependencies, &format!("{}{}", prefix, if last { "    " } else { "│   " }), lines);
+    }
+}
@@ -3,7 +3,9 @@
 //! This module contains all command handlers and the central dispatch system.
 //! Each command is implemented as an async function that takes a CommandContext.
 
-use pea_core::error::PeaResult;
+use camino::Utf8PathBuf;
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::DependencyKind;
 use std::path::PathBuf;
 use tracing::info;
 
@@ -12,9 +14,22 @@ pub mod new;
 pub mod install;
 pub mod add;
 pub mod remove;
+pub mod ls;
+pub mod outdated;
+pub mod update;
+pub mod why;
 pub mod run;
+pub mod exec;
 pub mod build;
 pub mod test;
+pub mod cache;
+pub mod git;
+pub mod lifecycle;
+pub mod lock;
+pub mod optional;
+pub mod workspace;
+pub mod pack;
+pub mod publish;
 
 #[cfg(test)]
 mod tests;
@@ -53,22 +68,58 @@ pub async fn dispatch_command(command: Commands, ctx: &CommandContext) -> PeaRes
             info!("Initializing project in current directory");
             init::execute(ctx).await
         }
-        Commands::Install { frozen } => {
-            info!("Installing dependencies (frozen: {})", frozen);
-            install::execute(frozen, ctx).await
+        Commands::Install { frozen, platform } => {
+            info!("Installing dependencies (frozen: {}, platform: {:?})", frozen, platform);
+            install::execute(frozen, platform, ctx).await
         }
-        Commands::Add { package, dev } => {
-            info!("Adding dependency: {} (dev: {})", package, dev);
-            add::execute(package, dev, ctx).await
+        Commands::Add { packages, dev, peer, optional, exact } => {
+            let kind = if dev {
+                DependencyKind::Dev
+            } else if peer {
+                DependencyKind::Peer
+            } else if optional {
+                DependencyKind::Optional
+            } else {
+                DependencyKind::Normal
+            };
+            info!("Adding dependencies: {:?} ({:?})", packages, kind);
+            add::execute(packages, kind, exact, ctx).await
         }
-        Commands::Remove { package } => {
-            info!("Removing dependency: {}", package);
-            remove::execute(package, ctx).await
+        Commands::Remove { packages } => {
+            info!("Removing dependencies: {:?}", packages);
+            remove::execute(packages, ctx).await
+        }
+        Commands::Update { packages, latest, interactive } => {
+            info!("Updating dependencies: {:?} (latest: {}, interactive: {})", packages, latest, interactive);
+            update::execute(packages, latest, interactive, ctx).await
+        }
+        Commands::Outdated { json } => {
+            info!("Checking for outdated dependencies");
+            outdated::execute(json, ctx).await
+        }
+        Commands::Ls { pattern, depth, all, prod, dev, json } => {
+            let only = if prod {
+                Some(DependencyKind::Normal)
+            } else if dev {
+                Some(DependencyKind::Dev)
+            } else {
+                None
+            };
+            info!("Listing dependencies (depth: {:?}, all: {}, only: {:?})", depth, all, only);
+            ls::execute(pattern, depth, all, only, json, ctx).await
+        }
+        Commands::Why { package, json, depth } => {
+            info!("Explaining why {} is installed (depth: {:?})", package, depth);
+            why::execute(package, depth, json, ctx).await
         }
         Commands::Run { script, args } => {
             info!("Running script: {} with args: {:?}", script, args);
             run::execute(script, args, ctx).await
         }
+        Commands::Exec { package, args } => {
+            info!("Executing package binary: {} with args: {:?}", package, args);
+            exec::execute(package, args, ctx).await
+        }
         Commands::Build { minify } => {
             info!("Building for production (minify: {})", minify);
             build::execute(minify, ctx).await
@@ -81,9 +132,13 @@ pub async fn dispatch_command(command: Commands, ctx: &CommandContext) -> PeaRes
             info!("Checking configuration");
             check_config(ctx).await
         }
-        Commands::Publish { dry_run } => {
+        Commands::Pack { dry_run, pack_destination } => {
+            info!("Packing package (dry_run: {})", dry_run);
+            pack::execute(dry_run, pack_destination, ctx).await
+        }
+        Commands::Publish { dry_run, tag, access, registry } => {
             info!("Publishing package (dry_run: {})", dry_run);
-            publish_package(dry_run, ctx).await
+            publish::execute(dry_run, tag, access, registry, ctx).await
         }
         Commands::Upgrade { check } => {
             info!("Upgrading pea (check: {})", check);
@@ -93,6 +148,10 @@ pub async fn dispatch_command(command: Commands, ctx: &CommandContext) -> PeaRes
             info!("Cleaning cache (unused: {})", unused);
             clean_cache(unused, ctx).await
         }
+        Commands::Cache { command } => {
+            info!("Running cache command");
+            cache::execute(command, ctx).await
+        }
         Commands::Version => {
             info!("Showing version information");
             show_version(ctx).await
@@ -138,18 +197,23 @@ pub async fn show_help(ctx: &CommandContext) -> PeaResult<()> {
     ctx.output.info("");
     ctx.output.info("Dependencies:");
     ctx.output.info("  install        Install dependencies");
-    ctx.output.info("  add <pkg>      Add a dependency");
-    ctx.output.info("  remove <pkg>   Remove a dependency");
-    ctx.output.info("  update         Update dependencies");
+    ctx.output.info("  add <pkg>...   Add dependencies (-D, --peer, --optional, --exact)");
+    ctx.output.info("  remove <pkg>   Remove dependencies");
+    ctx.output.info("  update [pkg]   Update dependencies within their ranges (--latest, --interactive)");
+    ctx.output.info("  outdated       List dependencies with newer versions (--json)");
+    ctx.output.info("  ls [pkg]       Show the dependency tree (--depth, --all, --prod, --dev, --json)");
+    ctx.output.info("  why <pkg>      Show why a package is installed");
     ctx.output.info("  check          Check configuration");
     ctx.output.info("");
     ctx.output.info("Execution:");
     ctx.output.info("  run <script>   Run a script");
+    ctx.output.info("  x <pkg>        Run a package binary, installing it if needed");
     ctx.output.info("  test           Run tests");
     ctx.output.info("  bench          Run benchmarks");
     ctx.output.info("  build          Build for production");
     ctx.output.info("");
     ctx.output.info("Publishing:");
+    ctx.output.info("  pack           Create a package tarball");
     ctx.output.info("  publish        Publish package");
     ctx.output.info("  login          Login to registry");
     ctx.output.info("");
@@ -157,6 +221,7 @@ pub async fn show_help(ctx: &CommandContext) -> PeaResult<()> {
     ctx.output.info("  upgrade        Upgrade pea");
     ctx.output.info("  doc            Generate documentation");
     ctx.output.info("  clean          Clean cache");
+    ctx.output.info("  cache <cmd>    Inspect the package store (verify, ls, info, path, prune, export, import)");
     ctx.output.info("  version        Show version information");
     ctx.output.info("");
     ctx.output.info("Run 'pea <command> --help' for more information on a command.");
@@ -170,16 +235,6 @@ async fn check_config(ctx: &CommandContext) -> PeaResult<()> {
     Ok(())
 }
 
-async fn publish_package(dry_run: bool, ctx: &CommandContext) -> PeaResult<()> {
-    if dry_run {
-        ctx.output.info("📦 Dry run: Would publish package");
-    } else {
-        ctx.output.info("📦 Publishing package...");
-        ctx.output.warn("Publishing not yet implemented");
-    }
-    Ok(())
-}
-
 async fn upgrade_pea(check: bool, ctx: &CommandContext) -> PeaResult<()> {
     if check {
         ctx.output.info("🔍 Checking for updates...");
@@ -214,25 +269,42 @@ async fn show_version(ctx: &CommandContext) -> PeaResult<()> {
     Ok(())
 }
 
+/// Get the pea home directory (~/.pea) holding the store and caches
+pub fn get_cache_dir() -> PeaResult<Utf8PathBuf> {
+    let home_dir = dirs::home_dir()
+        .ok_or_else(|| PeaError::ConfigValidation {
+            field: "home_directory".to_string(),
+            reason: "Could not determine home directory".to_string(),
+        })?;
+    
+    let cache_dir = home_dir.join(".pea");
+    Ok(Utf8PathBuf::from_path_buf(cache_dir).unwrap())
+}
+
 /// Suggest similar commands based on edit distance
 pub fn suggest_similar_command(input: &str) -> Option<String> {
     let commands = [
-        "new", "init", "install", "add", "remove", "run", "build", "test",
-        "check", "publish", "upgrade", "clean", "version", "help"
+        "new", "init", "install", "add", "remove", "update", "outdated", "ls", "why", "run", "exec", "build", "test",
+        "check", "pack", "publish", "upgrade", "clean", "cache", "version", "help"
     ];
     
+    closest_match(input, commands).map(|s| s.to_string())
+}
+
+/// The candidate closest to `input`, if any is within two edits
+pub(crate) fn closest_match<'a>(input: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
     let mut best_match = None;
     let mut best_distance = usize::MAX;
     
-    for &command in &commands {
-        let distance = edit_distance(input, command);
+    for candidate in candidates {
+        let distance = edit_distance(input, candidate);
         if distance < best_distance && distance <= 2 {
             best_distance = distance;
-            best_match = Some(command);
+            best_match = Some(candidate);
         }
     }
     
-    best_match.map(|s| s.to_string())
+    best_match
 }
 
 /// Calculate edit distance between two strings
@@ -0,0 +1,67 @@
+//! Optional dependencies on the install platform.
+//!
+//! Optional dependencies are resolved and locked for every platform, so one
+//! pea.lock serves them all. An install leaves out the packages whose `os`,
+//! `cpu` or `libc` exclude the platform it installs for, along with what
+//! only they lead to. Packages that only optional dependencies lead to may
+//! also fail to download or build without failing the install.
+
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::Platform;
+use pea_resolver::{DependencyEdge, PackageId, ResolutionResult};
+use std::collections::HashSet;
+
+/// Packages of a resolution to install on a platform
+#[derive(Debug, Default)]
+pub struct Selection {
+    /// Packages to install
+    pub install: HashSet<PackageId>,
+    /// Those of them that only optional dependencies lead to
+    pub optional: HashSet<PackageId>,
+}
+
+/// Choose the packages of `resolution` to install on `platform`, where
+/// `optional_roots` names the project's optional dependencies
+///
+/// A package the project can't do without failing to support the platform
+/// is an error, as with npm.
+pub fn select(resolution: &ResolutionResult, optional_roots: &HashSet<String>, platform: &Platform) -> PeaResult<Selection> {
+    let supported = |id: &PackageId| resolution.graph.get_package(id)
+        .map_or(true, |package| package.platform.supports(platform));
+
+    let required = reachable(resolution, |id| !optional_roots.contains(&id.name), |edge| !edge.optional, |_| true);
+    let mut unsupported: Vec<&PackageId> = required.iter().filter(|id| !supported(id)).collect();
+    unsupported.sort_by_key(|id| id.to_string());
+    if let Some(id) = unsupported.first() {
+        let support = resolution.graph.get_package(id).map(|package| package.platform.to_string()).unwrap_or_default();
+        return Err(PeaError::ConfigValidation {
+            field: "platform".to_string(),
+            reason: format!("{} only installs on {}, not {}", id, support, platform),
+        });
+    }
+
+    let install = reachable(resolution, |_| true, |_| true, supported);
+    let optional = install.difference(&required).cloned().collect();
+    Ok(Selection { install, optional })
+}
+
+/// Packages reachable from the roots `root` accepts along edges `follow`
+/// accepts, without entering packages `enter` rejects
+fn reachable(
+    resolution: &ResolutionResult,
+    root: impl Fn(&PackageId) -> bool,
+    follow: impl Fn(&DependencyEdge) -> bool,
+    enter: impl Fn(&PackageId) -> bool,
+) -> HashSet<PackageId> {
+    let mut seen = HashSet::new();
+    let mut pending: Vec<PackageId> = resolution.roots.iter().filter(|id| root(id)).cloned().collect();
+    while let Some(id) = pending.pop() {
+        if !enter(&id) || !seen.insert(id.clone()) {
+            continue;
+        }
+        pending.extend(resolution.graph.dependencies_of(&id).into_iter()
+            .filter(|(_, edge)| follow(edge))
+            .map(|(dependency, _)| dependency.id.clone()));
+    }
+    seen
+}
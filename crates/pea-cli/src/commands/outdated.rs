@@ -0,0 +1,227 @@
+//! `pea outdated` command implementation.
+//!
+//! Compares each direct dependency's current version (locked in pea.lock,
+//! or installed in node_modules) with the newest version its range allows
+//! and the registry's `latest` tag, for the project and every workspace
+//! member. Any outdated dependency makes the command fail, so CI can gate
+//! on it.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_config::merge::ConfigSource;
+use pea_config::{ConfigLoader, PeaToml};
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::{Specifier, Version, VersionReq};
+use pea_lockfile::{Lockfile, LOCKFILE_NAME};
+use pea_registry::PackageMetadataResponse;
+use pea_resolver::semver::VersionSelector;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::{add, publish, workspace, CommandContext};
+
+/// A dependency with a newer version available
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outdated {
+    pub name: String,
+    /// Locked or installed version, `None` when not installed
+    pub current: Option<String>,
+    /// Newest version the declared range allows
+    pub wanted: Option<String>,
+    /// The registry's `latest` tag
+    pub latest: String,
+    /// Manifest section, e.g. `devDependencies`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Name of the project or workspace member that declares it
+    pub dependent: String,
+    /// Directory of that project relative to the workspace root
+    pub location: String,
+}
+
+/// Execute the `pea outdated` command
+pub async fn execute(json: bool, ctx: &CommandContext) -> PeaResult<()> {
+    let cwd = Utf8PathBuf::from_path_buf(ctx.cwd.clone())
+        .map_err(|path| PeaError::ConfigValidation {
+            field: "cwd".to_string(),
+            reason: format!("Path is not valid UTF-8: {}", path.display()),
+        })?;
+    let (config, source) = ConfigLoader::new(cwd.clone()).load_project_config().await?;
+    let project_dir = match &source {
+        ConfigSource::ProjectToml(path) | ConfigSource::ProjectJson(path) => {
+            path.parent().map(Utf8Path::to_path_buf).unwrap_or(cwd)
+        }
+        _ => cwd,
+    };
+
+    let mut projects = vec![(".".to_string(), project_dir.clone(), config.clone())];
+    if let Some(section) = &config.workspace {
+        for member in workspace::members(&project_dir, section).await? {
+            projects.push((member.path, member.dir, member.config));
+        }
+    }
+    let lockfile = Lockfile::load(project_dir.join(LOCKFILE_NAME).as_std_path())?;
+    let npmrc = publish::load_npmrc(project_dir.as_std_path());
+
+    let mut packuments: HashMap<String, PackageMetadataResponse> = HashMap::new();
+    let mut outdated = Vec::new();
+    for (location, dir, config) in &projects {
+        for (kind, name, range) in declared_ranges(config) {
+            let Ok(range) = VersionReq::parse(&range) else {
+                continue;
+            };
+            let current = if location == "." {
+                lockfile.as_ref().and_then(|lockfile| lockfile.dependencies.get(&name)).map(|root| root.version.clone())
+            } else {
+                None
+            }
+            .or_else(|| installed_version(&[dir, &project_dir], &name));
+
+            if !packuments.contains_key(&name) {
+                let metadata = add::fetch_metadata(&npmrc, &name).await?;
+                packuments.insert(name.clone(), metadata);
+            }
+            let Some((wanted, latest)) = compare(&packuments[&name], &range, current.as_deref()) else {
+                continue;
+            };
+            outdated.push(Outdated {
+                name,
+                current,
+                wanted,
+                latest,
+                kind: kind.to_string(),
+                dependent: config.package.name.clone(),
+                location: location.clone(),
+            });
+        }
+    }
+
+    if json {
+        let output = serde_json::to_string_pretty(&outdated)
+            .map_err(|e| PeaError::JsonParse { message: e.to_string() })?;
+        println!("{}", output);
+    } else if outdated.is_empty() {
+        ctx.output.success("All dependencies are up to date");
+    } else {
+        print_table(&outdated, projects.len() > 1, ctx);
+    }
+
+    match outdated.len() {
+        0 => Ok(()),
+        count => Err(PeaError::ConfigValidation {
+            field: "dependencies".to_string(),
+            reason: format!("{} outdated dependenc{}", count, if count == 1 { "y" } else { "ies" }),
+        }),
+    }
+}
+
+/// `(wanted, latest)` for a dependency on `range` at `current`, or `None`
+/// when it is up to date
+///
+/// Wanted is the newest version the range allows, preferring stable
+/// releases; it is `None` when nothing in the registry satisfies the range.
+pub(super) fn compare(
+    metadata: &PackageMetadataResponse,
+    range: &VersionReq,
+    current: Option<&str>,
+) -> Option<(Option<String>, String)> {
+    let versions: Vec<Version> = metadata.versions.keys()
+        .filter_map(|version| version.parse().ok())
+        .collect();
+    let latest = metadata.dist_tags.get("latest")
+        .and_then(|latest| latest.parse::<Version>().ok())
+        .or_else(|| versions.iter().filter(|version| !version.is_prerelease()).max().cloned())?;
+    let wanted = VersionSelector::new(versions).select_preferred(std::slice::from_ref(range), false);
+
+    let current = current.and_then(|current| current.parse::<Version>().ok());
+    let behind = match &current {
+        None => true,
+        Some(current) => wanted.as_ref().is_some_and(|wanted| wanted > current) || &latest > current,
+    };
+    behind.then(|| (wanted.map(|wanted| wanted.to_string()), latest.to_string()))
+}
+
+/// Registry dependencies declared by `config` as `(section, name, range)`,
+/// sorted by section and name; aliases, git, path, tarball and workspace
+/// dependencies are skipped
+pub(super) fn declared_ranges(config: &PeaToml) -> Vec<(&'static str, String, String)> {
+    let sections = [
+        ("dependencies", &config.dependencies),
+        ("devDependencies", &config.dev_dependencies),
+        ("optionalDependencies", &config.optional_dependencies),
+        ("peerDependencies", &config.peer_dependencies),
+    ];
+    let mut ranges = Vec::new();
+    for (kind, dependencies) in sections {
+        let mut names: Vec<&String> = dependencies.keys().collect();
+        names.sort();
+        for name in names {
+            if let Specifier::Range(range) = dependencies[name].specifier() {
+                if VersionReq::parse(&range).is_ok() {
+                    ranges.push((kind, name.clone(), range));
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// Version of `name` in the first of `dirs`' node_modules that has it
+fn installed_version(dirs: &[&Utf8PathBuf], name: &str) -> Option<String> {
+    dirs.iter().find_map(|dir| {
+        let content = std::fs::read_to_string(dir.join("node_modules").join(name).join("package.json")).ok()?;
+        let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+        manifest.get("version")?.as_str().map(str::to_string)
+    })
+}
+
+/// Print `outdated` as a table, one per workspace member when `grouped`
+///
+/// Red rows can be updated within their range; yellow rows need a range
+/// change to reach `latest`.
+fn print_table(outdated: &[Outdated], grouped: bool, ctx: &CommandContext) {
+    let colors = ctx.output.colors();
+    let cells = |entry: &Outdated| [
+        entry.name.clone(),
+        entry.current.clone().unwrap_or_else(|| "MISSING".to_string()),
+        entry.wanted.clone().unwrap_or_else(|| "-".to_string()),
+        entry.latest.clone(),
+        entry.kind.clone(),
+    ];
+    let header = ["Package", "Current", "Wanted", "Latest", "Type"].map(str::to_string);
+    let mut widths = header.clone().map(|cell| cell.len());
+    for entry in outdated {
+        for (width, cell) in widths.iter_mut().zip(cells(entry)) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let row = |cells: [String; 5]| -> String {
+        cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut location = None;
+    for entry in outdated {
+        if location != Some(&entry.location) {
+            if location.is_some() {
+                ctx.output.line("");
+            }
+            if grouped {
+                ctx.output.step("📦", &format!("{} ({})", entry.dependent, entry.location));
+            }
+            ctx.output.line(&row(header.clone()));
+            location = Some(&entry.location);
+        }
+        let within_range = match (&entry.current, &entry.wanted) {
+            (None, _) => true,
+            (Some(current), Some(wanted)) => current != wanted,
+            (Some(_), None) => false,
+        };
+        let line = row(cells(entry));
+        ctx.output.line(&if within_range { colors.red(&line) } else { colors.yellow(&line) });
+    }
+}
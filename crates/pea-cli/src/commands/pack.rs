@@ -0,0 +1,215 @@
+//! `pea pack` command implementation.
+//!
+//! Builds the tarball `pea publish` would upload and writes it to
+//! `<name>-<version>.tgz`. Projects without a package.json get one
+//! generated from pea.toml inside the tarball.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_cache::link::read_bin_entries;
+use pea_cache::tarball::{integrity, pack_list, shasum, write_package_tarball, PackedFile};
+use pea_config::merge::ConfigSource;
+use pea_config::{ConfigLoader, DependencySpec, PeaToml};
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::Specifier;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use super::cache::format_bytes;
+use super::CommandContext;
+
+/// A packed project, ready to be written or published
+#[derive(Debug)]
+pub struct PackedPackage {
+    /// Directory the package was packed from
+    pub project_dir: PathBuf,
+    /// Package name from package.json
+    pub name: String,
+    /// Package version from package.json
+    pub version: String,
+    /// The package.json that was packed
+    pub manifest: Value,
+    /// Gzipped tarball contents
+    pub tarball: Vec<u8>,
+    /// Files in the tarball, in archive order
+    pub files: Vec<PackedFile>,
+    /// sha512 subresource integrity of the tarball
+    pub integrity: String,
+    /// Hex sha1 of the tarball
+    pub shasum: String,
+}
+
+/// Execute the `pea pack` command
+pub async fn execute(dry_run: bool, destination: Option<PathBuf>, ctx: &CommandContext) -> PeaResult<()> {
+    let packed = pack_project(&ctx.cwd).await?;
+    print_summary(&packed, ctx);
+    if packed.manifest.get("private").and_then(Value::as_bool) == Some(true) {
+        ctx.output.warn("This package is private; registries will refuse to publish it");
+    }
+
+    let filename = tarball_name(&packed.name, &packed.version);
+    if dry_run {
+        ctx.output.info(&format!("Dry run: {} not written", filename));
+        return Ok(());
+    }
+
+    let destination = destination.unwrap_or_else(|| ctx.cwd.clone());
+    tokio::fs::create_dir_all(&destination).await
+        .map_err(|e| PeaError::io(format!("Failed to create {}", destination.display()), e))?;
+    let path = destination.join(&filename);
+    tokio::fs::write(&path, &packed.tarball).await
+        .map_err(|e| PeaError::io(format!("Failed to write {}", path.display()), e))?;
+
+    ctx.output.success(&format!("Packed {}", path.display()));
+    Ok(())
+}
+
+/// Pack the project containing `cwd`
+pub async fn pack_project(cwd: &std::path::Path) -> PeaResult<PackedPackage> {
+    let cwd = Utf8PathBuf::from_path_buf(cwd.to_path_buf())
+        .map_err(|path| PeaError::ConfigValidation {
+            field: "cwd".to_string(),
+            reason: format!("Path is not valid UTF-8: {}", path.display()),
+        })?;
+    let (config, source) = ConfigLoader::new(cwd.clone()).load_project_config().await?;
+    let project_dir = match &source {
+        ConfigSource::ProjectToml(path) | ConfigSource::ProjectJson(path) => {
+            path.parent().map(Utf8Path::to_path_buf).unwrap_or(cwd)
+        }
+        _ => cwd,
+    };
+
+    // An existing package.json is packed as written; otherwise one is generated
+    let package_json = project_dir.join("package.json");
+    let (manifest, generated) = if package_json.is_file() {
+        let content = std::fs::read_to_string(&package_json)
+            .map_err(|e| PeaError::io(format!("Failed to read {}", package_json), e))?;
+        let manifest: Value = serde_json::from_str(&content)
+            .map_err(|e| PeaError::JsonParse { message: format!("In file {}: {}", package_json, e) })?;
+        (manifest, None)
+    } else {
+        let manifest = manifest_from_toml(&config);
+        let bytes = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| PeaError::JsonParse { message: format!("Failed to generate package.json: {}", e) })?;
+        (manifest, Some(bytes))
+    };
+
+    let files: Vec<String> = manifest.get("files")
+        .and_then(Value::as_array)
+        .map(|files| files.iter().filter_map(|file| file.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    let mut entry_points: Vec<String> = manifest.get("main")
+        .and_then(Value::as_str)
+        .map(|main| vec![main.to_string()])
+        .unwrap_or_default();
+    if generated.is_none() {
+        entry_points.extend(read_bin_entries(&project_dir)?.into_values());
+    }
+
+    let paths = pack_list(project_dir.as_std_path(), &files, &entry_points)?;
+    let mut tarball = Vec::new();
+    let files = write_package_tarball(&mut tarball, project_dir.as_std_path(), &paths, generated.as_deref())?;
+
+    let field = |key: &str| manifest.get(key).and_then(Value::as_str).map(str::to_string);
+    let name = field("name").unwrap_or_else(|| config.package.name.clone());
+    let version = field("version").unwrap_or_else(|| config.package.version.to_string());
+
+    Ok(PackedPackage {
+        project_dir: project_dir.into_std_path_buf(),
+        name,
+        version,
+        integrity: integrity(&tarball),
+        shasum: shasum(&tarball),
+        manifest,
+        tarball,
+        files,
+    })
+}
+
+/// npm's tarball file name: `@scope/name` packs to `scope-name-<version>.tgz`
+pub fn tarball_name(name: &str, version: &str) -> String {
+    let name = name.trim_start_matches('@').replace('/', "-");
+    format!("{}-{}.tgz", name, version)
+}
+
+/// Print the file list, sizes and integrity of a packed package
+pub fn print_summary(packed: &PackedPackage, ctx: &CommandContext) {
+    ctx.output.step("📦", &format!("{}@{}", packed.name, packed.version));
+    ctx.output.info("Tarball Contents");
+    for file in &packed.files {
+        ctx.output.info(&format!("  {:>10}  {}", format_bytes(file.size), file.path));
+    }
+
+    let unpacked: u64 = packed.files.iter().map(|file| file.size).sum();
+    ctx.output.info("Tarball Details");
+    ctx.output.info(&format!("  name:          {}", packed.name));
+    ctx.output.info(&format!("  version:       {}", packed.version));
+    ctx.output.info(&format!("  filename:      {}", tarball_name(&packed.name, &packed.version)));
+    ctx.output.info(&format!("  package size:  {}", format_bytes(packed.tarball.len() as u64)));
+    ctx.output.info(&format!("  unpacked size: {}", format_bytes(unpacked)));
+    ctx.output.info(&format!("  shasum:        {}", packed.shasum));
+    ctx.output.info(&format!("  integrity:     {}", packed.integrity));
+    ctx.output.info(&format!("  total files:   {}", packed.files.len()));
+}
+
+/// package.json equivalent of a pea.toml project
+fn manifest_from_toml(config: &PeaToml) -> Value {
+    let package = &config.package;
+    let mut manifest = Map::new();
+    manifest.insert("name".into(), json!(package.name));
+    manifest.insert("version".into(), json!(package.version.to_string()));
+
+    let optional = [
+        ("description", &package.description),
+        ("main", &package.main),
+        ("license", &package.license),
+        ("homepage", &package.homepage),
+        ("repository", &package.repository),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            manifest.insert(key.into(), json!(value));
+        }
+    }
+    if let Some(author) = package.authors.first() {
+        manifest.insert("author".into(), json!(author));
+    }
+    if !package.keywords.is_empty() {
+        manifest.insert("keywords".into(), json!(package.keywords));
+    }
+    if !package.files.is_empty() {
+        manifest.insert("files".into(), json!(package.files));
+    }
+    if !config.scripts.is_empty() {
+        manifest.insert("scripts".into(), json!(config.scripts.iter().collect::<BTreeMap<_, _>>()));
+    }
+
+    let dependencies = [
+        ("dependencies", &config.dependencies),
+        ("devDependencies", &config.dev_dependencies),
+        ("peerDependencies", &config.peer_dependencies),
+        ("optionalDependencies", &config.optional_dependencies),
+    ];
+    for (key, deps) in dependencies {
+        if !deps.is_empty() {
+            manifest.insert(key.into(), json!(npm_dependencies(deps)));
+        }
+    }
+
+    Value::Object(manifest)
+}
+
+/// Dependency specs as npm range strings, sorted by name
+fn npm_dependencies(deps: &HashMap<String, DependencySpec>) -> BTreeMap<&str, String> {
+    deps.iter()
+        .map(|(name, spec)| {
+            // Registries don't understand `workspace:`; keep only an explicit range
+            let range = match spec.specifier() {
+                Specifier::Workspace(range) if matches!(range.as_str(), "*" | "^" | "~") => "*".to_string(),
+                Specifier::Workspace(range) => range,
+                specifier => specifier.to_string(),
+            };
+            (name.as_str(), range)
+        })
+        .collect()
+}
@@ -0,0 +1,88 @@
+//! `pea publish` command implementation.
+//!
+//! Packs the project like `pea pack` and uploads it to the registry picked
+//! from `--registry`, `publishConfig.registry` or `.npmrc`, authenticating
+//! with the matching `.npmrc` credentials. Command line flags win over
+//! `publishConfig`, which wins over `.npmrc`.
+
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::Version;
+use pea_registry::{publish_document, Access, RegistryClient, RegistryConfig};
+use serde_json::Value;
+use std::path::Path;
+
+use super::pack;
+use super::CommandContext;
+
+/// Dist-tag used when none is given
+const DEFAULT_TAG: &str = "latest";
+
+/// Execute the `pea publish` command
+pub async fn execute(
+    dry_run: bool,
+    tag: Option<String>,
+    access: Option<String>,
+    registry: Option<String>,
+    ctx: &CommandContext,
+) -> PeaResult<()> {
+    let packed = pack::pack_project(&ctx.cwd).await?;
+    if packed.manifest.get("private").and_then(Value::as_bool) == Some(true) {
+        return Err(PeaError::ConfigValidation {
+            field: "private".to_string(),
+            reason: format!("{} is marked private and can't be published", packed.name),
+        });
+    }
+
+    let npmrc = load_npmrc(&packed.project_dir);
+    let publish_config = |key: &str| {
+        packed.manifest.get("publishConfig")
+            .and_then(|config| config.get(key))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let registry = registry.or_else(|| publish_config("registry"))
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|| npmrc.registry_for(&packed.name));
+    let tag = tag.or_else(|| publish_config("tag")).unwrap_or_else(|| DEFAULT_TAG.to_string());
+    // A tag that looks like a version would be ambiguous with version ranges
+    if tag.parse::<Version>().is_ok() {
+        return Err(PeaError::ConfigValidation {
+            field: "tag".to_string(),
+            reason: format!("Tag '{}' can't be a version number", tag),
+        });
+    }
+    let access = access.or_else(|| publish_config("access"))
+        .map(|access| access.parse::<Access>())
+        .transpose()?;
+
+    let document = publish_document(&packed.manifest, &packed.tarball, &packed.integrity,
+        &packed.shasum, &registry, &tag, access)?;
+    pack::print_summary(&packed, ctx);
+
+    let target = format!("{}@{} to {} with tag {}", packed.name, packed.version, registry, tag);
+    if dry_run {
+        ctx.output.info(&format!("Dry run: would publish {}", target));
+        return Ok(());
+    }
+
+    ctx.output.step("🚀", &format!("Publishing {}", target));
+    let client = match npmrc.auth_for(&registry) {
+        Some(auth) => RegistryClient::with_auth(auth)?,
+        None => RegistryClient::new()?,
+    };
+    client.with_registry(&registry)
+        .publish(&packed.name, &document)
+        .await?;
+
+    ctx.output.success(&format!("+ {}@{}", packed.name, packed.version));
+    Ok(())
+}
+
+/// The project's `.npmrc`, then the user's
+pub(super) fn load_npmrc(project_dir: &Path) -> RegistryConfig {
+    let project = project_dir.join(".npmrc");
+    let user = dirs::home_dir().map(|home| home.join(".npmrc"));
+    let mut paths = vec![project.as_path()];
+    paths.extend(user.as_deref());
+    RegistryConfig::load(&paths)
+}
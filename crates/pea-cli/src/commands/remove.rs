@@ -1,17 +1,58 @@
 //! `pea remove` command implementation.
 //!
-//! Removes a dependency from the project.
+//! Removes dependencies from pea.toml or package.json without reformatting
+//! the rest of the file, drops the packages only they needed from pea.lock
+//! and reinstalls so node_modules matches.
 
-use pea_core::error::PeaResult;
-use super::CommandContext;
+use pea_core::error::{PeaError, PeaResult};
+use pea_lockfile::{Lockfile, LOCKFILE_NAME};
+
+use super::{add, install, CommandContext};
 
 /// Execute the `pea remove` command
-pub async fn execute(package: String, ctx: &CommandContext) -> PeaResult<()> {
-    ctx.output.step("➖", &format!("Removing dependency {}", package));
-    
-    // TODO: Implement actual dependency removal
-    ctx.output.warn("Dependency removal not yet implemented");
-    ctx.output.info("This will be implemented when the config parser is ready");
-    
-    Ok(())
-}
\ No newline at end of file
+pub async fn execute(packages: Vec<String>, ctx: &CommandContext) -> PeaResult<()> {
+    let (format, manifest_path) = add::project_manifest(ctx).await?;
+    let mut content = tokio::fs::read_to_string(&manifest_path).await
+        .map_err(|e| PeaError::io(format!("Failed to read {}", manifest_path), e))?;
+    let lockfile_path = ctx.cwd.join(LOCKFILE_NAME);
+    let lockfile = Lockfile::load(&lockfile_path)?;
+
+    let mut removed = Vec::new();
+    for name in &packages {
+        ctx.output.step("➖", &format!("Removing {}", name));
+        match format.remove_dependency(&content, name)? {
+            Some(edited) => {
+                content = edited;
+                removed.push(name.as_str());
+                ctx.output.info(&format!("  - {}", name));
+            }
+            None if lockfile.as_ref().is_some_and(|lockfile| lockfile.packages.iter().any(|package| &package.name == name)) => {
+                ctx.output.warn(&format!(
+                    "{} is not a direct dependency; it's installed because another package depends on it", name));
+            }
+            None => ctx.output.warn(&format!("{} is not a dependency of this project", name)),
+        }
+    }
+    if removed.is_empty() {
+        return Ok(());
+    }
+
+    tokio::fs::write(&manifest_path, &content).await
+        .map_err(|e| PeaError::io(format!("Failed to write {}", manifest_path), e))?;
+    ctx.output.success(&format!("Updated {}", format.file_name()));
+
+    // Prune the lockfile so the install below keeps every other locked version
+    if let Some(mut lockfile) = lockfile {
+        let remaining: Vec<String> = lockfile.dependencies.keys()
+            .filter(|name| !removed.contains(&name.as_str()))
+            .cloned()
+            .collect();
+        let remaining: Vec<&str> = remaining.iter().map(String::as_str).collect();
+        for package in lockfile.retain_roots(&remaining) {
+            ctx.output.info(&format!("  🗑️  {}@{}", package.name, package.version));
+        }
+        lockfile.save(&lockfile_path)?;
+    }
+
+    install::execute(false, None, ctx).await
+}
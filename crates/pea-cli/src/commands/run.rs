@@ -1,21 +1,83 @@
 //! `pea run` command implementation.
 //!
-//! Runs a script defined in pea.toml or executes a file directly.
+//! Runs a script from pea.toml (or package.json) through the system shell,
+//! with its `pre<name>` and `post<name>` hooks, or executes a file directly
+//! when no script has that name. Arguments after `--` are passed to the
+//! script itself, not the hooks.
 
-use pea_core::error::PeaResult;
-use super::CommandContext;
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_config::merge::ConfigSource;
+use pea_config::ConfigLoader;
+use pea_core::error::{PeaError, PeaResult};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::{closest_match, execute_file, lifecycle, suggest_similar_command, CommandContext};
 
 /// Execute the `pea run` command
 pub async fn execute(script: String, args: Vec<String>, ctx: &CommandContext) -> PeaResult<()> {
-    if args.is_empty() {
-        ctx.output.step("🚀", &format!("Running script: {}", script));
-    } else {
-        ctx.output.step("🚀", &format!("Running script: {} with args: {:?}", script, args));
+    let cwd = Utf8PathBuf::from_path_buf(ctx.cwd.clone())
+        .map_err(|path| PeaError::ConfigValidation {
+            field: "cwd".to_string(),
+            reason: format!("Path is not valid UTF-8: {}", path.display()),
+        })?;
+    let (config, source) = ConfigLoader::new(cwd.clone()).load_project_config().await?;
+    let project_dir = match &source {
+        ConfigSource::ProjectToml(path) | ConfigSource::ProjectJson(path) => {
+            path.parent().map(Utf8Path::to_path_buf).unwrap_or(cwd)
+        }
+        _ => cwd,
+    };
+
+    let Some(command) = config.scripts.get(&script) else {
+        return missing_script(&script, &config.scripts, ctx).await;
+    };
+
+    let name = &config.package.name;
+    let version = config.package.version.to_string();
+    let node_modules_dir = project_dir.join("node_modules");
+    let pre = format!("pre{}", script);
+    let post = format!("post{}", script);
+    let main = lifecycle::with_args(command, &args);
+    let steps = [
+        (pre.as_str(), config.scripts.get(&pre).cloned()),
+        (script.as_str(), Some(main)),
+        (post.as_str(), config.scripts.get(&post).cloned()),
+    ];
+
+    for (event, command) in steps {
+        let Some(command) = command else {
+            continue;
+        };
+        ctx.output.step("🚀", &format!("{}@{} {}", name, version, event));
+        ctx.output.info(&format!("$ {}", command));
+        lifecycle::run_script(name, &version, event, &command, project_dir.as_std_path(),
+            node_modules_dir.as_std_path()).await?;
     }
-    
-    // TODO: Implement actual script execution
-    ctx.output.warn("Script execution not yet implemented");
-    ctx.output.info("This will be implemented when the runtime is ready");
-    
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Run `script` as a file if it is one, otherwise fail with suggestions
+async fn missing_script(script: &str, scripts: &HashMap<String, String>, ctx: &CommandContext) -> PeaResult<()> {
+    let file = PathBuf::from(script);
+    if ctx.cwd.join(&file).is_file() {
+        return execute_file(file, ctx).await;
+    }
+
+    let mut names: Vec<&str> = scripts.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    ctx.output.error(&format!("Script '{}' not found", script));
+    if let Some(suggestion) = closest_match(script, names.iter().copied()) {
+        ctx.output.info(&format!("Did you mean 'pea run {}'?", suggestion));
+    } else if suggest_similar_command(script).as_deref() == Some(script) {
+        ctx.output.info(&format!("Did you mean 'pea {}'?", script));
+    }
+    if !names.is_empty() {
+        ctx.output.info(&format!("Available scripts: {}", names.join(", ")));
+    }
+
+    Err(PeaError::ConfigValidation {
+        field: "scripts".to_string(),
+        reason: format!("No script named '{}'", script),
+    })
+}
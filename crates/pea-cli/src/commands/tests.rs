@@ -187,4 +187,1357 @@ async fn test_show_help() {
     // Test help command
     let result = show_help(&ctx).await;
     assert!(result.is_ok());
-}
\ No newline at end of file
+}
+/// Create a CAS store inside a temporary directory
+fn create_test_store(temp_dir: &TempDir) -> pea_cache::CasStore {
+    let path = camino::Utf8PathBuf::from_path_buf(temp_dir.path().join("store")).unwrap();
+    pea_cache::CasStore::new(path).unwrap()
+}
+
+#[tokio::test]
+async fn test_cache_export_import_by_package() {
+    use crate::CacheCommands;
+    
+    let source_dir = create_temp_dir();
+    let ctx = create_test_context(&source_dir).await;
+    let source = create_test_store(&source_dir);
+    let lodash = source.store(b"lodash tarball").unwrap();
+    let react = source.store(b"react tarball").unwrap();
+    source.label(&lodash, "lodash@4.17.21");
+    source.label(&react, "react@18.2.0");
+    
+    let archive = source_dir.path().join("bundle.tgz");
+    let result = cache::run(CacheCommands::Export {
+        output: archive.clone(),
+        packages: vec!["lodash".to_string()],
+    }, &source, &ctx);
+    assert!(result.is_ok());
+    
+    let target_dir = create_temp_dir();
+    let target = create_test_store(&target_dir);
+    let result = cache::run(CacheCommands::Import { archive }, &target, &ctx);
+    assert!(result.is_ok());
+    assert!(target.contains(&lodash));
+    assert!(!target.contains(&react));
+    
+    assert!(cache::run(CacheCommands::Info { package: "lodash@4.17.21".to_string() }, &target, &ctx).is_ok());
+}
+
+#[tokio::test]
+async fn test_cache_unknown_package() {
+    use crate::CacheCommands;
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    let store = create_test_store(&temp_dir);
+    let hash = store.store(b"lodash tarball").unwrap();
+    store.label(&hash, "lodash@4.17.21");
+    
+    // A prefix of another package name must not match
+    let result = cache::run(CacheCommands::Info { package: "lod".to_string() }, &store, &ctx);
+    assert!(matches!(result, Err(PeaError::NotInCache { ref package }) if package == "lod"));
+    
+    let result = cache::run(CacheCommands::Export {
+        output: temp_dir.path().join("out.tgz"),
+        packages: vec!["react".to_string()],
+    }, &store, &ctx);
+    assert!(matches!(result, Err(PeaError::NotInCache { ref package }) if package == "react"));
+}
+
+#[tokio::test]
+async fn test_cache_verify_and_prune() {
+    use crate::CacheCommands;
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    let store = create_test_store(&temp_dir);
+    let hash = store.store(b"content").unwrap();
+    fs::write(store.hash_to_path(&hash), b"corrupted").unwrap();
+    
+    assert!(cache::run(CacheCommands::Verify, &store, &ctx).is_ok());
+    assert!(!store.contains(&hash));
+    assert!(cache::run(CacheCommands::Prune { max_age_days: 30 }, &store, &ctx).is_ok());
+    assert!(cache::run(CacheCommands::Ls, &store, &ctx).is_ok());
+}
+
+/// Link a single package whose package.json declares `scripts`
+#[cfg(unix)]
+fn link_scripted_package(root: &std::path::Path, scripts: serde_json::Value) -> (camino::Utf8PathBuf, pea_cache::link::PackageInfo) {
+    use camino::Utf8PathBuf;
+    use pea_cache::{CasStore, Linker};
+    use pea_cache::link::PackageInfo;
+    
+    let root = Utf8PathBuf::from_path_buf(root.to_path_buf()).unwrap();
+    let source = root.join("src/native");
+    fs::create_dir_all(&source).unwrap();
+    let manifest = serde_json::json!({"name": "native", "version": "1.0.0", "scripts": scripts});
+    fs::write(source.join("package.json"), manifest.to_string()).unwrap();
+    
+    let package = PackageInfo::new("native".into(), "1.0.0".into(), source).as_direct();
+    let linker = Linker::new(std::sync::Arc::new(CasStore::new(root.join("store")).unwrap()));
+    let node_modules = root.join("node_modules");
+    linker.install_node_modules(std::slice::from_ref(&package), &node_modules).unwrap();
+    (node_modules, package)
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_lifecycle_scripts_require_trust() {
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    let (node_modules, package) = link_scripted_package(temp_dir.path(), serde_json::json!({
+        "preinstall": "echo pre > order.txt",
+        "postinstall": "echo \"$npm_lifecycle_event\" >> order.txt",
+    }));
+    let packages = [package];
+    
+    // Untrusted packages are reported, not run
+    let report = lifecycle::run_dependency_scripts(&packages, &node_modules, &[], &ctx).await.unwrap();
+    assert_eq!(report.ran, 0);
+    assert!(report.blocked.contains("native@1.0.0"));
+    assert!(!node_modules.join("native/order.txt").exists());
+    
+    let trusted = vec!["native".to_string()];
+    let report = lifecycle::run_dependency_scripts(&packages, &node_modules, &trusted, &ctx).await.unwrap();
+    assert_eq!(report.ran, 1);
+    assert_eq!(fs::read_to_string(node_modules.join("native/order.txt")).unwrap(), "pre\npostinstall\n");
+    
+    // Scripts don't run again for an unchanged package
+    let report = lifecycle::run_dependency_scripts(&packages, &node_modules, &trusted, &ctx).await.unwrap();
+    assert_eq!(report.ran, 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_lifecycle_script_failure_exit_code() {
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    let (node_modules, package) = link_scripted_package(temp_dir.path(), serde_json::json!({
+        "install": "exit 3",
+    }));
+    
+    let trusted = vec!["native".to_string()];
+    let error = lifecycle::run_dependency_scripts(std::slice::from_ref(&package), &node_modules, &trusted, &ctx).await.unwrap_err();
+    assert!(matches!(&error, pea_core::error::PeaError::ScriptFailed { script, .. } if script == "install"));
+    assert_eq!(error.exit_code(), 3);
+    
+    // An optional package failing to build doesn't fail the install
+    let report = lifecycle::run_dependency_scripts(&[package.as_optional()], &node_modules, &trusted, &ctx).await.unwrap();
+    assert_eq!(report.ran, 0);
+    assert!(report.failed.contains("native@1.0.0"));
+}
+
+#[tokio::test]
+async fn test_pack_generates_manifest_from_pea_toml() {
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join("pea.toml"), r#"
+[package]
+name = "@scope/lib"
+version = "1.2.3"
+main = "index.js"
+files = ["lib"]
+
+[dependencies]
+left-pad = "^1.3.0"
+"#).unwrap();
+    fs::write(temp_dir.path().join("index.js"), "module.exports = 1;").unwrap();
+    fs::create_dir_all(temp_dir.path().join("lib")).unwrap();
+    fs::write(temp_dir.path().join("lib/util.js"), "").unwrap();
+    fs::write(temp_dir.path().join("notes.txt"), "").unwrap();
+    
+    let packed = pack::pack_project(temp_dir.path()).await.unwrap();
+    let paths: Vec<&str> = packed.files.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, ["index.js", "lib/util.js", "package.json"]);
+    assert_eq!(packed.manifest["dependencies"]["left-pad"], "^1.3.0");
+    assert_eq!(pack::tarball_name(&packed.name, &packed.version), "scope-lib-1.2.3.tgz");
+    
+    let out_dir = temp_dir.path().join("out");
+    pack::execute(false, Some(out_dir.clone()), &ctx).await.unwrap();
+    let written = fs::read(out_dir.join("scope-lib-1.2.3.tgz")).unwrap();
+    assert_eq!(written, packed.tarball);
+}
+
+#[tokio::test]
+async fn test_publish_refuses_private_packages() {
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join("package.json"),
+        r#"{"name":"secret","version":"1.0.0","private":true}"#).unwrap();
+    
+    let error = publish::execute(false, None, None, None, &ctx).await.unwrap_err();
+    assert!(matches!(error, pea_core::error::PeaError::ConfigValidation { ref field, .. } if field == "private"));
+}
+
+#[tokio::test]
+async fn test_publish_to_mock_registry() {
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    let mock_server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/@acme%2fwidgets"))
+        .and(header("authorization", "Bearer project-token"))
+        .and(body_partial_json(serde_json::json!({
+            "dist-tags": { "next": "2.0.0" },
+            "access": "public",
+        })))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    let registry = mock_server.uri();
+    fs::write(temp_dir.path().join("package.json"), serde_json::json!({
+        "name": "@acme/widgets",
+        "version": "2.0.0",
+        "publishConfig": { "tag": "next", "access": "public", "registry": registry },
+    }).to_string()).unwrap();
+    let host = registry.trim_start_matches("http:");
+    fs::write(temp_dir.path().join(".npmrc"), format!("{}/:_authToken=project-token\n", host)).unwrap();
+    
+    publish::execute(false, None, None, None, &ctx).await.unwrap();
+    
+    // Version-like tags are rejected before anything is sent
+    assert!(publish::execute(false, Some("1.0.0".to_string()), None, None, &ctx).await.is_err());
+}
+
+/// Mount registry metadata for `name` with `versions` and a `latest` tag
+async fn mock_package(server: &wiremock::MockServer, name: &str, versions: &[&str], latest: &str) {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, ResponseTemplate};
+    
+    let versions: serde_json::Map<String, serde_json::Value> = versions.iter()
+        .map(|version| (version.to_string(), serde_json::json!({
+            "version": version,
+            "dist": { "tarball": format!("{}/{}/-/{}-{}.tgz", server.uri(), name, name, version), "shasum": "0" },
+        })))
+        .collect();
+    Mock::given(method("GET"))
+        .and(path(format!("/{}", name)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "name": name,
+            "dist-tags": { "latest": latest, "next": "3.0.0-beta.1" },
+            "versions": versions,
+            "time": {},
+        })))
+        .mount(server)
+        .await;
+}
+
+/// Serve `name` with a single `version` whose metadata also has `fields`
+/// (dependencies, peerDependencies, ...)
+async fn mock_package_with(server: &wiremock::MockServer, name: &str, version: &str, fields: serde_json::Value) {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, ResponseTemplate};
+    
+    let mut metadata = serde_json::json!({
+        "version": version,
+        "dist": { "tarball": format!("{}/{}/-/{}-{}.tgz", server.uri(), name, name, version), "shasum": "0" },
+    });
+    metadata.as_object_mut().unwrap().extend(fields.as_object().cloned().unwrap_or_default());
+    Mock::given(method("GET"))
+        .and(path(format!("/{}", name)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "name": name,
+            "dist-tags": { "latest": version },
+            "versions": { version: metadata },
+            "time": {},
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_add_edits_pea_toml_and_writes_lockfile() {
+    use pea_core::types::DependencyKind;
+    
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "react", &["17.0.2", "18.2.0", "3.0.0-beta.1"], "18.2.0").await;
+    mock_package(&mock_server, "typescript", &["5.3.3", "5.4.5"], "5.4.5").await;
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join(".npmrc"), format!("registry={}/\n", mock_server.uri())).unwrap();
+    fs::write(temp_dir.path().join("pea.toml"), concat!(
+        "[package]\n",
+        "name = \"app\"\n",
+        "version = \"1.0.0\"\n",
+        "\n",
+        "# Hosts provide these\n",
+        "[peer-dependencies]\n",
+        "typescript = \"^5.0.0\" # compiler\n",
+    )).unwrap();
+    
+    add::execute(vec!["react".to_string(), "typescript@~5.3.0".to_string()],
+        DependencyKind::Peer, false, &ctx).await.unwrap();
+    
+    let manifest = fs::read_to_string(temp_dir.path().join("pea.toml")).unwrap();
+    assert!(manifest.contains("# Hosts provide these\n[peer-dependencies]\nreact = \"^18.2.0\"\ntypescript = \"~5.3.0\" # compiler\n"));
+    assert!(temp_dir.path().join("pea.lock").exists());
+    assert!(temp_dir.path().join("node_modules").is_dir());
+    
+    // Unknown tags and unsatisfiable ranges leave the manifest alone
+    assert!(add::execute(vec!["react@canary".to_string()], DependencyKind::Peer, false, &ctx).await.is_err());
+    assert!(add::execute(vec!["react@^19.0.0".to_string()], DependencyKind::Peer, false, &ctx).await.is_err());
+    assert_eq!(fs::read_to_string(temp_dir.path().join("pea.toml")).unwrap(), manifest);
+}
+
+#[tokio::test]
+async fn test_add_exact_to_package_json() {
+    use pea_core::types::DependencyKind;
+    
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "react", &["18.2.0", "3.0.0-beta.1"], "18.2.0").await;
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join(".npmrc"), format!("registry={}\n", mock_server.uri())).unwrap();
+    fs::write(temp_dir.path().join("package.json"),
+        "{\n    \"name\": \"app\",\n    \"version\": \"1.0.0\",\n    \"license\": \"MIT\"\n}\n").unwrap();
+    
+    add::execute(vec!["react@next".to_string()], DependencyKind::Optional, true, &ctx).await.unwrap();
+    
+    let manifest = fs::read_to_string(temp_dir.path().join("package.json")).unwrap();
+    assert_eq!(manifest, concat!(
+        "{\n    \"name\": \"app\",\n    \"version\": \"1.0.0\",\n    \"license\": \"MIT\",\n",
+        "    \"optionalDependencies\": {\n        \"react\": \"3.0.0-beta.1\"\n    }\n}\n",
+    ));
+}
+
+#[test]
+fn test_lockfile_round_trips_resolution() {
+    use pea_core::types::VersionReq;
+    use pea_resolver::{DependencyEdge, DependencyGraph, PackageId, PackageNode, ResolutionResult};
+    
+    let mut graph = DependencyGraph::new();
+    let node = |name: &str, version: &str| {
+        let id = PackageId::from_name_version(name, version).unwrap();
+        PackageNode::new(id.name, id.version, format!("https://r/{}.tgz", name), format!("sha512-{}", name))
+    };
+    let (app, dep) = (node("app-dep", "1.2.0"), node("leaf", "2.0.0"));
+    let (app_id, dep_id) = (app.id.clone(), dep.id.clone());
+    graph.add_package(app);
+    graph.add_package(dep);
+    graph.add_dependency(&app_id, &dep_id, DependencyEdge::normal(VersionReq::parse("^2.0.0").unwrap())).unwrap();
+    let resolution = ResolutionResult { graph, roots: vec![app_id.clone()], package_count: 2, resolution_time_ms: 0, peer_issues: Vec::new(), skipped_optional: Vec::new() };
+    
+    let roots = vec![("app-dep".to_string(), "^1.0.0".to_string())];
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &Default::default());
+    assert!(lockfile.is_up_to_date(&roots));
+    assert_eq!(lockfile.dependencies["app-dep"].version, "1.2.0");
+    assert_eq!(lockfile.find("app-dep", "1.2.0").unwrap().dependencies["leaf"], "2.0.0");
+    assert_eq!(lockfile.find("app-dep", "1.2.0").unwrap().specifiers["leaf"], "^2.0.0");
+    
+    let rebuilt = lock::resolution_from_lockfile(&lockfile).unwrap();
+    assert_eq!(rebuilt.roots, vec![app_id.clone()]);
+    assert_eq!(rebuilt.package_count, 2);
+    let deps: Vec<String> = rebuilt.graph.dependencies_of(&app_id).iter()
+        .map(|(node, edge)| format!("{} {}", node.id, edge.version_req))
+        .collect();
+    assert_eq!(deps, ["leaf@2.0.0 ^2.0.0"]);
+    assert_eq!(lock::lockfile_from_resolution(&rebuilt, &roots, &Default::default()), lockfile);
+}
+
+#[tokio::test]
+async fn test_update_relocks_chosen_packages_only() {
+    use pea_lockfile::{LockedPackage, Lockfile, RootDependency};
+    use std::collections::BTreeSet;
+    
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "a", &["1.0.0", "1.1.0", "2.0.0"], "2.0.0").await;
+    mock_package(&mock_server, "b", &["2.0.0", "2.0.5", "2.1.0", "3.0.0"], "3.0.0").await;
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join(".npmrc"), format!("registry={}/\n", mock_server.uri())).unwrap();
+    fs::write(temp_dir.path().join("pea.toml"),
+        "[package]\nname = \"app\"\nversion = \"1.0.0\"\n\n[dependencies]\na = \"^1.0.0\"\nb = \"~2.0.0\"\n").unwrap();
+    
+    let roots = vec![("a".to_string(), "^1.0.0".to_string()), ("b".to_string(), "~2.0.0".to_string())];
+    let mut lockfile = Lockfile::default();
+    for (name, range, version) in [("a", "^1.0.0", "1.0.0"), ("b", "~2.0.0", "2.0.0")] {
+        lockfile.dependencies.insert(name.to_string(),
+            RootDependency { range: range.to_string(), version: version.to_string() });
+        lockfile.packages.push(LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            resolved: format!("{}/{}/-/{}-{}.tgz", mock_server.uri(), name, name, version),
+            integrity: "0".to_string(),
+            os: Vec::new(),
+            cpu: Vec::new(),
+            libc: Vec::new(),
+            optional: Default::default(),
+            dependencies: Default::default(),
+            specifiers: Default::default(),
+            overrides: Default::default(),
+        });
+    }
+    
+    let resolver = || {
+        let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+        pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()))
+    };
+    let versions = |resolution: &pea_resolver::ResolutionResult| -> Vec<String> {
+        resolution.roots.iter().map(|root| root.to_string()).collect()
+    };
+    
+    let only_a = BTreeSet::from(["a".to_string()]);
+    let resolution = update::relock(&roots, Some(&lockfile), &only_a, resolver(), &ctx).await.unwrap();
+    assert_eq!(versions(&resolution), ["a@1.1.0", "b@2.0.0"]);
+    let resolution = update::relock(&roots, Some(&lockfile), &BTreeSet::new(), resolver(), &ctx).await.unwrap();
+    assert_eq!(versions(&resolution), ["a@1.1.0", "b@2.0.5"]);
+    
+    // Installing after a manifest change keeps what the stale lockfile locked
+    let preferred = install::locked_versions(&lockfile, &BTreeSet::new());
+    let resolution = install::resolve(&roots[..1], &resolver().with_preferred_versions(preferred), &ctx).await.unwrap();
+    assert_eq!(versions(&resolution), ["a@1.0.0"]);
+    
+    assert_eq!(update::bump_range("^1.0.0", "2.0.0").as_deref(), Some("^2.0.0"));
+    assert_eq!(update::bump_range("~2.0", "3.0.0").as_deref(), Some("~3.0.0"));
+    assert_eq!(update::bump_range("1.0.0", "2.0.0").as_deref(), Some("2.0.0"));
+    assert_eq!(update::bump_range(">=1.2.3", "2.0.0").as_deref(), Some(">=2.0.0"));
+    assert_eq!(update::bump_range("*", "2.0.0"), None);
+    assert_eq!(update::bump_range("1.x", "2.0.0"), None);
+    
+    assert_eq!(update::parse_selection("1 3-4", 5), Some(vec![0, 2, 3]));
+    assert_eq!(update::parse_selection("2,1", 2), Some(vec![0, 1]));
+    assert_eq!(update::parse_selection("a", 3), Some(vec![0, 1, 2]));
+    assert_eq!(update::parse_selection("", 3), Some(vec![]));
+    assert_eq!(update::parse_selection("0", 3), None);
+    assert_eq!(update::parse_selection("2-5", 3), None);
+    assert_eq!(update::parse_selection("x", 3), None);
+    
+    // Only dependencies can be updated
+    assert!(update::execute(vec!["left-pad".to_string()], false, false, &ctx).await.is_err());
+}
+
+#[tokio::test]
+async fn test_resolve_honors_aliases_and_workspace_specifiers() {
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "real", &["1.0.0", "1.1.0", "2.0.0"], "2.0.0").await;
+
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::create_dir_all(temp_dir.path().join("packages/lib")).unwrap();
+    fs::write(temp_dir.path().join("packages/lib/package.json"),
+        r#"{"name": "lib", "version": "0.3.0"}"#).unwrap();
+    fs::write(temp_dir.path().join("package.json"), r#"{
+        "name": "app",
+        "version": "1.0.0",
+        "workspaces": ["packages/*"],
+        "dependencies": {"legacy": "npm:real@^1.0.0", "lib": "workspace:^"}
+    }"#).unwrap();
+
+    let (config, _) = pea_config::ConfigLoader::new(camino::Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap())
+        .load_project_config().await.unwrap();
+    let roots = lock::root_ranges(&config);
+    assert_eq!(roots, [
+        ("legacy".to_string(), "npm:real@^1.0.0".to_string()),
+        ("lib".to_string(), "workspace:^".to_string()),
+    ]);
+
+    let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+    let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()));
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+
+    // The alias is installed under its own name with the real package's tarball
+    let legacy = resolution.graph.packages().find(|package| package.name == "legacy").unwrap();
+    assert_eq!(legacy.version.to_string(), "1.1.0");
+    assert!(legacy.resolved_url.ends_with("/real/-/real-1.1.0.tgz"));
+    let lib = resolution.graph.packages().find(|package| package.name == "lib").unwrap();
+    assert!(lib.resolved_url.starts_with("file://") && lib.resolved_url.ends_with("packages/lib"));
+
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &Default::default());
+    assert_eq!(lockfile.dependencies["legacy"].range, "npm:real@^1.0.0");
+    assert!(lockfile.is_up_to_date(&roots));
+
+    // Sources the resolver can't fetch fail with the specifier in the message
+    let git = vec![("forked".to_string(), "github:user/forked#main".to_string())];
+    let error = install::resolve(&git, &resolver, &ctx).await.unwrap_err();
+    assert!(error.to_string().contains("git dependencies"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_git_dependencies_lock_commits_and_run_prepare() {
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "real", &["1.0.0", "1.2.0"], "1.2.0").await;
+
+    // v1.0.0 depends on a registry package; main adds a prepare script instead
+    let temp_dir = create_temp_dir();
+    let work = temp_dir.path().join("work");
+    fs::create_dir_all(&work).unwrap();
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git").args(args).current_dir(&work).output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    git(&["init", "--quiet", "--initial-branch=main"]);
+    git(&["config", "user.email", "dev@example.com"]);
+    git(&["config", "user.name", "Dev"]);
+    fs::write(work.join("package.json"),
+        r#"{"name": "lib", "version": "1.0.0", "dependencies": {"real": "^1.0.0"}}"#).unwrap();
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "-m", "1.0.0"]);
+    git(&["tag", "v1.0.0"]);
+    let tagged = git(&["rev-parse", "HEAD"]);
+    fs::write(work.join("package.json"),
+        r#"{"name": "lib", "version": "1.1.0", "scripts": {"prepare": "echo built > dist.txt"}}"#).unwrap();
+    git(&["commit", "--quiet", "-am", "1.1.0"]);
+    let head = git(&["rev-parse", "HEAD"]);
+    let bare = temp_dir.path().join("lib.git");
+    git(&["clone", "--quiet", "--bare", work.to_str().unwrap(), bare.to_str().unwrap()]);
+    let url = format!("file://{}", bare.display());
+
+    let ctx = create_test_context(&temp_dir).await;
+    let git_cache = pea_cache::GitCache::new(camino::Utf8PathBuf::from_path_buf(temp_dir.path().join("git-cache")).unwrap());
+    let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+    let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()))
+        .with_git_cache(std::sync::Arc::new(git_cache.clone()));
+    let roots = vec![("lib".to_string(), format!("git+{}#v1.0.0", url))];
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+
+    // The tag is locked as its commit and the checkout's dependencies are expanded
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &Default::default());
+    let lib = lockfile.find("lib", "1.0.0").unwrap();
+    assert_eq!(lib.resolved, format!("git+{}#{}", url, tagged));
+    assert_eq!(lib.dependencies["real"], "1.2.0");
+
+    let extract = |tarball: Vec<u8>, name: &str| {
+        let dir = temp_dir.path().join(name);
+        pea_cache::extract_tarball(tarball.as_slice(), &dir).unwrap();
+        dir.join("package")
+    };
+    let resolved = format!("git+{}#{}", url, head);
+    let untrusted = git::tarball("lib", &resolved, &git_cache, &[], &ctx).await.unwrap();
+    assert!(!extract(untrusted, "untrusted").join("dist.txt").exists());
+    let prepared = git::tarball("lib", &resolved, &git_cache, &["lib".to_string()], &ctx).await.unwrap();
+    let prepared = extract(prepared, "prepared");
+    assert_eq!(fs::read_to_string(prepared.join("dist.txt")).unwrap().trim(), "built");
+    assert!(!prepared.join("node_modules").exists());
+
+    // The shared checkout is left as committed
+    let checkout = git_cache.checkout(&url, &head).unwrap();
+    assert!(!checkout.join("dist.txt").exists());
+    assert!(!checkout.join("node_modules").exists());
+    assert!(checkout.join(".git").exists());
+}
+
+#[tokio::test]
+async fn test_path_dependencies_lock_and_detect_changes() {
+    use pea_lockfile::{Lockfile, LOCKFILE_NAME};
+
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "real", &["1.0.0", "1.2.0"], "1.2.0").await;
+
+    // ../lib is a directory depending on the registry and on a tarball beside it
+    let temp_dir = create_temp_dir();
+    let root = camino::Utf8PathBuf::from_path_buf(temp_dir.path().canonicalize().unwrap()).unwrap();
+    let app = root.join("app");
+    fs::create_dir_all(&app).unwrap();
+    fs::create_dir_all(root.join("lib")).unwrap();
+    fs::create_dir_all(root.join("inner")).unwrap();
+    fs::write(root.join("lib/package.json"),
+        r#"{"name": "lib", "version": "2.0.0", "dependencies": {"real": "^1.0.0", "inner": "file:../inner.tgz"}}"#).unwrap();
+    fs::write(root.join("inner/package.json"), r#"{"name": "inner", "version": "0.1.0"}"#).unwrap();
+    let mut tarball = Vec::new();
+    pea_cache::tarball::write_package_tarball(&mut tarball, root.join("inner").as_std_path(),
+        &["package.json".to_string()], None).unwrap();
+    fs::write(root.join("inner.tgz"), &tarball).unwrap();
+
+    let ctx = CommandContext { cwd: app.clone().into_std_path_buf(), output: crate::output::OutputHandler::new() };
+    let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+    let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()))
+        .with_project_dir(app.clone());
+    let roots = vec![("lib".to_string(), "file:../lib".to_string())];
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &Default::default());
+    let lib = lockfile.find("lib", "2.0.0").unwrap();
+    assert_eq!(lib.resolved, format!("file://{}", root.join("lib")));
+    assert_eq!(lib.dependencies["real"], "1.2.0");
+    assert_eq!(lockfile.find("inner", "0.1.0").unwrap().integrity, pea_cache::tarball::integrity(&tarball));
+    assert_eq!(lock::changed_local_package(&lockfile), None);
+
+    // On disk the paths are relative to the project, so the lock survives moving it
+    lockfile.save(app.join(LOCKFILE_NAME).as_std_path()).unwrap();
+    let content = fs::read_to_string(app.join(LOCKFILE_NAME)).unwrap();
+    assert!(content.contains("resolved = \"file:../lib\""));
+    assert!(content.contains("resolved = \"file:../inner.tgz\""));
+    assert!(!content.contains(root.as_str()));
+    let moved = root.with_file_name(format!("{}-moved", root.file_name().unwrap()));
+    fs::rename(&root, &moved).unwrap();
+    let loaded = Lockfile::load(moved.join("app").join(LOCKFILE_NAME).as_std_path()).unwrap().unwrap();
+    assert_eq!(loaded.find("lib", "2.0.0").unwrap().resolved, format!("file://{}", moved.join("lib")));
+    assert_eq!(lock::changed_local_package(&loaded), None);
+    fs::rename(&moved, &root).unwrap();
+
+    // Editing the directory's manifest or replacing the tarball invalidates the lock
+    fs::write(root.join("lib/package.json"), r#"{"name": "lib", "version": "2.0.1"}"#).unwrap();
+    assert_eq!(lock::changed_local_package(&lockfile), Some("lib"));
+    let relocked = lock::lockfile_from_resolution(&install::resolve(&roots, &pea_resolver::Resolver::new(
+        std::sync::Arc::new(pea_registry::RegistryClient::new().unwrap()), std::sync::Arc::new(pea_registry::MetadataCache::new()))
+        .with_project_dir(app.clone()), &ctx).await.unwrap(), &roots, &Default::default());
+    assert_eq!(lock::changed_local_package(&relocked), None);
+    fs::write(root.join("inner.tgz"), b"not the locked tarball").unwrap();
+    assert_eq!(lock::changed_local_package(&lockfile), Some("inner"));
+}
+
+#[tokio::test]
+async fn test_tarball_urls_are_downloaded_and_locked() {
+    use wiremock::matchers::{method, path};
+
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "real", &["1.0.0"], "1.0.0").await;
+    let temp_dir = create_temp_dir();
+    let root = camino::Utf8PathBuf::from_path_buf(temp_dir.path().canonicalize().unwrap()).unwrap();
+    fs::write(root.join("package.json"), r#"{"name": "remote", "version": "3.1.0", "dependencies": {"real": "^1.0.0"}}"#).unwrap();
+    let mut tarball = Vec::new();
+    pea_cache::tarball::write_package_tarball(&mut tarball, root.as_std_path(), &["package.json".to_string()], None).unwrap();
+    wiremock::Mock::given(method("GET"))
+        .and(path("/downloads/remote-3.1.0.tgz"))
+        .respond_with(wiremock::ResponseTemplate::new(200).set_body_bytes(tarball.clone()))
+        .mount(&mock_server)
+        .await;
+
+    let ctx = create_test_context(&temp_dir).await;
+    let client = std::sync::Arc::new(pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri()));
+    let resolver = pea_resolver::Resolver::new(client.clone(), std::sync::Arc::new(pea_registry::MetadataCache::new()));
+    let url = format!("{}/downloads/remote-3.1.0.tgz", mock_server.uri());
+    let roots = vec![("remote".to_string(), url.clone())];
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &Default::default());
+    let remote = lockfile.find("remote", "3.1.0").unwrap();
+    assert_eq!(remote.resolved, url);
+    assert_eq!(remote.integrity, pea_cache::tarball::integrity(&tarball));
+    assert_eq!(remote.dependencies["real"], "1.0.0");
+
+    // Installing downloads it again and checks it against the lock
+    let locked = pea_registry::api::DistInfo {
+        tarball: remote.resolved.clone(),
+        shasum: String::new(),
+        integrity: Some(remote.integrity.clone()),
+        unpacked_size: None,
+        file_count: None,
+    };
+    assert_eq!(client.download_tarball(&locked).await.unwrap(), tarball);
+    let tampered = pea_registry::api::DistInfo { integrity: Some(pea_cache::tarball::integrity(b"other")), ..locked };
+    assert!(client.download_tarball(&tampered).await.is_err());
+}
+
+#[tokio::test]
+async fn test_overrides_force_transitive_specifiers_and_are_locked() {
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "minimist", &["1.2.5", "1.2.8", "2.0.0"], "1.2.8").await;
+
+    // lib and sub both depend on minimist; lib also on a package only an override provides
+    let temp_dir = create_temp_dir();
+    let root = camino::Utf8PathBuf::from_path_buf(temp_dir.path().canonicalize().unwrap()).unwrap();
+    for (dir, manifest) in [
+        ("lib", r#"{"name": "lib", "version": "1.0.0", "dependencies": {"minimist": "^1.0.0", "parser": "^9.0.0", "sub": "file:../sub"}}"#),
+        ("sub", r#"{"name": "sub", "version": "1.0.0", "dependencies": {"minimist": "^1.0.0"}}"#),
+    ] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+        fs::write(root.join(dir).join("package.json"), manifest).unwrap();
+    }
+    let app = root.join("app");
+    fs::create_dir_all(&app).unwrap();
+    let config = pea_config::toml::parse_pea_toml(concat!(
+        "[package]\n",
+        "name = \"app\"\n",
+        "version = \"1.0.0\"\n",
+        "\n",
+        "[dependencies]\n",
+        "lib = { path = \"../lib\" }\n",
+        "\n",
+        "[dev-dependencies]\n",
+        "minimist = \"1.2.5\"\n",
+        "\n",
+        "[overrides]\n",
+        "\"lib>minimist\" = \"$minimist\"\n",
+        "\"minimist@<1.2.8\" = \"2.0.0\"\n",
+        "parser = \"npm:minimist@2.0.0\"\n",
+    )).unwrap();
+
+    let ctx = CommandContext { cwd: app.clone().into_std_path_buf(), output: crate::output::OutputHandler::new() };
+    let roots = lock::root_ranges(&config);
+    let overrides = config.resolved_overrides().unwrap();
+    assert_eq!(overrides["lib>minimist"], "1.2.5");
+    let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+    let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()))
+        .with_project_dir(app.clone())
+        .with_overrides(overrides.clone());
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &overrides);
+
+    // The nested selector beats the ranged one, which doesn't match sub's 1.2.8
+    let lib = lockfile.find("lib", "1.0.0").unwrap();
+    assert_eq!(lib.dependencies["minimist"], "1.2.5");
+    assert_eq!(lib.dependencies["parser"], "2.0.0");
+    assert_eq!(lib.overrides["minimist"], "lib>minimist");
+    assert_eq!(lib.overrides["parser"], "parser");
+    // Specifiers stay what lib declares; the override is recorded beside them
+    assert_eq!(lib.specifiers["minimist"], "^1.0.0");
+    assert_eq!(lib.specifiers["parser"], "^9.0.0");
+    let sub = lockfile.find("sub", "1.0.0").unwrap();
+    assert_eq!(sub.dependencies["minimist"], "1.2.8");
+    assert!(sub.overrides.is_empty());
+    assert_eq!(lockfile.overrides, overrides);
+
+    let rebuilt = lock::resolution_from_lockfile(&lockfile).unwrap();
+    assert_eq!(lock::lockfile_from_resolution(&rebuilt, &roots, &overrides), lockfile);
+}
+
+#[tokio::test]
+async fn test_nested_overrides_apply_to_shared_packages_per_path() {
+    use serde_json::json;
+
+    // a and x both depend on b, which depends on c
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "c", &["1.0.0", "2.0.0"], "2.0.0").await;
+    mock_package_with(&mock_server, "b", "1.0.0", json!({ "dependencies": { "c": "^2.0.0" } })).await;
+    mock_package_with(&mock_server, "a", "1.0.0", json!({ "dependencies": { "b": "^1.0.0" } })).await;
+    mock_package_with(&mock_server, "x", "1.0.0", json!({ "dependencies": { "b": "^1.0.0" } })).await;
+
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    let roots = vec![("a".to_string(), "^1.0.0".to_string()), ("x".to_string(), "^1.0.0".to_string())];
+    // The path the selector names is reached first with a>, last with x>
+    for parent in ["a", "x"] {
+        let overrides: std::collections::BTreeMap<String, String> = [(format!("{}>b>c", parent), "1.0.0".to_string())].into();
+        let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+        let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()))
+            .with_overrides(overrides.clone());
+        let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+        let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &overrides);
+
+        let c_below = |dependent: &str| {
+            let b = &lockfile.find(dependent, "1.0.0").unwrap().dependencies["b"];
+            lockfile.find("b", b).unwrap().dependencies["c"].clone()
+        };
+        let other = if parent == "a" { "x" } else { "a" };
+        assert_eq!(c_below(parent), "1.0.0", "{}>b>c", parent);
+        assert_eq!(c_below(other), "2.0.0", "{}>b>c leaked to {}", parent, other);
+        assert_eq!(lockfile.packages.iter().filter(|package| package.name == "b").count(), 2);
+
+        let rebuilt = lock::resolution_from_lockfile(&lockfile).unwrap();
+        assert_eq!(lock::lockfile_from_resolution(&rebuilt, &roots, &overrides), lockfile);
+    }
+
+    // Without a selector that reaches below it, b stays a single copy
+    let overrides: std::collections::BTreeMap<String, String> = [("a>c".to_string(), "1.0.0".to_string())].into();
+    let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+    let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()))
+        .with_overrides(overrides.clone());
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &overrides);
+    assert!(lockfile.find("b", "1.0.0").is_some());
+}
+
+#[tokio::test]
+async fn test_peer_dependencies_resolve_beside_their_dependent() {
+    use serde_json::json;
+    
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "react", &["17.0.2", "18.2.0"], "18.2.0").await;
+    mock_package_with(&mock_server, "ui", "1.0.0", json!({
+        "peerDependencies": { "react": ">=17.0.0", "ui-icons": "^1.0.0", "ui-theme": "^1.0.0" },
+        "peerDependenciesMeta": { "ui-theme": { "optional": true } },
+    })).await;
+    mock_package_with(&mock_server, "ui-icons", "1.0.0", json!({})).await;
+    mock_package_with(&mock_server, "widgets", "1.0.0", json!({
+        "dependencies": { "ui": "^1.0.0", "react": "18.2.0" },
+    })).await;
+    mock_package_with(&mock_server, "legacy", "1.0.0", json!({
+        "peerDependencies": { "react": "^16.0.0" },
+    })).await;
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    let roots: Vec<(String, String)> = [("legacy", "^1.0.0"), ("react", "17.0.2"), ("ui", "^1.0.0"), ("widgets", "^1.0.0")]
+        .iter()
+        .map(|(name, range)| (name.to_string(), range.to_string()))
+        .collect();
+    let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+    let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()));
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &Default::default());
+    
+    // Each ui is a separate copy for the react it sits beside; the missing
+    // required peer is installed once at the top and the optional one skipped
+    let top_ui = "1.0.0(react@17.0.2)(ui-icons@1.0.0)";
+    assert_eq!(lockfile.dependencies["ui"].version, top_ui);
+    assert_eq!(lockfile.find("ui", top_ui).unwrap().dependencies["react"], "17.0.2");
+    // widgets' ui takes that peer from the top too, which names widgets' copy
+    let nested_ui = &lockfile.find("widgets", "1.0.0(ui-icons@1.0.0)").unwrap().dependencies["ui"];
+    assert_eq!(nested_ui, "1.0.0(react@18.2.0)(ui-icons@1.0.0)");
+    assert_eq!(lockfile.find("ui", nested_ui).unwrap().dependencies["react"], "18.2.0");
+    assert!(lockfile.find("ui-icons", "1.0.0").is_some());
+    assert!(!lockfile.dependencies.contains_key("ui-icons"));
+    assert!(lockfile.packages.iter().all(|package| package.name != "ui-theme"));
+    
+    // Out-of-range peers are reported, and fail strict installs
+    let issues: Vec<String> = resolution.peer_issues.iter().map(ToString::to_string).collect();
+    assert_eq!(issues, ["legacy@1.0.0(react@17.0.2): invalid peer react@^16.0.0 (found 17.0.2)"]);
+    assert!(install::check_peer_issues(&resolution, false, &ctx).is_ok());
+    assert!(install::check_peer_issues(&resolution, true, &ctx).is_err());
+    
+    let rebuilt = lock::resolution_from_lockfile(&lockfile).unwrap();
+    assert_eq!(lock::lockfile_from_resolution(&rebuilt, &roots, &Default::default()), lockfile);
+}
+
+#[tokio::test]
+async fn test_peers_of_dependencies_make_separate_copies_of_their_dependents() {
+    use serde_json::json;
+
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "react", &["17.0.2", "18.2.0"], "18.2.0").await;
+    mock_package_with(&mock_server, "ui", "1.0.0", json!({
+        "peerDependencies": { "react": ">=17.0.0" },
+    })).await;
+    mock_package_with(&mock_server, "form", "1.0.0", json!({
+        "dependencies": { "ui": "^1.0.0" },
+    })).await;
+    for name in ["admin", "widgets"] {
+        mock_package_with(&mock_server, name, "1.0.0", json!({
+            "dependencies": { "form": "^1.0.0", "react": "18.2.0" },
+        })).await;
+    }
+
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    // admin reaches form before the root form is expanded
+    let roots: Vec<(String, String)> = [("admin", "^1.0.0"), ("form", "^1.0.0"), ("react", "17.0.2"), ("widgets", "^1.0.0")]
+        .iter()
+        .map(|(name, range)| (name.to_string(), range.to_string()))
+        .collect();
+    let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+    let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()));
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &Default::default());
+
+    // form has no peers of its own, but the react its ui resolves to
+    // depends on where form is, so each place gets its own copy
+    let top_form = "1.0.0(react@17.0.2)";
+    assert_eq!(lockfile.dependencies["form"].version, top_form);
+    let top_ui = &lockfile.find("form", top_form).unwrap().dependencies["ui"];
+    assert_eq!(top_ui, "1.0.0(react@17.0.2)");
+    assert_eq!(lockfile.find("ui", top_ui).unwrap().dependencies["react"], "17.0.2");
+
+    let nested_form = "1.0.0(react@18.2.0)";
+    for name in ["admin", "widgets"] {
+        assert_eq!(lockfile.find(name, "1.0.0").unwrap().dependencies["form"], nested_form);
+    }
+    let nested_ui = &lockfile.find("form", nested_form).unwrap().dependencies["ui"];
+    assert_eq!(nested_ui, "1.0.0(react@18.2.0)");
+    assert_eq!(lockfile.find("ui", nested_ui).unwrap().dependencies["react"], "18.2.0");
+    assert_eq!(lockfile.packages.iter().filter(|package| package.name == "form").count(), 2);
+    assert!(resolution.peer_issues.is_empty());
+
+    let rebuilt = lock::resolution_from_lockfile(&lockfile).unwrap();
+    assert_eq!(lock::lockfile_from_resolution(&rebuilt, &roots, &Default::default()), lockfile);
+}
+
+#[tokio::test]
+async fn test_optional_dependencies_are_locked_for_every_platform() {
+    use serde_json::json;
+    use std::collections::HashSet;
+    
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package_with(&mock_server, "bundler", "1.0.0", json!({
+        "dependencies": { "bundler-darwin": "1.0.0" },
+        "optionalDependencies": { "bundler-darwin": "1.0.0", "bundler-linux": "1.0.0", "bundler-gone": "^1.0.0" },
+    })).await;
+    mock_package_with(&mock_server, "bundler-darwin", "1.0.0", json!({ "os": ["darwin"], "cpu": ["arm64", "x64"] })).await;
+    mock_package_with(&mock_server, "bundler-linux", "1.0.0", json!({ "os": ["linux"], "libc": ["glibc"] })).await;
+    mock_package_with(&mock_server, "watcher", "1.0.0", json!({ "os": ["darwin"] })).await;
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    let roots: Vec<(String, String)> = [("bundler", "^1.0.0"), ("unpublished", "^1.0.0"), ("watcher", "^1.0.0")]
+        .iter()
+        .map(|(name, range)| (name.to_string(), range.to_string()))
+        .collect();
+    let optional_roots: HashSet<String> = HashSet::from(["unpublished".to_string(), "watcher".to_string()]);
+    let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+    let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()))
+        .with_optional_roots(optional_roots.clone());
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+    
+    // Optional dependencies that can't be resolved are left out; the rest
+    // are locked with the platforms they install on
+    let skipped: Vec<&str> = resolution.skipped_optional.iter().map(|skipped| skipped.package.as_str()).collect();
+    assert_eq!(skipped, ["bundler-gone", "unpublished"]);
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &Default::default());
+    let bundler = lockfile.find("bundler", "1.0.0").unwrap();
+    assert_eq!(bundler.optional.iter().collect::<Vec<_>>(), ["bundler-darwin", "bundler-linux"]);
+    assert_eq!(lockfile.find("bundler-darwin", "1.0.0").unwrap().cpu, ["arm64", "x64"]);
+    let rebuilt = lock::resolution_from_lockfile(&lockfile).unwrap();
+    assert_eq!(lock::lockfile_from_resolution(&rebuilt, &roots, &Default::default()), lockfile);
+    
+    // Each install takes what its platform supports
+    let installed = |platform: &str| {
+        let selection = optional::select(&rebuilt, &optional_roots, &platform.parse().unwrap()).unwrap();
+        let mut names: Vec<String> = selection.install.iter()
+            .map(|id| format!("{}{}", id.name, if selection.optional.contains(id) { "?" } else { "" }))
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(installed("linux-x64-glibc"), ["bundler", "bundler-linux?"]);
+    assert_eq!(installed("linux-arm64-musl"), ["bundler"]);
+    assert_eq!(installed("darwin-arm64"), ["bundler", "bundler-darwin?", "watcher?"]);
+    
+    // A package the project requires must support the platform
+    let error = optional::select(&rebuilt, &HashSet::new(), &"linux-x64-glibc".parse().unwrap()).unwrap_err();
+    assert!(error.to_string().contains("watcher@1.0.0 only installs on os: darwin, not linux-x64-glibc"));
+}
+
+#[tokio::test]
+async fn test_optional_dependencies_that_fail_below_are_left_out() {
+    use serde_json::json;
+    use std::collections::HashSet;
+
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package_with(&mock_server, "bundler", "1.0.0", json!({
+        "dependencies": { "left-pad": "^1.0.0" },
+        "optionalDependencies": { "bundler-linux": "1.0.0", "bundler-native": "^1.0.0" },
+    })).await;
+    mock_package_with(&mock_server, "bundler-linux", "1.0.0", json!({ "os": ["linux"] })).await;
+    // native-helper and watcher-core aren't published
+    mock_package_with(&mock_server, "bundler-native", "1.0.0", json!({
+        "dependencies": { "left-pad": "^1.0.0", "native-helper": "^1.0.0", "node-gyp-build": "^4.0.0" },
+    })).await;
+    mock_package_with(&mock_server, "node-gyp-build", "4.8.0", json!({})).await;
+    mock_package_with(&mock_server, "left-pad", "1.3.0", json!({})).await;
+    mock_package_with(&mock_server, "watcher", "1.0.0", json!({
+        "dependencies": { "watcher-core": "^1.0.0" },
+    })).await;
+
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    let roots: Vec<(String, String)> = [("bundler", "^1.0.0"), ("watcher", "^1.0.0")]
+        .iter()
+        .map(|(name, range)| (name.to_string(), range.to_string()))
+        .collect();
+    let client = pea_registry::RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+    let resolver = pea_resolver::Resolver::new(std::sync::Arc::new(client), std::sync::Arc::new(pea_registry::MetadataCache::new()))
+        .with_optional_roots(HashSet::from(["watcher".to_string()]));
+    let resolution = install::resolve(&roots, &resolver, &ctx).await.unwrap();
+
+    // Both resolve, but something below them doesn't, so they're left out
+    // with what they brought in
+    let skipped: Vec<String> = resolution.skipped_optional.iter()
+        .map(|skipped| format!("{}@{}", skipped.package, skipped.required))
+        .collect();
+    assert_eq!(skipped, ["bundler-native@1.0.0", "watcher@1.0.0"]);
+    assert!(resolution.skipped_optional[0].conflict.contains("native-helper"));
+    assert!(resolution.skipped_optional[1].conflict.contains("watcher-core"));
+
+    let lockfile = lock::lockfile_from_resolution(&resolution, &roots, &Default::default());
+    let names: HashSet<&str> = lockfile.packages.iter().map(|package| package.name.as_str()).collect();
+    assert_eq!(names, HashSet::from(["bundler", "bundler-linux", "left-pad"]));
+    assert!(!lockfile.dependencies.contains_key("watcher"));
+    let bundler = lockfile.find("bundler", "1.0.0").unwrap();
+    assert_eq!(bundler.optional.iter().collect::<Vec<_>>(), ["bundler-linux"]);
+    assert!(!bundler.dependencies.contains_key("bundler-native"));
+    assert!(resolution.graph.validate_no_cycles().is_ok());
+}
+
+#[tokio::test]
+async fn test_outdated_reports_wanted_and_latest_per_member() {
+    use pea_core::types::VersionReq;
+    use pea_lockfile::{Lockfile, RootDependency};
+    
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "react", &["17.0.1", "17.0.2", "18.2.0", "3.0.0-beta.1"], "18.2.0").await;
+    mock_package(&mock_server, "left-pad", &["1.3.0"], "1.3.0").await;
+    mock_package(&mock_server, "typescript", &["4.9.5", "5.4.5"], "5.4.5").await;
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join(".npmrc"), format!("registry={}/\n", mock_server.uri())).unwrap();
+    fs::write(temp_dir.path().join("pea.toml"), concat!(
+        "[package]\n",
+        "name = \"app\"\n",
+        "version = \"1.0.0\"\n",
+        "\n",
+        "[dependencies]\n",
+        "react = \"^17.0.0\"\n",
+        "local = { path = \"../local\" }\n",
+        "\n",
+        "[workspace]\n",
+        "members = [\"packages/*\"]\n",
+    )).unwrap();
+    let mut lockfile = Lockfile::default();
+    lockfile.dependencies.insert("react".to_string(),
+        RootDependency { range: "^17.0.0".to_string(), version: "17.0.1".to_string() });
+    lockfile.save(&temp_dir.path().join("pea.lock")).unwrap();
+    
+    let ui = temp_dir.path().join("packages/ui");
+    fs::create_dir_all(ui.join("node_modules/left-pad")).unwrap();
+    fs::write(ui.join("package.json"), r#"{"name": "ui", "version": "0.1.0",
+        "dependencies": {"left-pad": "^1.3.0"}, "devDependencies": {"typescript": "^4.0.0"}}"#).unwrap();
+    fs::write(ui.join("node_modules/left-pad/package.json"), r#"{"name": "left-pad", "version": "1.3.0"}"#).unwrap();
+    fs::create_dir_all(temp_dir.path().join("packages/not-a-package")).unwrap();
+    
+    let members = workspace::members(
+        camino::Utf8Path::from_path(temp_dir.path()).unwrap(),
+        &pea_config::WorkspaceSection { members: vec!["packages/*".to_string()], exclude: vec![], dependencies: Default::default() },
+    ).await.unwrap();
+    assert_eq!(members.iter().map(|member| member.path.as_str()).collect::<Vec<_>>(), ["packages/ui"]);
+    
+    // react is behind within its range; typescript only behind latest and not installed
+    assert!(outdated::execute(false, &ctx).await.is_err());
+    assert!(outdated::execute(true, &ctx).await.is_err());
+    
+    let react: pea_registry::PackageMetadataResponse = serde_json::from_value(serde_json::json!({
+        "name": "react",
+        "dist-tags": { "latest": "18.2.0", "next": "19.0.0-rc.1" },
+        "versions": { "17.0.1": {"name": "react", "version": "17.0.1", "dist": {"tarball": "t", "shasum": "0"}},
+                      "17.0.2": {"name": "react", "version": "17.0.2", "dist": {"tarball": "t", "shasum": "0"}},
+                      "18.2.0": {"name": "react", "version": "18.2.0", "dist": {"tarball": "t", "shasum": "0"}},
+                      "19.0.0-rc.1": {"name": "react", "version": "19.0.0-rc.1", "dist": {"tarball": "t", "shasum": "0"}} },
+        "time": {},
+    })).unwrap();
+    let range = |range: &str| VersionReq::parse(range).unwrap();
+    assert_eq!(outdated::compare(&react, &range("^17.0.0"), Some("17.0.1")),
+        Some((Some("17.0.2".to_string()), "18.2.0".to_string())));
+    assert_eq!(outdated::compare(&react, &range("^17.0.0"), Some("17.0.2")),
+        Some((Some("17.0.2".to_string()), "18.2.0".to_string())));
+    assert_eq!(outdated::compare(&react, &range("^18.0.0"), Some("18.2.0")), None);
+    assert_eq!(outdated::compare(&react, &range("^18.0.0"), None),
+        Some((Some("18.2.0".to_string()), "18.2.0".to_string())));
+    assert_eq!(outdated::compare(&react, &range("^20.0.0"), Some("18.2.0")), None);
+    
+    // Up to date everywhere
+    fs::write(ui.join("package.json"), r#"{"name": "ui", "version": "0.1.0", "dependencies": {"left-pad": "^1.3.0"}}"#).unwrap();
+    fs::write(temp_dir.path().join("pea.toml"), "[package]\nname = \"app\"\nversion = \"1.0.0\"\n\n[workspace]\nmembers = [\"packages/*\"]\n").unwrap();
+    outdated::execute(false, &ctx).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_why_lists_every_path_with_ranges() {
+    use pea_core::types::VersionReq;
+    use pea_lockfile::{LockedPackage, Lockfile, RootDependency};
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join("pea.toml"), concat!(
+        "[package]\n",
+        "name = \"app\"\n",
+        "version = \"1.0.0\"\n",
+        "\n",
+        "[dependencies]\n",
+        "a = \"^1.0.0\"\n",
+        "b = \"^2.0.0\"\n",
+    )).unwrap();
+    
+    // app -> a -> shared@1, app -> b -> mid -> shared@2, app -> b -> shared@1
+    let package = |name: &str, version: &str, deps: &[(&str, &str, &str)]| LockedPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        resolved: format!("https://r/{}-{}.tgz", name, version),
+        integrity: format!("sha512-{}", name),
+        os: Vec::new(),
+        cpu: Vec::new(),
+        libc: Vec::new(),
+        optional: Default::default(),
+        dependencies: deps.iter().map(|(name, version, _)| (name.to_string(), version.to_string())).collect(),
+        specifiers: deps.iter().map(|(name, _, range)| (name.to_string(), range.to_string())).collect(),
+        overrides: Default::default(),
+    };
+    let mut lockfile = Lockfile::default();
+    for (name, range, version) in [("a", "^1.0.0", "1.0.0"), ("b", "^2.0.0", "2.0.0")] {
+        lockfile.dependencies.insert(name.to_string(),
+            RootDependency { range: range.to_string(), version: version.to_string() });
+    }
+    lockfile.packages = vec![
+        package("a", "1.0.0", &[("shared", "1.0.0", "^1.0.0")]),
+        package("b", "2.0.0", &[("mid", "1.0.0", "^1.0.0"), ("shared", "1.0.0", "~1.0.0")]),
+        package("mid", "1.0.0", &[("shared", "2.0.0", "^2.0.0")]),
+        package("shared", "1.0.0", &[]),
+        package("shared", "2.0.0", &[]),
+    ];
+    lockfile.save(&temp_dir.path().join("pea.lock")).unwrap();
+    
+    let resolution = lock::resolution_from_lockfile(&lockfile).unwrap();
+    let roots = vec![("a".to_string(), "^1.0.0".to_string()), ("b".to_string(), "^2.0.0".to_string())];
+    let render = |explanations: Vec<why::Explanation>| -> Vec<String> {
+        explanations.iter()
+            .map(|explanation| {
+                let paths: Vec<String> = explanation.paths.iter()
+                    .map(|path| path.iter()
+                        .map(|step| format!("{}@{} ({})", step.name, step.version, step.range))
+                        .collect::<Vec<_>>()
+                        .join(" > "))
+                    .collect();
+                format!("{}@{}: {}", explanation.name, explanation.version, paths.join(" | "))
+            })
+            .collect()
+    };
+    
+    assert_eq!(render(why::explain(&resolution, &roots, "shared", None, None)), [
+        "shared@1.0.0: a@1.0.0 (^1.0.0) > shared@1.0.0 (^1.0.0) | b@2.0.0 (^2.0.0) > shared@1.0.0 (~1.0.0)",
+        "shared@2.0.0: b@2.0.0 (^2.0.0) > mid@1.0.0 (^1.0.0) > shared@2.0.0 (^2.0.0)",
+    ]);
+    let major_two = VersionReq::parse("^2.0.0").unwrap();
+    assert_eq!(render(why::explain(&resolution, &roots, "shared", Some(&major_two), Some(1))), ["shared@2.0.0: "]);
+    assert_eq!(render(why::explain(&resolution, &roots, "b", None, Some(0))), ["b@2.0.0: b@2.0.0 (^2.0.0)"]);
+    assert!(why::explain(&resolution, &roots, "missing", None, None).is_empty());
+    
+    why::execute("shared".to_string(), None, false, &ctx).await.unwrap();
+    why::execute("shared@^2.0.0".to_string(), Some(2), true, &ctx).await.unwrap();
+    assert!(why::execute("missing".to_string(), None, false, &ctx).await.is_err());
+    assert!(why::execute("shared@^3.0.0".to_string(), None, false, &ctx).await.is_err());
+}
+
+#[tokio::test]
+async fn test_ls_marks_deduped_missing_extraneous_and_peers() {
+    use pea_core::types::DependencyKind;
+    use pea_lockfile::{LockedPackage, Lockfile, RootDependency};
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join("pea.toml"), concat!(
+        "[package]\n",
+        "name = \"app\"\n",
+        "version = \"1.0.0\"\n",
+        "\n",
+        "[dependencies]\n",
+        "b = \"^2.0.0\"\n",
+        "\n",
+        "[dev-dependencies]\n",
+        "a = \"^1.0.0\"\n",
+    )).unwrap();
+    
+    // app -> a -> shared@1, app -> b -> mid -> shared@2, app -> b -> shared@1
+    let package = |name: &str, version: &str, deps: &[(&str, &str)]| LockedPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        resolved: format!("https://r/{}-{}.tgz", name, version),
+        integrity: format!("sha512-{}", name),
+        os: Vec::new(),
+        cpu: Vec::new(),
+        libc: Vec::new(),
+        optional: Default::default(),
+        dependencies: deps.iter().map(|(name, version)| (name.to_string(), version.to_string())).collect(),
+        specifiers: deps.iter().map(|(name, version)| (name.to_string(), format!("^{}", version))).collect(),
+        overrides: Default::default(),
+    };
+    let mut lockfile = Lockfile::default();
+    for (name, range, version) in [("a", "^1.0.0", "1.0.0"), ("b", "^2.0.0", "2.0.0")] {
+        lockfile.dependencies.insert(name.to_string(),
+            RootDependency { range: range.to_string(), version: version.to_string() });
+    }
+    lockfile.packages = vec![
+        package("a", "1.0.0", &[("shared", "1.0.0")]),
+        package("b", "2.0.0", &[("mid", "1.0.0"), ("shared", "1.0.0")]),
+        package("mid", "1.0.0", &[("shared", "2.0.0")]),
+        package("shared", "1.0.0", &[]),
+        package("shared", "2.0.0", &[]),
+    ];
+    lockfile.save(&temp_dir.path().join("pea.lock")).unwrap();
+    
+    let install = |path: &str, manifest: &str| {
+        let dir = temp_dir.path().join("node_modules").join(path);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("package.json"), manifest).unwrap();
+    };
+    install("a", r#"{"name": "a", "version": "1.0.0"}"#);
+    install("b", r#"{"name": "b", "version": "2.0.0", "peerDependencies": {"a": "^2.0.0", "c": "^1.0.0"}, "peerDependenciesMeta": {"c": {"optional": true}}}"#);
+    install("shared", r#"{"name": "shared", "version": "1.0.0"}"#);
+    install("mid", r#"{"name": "mid", "version": "1.0.0"}"#);
+    install("@old/pkg", r#"{"name": "@old/pkg", "version": "0.1.0"}"#);
+    
+    let resolution = lock::resolution_from_lockfile(&lockfile).unwrap();
+    let roots = vec![("a".to_string(), "^1.0.0".to_string()), ("b".to_string(), "^2.0.0".to_string())];
+    let installed = ls::scan_node_modules(temp_dir.path()).unwrap();
+    let report = ls::build_report(&resolution, &roots, &installed, None, None);
+    
+    assert_eq!(report.extraneous, ["node_modules/@old/pkg"]);
+    assert_eq!(report.problems, [
+        "extraneous: @old/pkg@0.1.0 node_modules/@old/pkg",
+        "b@2.0.0: invalid peer a@^2.0.0 (found 1.0.0)",
+        "missing: shared@2.0.0",
+    ]);
+    let b = &report.dependencies["b"];
+    assert_eq!(b.peer_problems, ["invalid peer a@^2.0.0 (found 1.0.0)"]);
+    assert!(!report.dependencies["a"].dependencies["shared"].deduped);
+    assert!(b.dependencies["shared"].deduped);
+    assert!(b.dependencies["mid"].dependencies["shared"].missing);
+    assert_eq!(b.dependencies["mid"].dependencies["shared"].range, "^2.0.0");
+    
+    // Depth and patterns trim the tree but not the problems
+    let shallow = ls::build_report(&resolution, &roots, &installed, Some(0), None);
+    assert!(shallow.dependencies.values().all(|node| node.dependencies.is_empty()));
+    assert_eq!(shallow.problems, report.problems);
+    let to_mid = ls::build_report(&resolution, &roots, &installed, None, Some(("mid", None)));
+    assert_eq!(to_mid.dependencies.keys().collect::<Vec<_>>(), ["b"]);
+    assert_eq!(to_mid.dependencies["b"].dependencies.keys().collect::<Vec<_>>(), ["mid"]);
+    
+    assert!(ls::execute(None, None, true, None, false, &ctx).await.is_err());
+    
+    // A healthy tree lists cleanly
+    fs::remove_dir_all(temp_dir.path().join("node_modules/@old")).unwrap();
+    install("b", r#"{"name": "b", "version": "2.0.0", "peerDependencies": {"a": "^1.0.0"}}"#);
+    install("mid/node_modules/shared", r#"{"name": "shared", "version": "2.0.0"}"#);
+    ls::execute(None, None, true, None, true, &ctx).await.unwrap();
+    ls::execute(Some("shared@^2.0.0".to_string()), None, false, Some(DependencyKind::Dev), false, &ctx).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_remove_prunes_manifest_and_lockfile() {
+    use pea_lockfile::{LockedPackage, Lockfile, RootDependency};
+    
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join("pea.toml"), concat!(
+        "[package]\n",
+        "name = \"app\"\n",
+        "version = \"1.0.0\"\n",
+        "\n",
+        "[dependencies]\n",
+        "# the only dependency\n",
+        "left-pad = \"^1.3.0\"\n",
+        "\n",
+        "[scripts]\n",
+        "start = \"node index.js\" # entry\n",
+    )).unwrap();
+    let mut lockfile = Lockfile::default();
+    lockfile.dependencies.insert("left-pad".to_string(), RootDependency {
+        range: "^1.3.0".to_string(),
+        version: "1.3.0".to_string(),
+    });
+    lockfile.packages.push(LockedPackage {
+        name: "left-pad".to_string(),
+        version: "1.3.0".to_string(),
+        resolved: "https://registry.npmjs.org/left-pad/-/left-pad-1.3.0.tgz".to_string(),
+        integrity: "sha512-left".to_string(),
+        os: Vec::new(),
+        cpu: Vec::new(),
+        libc: Vec::new(),
+        optional: Default::default(),
+        dependencies: [("repeat".to_string(), "1.0.0".to_string())].into(),
+        specifiers: [("repeat".to_string(), "^1.0.0".to_string())].into(),
+        overrides: Default::default(),
+    });
+    lockfile.packages.push(LockedPackage {
+        name: "repeat".to_string(),
+        version: "1.0.0".to_string(),
+        resolved: "https://registry.npmjs.org/repeat/-/repeat-1.0.0.tgz".to_string(),
+        integrity: "sha512-repeat".to_string(),
+        os: Vec::new(),
+        cpu: Vec::new(),
+        libc: Vec::new(),
+        optional: Default::default(),
+        dependencies: Default::default(),
+        specifiers: Default::default(),
+        overrides: Default::default(),
+    });
+    let lockfile_path = temp_dir.path().join("pea.lock");
+    lockfile.save(&lockfile_path).unwrap();
+    fs::create_dir_all(temp_dir.path().join("node_modules/left-pad")).unwrap();
+    
+    // Transitive and unknown packages only warn
+    remove::execute(vec!["repeat".to_string(), "nope".to_string()], &ctx).await.unwrap();
+    assert_eq!(Lockfile::load(&lockfile_path).unwrap().unwrap(), lockfile);
+    
+    remove::execute(vec!["left-pad".to_string()], &ctx).await.unwrap();
+    
+    let manifest = fs::read_to_string(temp_dir.path().join("pea.toml")).unwrap();
+    assert!(manifest.contains("[dependencies]\n\n[scripts]\nstart = \"node index.js\" # entry\n"));
+    let lockfile = Lockfile::load(&lockfile_path).unwrap().unwrap();
+    assert!(lockfile.dependencies.is_empty() && lockfile.packages.is_empty());
+    assert!(!temp_dir.path().join("node_modules/left-pad").exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_run_script_with_hooks_args_and_path() {
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join("package.json"), serde_json::json!({
+        "name": "app",
+        "version": "2.0.0",
+        "scripts": {
+            "prebuild": "echo \"pre $npm_lifecycle_event\" > out.txt",
+            "build": "greet",
+            "postbuild": "echo \"post $npm_package_name@$npm_package_version\" >> out.txt",
+            "fail": "exit 7",
+        },
+    }).to_string()).unwrap();
+    let bin = temp_dir.path().join("node_modules/.bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("greet"), "#!/bin/sh\nfor arg in \"$@\"; do echo \"arg: $arg\" >> out.txt; done\n").unwrap();
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(bin.join("greet"), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    
+    let args = vec!["--flag".to_string(), "it's two words".to_string()];
+    run::execute("build".to_string(), args, &ctx).await.unwrap();
+    assert_eq!(fs::read_to_string(temp_dir.path().join("out.txt")).unwrap(),
+        "pre prebuild\narg: --flag\narg: it's two words\npost app@2.0.0\n");
+    
+    let error = run::execute("fail".to_string(), Vec::new(), &ctx).await.unwrap_err();
+    assert_eq!(error.exit_code(), 7);
+    
+    let error = run::execute("biuld".to_string(), Vec::new(), &ctx).await.unwrap_err();
+    assert!(matches!(error, pea_core::error::PeaError::ConfigValidation { ref field, .. } if field == "scripts"));
+}
+
+/// Write an executable shell script
+#[cfg(unix)]
+fn write_bin(path: &std::path::Path, script: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, script).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_prefers_local_bin() {
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    write_bin(&temp_dir.path().join("node_modules/.bin/hello"), "#!/bin/sh\necho \"local $*\" > out.txt\nexit 4\n");
+    
+    let exec_root = temp_dir.path().join("exec");
+    let error = exec::run("hello", &["-v".to_string(), "a b".to_string()], &exec_root, &ctx).await.unwrap_err();
+    assert_eq!(error.exit_code(), 4);
+    assert_eq!(fs::read_to_string(temp_dir.path().join("out.txt")).unwrap(), "local -v a b\n");
+    assert!(!exec_root.exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_reuses_cached_prefix() {
+    let mock_server = wiremock::MockServer::start().await;
+    mock_package(&mock_server, "acme-tool", &["1.2.0", "1.3.0"], "1.3.0").await;
+    let temp_dir = create_temp_dir();
+    let ctx = create_test_context(&temp_dir).await;
+    fs::write(temp_dir.path().join(".npmrc"), format!("registry={}/\n", mock_server.uri())).unwrap();
+    let exec_root = temp_dir.path().join("exec");
+    
+    // Requests that resolve to the same packages share a prefix
+    let lockfile = exec::resolve("acme-tool", Some("1.2.0"), &exec_root, &ctx).await.unwrap();
+    let prefix = exec::prefix_dir(&exec_root, &lockfile);
+    let ranged = exec::resolve("acme-tool", Some("~1.2.0"), &exec_root, &ctx).await.unwrap();
+    assert_eq!(exec::prefix_dir(&exec_root, &ranged), prefix);
+    let latest = exec::resolve("acme-tool", None, &exec_root, &ctx).await.unwrap();
+    assert_ne!(exec::prefix_dir(&exec_root, &latest), prefix);
+    
+    // A completed prefix is used as-is; no tarball is fetched
+    fs::create_dir_all(prefix.join("node_modules/acme-tool")).unwrap();
+    fs::write(prefix.join("node_modules/acme-tool/package.json"),
+        r#"{ "name": "acme-tool", "version": "1.2.0", "bin": { "acme": "cli.sh" } }"#).unwrap();
+    write_bin(&prefix.join("node_modules/.bin/acme"), "#!/bin/sh\necho \"$npm_package_name@$npm_package_version $1\" > out.txt\n");
+    fs::write(prefix.join(".pea-exec-ready"), "acme-tool@1.2.0\n").unwrap();
+    
+    exec::run("acme-tool@~1.2.0", &["run".to_string()], &exec_root, &ctx).await.unwrap();
+    assert_eq!(fs::read_to_string(temp_dir.path().join("out.txt")).unwrap(), "acme-tool@1.2.0 run\n");
+}
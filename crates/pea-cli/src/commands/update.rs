@@ -0,0 +1,217 @@
+//! `pea update` command implementation.
+//!
+//! Re-resolves the chosen packages (all of them by default) to the newest
+//! versions their ranges allow while every other package keeps its locked
+//! version, then rewrites pea.lock and installs. With `--latest` the
+//! manifest ranges are first moved to each package's `latest` version,
+//! keeping their `^`/`~`/exact style.
+
+use camino::Utf8Path;
+use pea_cache::GitCache;
+use pea_config::ConfigLoader;
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::{Version, VersionReq};
+use pea_lockfile::{Lockfile, LOCKFILE_NAME};
+use pea_registry::MetadataCache;
+use pea_resolver::{ResolutionResult, Resolver};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::Arc;
+
+use super::{add, get_cache_dir, install, lock, outdated, publish, CommandContext};
+
+/// Range prefixes `--latest` keeps when moving a range
+const RANGE_PREFIXES: [&str; 5] = [">=", "^", "~", ">", "="];
+
+/// Execute the `pea update` command
+///
+/// `packages` are names of direct or transitive dependencies; none means
+/// every package.
+pub async fn execute(packages: Vec<String>, latest: bool, interactive: bool, ctx: &CommandContext) -> PeaResult<()> {
+    let (format, manifest_path) = add::project_manifest(ctx).await?;
+    let project_dir = manifest_path.parent().unwrap_or(Utf8Path::new(".")).to_path_buf();
+    let (mut config, _source) = ConfigLoader::new(project_dir.clone()).load_project_config().await?;
+    let lockfile = Lockfile::load(project_dir.join(LOCKFILE_NAME).as_std_path())?;
+    let npmrc = publish::load_npmrc(project_dir.as_std_path());
+
+    let direct = outdated::declared_ranges(&config);
+    for name in &packages {
+        let known = direct.iter().any(|(_, direct, _)| direct == name)
+            || lockfile.as_ref().is_some_and(|lockfile| lockfile.packages.iter().any(|package| &package.name == name));
+        if !known {
+            return Err(PeaError::ConfigValidation {
+                field: name.clone(),
+                reason: format!("{} is not a dependency of this project", name),
+            });
+        }
+    }
+
+    let mut chosen: BTreeSet<String> = packages.into_iter().collect();
+    if interactive {
+        let mut candidates = Vec::new();
+        for (_, name, range) in &direct {
+            if !chosen.is_empty() && !chosen.contains(name) {
+                continue;
+            }
+            let current = lockfile.as_ref()
+                .and_then(|lockfile| lockfile.dependencies.get(name))
+                .map(|root| root.version.as_str());
+            let metadata = add::fetch_metadata(&npmrc, name).await?;
+            let Ok(req) = VersionReq::parse(range) else {
+                continue;
+            };
+            if let Some((wanted, newest)) = outdated::compare(&metadata, &req, current) {
+                let target = if latest { Some(newest) } else { wanted };
+                if let Some(target) = target.filter(|target| Some(target.as_str()) != current) {
+                    candidates.push((name.clone(), current.unwrap_or("-").to_string(), target));
+                }
+            }
+        }
+        chosen = select(&candidates, ctx)?.into_iter().collect();
+        if chosen.is_empty() {
+            ctx.output.info("Nothing to update");
+            return Ok(());
+        }
+    }
+
+    // Move manifest ranges before re-resolving so the lockfile matches them
+    if latest {
+        let mut content = tokio::fs::read_to_string(&manifest_path).await
+            .map_err(|e| PeaError::io(format!("Failed to read {}", manifest_path), e))?;
+        let mut changed = false;
+        for (_, name, range) in &direct {
+            if !chosen.is_empty() && !chosen.contains(name) {
+                continue;
+            }
+            let metadata = add::fetch_metadata(&npmrc, name).await?;
+            let Some(newest) = metadata.dist_tags.get("latest") else {
+                continue;
+            };
+            let Some(bumped) = bump_range(range, newest).filter(|bumped| bumped != range) else {
+                continue;
+            };
+            if let Some(edited) = format.set_range(&content, name, &bumped)? {
+                ctx.output.info(&format!("  {}: {} → {}", name, range, bumped));
+                content = edited;
+                changed = true;
+            }
+        }
+        if changed {
+            tokio::fs::write(&manifest_path, &content).await
+                .map_err(|e| PeaError::io(format!("Failed to write {}", manifest_path), e))?;
+            ctx.output.success(&format!("Updated {}", format.file_name()));
+            config = ConfigLoader::new(project_dir.clone()).load_project_config().await?.0;
+        }
+    }
+
+    let roots = lock::root_ranges(&config);
+    let overrides = config.resolved_overrides()?;
+    let client = add::registry_client(&npmrc, "")?;
+    let resolver = Resolver::new(Arc::new(client), Arc::new(MetadataCache::new()))
+        .with_git_cache(Arc::new(GitCache::new(get_cache_dir()?.join("git"))))
+        .with_project_dir(project_dir.clone())
+        .with_overrides(overrides.clone())
+        .with_optional_roots(lock::optional_roots(&config));
+    let resolution = relock(&roots, lockfile.as_ref(), &chosen, resolver, ctx).await?;
+    let strict_peers = config.install.as_ref().is_some_and(|install| install.strict_peer_dependencies);
+    install::check_peer_issues(&resolution, strict_peers, ctx)?;
+
+    let updated = lock::lockfile_from_resolution(&resolution, &roots, &overrides);
+    if lockfile.as_ref() == Some(&updated) {
+        ctx.output.info("Everything is already at the newest allowed versions");
+    }
+    for (name, root) in &updated.dependencies {
+        let before = lockfile.as_ref()
+            .and_then(|lockfile| lockfile.dependencies.get(name))
+            .map(|root| root.version.as_str());
+        if before != Some(root.version.as_str()) {
+            ctx.output.info(&format!("  {} {} → {}", name, before.unwrap_or("(new)"), root.version));
+        }
+    }
+    updated.save(project_dir.join(LOCKFILE_NAME).as_std_path())?;
+
+    install::execute(false, None, ctx).await
+}
+
+/// Resolve `roots`, keeping the locked version of every package not in
+/// `chosen` when it still satisfies the ranges (nothing is kept when
+/// `chosen` is empty)
+pub(super) async fn relock(
+    roots: &[(String, String)],
+    lockfile: Option<&Lockfile>,
+    chosen: &BTreeSet<String>,
+    resolver: Resolver,
+    ctx: &CommandContext,
+) -> PeaResult<ResolutionResult> {
+    let preferred = match lockfile {
+        Some(lockfile) if !chosen.is_empty() => install::locked_versions(lockfile, chosen),
+        _ => HashMap::new(),
+    };
+    ctx.output.step("🔄", &match chosen.len() {
+        0 => "Updating all dependencies".to_string(),
+        _ => format!("Updating {}", chosen.iter().cloned().collect::<Vec<_>>().join(", ")),
+    });
+    install::resolve(roots, &resolver.with_preferred_versions(preferred), ctx).await
+}
+
+/// `range` moved to `version`, keeping its prefix; `None` for ranges that
+/// aren't a prefixed version (`*`, `1.x`, tags, unions)
+pub(super) fn bump_range(range: &str, version: &str) -> Option<String> {
+    let range = range.trim();
+    let prefix = RANGE_PREFIXES.iter().find(|prefix| range.starts_with(**prefix)).copied().unwrap_or("");
+    let rest = range[prefix.len()..].trim_start();
+    let plain = !rest.is_empty() && rest.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    (plain || rest.parse::<Version>().is_ok()).then(|| format!("{}{}", prefix, version))
+}
+
+/// Ask which of `candidates` (`name`, `current`, `target`) to update
+fn select(candidates: &[(String, String, String)], ctx: &CommandContext) -> PeaResult<Vec<String>> {
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(PeaError::ConfigValidation {
+            field: "interactive".to_string(),
+            reason: "--interactive needs a terminal; name the packages to update instead".to_string(),
+        });
+    }
+    for (i, (name, current, target)) in candidates.iter().enumerate() {
+        ctx.output.line(&format!("  {:>2}) {} {} → {}", i + 1, name, current, target));
+    }
+    loop {
+        print!("Packages to update (e.g. 1 3-4, a for all, empty for none): ");
+        std::io::stdout().flush()
+            .map_err(|e| PeaError::io("Failed to write prompt".to_string(), e))?;
+        let mut input = String::new();
+        std::io::stdin().lock().read_line(&mut input)
+            .map_err(|e| PeaError::io("Failed to read selection".to_string(), e))?;
+        match parse_selection(&input, candidates.len()) {
+            Some(selection) => return Ok(selection.into_iter().map(|i| candidates[i].0.clone()).collect()),
+            None => ctx.output.warn("Enter numbers or ranges from the list"),
+        }
+    }
+}
+
+/// Zero-based indices picked by `input` (`1 3-4`, `1,2`, `a`), `None` if
+/// it doesn't parse or goes outside `1..=count`
+pub(super) fn parse_selection(input: &str, count: usize) -> Option<Vec<usize>> {
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("a") || input.eq_ignore_ascii_case("all") {
+        return Some((0..count).collect());
+    }
+    let mut picked = BTreeSet::new();
+    for token in input.split(|c: char| c == ',' || c.is_whitespace()).filter(|token| !token.is_empty()) {
+        let (start, end) = match token.split_once('-') {
+            Some((start, end)) => (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?),
+            None => {
+                let index = token.parse::<usize>().ok()?;
+                (index, index)
+            }
+        };
+        if start == 0 || start > end || end > count {
+            return None;
+        }
+        picked.extend(start - 1..end);
+    }
+    Some(picked.into_iter().collect())
+}
@@ -0,0 +1,198 @@
+//! `pea why` command implementation.
+//!
+//! Explains why a package is installed by listing every dependency path from
+//! the project's roots to each installed version of it, with the range each
+//! step declares. The tree comes from pea.lock, or a fresh resolution when
+//! the project has no lockfile.
+
+use camino::Utf8PathBuf;
+use pea_config::ConfigLoader;
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::VersionReq;
+use pea_resolver::{PackageId, ResolutionResult};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use super::{add, lock, CommandContext};
+
+/// One step of a dependency path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PathStep {
+    /// Package name
+    pub name: String,
+    /// Installed version
+    pub version: String,
+    /// Range the previous step (or the manifest) declares for this package
+    pub range: String,
+}
+
+/// Every path to one installed version of a package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Explanation {
+    /// Package name
+    pub name: String,
+    /// The installed version being explained
+    pub version: String,
+    /// Paths from a root dependency down to this version, each ending with it
+    pub paths: Vec<Vec<PathStep>>,
+}
+
+/// Execute the `pea why` command
+///
+/// `package` is `name` or `name@range`; `depth` limits how many steps below
+/// a root dependency a path may go.
+pub async fn execute(package: String, depth: Option<usize>, json: bool, ctx: &CommandContext) -> PeaResult<()> {
+    let cwd = Utf8PathBuf::from_path_buf(ctx.cwd.clone())
+        .map_err(|path| PeaError::ConfigValidation {
+            field: "cwd".to_string(),
+            reason: format!("Path is not valid UTF-8: {}", path.display()),
+        })?;
+    let (config, _source) = ConfigLoader::new(cwd).load_project_config().await?;
+
+    let (name, requested) = add::parse_spec(&package);
+    let range = requested
+        .map(|range| VersionReq::parse(range).map_err(|e| PeaError::ConfigValidation {
+            field: name.to_string(),
+            reason: format!("Invalid range '{}': {}", range, e),
+        }))
+        .transpose()?;
+
+    let resolution = lock::installed_resolution(&config, ctx).await?;
+    let explanations = explain(&resolution, &lock::root_ranges(&config), name, range.as_ref(), depth);
+    if explanations.is_empty() {
+        return Err(PeaError::ConfigValidation {
+            field: name.to_string(),
+            reason: format!("{} is not installed", package),
+        });
+    }
+
+    if json {
+        let output = serde_json::to_string_pretty(&explanations)
+            .map_err(|e| PeaError::JsonParse { message: e.to_string() })?;
+        println!("{}", output);
+        return Ok(());
+    }
+
+    let project = &config.package.name;
+    for explanation in &explanations {
+        ctx.output.step("📦", &format!("{}@{}", explanation.name, explanation.version));
+        if explanation.paths.is_empty() {
+            ctx.output.info(&match depth {
+                Some(depth) => format!("  No path within {} levels of the project's dependencies", depth),
+                None => "  Not reachable from the project's dependencies".to_string(),
+            });
+        }
+        for path in &explanation.paths {
+            let steps: Vec<String> = path.iter()
+                .map(|step| format!("{}@{} ({})", step.name, step.version, step.range))
+                .collect();
+            ctx.output.info(&format!("  {} > {}", project, steps.join(" > ")));
+        }
+    }
+    Ok(())
+}
+
+/// Every path from the roots to each version of `name` matching `range`
+///
+/// Versions are listed in ascending order. A version that is installed but
+/// unreachable within `depth` steps below a root is listed without paths.
+pub(super) fn explain(
+    resolution: &ResolutionResult,
+    roots: &[(String, String)],
+    name: &str,
+    range: Option<&VersionReq>,
+    depth: Option<usize>,
+) -> Vec<Explanation> {
+    let mut targets: Vec<&PackageId> = resolution.graph.packages()
+        .filter(|package| package.name == name)
+        .filter(|package| range.map_or(true, |range| range.matches(&package.version)))
+        .map(|package| &package.id)
+        .collect();
+    targets.sort_by(|a, b| a.version.cmp(&b.version));
+
+    targets.into_iter()
+        .map(|target| {
+            let leads_to_target = ancestors(resolution, target);
+            let mut paths = Vec::new();
+            for root in &resolution.roots {
+                let Some((_, root_range)) = roots.iter().find(|(name, _)| name == &root.name) else {
+                    continue;
+                };
+                if !leads_to_target.contains(root) {
+                    continue;
+                }
+                let search = Search { resolution, target, depth, leads_to_target: &leads_to_target };
+                let mut path = vec![step(root, root_range)];
+                let mut visiting = HashSet::from([root.clone()]);
+                search.collect(root, &mut path, &mut visiting, &mut paths);
+            }
+            Explanation { name: target.name.clone(), version: target.version.to_string(), paths }
+        })
+        .collect()
+}
+
+/// `target` and every package that depends on it, directly or not
+pub(super) fn ancestors(resolution: &ResolutionResult, target: &PackageId) -> HashSet<PackageId> {
+    let mut dependents: HashMap<&PackageId, Vec<&PackageId>> = HashMap::new();
+    for package in resolution.graph.packages() {
+        for (dependency, _) in resolution.graph.dependencies_of(&package.id) {
+            dependents.entry(&dependency.id).or_default().push(&package.id);
+        }
+    }
+
+    let mut found = HashSet::from([target.clone()]);
+    let mut queue = vec![target];
+    while let Some(id) = queue.pop() {
+        for dependent in dependents.get(id).into_iter().flatten() {
+            if found.insert((*dependent).clone()) {
+                queue.push(dependent);
+            }
+        }
+    }
+    found
+}
+
+/// Depth-first search for paths to one target
+struct Search<'a> {
+    resolution: &'a ResolutionResult,
+    target: &'a PackageId,
+    depth: Option<usize>,
+    /// Packages the target is reachable from; the search never leaves them
+    leads_to_target: &'a HashSet<PackageId>,
+}
+
+impl Search<'_> {
+    /// Record each path from `from` that ends at the target
+    fn collect(
+        &self,
+        from: &PackageId,
+        path: &mut Vec<PathStep>,
+        visiting: &mut HashSet<PackageId>,
+        paths: &mut Vec<Vec<PathStep>>,
+    ) {
+        if from == self.target {
+            paths.push(path.clone());
+            return;
+        }
+        if self.depth.is_some_and(|depth| path.len() > depth) {
+            return;
+        }
+        let mut dependencies = self.resolution.graph.dependencies_of(from);
+        dependencies.retain(|(dependency, _)| self.leads_to_target.contains(&dependency.id));
+        dependencies.sort_by(|a, b| (&a.0.name, &a.0.version).cmp(&(&b.0.name, &b.0.version)));
+        for (dependency, edge) in dependencies {
+            // A cycle never leads anywhere new
+            if !visiting.insert(dependency.id.clone()) {
+                continue;
+            }
+            path.push(step(&dependency.id, &edge.version_req.to_string()));
+            self.collect(&dependency.id, path, visiting, paths);
+            path.pop();
+            visiting.remove(&dependency.id);
+        }
+    }
+}
+
+fn step(id: &PackageId, range: &str) -> PathStep {
+    PathStep { name: id.name.clone(), version: id.version.to_string(), range: range.to_string() }
+}
@@ -0,0 +1,71 @@
+//! Workspace member discovery.
+//!
+//! Members are the directories matched by the root manifest's
+//! `workspace.members` globs (or package.json `workspaces`) that hold their
+//! own pea.toml or package.json, minus anything matched by `exclude`.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_config::{PeaToml, WorkspaceSection};
+use pea_core::error::{PeaError, PeaResult};
+
+/// A package in the workspace
+#[derive(Debug, Clone)]
+pub struct Member {
+    /// Directory relative to the workspace root, with `/` separators
+    pub path: String,
+    pub dir: Utf8PathBuf,
+    pub config: PeaToml,
+}
+
+/// The members of the workspace rooted at `root_dir`, sorted by path
+pub async fn members(root_dir: &Utf8Path, workspace: &WorkspaceSection) -> PeaResult<Vec<Member>> {
+    let excludes = workspace.exclude.iter()
+        .map(|pattern| glob::Pattern::new(pattern).map_err(|e| invalid_pattern(pattern, e)))
+        .collect::<PeaResult<Vec<_>>>()?;
+
+    let mut dirs = Vec::new();
+    for pattern in &workspace.members {
+        let full = root_dir.join(pattern);
+        let matches = glob::glob(full.as_str()).map_err(|e| invalid_pattern(pattern, e))?;
+        for dir in matches.flatten().filter(|dir| dir.is_dir()) {
+            let Ok(dir) = Utf8PathBuf::from_path_buf(dir) else {
+                continue;
+            };
+            let path = dir.strip_prefix(root_dir).unwrap_or(&dir).as_str().replace('\\', "/");
+            if !path.is_empty() && !excludes.iter().any(|exclude| exclude.matches(&path)) {
+                dirs.push((path, dir));
+            }
+        }
+    }
+    dirs.sort();
+    dirs.dedup();
+
+    let mut members = Vec::new();
+    for (path, dir) in dirs {
+        if let Some(config) = load_manifest(&dir).await? {
+            members.push(Member { path, dir, config });
+        }
+    }
+    Ok(members)
+}
+
+/// The manifest in `dir` itself (pea.toml first), without searching parents
+pub async fn load_manifest(dir: &Utf8Path) -> PeaResult<Option<PeaToml>> {
+    let pea_toml = dir.join("pea.toml");
+    if pea_toml.is_file() {
+        return pea_config::toml::load_from_file(&pea_toml).await.map(Some);
+    }
+    let package_json = dir.join("package.json");
+    if package_json.is_file() {
+        let manifest = pea_config::json::load_from_file(&package_json).await?;
+        return pea_config::json::import_to_pea_toml(&manifest).map(Some);
+    }
+    Ok(None)
+}
+
+fn invalid_pattern(pattern: &str, e: impl std::fmt::Display) -> PeaError {
+    PeaError::ConfigValidation {
+        field: "workspace.members".to_string(),
+        reason: format!("Invalid pattern '{}': {}", pattern, e),
+    }
+}
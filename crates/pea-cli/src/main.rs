@@ -44,17 +44,83 @@ pub enum Commands {
     /// Install dependencies
     Install { 
         #[arg(long)] 
-        frozen: bool 
+        frozen: bool, 
+        /// Install optional dependencies for another platform, as
+        /// <os>-<cpu>[-<libc>] (e.g. linux-arm64-musl)
+        #[arg(long)] 
+        platform: Option<pea_core::types::Platform> 
     },
-    /// Add a dependency
+    /// Add dependencies
     Add { 
-        package: String, 
-        #[arg(short = 'D')] 
-        dev: bool 
+        /// Packages as name, name@tag, name@version or name@range
+        #[arg(required = true)] 
+        packages: Vec<String>, 
+        /// Save as dev dependencies
+        #[arg(short = 'D', long, conflicts_with_all = ["peer", "optional"])] 
+        dev: bool, 
+        /// Save as peer dependencies
+        #[arg(long, conflicts_with = "optional")] 
+        peer: bool, 
+        /// Save as optional dependencies
+        #[arg(long)] 
+        optional: bool, 
+        /// Save the exact version instead of a ^ range
+        #[arg(short = 'E', long)] 
+        exact: bool 
     },
-    /// Remove a dependency
+    /// Remove dependencies
     Remove { 
-        package: String 
+        #[arg(required = true)] 
+        packages: Vec<String> 
+    },
+    /// Update dependencies within their ranges, or to latest
+    Update { 
+        /// Packages to update (all if none given)
+        packages: Vec<String>, 
+        /// Move manifest ranges to each package's latest version
+        #[arg(short = 'L', long)] 
+        latest: bool, 
+        /// Choose the packages to update from a list
+        #[arg(short = 'i', long)] 
+        interactive: bool 
+    },
+    /// List dependencies with newer versions available
+    Outdated { 
+        /// Print the report as JSON
+        #[arg(long)] 
+        json: bool 
+    },
+    /// Show the installed dependency tree
+    #[command(alias = "list")]
+    Ls { 
+        /// Only show paths to packages matching name or name@range
+        pattern: Option<String>, 
+        /// Levels below the project's dependencies to show (default 0)
+        #[arg(long, conflicts_with = "all")] 
+        depth: Option<usize>, 
+        /// Show the whole tree
+        #[arg(long)] 
+        all: bool, 
+        /// Only show dependencies, not dev dependencies
+        #[arg(long, conflicts_with = "dev")] 
+        prod: bool, 
+        /// Only show dev dependencies
+        #[arg(long)] 
+        dev: bool, 
+        /// Print the tree as JSON
+        #[arg(long)] 
+        json: bool 
+    },
+    /// Show why a package is installed
+    Why { 
+        /// Package as name or name@range
+        package: String, 
+        /// Print the paths as JSON
+        #[arg(long)] 
+        json: bool, 
+        /// Only show paths this many levels below the project's dependencies
+        #[arg(long)] 
+        depth: Option<usize> 
     },
     /// Run a script
     Run { 
@@ -62,6 +128,14 @@ pub enum Commands {
         #[arg(last = true)] 
         args: Vec<String> 
     },
+    /// Run a package binary, downloading the package if needed
+    #[command(alias = "x")]
+    Exec { 
+        /// Package as name, name@version, name@tag or name@range
+        package: String, 
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)] 
+        args: Vec<String> 
+    },
     /// Build for production
     Build { 
         #[arg(long)] 
@@ -75,10 +149,27 @@ pub enum Commands {
     },
     /// Check configuration
     Check,
+    /// Create a package tarball
+    Pack { 
+        #[arg(long)] 
+        dry_run: bool, 
+        /// Directory to write the tarball to
+        #[arg(long)] 
+        pack_destination: Option<PathBuf> 
+    },
     /// Publish package
     Publish { 
         #[arg(long)] 
-        dry_run: bool 
+        dry_run: bool, 
+        /// Dist-tag to publish under (default: latest)
+        #[arg(long)] 
+        tag: Option<String>, 
+        /// Access level for scoped packages: public or restricted
+        #[arg(long)] 
+        access: Option<String>, 
+        /// Registry URL, overriding publishConfig and .npmrc
+        #[arg(long)] 
+        registry: Option<String> 
     },
     /// Upgrade pea
     Upgrade { 
@@ -90,11 +181,45 @@ pub enum Commands {
         #[arg(long)] 
         unused: bool 
     },
+    /// Inspect and maintain the package store
+    Cache { 
+        #[command(subcommand)] 
+        command: CacheCommands 
+    },
     /// Show version information
     Version,
 }
 
-fn main() -> PeaResult<()> {
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    /// Re-hash every blob and quarantine corrupted ones
+    Verify,
+    /// List cached packages with sizes and ages
+    #[command(alias = "list")]
+    Ls,
+    /// Show cache details for a package
+    Info { 
+        package: String 
+    },
+    /// Print the store path
+    Path,
+    /// Remove entries that haven't been used recently
+    Prune { 
+        #[arg(long, default_value_t = 30)] 
+        max_age_days: i64 
+    },
+    /// Export packages (all if none given) to a single archive
+    Export { 
+        output: PathBuf, 
+        packages: Vec<String> 
+    },
+    /// Import packages from an archive created by `pea cache export`
+    Import { 
+        archive: PathBuf 
+    },
+}
+
+fn main() {
     let cli = Cli::parse();
     
     setup_logging(cli.verbose);
@@ -102,7 +227,11 @@ fn main() -> PeaResult<()> {
     
     info!("Starting Pea CLI v{}", env!("CARGO_PKG_VERSION"));
     
-    run_cli(cli)
+    if let Err(e) = run_cli(cli) {
+        // Failed scripts pass their exit code through to the caller
+        eprint!("{}", output::errors::ErrorFormatter::new().format_error(&e));
+        std::process::exit(e.exit_code());
+    }
 }
 
 fn run_cli(cli: Cli) -> PeaResult<()> {
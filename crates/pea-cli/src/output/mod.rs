@@ -46,6 +46,16 @@ impl OutputHandler {
     pub fn step(&self, emoji: &str, message: &str) {
         println!("{} {}", emoji, message);
     }
+
+    /// Print a message as-is, e.g. a table row the caller colored itself
+    pub fn line(&self, message: &str) {
+        println!("{}", message);
+    }
+
+    /// Colors matching the rest of the output
+    pub fn colors(&self) -> &colors::ColorSupport {
+        &self.colors
+    }
 }
 
 impl Default for OutputHandler {
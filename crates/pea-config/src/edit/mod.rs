@@ -0,0 +1,83 @@
+//! In-place manifest edits
+//!
+//! Adds, updates and removes dependencies in pea.toml or package.json without reformatting the
+//! rest of the file: pea.toml goes through `toml_edit` so comments and
+//! ordering survive, and package.json keeps its key order, indentation and
+//! trailing newline.
+
+use pea_core::types::DependencyKind;
+
+use crate::ConfigResult;
+
+mod package_json;
+mod pea_toml;
+
+/// Every dependency kind, in the order sections usually appear
+const KINDS: [DependencyKind; 4] = [
+    DependencyKind::Normal,
+    DependencyKind::Dev,
+    DependencyKind::Peer,
+    DependencyKind::Optional,
+];
+
+/// A manifest file that can be edited in place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    PeaToml,
+    PackageJson,
+}
+
+impl ManifestFormat {
+    /// File name of the manifest
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ManifestFormat::PeaToml => "pea.toml",
+            ManifestFormat::PackageJson => "package.json",
+        }
+    }
+
+    /// Set `name` to `range` under `kind`, moving it out of any other section
+    pub fn add_dependency(self, content: &str, kind: DependencyKind, name: &str, range: &str) -> ConfigResult<String> {
+        match self {
+            ManifestFormat::PeaToml => pea_toml::add_dependency(content, kind, name, range),
+            ManifestFormat::PackageJson => package_json::add_dependency(content, kind, name, range),
+        }
+    }
+
+    /// Change the range of `name` in every section that has it, leaving the
+    /// entries where they are; `None` if no section has it
+    pub fn set_range(self, content: &str, name: &str, range: &str) -> ConfigResult<Option<String>> {
+        match self {
+            ManifestFormat::PeaToml => pea_toml::set_range(content, name, range),
+            ManifestFormat::PackageJson => package_json::set_range(content, name, range),
+        }
+    }
+
+    /// Remove `name` from every dependency section, `None` if no section has it
+    pub fn remove_dependency(self, content: &str, name: &str) -> ConfigResult<Option<String>> {
+        match self {
+            ManifestFormat::PeaToml => pea_toml::remove_dependency(content, name),
+            ManifestFormat::PackageJson => package_json::remove_dependency(content, name),
+        }
+    }
+}
+
+/// Section name for `kind` in pea.toml
+fn toml_section(kind: DependencyKind) -> &'static str {
+    match kind {
+        DependencyKind::Normal => "dependencies",
+        DependencyKind::Dev => "dev-dependencies",
+        DependencyKind::Peer => "peer-dependencies",
+        DependencyKind::Optional => "optional-dependencies",
+    }
+}
+
+/// Section name for `kind` in package.json
+fn json_section(kind: DependencyKind) -> &'static str {
+    match kind {
+        DependencyKind::Normal => "dependencies",
+        DependencyKind::Dev => "devDependencies",
+        DependencyKind::Peer => "peerDependencies",
+        DependencyKind::Optional => "optionalDependencies",
+    }
+}
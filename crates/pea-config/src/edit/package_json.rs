@@ -0,0 +1,158 @@
+//! Format-preserving edits to package.json
+
+use pea_core::error::PeaError;
+use pea_core::types::DependencyKind;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{json_section, KINDS};
+use crate::ConfigResult;
+
+/// Set `name` to `range` in the section for `kind`
+///
+/// Keys keep their order (a sorted section stays sorted), and the file's
+/// indentation, line endings and trailing newline are kept.
+pub(super) fn add_dependency(content: &str, kind: DependencyKind, name: &str, range: &str) -> ConfigResult<String> {
+    let mut manifest: Value = serde_json::from_str(content)
+        .map_err(|e| PeaError::JsonParse { message: format!("JSON parsing error: {}", e) })?;
+    let object = manifest.as_object_mut().ok_or_else(|| PeaError::JsonParse {
+        message: "package.json must contain an object".to_string(),
+    })?;
+
+    for other in KINDS.iter().filter(|other| **other != kind) {
+        if let Some(Value::Object(section)) = object.get_mut(json_section(*other)) {
+            section.shift_remove(name);
+        }
+    }
+
+    let key = json_section(kind);
+    let Value::Object(section) = object.entry(key).or_insert_with(|| Value::Object(Map::new())) else {
+        return Err(PeaError::ConfigValidation {
+            field: key.to_string(),
+            reason: format!("\"{}\" must be an object", key),
+        });
+    };
+    let was_sorted = section.keys().zip(section.keys().skip(1)).all(|(a, b)| a <= b);
+    section.insert(name.to_string(), Value::String(range.to_string()));
+    if was_sorted {
+        let mut entries: Vec<(String, Value)> = std::mem::take(section).into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        section.extend(entries);
+    }
+
+    write(&manifest, content)
+}
+
+/// Set the range of `name` wherever it is declared
+pub(super) fn set_range(content: &str, name: &str, range: &str) -> ConfigResult<Option<String>> {
+    let mut manifest: Value = serde_json::from_str(content)
+        .map_err(|e| PeaError::JsonParse { message: format!("JSON parsing error: {}", e) })?;
+    let mut found = false;
+    if let Some(object) = manifest.as_object_mut() {
+        for kind in KINDS {
+            if let Some(Value::Object(section)) = object.get_mut(json_section(kind)) {
+                if let Some(entry) = section.get_mut(name) {
+                    *entry = Value::String(range.to_string());
+                    found = true;
+                }
+            }
+        }
+    }
+    if !found {
+        return Ok(None);
+    }
+    write(&manifest, content).map(Some)
+}
+
+/// Remove `name` from every dependency section
+pub(super) fn remove_dependency(content: &str, name: &str) -> ConfigResult<Option<String>> {
+    let mut manifest: Value = serde_json::from_str(content)
+        .map_err(|e| PeaError::JsonParse { message: format!("JSON parsing error: {}", e) })?;
+    let mut removed = false;
+    if let Some(object) = manifest.as_object_mut() {
+        for kind in KINDS {
+            if let Some(Value::Object(section)) = object.get_mut(json_section(kind)) {
+                removed |= section.shift_remove(name).is_some();
+            }
+        }
+    }
+    if !removed {
+        return Ok(None);
+    }
+    write(&manifest, content).map(Some)
+}
+
+/// Serialize `manifest` in the style of the `original` file
+fn write(manifest: &Value, original: &str) -> ConfigResult<String> {
+    let indent = detect_indent(original);
+    let mut output = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(
+        &mut output,
+        serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes()),
+    );
+    manifest.serialize(&mut serializer)
+        .map_err(|e| PeaError::JsonParse { message: format!("JSON serialization error: {}", e) })?;
+
+    let mut output = String::from_utf8(output)
+        .map_err(|e| PeaError::JsonParse { message: format!("JSON serialization error: {}", e) })?;
+    if original.ends_with('\n') {
+        output.push('\n');
+    }
+    if original.contains("\r\n") {
+        output = output.replace('\n', "\r\n");
+    }
+    Ok(output)
+}
+
+/// Indentation of the first indented line, two spaces if there is none
+fn detect_indent(content: &str) -> &str {
+    content.lines()
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_keeps_key_order_and_indent() {
+        let content = "{\n    \"name\": \"app\",\n    \"version\": \"1.0.0\",\n    \"dependencies\": {\n        \"axios\": \"^1.0.0\",\n        \"zod\": \"^3.0.0\"\n    },\n    \"author\": \"me\"\n}\n";
+        let edited = add_dependency(content, DependencyKind::Normal, "lodash", "^4.17.21").unwrap();
+        assert_eq!(edited, content.replace(
+            "\"axios\": \"^1.0.0\",\n",
+            "\"axios\": \"^1.0.0\",\n        \"lodash\": \"^4.17.21\",\n",
+        ));
+    }
+
+    #[test]
+    fn test_add_new_section_moves_entry_and_keeps_tabs() {
+        let content = "{\n\t\"name\": \"app\",\n\t\"dependencies\": {\n\t\t\"jest\": \"^29.0.0\"\n\t}\n}";
+        let edited = add_dependency(content, DependencyKind::Dev, "jest", "^29.7.0").unwrap();
+        assert_eq!(edited, "{\n\t\"name\": \"app\",\n\t\"dependencies\": {},\n\t\"devDependencies\": {\n\t\t\"jest\": \"^29.7.0\"\n\t}\n}");
+    }
+
+    #[test]
+    fn test_remove_keeps_layout() {
+        let content = "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"b\": \"1\",\n    \"a\": \"1\"\n  },\n  \"peerDependencies\": {\n    \"a\": \"1\"\n  }\n}\n";
+        let edited = remove_dependency(content, "a").unwrap().unwrap();
+        assert_eq!(edited, "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"b\": \"1\"\n  },\n  \"peerDependencies\": {}\n}\n");
+        assert_eq!(remove_dependency(&edited, "a").unwrap(), None);
+    }
+
+    #[test]
+    fn test_set_range_in_place() {
+        let content = "{\n  \"devDependencies\": {\n    \"react\": \"~17.0.0\"\n  },\n  \"peerDependencies\": {\n    \"react\": \"~17.0.0\"\n  }\n}";
+        let edited = set_range(content, "react", "~18.2.0").unwrap().unwrap();
+        assert_eq!(edited, content.replace("~17.0.0", "~18.2.0"));
+        assert_eq!(set_range(content, "zod", "^3.0.0").unwrap(), None);
+    }
+
+    #[test]
+    fn test_unsorted_sections_append() {
+        let content = "{\r\n  \"dependencies\": {\r\n    \"b\": \"1\",\r\n    \"a\": \"1\"\r\n  }\r\n}\r\n";
+        let edited = add_dependency(content, DependencyKind::Normal, "c", "2").unwrap();
+        assert_eq!(edited, "{\r\n  \"dependencies\": {\r\n    \"b\": \"1\",\r\n    \"a\": \"1\",\r\n    \"c\": \"2\"\r\n  }\r\n}\r\n");
+    }
+}
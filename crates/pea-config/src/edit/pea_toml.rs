@@ -0,0 +1,245 @@
+//! Format-preserving edits to pea.toml
+
+use pea_core::error::PeaError;
+use pea_core::types::DependencyKind;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use super::{toml_section, KINDS};
+use crate::ConfigResult;
+
+/// Keys of a detailed entry that point somewhere other than the registry
+/// package `add_dependency` picked the range from
+const SOURCE_KEYS: [&str; 9] = ["git", "branch", "tag", "rev", "path", "link", "tarball", "workspace", "package"];
+
+/// Set `name` to `range` in the section for `kind`
+///
+/// Detailed entries keep their other keys and only get a new `version`,
+/// dropping any git, path, tarball, workspace or alias source it replaces;
+/// a section whose keys were sorted stays sorted.
+pub(super) fn add_dependency(content: &str, kind: DependencyKind, name: &str, range: &str) -> ConfigResult<String> {
+    let mut document = parse(content)?;
+    for other in KINDS.iter().filter(|other| **other != kind) {
+        if let Some(table) = document.get_mut(toml_section(*other)).and_then(Item::as_table_like_mut) {
+            table.remove(name);
+        }
+    }
+
+    let section = toml_section(kind);
+    let table = document.entry(section)
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .ok_or_else(|| PeaError::ConfigValidation {
+            field: section.to_string(),
+            reason: format!("[{}] must be a table", section),
+        })?;
+    let was_sorted = is_sorted(table);
+
+    match table.get_mut(name) {
+        Some(entry) if entry.as_table_like().is_some() => {
+            let detailed = entry.as_table_like_mut().unwrap();
+            let mut replaced = false;
+            for key in SOURCE_KEYS {
+                replaced |= detailed.remove(key).is_some();
+            }
+            match detailed.get_mut("version").and_then(Item::as_value_mut) {
+                Some(version) => set_keeping_decor(version, range),
+                None => {
+                    detailed.insert("version", toml_edit::value(range));
+                }
+            }
+            // Removing keys leaves the spacing of the ones that followed them
+            if let Some(inline) = entry.as_inline_table_mut().filter(|_| replaced) {
+                inline.fmt();
+            }
+        }
+        Some(entry) => match entry.as_value_mut() {
+            Some(version) => set_keeping_decor(version, range),
+            None => *entry = toml_edit::value(range),
+        },
+        None => {
+            table.insert(name, toml_edit::value(range));
+        }
+    }
+
+    if was_sorted {
+        table.sort_values();
+    }
+    Ok(document.to_string())
+}
+
+/// Set the range of `name` wherever it is declared
+pub(super) fn set_range(content: &str, name: &str, range: &str) -> ConfigResult<Option<String>> {
+    let mut document = parse(content)?;
+    let mut found = false;
+    for kind in KINDS {
+        let Some(entry) = document.get_mut(toml_section(kind))
+            .and_then(Item::as_table_like_mut)
+            .and_then(|table| table.get_mut(name))
+        else {
+            continue;
+        };
+        let version = match entry.as_table_like_mut() {
+            Some(detailed) => detailed.get_mut("version").and_then(Item::as_value_mut),
+            None => entry.as_value_mut(),
+        };
+        // Git, path and workspace entries have no range to change
+        if let Some(version) = version.filter(|version| version.is_str()) {
+            set_keeping_decor(version, range);
+            found = true;
+        }
+    }
+    Ok(found.then(|| document.to_string()))
+}
+
+/// Remove `name` from every dependency section
+pub(super) fn remove_dependency(content: &str, name: &str) -> ConfigResult<Option<String>> {
+    let mut document = parse(content)?;
+    let mut removed = false;
+    for kind in KINDS {
+        if let Some(table) = document.get_mut(toml_section(kind)).and_then(Item::as_table_like_mut) {
+            removed |= table.remove(name).is_some();
+        }
+    }
+    Ok(removed.then(|| document.to_string()))
+}
+
+fn parse(content: &str) -> ConfigResult<DocumentMut> {
+    content.parse::<DocumentMut>().map_err(|e| {
+        // Report where the syntax error is, counting from 1
+        let offset = e.span().map(|span| span.start).unwrap_or(0).min(content.len());
+        let before = &content[..offset];
+        PeaError::TomlParse {
+            message: e.message().to_string(),
+            line: before.matches('\n').count() + 1,
+            column: offset - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1,
+        }
+    })
+}
+
+/// Replace a value but keep the whitespace and comments around it
+fn set_keeping_decor(value: &mut toml_edit::Value, range: &str) {
+    let decor = value.decor().clone();
+    *value = range.into();
+    *value.decor_mut() = decor;
+}
+
+fn is_sorted(table: &dyn TableLike) -> bool {
+    let keys: Vec<&str> = table.iter().map(|(key, _)| key).collect();
+    keys.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_preserves_comments_and_layout() {
+        let content = "\
+# Project manifest
+[package]
+name = \"app\" # the app
+version = \"1.0.0\"
+
+[dependencies]
+# http
+axios = \"^1.0.0\"
+zod = \"^3.0.0\" # validation
+
+[scripts]
+test = \"pea test\"
+";
+        let edited = add_dependency(content, DependencyKind::Normal, "lodash", "^4.17.21").unwrap();
+        assert_eq!(edited, content.replace(
+            "axios = \"^1.0.0\"\n",
+            "axios = \"^1.0.0\"\nlodash = \"^4.17.21\"\n",
+        ));
+
+        // Updating keeps the trailing comment
+        let edited = add_dependency(&edited, DependencyKind::Normal, "zod", "^3.2.0").unwrap();
+        assert!(edited.contains("zod = \"^3.2.0\" # validation"));
+    }
+
+    #[test]
+    fn test_add_creates_section_and_moves_between_kinds() {
+        let content = "[package]\nname = \"app\"\nversion = \"1.0.0\"\n\n[dependencies]\njest = \"^29.0.0\"\n";
+        let edited = add_dependency(content, DependencyKind::Dev, "jest", "^29.7.0").unwrap();
+
+        let parsed: toml::Value = toml::from_str(&edited).unwrap();
+        assert!(parsed["dependencies"].get("jest").is_none());
+        assert_eq!(parsed["dev-dependencies"]["jest"].as_str(), Some("^29.7.0"));
+        assert!(edited.starts_with("[package]\nname = \"app\""));
+    }
+
+    #[test]
+    fn test_detailed_entries_keep_other_keys() {
+        let content = "[dependencies]\nreact = { version = \"^17.0.0\", features = [\"dom\"] }\n";
+        let edited = add_dependency(content, DependencyKind::Normal, "react", "^18.2.0").unwrap();
+        assert_eq!(edited, "[dependencies]\nreact = { version = \"^18.2.0\", features = [\"dom\"] }\n");
+    }
+
+    #[test]
+    fn test_detailed_entries_from_other_sources_become_registry_entries() {
+        let content = "\
+[package]
+name = \"app\"
+version = \"1.0.0\"
+
+[dependencies]
+ui = { git = \"https://github.com/acme/ui.git\", tag = \"v1.0.0\", optional = true }
+utils = { path = \"../utils\", link = true }
+
+[dependencies.theme]
+tarball = \"https://example.com/theme.tgz\"
+";
+        let mut edited = content.to_string();
+        for name in ["ui", "utils", "theme"] {
+            edited = add_dependency(&edited, DependencyKind::Normal, name, "^2.0.0").unwrap();
+        }
+        assert!(edited.contains("ui = { optional = true, version = \"^2.0.0\" }\n"));
+        assert!(edited.contains("utils = { version = \"^2.0.0\" }\n"));
+        assert!(edited.contains("[dependencies.theme]\nversion = \"^2.0.0\"\n"));
+
+        let config = crate::toml::parse_pea_toml(&edited).unwrap();
+        for name in ["ui", "utils", "theme"] {
+            assert_eq!(config.dependencies[name].version_req().unwrap().unwrap().to_string(), "^2.0.0");
+        }
+    }
+
+    #[test]
+    fn test_set_range_keeps_sections_and_skips_non_registry_entries() {
+        let content = "\
+[dev-dependencies]
+react = \"^17.0.0\" # tests
+
+[peer-dependencies]
+react = { version = \"^17.0.0\", optional = true }
+local = { path = \"../local\" }
+";
+        let edited = set_range(content, "react", "^18.2.0").unwrap().unwrap();
+        assert_eq!(edited, content.replace("^17.0.0", "^18.2.0"));
+        assert_eq!(set_range(content, "local", "^1.0.0").unwrap(), None);
+        assert_eq!(set_range(content, "missing", "^1.0.0").unwrap(), None);
+    }
+
+    #[test]
+    fn test_remove_from_every_section() {
+        let content = "\
+[dependencies]
+# http
+axios = \"^1.0.0\"
+zod = \"^3.0.0\" # validation
+
+[dev-dependencies]
+zod = \"^3.0.0\"
+";
+        let edited = remove_dependency(content, "zod").unwrap().unwrap();
+        assert_eq!(edited, "[dependencies]\n# http\naxios = \"^1.0.0\"\n\n[dev-dependencies]\n");
+        assert_eq!(remove_dependency(&edited, "zod").unwrap(), None);
+    }
+
+    #[test]
+    fn test_syntax_error_location() {
+        let error = add_dependency("[package]\nname = \n", DependencyKind::Normal, "a", "1").unwrap_err();
+        assert!(matches!(error, PeaError::TomlParse { line: 2, .. }));
+    }
+}
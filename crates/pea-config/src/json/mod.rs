@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use pea_core::error::PeaError;
+use pea_core::types::Specifier;
 use crate::{ConfigResult, toml::{PeaToml, PackageSection, DependencySpec}};
 
 /// Complete package.json configuration
@@ -113,6 +114,14 @@ pub struct PackageJson {
     /// TypeScript typings entry point
     #[serde(skip_serializing_if = "Option::is_none")]
     pub typings: Option<String>,
+    
+    /// npm overrides, nested by dependent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<serde_json::Value>,
+    
+    /// yarn resolutions, keyed by dependency path
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub resolutions: HashMap<String, String>,
 }
 
 /// Repository information
@@ -191,6 +200,7 @@ pub fn import_to_pea_toml(package_json: &PackageJson) -> ConfigResult<PeaToml> {
         keywords: package_json.keywords.clone(),
         authors: extract_authors(package_json),
         homepage: package_json.homepage.clone(),
+        files: package_json.files.clone(),
     };
     
     // Convert dependencies
@@ -223,6 +233,8 @@ pub fn import_to_pea_toml(package_json: &PackageJson) -> ConfigResult<PeaToml> {
         features: HashMap::new(),
         workspace,
         profile: HashMap::new(),
+        install: None,
+        overrides: convert_overrides(package_json)?,
     })
 }
 
@@ -241,49 +253,68 @@ pub async fn load_from_file(path: &camino::Utf8Path) -> ConfigResult<PackageJson
 }
 
 /// Convert npm dependency map to Pea dependency specs
+///
+/// Plain ranges stay simple strings; aliases, workspace, path, tarball and
+/// git specifiers become detailed specs.
 fn convert_dependencies(deps: &HashMap<String, String>) -> ConfigResult<HashMap<String, DependencySpec>> {
     let mut result = HashMap::new();
     
     for (name, version) in deps {
-        // Handle special npm version formats
-        let spec = if version.starts_with("file:") {
-            // Local file dependency
-            let path = version.strip_prefix("file:").unwrap().to_string();
-            DependencySpec::Detailed {
-                version: None,
-                git: None,
-                branch: None,
-                tag: None,
-                rev: None,
-                path: Some(path),
-                workspace: None,
-                features: Vec::new(),
-                optional: false,
-                default_features: true,
+        let detailed = |version: Option<String>| DependencySpec::Detailed {
+            version,
+            git: None,
+            branch: None,
+            tag: None,
+            rev: None,
+            path: None,
+            workspace: None,
+            package: None,
+            tarball: None,
+            link: None,
+            features: Vec::new(),
+            optional: false,
+            default_features: true,
+        };
+        
+        let spec = match Specifier::parse(version) {
+            Specifier::Range(range) => DependencySpec::Simple(range),
+            Specifier::Alias { package: real, range } => {
+                let mut spec = detailed(Some(range));
+                if let DependencySpec::Detailed { package, .. } = &mut spec {
+                    *package = Some(real);
+                }
+                spec
             }
-        } else if version.starts_with("git+") || version.contains("github.com") {
-            // Git dependency
-            let git_url = if version.starts_with("git+") {
-                version.strip_prefix("git+").unwrap().to_string()
-            } else {
-                version.clone()
-            };
-            
-            DependencySpec::Detailed {
-                version: None,
-                git: Some(git_url),
-                branch: None,
-                tag: None,
-                rev: None,
-                path: None,
-                workspace: None,
-                features: Vec::new(),
-                optional: false,
-                default_features: true,
+            Specifier::Workspace(range) => {
+                let mut spec = detailed((range != "*").then_some(range));
+                if let DependencySpec::Detailed { workspace, .. } = &mut spec {
+                    *workspace = Some(true);
+                }
+                spec
+            }
+            Specifier::Path { path: local, link: symlink } => {
+                let mut spec = detailed(None);
+                if let DependencySpec::Detailed { path, link, .. } = &mut spec {
+                    *path = Some(local);
+                    *link = symlink.then_some(true);
+                }
+                spec
+            }
+            Specifier::Tarball(url) => {
+                let mut spec = detailed(None);
+                if let DependencySpec::Detailed { tarball, .. } = &mut spec {
+                    *tarball = Some(url);
+                }
+                spec
+            }
+            Specifier::Git { url, committish } => {
+                let mut spec = detailed(None);
+                if let DependencySpec::Detailed { git, rev, .. } = &mut spec {
+                    *git = Some(url);
+                    *rev = committish;
+                }
+                spec
             }
-        } else {
-            // Regular version dependency
-            DependencySpec::Simple(version.clone())
         };
         
         result.insert(name.clone(), spec);
@@ -292,6 +323,70 @@ fn convert_dependencies(deps: &HashMap<String, String>) -> ConfigResult<HashMap<
     Ok(result)
 }
 
+/// Flatten npm `overrides` and yarn `resolutions` into `>`-separated selectors
+///
+/// npm nests overrides by dependent (`{"a": {"b": "1.0.0"}}`, with `"."` for
+/// the dependent itself); yarn keys are paths (`a/b`, `**/b`). npm's win when
+/// both name the same selector.
+fn convert_overrides(package_json: &PackageJson) -> ConfigResult<HashMap<String, String>> {
+    let mut overrides = HashMap::new();
+    for (path, specifier) in &package_json.resolutions {
+        overrides.insert(resolution_selector(path)?, specifier.clone());
+    }
+    if let Some(npm_overrides) = &package_json.overrides {
+        flatten_npm_overrides(npm_overrides, None, &mut overrides)?;
+    }
+    Ok(overrides)
+}
+
+fn flatten_npm_overrides(
+    value: &serde_json::Value,
+    parent: Option<&str>,
+    overrides: &mut HashMap<String, String>,
+) -> ConfigResult<()> {
+    let invalid = |reason: String| PeaError::ConfigValidation { field: "overrides".to_string(), reason };
+    let entries = value.as_object()
+        .ok_or_else(|| invalid(format!("Overrides for '{}' must be an object", parent.unwrap_or("overrides"))))?;
+    for (key, value) in entries {
+        let selector = match (key.as_str(), parent) {
+            (".", Some(parent)) => parent.to_string(),
+            (".", None) => return Err(invalid("'.' only applies inside a package's overrides".to_string())),
+            (key, Some(parent)) => format!("{}>{}", parent, key),
+            (key, None) => key.to_string(),
+        };
+        match value {
+            serde_json::Value::String(specifier) => {
+                overrides.insert(selector, specifier.clone());
+            }
+            serde_json::Value::Object(_) => flatten_npm_overrides(value, Some(&selector), overrides)?,
+            _ => return Err(invalid(format!("Override '{}' must be a string or an object", selector))),
+        }
+    }
+    Ok(())
+}
+
+/// Selector for a yarn resolution path; only a leading `**/` is supported
+fn resolution_selector(path: &str) -> ConfigResult<String> {
+    let mut names: Vec<String> = Vec::new();
+    for part in path.trim_start_matches("**/").split('/') {
+        if part == "**" || part.is_empty() {
+            return Err(PeaError::ConfigValidation {
+                field: format!("resolutions.{}", path),
+                reason: format!("Resolution '{}' is not supported; use a path of package names, optionally starting with **/", path),
+            });
+        }
+        // A scope and its package name are one step
+        match names.last_mut() {
+            Some(scope) if scope.starts_with('@') && !scope.contains('/') => {
+                scope.push('/');
+                scope.push_str(part);
+            }
+            _ => names.push(part.to_string()),
+        }
+    }
+    Ok(names.join(">"))
+}
+
 /// Extract repository URL from repository info
 fn extract_repository_url(repo: &Option<RepositoryInfo>) -> Option<String> {
     match repo {
@@ -415,6 +510,86 @@ mod tests {
         assert!(spec.is_path());
     }
     
+    #[test]
+    fn test_import_overrides_and_resolutions() {
+        let json = r#"{
+            "name": "app",
+            "version": "1.0.0",
+            "dependencies": {"react": "^18.2.0"},
+            "overrides": {
+                "minimist": "1.2.8",
+                "webpack": {".": "5.90.0", "terser": "npm:@swc/terser@1.0.0"},
+                "@scope/a": {"b": {"c": "$react"}}
+            },
+            "resolutions": {"**/minimist": "1.2.6", "left-pad/@types/node": "20.0.0"}
+        }"#;
+        
+        let config = import_to_pea_toml(&parse_package_json(json).unwrap()).unwrap();
+        assert_eq!(config.overrides["minimist"], "1.2.8");
+        assert_eq!(config.overrides["webpack"], "5.90.0");
+        assert_eq!(config.overrides["webpack>terser"], "npm:@swc/terser@1.0.0");
+        assert_eq!(config.overrides["@scope/a>b>c"], "$react");
+        assert_eq!(config.overrides["left-pad>@types/node"], "20.0.0");
+        assert_eq!(config.overrides.len(), 5);
+        
+        let nested_glob = r#"{"name": "app", "version": "1.0.0", "resolutions": {"a/**/b": "1.0.0"}}"#;
+        assert!(import_to_pea_toml(&parse_package_json(nested_glob).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_convert_protocol_specifiers() {
+        let deps = HashMap::from([
+            ("lodash".to_string(), "npm:lodash-es@^4.17.0".to_string()),
+            ("shared".to_string(), "workspace:^".to_string()),
+            ("vendored".to_string(), "link:../vendored".to_string()),
+            ("remote".to_string(), "https://example.com/remote-1.0.0.tgz".to_string()),
+            ("forked".to_string(), "github:user/forked#v2".to_string()),
+            ("ssh".to_string(), "git+ssh://git@github.com:user/ssh.git".to_string()),
+        ]);
+        
+        let converted = convert_dependencies(&deps).unwrap();
+        
+        let lodash = &converted["lodash"];
+        assert!(matches!(lodash, DependencySpec::Detailed { package: Some(package), version: Some(version), .. }
+            if package == "lodash-es" && version == "^4.17.0"));
+        assert_eq!(lodash.version_req().unwrap().unwrap().to_string(), "^4.17.0");
+        assert!(converted["shared"].is_workspace());
+        assert_eq!(converted["shared"].specifier().to_string(), "workspace:^");
+        assert!(converted["shared"].version_req().unwrap().is_none());
+        assert!(matches!(&converted["vendored"], DependencySpec::Detailed { link: Some(true), .. }));
+        assert!(converted["vendored"].is_path());
+        assert!(matches!(&converted["remote"], DependencySpec::Detailed { tarball: Some(url), .. } if url.ends_with(".tgz")));
+        assert!(matches!(&converted["forked"], DependencySpec::Detailed { git: Some(url), rev: Some(rev), .. }
+            if url == "https://github.com/user/forked.git" && rev == "v2"));
+        assert!(matches!(&converted["ssh"], DependencySpec::Detailed { git: Some(url), .. } if url == "git@github.com:user/ssh.git"));
+        
+        let config = PeaToml {
+            package: PackageSection {
+                name: "app".to_string(),
+                version: "1.0.0".parse().unwrap(),
+                description: None,
+                main: None,
+                license: None,
+                repository: None,
+                keywords: Vec::new(),
+                authors: Vec::new(),
+                homepage: None,
+                files: Vec::new(),
+            },
+            dependencies: converted,
+            dev_dependencies: HashMap::new(),
+            peer_dependencies: HashMap::new(),
+            optional_dependencies: HashMap::new(),
+            scripts: HashMap::new(),
+            features: HashMap::new(),
+            workspace: None,
+            profile: HashMap::new(),
+            install: None,
+            overrides: HashMap::new(),
+        };
+        crate::toml::validate_config(&config).unwrap();
+    }
+    
     #[test]
     fn test_convert_git_dependency() {
         let deps = HashMap::from([
@@ -468,6 +643,8 @@ mod property_tests {
                 exports: None,
                 types: None,
                 typings: None,
+                overrides: None,
+                resolutions: HashMap::new(),
             }
         }
     }
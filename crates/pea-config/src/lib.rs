@@ -6,11 +6,13 @@
 pub mod toml;
 pub mod json;
 pub mod merge;
+pub mod edit;
 
 // Re-export main types
-pub use toml::{PeaToml, PackageSection, DependencySpec, WorkspaceSection, ProfileSection};
+pub use toml::{PeaToml, PackageSection, DependencySpec, WorkspaceSection, ProfileSection, InstallSection};
 pub use json::PackageJson;
 pub use merge::{ConfigLoader, ConfigLayering};
+pub use edit::ManifestFormat;
 
 use pea_core::error::PeaError;
 
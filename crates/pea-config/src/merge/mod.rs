@@ -259,6 +259,7 @@ mod tests {
                 keywords: Vec::new(),
                 authors: Vec::new(),
                 homepage: None,
+                files: Vec::new(),
             },
             dependencies: HashMap::new(),
             dev_dependencies: HashMap::new(),
@@ -268,6 +269,8 @@ mod tests {
             features: HashMap::new(),
             workspace: None,
             profile: HashMap::new(),
+            install: None,
+            overrides: HashMap::new(),
         }
     }
     
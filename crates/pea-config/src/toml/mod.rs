@@ -1,9 +1,9 @@
 //! pea.toml configuration parsing and serialization
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
 use serde::{Deserialize, Serialize};
-use pea_core::types::{Version, VersionReq};
+use pea_core::types::{NodeLinker, OverrideSelector, PackageImportMethod, Specifier, Version, VersionReq};
 use pea_core::error::PeaError;
 use crate::ConfigResult;
 
@@ -44,6 +44,17 @@ pub struct PeaToml {
     /// Build profiles
     #[serde(default)]
     pub profile: HashMap<String, ProfileSection>,
+    
+    /// Install behaviour settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install: Option<InstallSection>,
+    
+    /// Forced specifiers for transitive dependencies (selector -> specifier)
+    ///
+    /// Selectors are `name`, `name@range` or `>`-separated chains such as
+    /// `parent>name`; `$name` uses the project's own specifier for `name`.
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
 }
 
 /// Package metadata section
@@ -83,6 +94,10 @@ pub struct PackageSection {
     /// Homepage URL
     #[serde(skip_serializing_if = "Option::is_none")]
     pub homepage: Option<String>,
+    
+    /// Files and globs to publish (everything not ignored when empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
 }
 
 /// Dependency specification (simple string or detailed object)
@@ -118,10 +133,22 @@ pub enum DependencySpec {
         #[serde(skip_serializing_if = "Option::is_none")]
         path: Option<String>,
         
-        /// Workspace reference
+        /// Workspace reference; `version` may narrow it (`*`, `^`, `~` or a range)
         #[serde(skip_serializing_if = "Option::is_none")]
         workspace: Option<bool>,
         
+        /// Registry package this dependency is an alias for (`npm:` specifier)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        package: Option<String>,
+        
+        /// Remote tarball URL
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tarball: Option<String>,
+        
+        /// Symlink `path` without installing its dependencies (`link:` specifier)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<bool>,
+        
         /// Features to enable
         #[serde(default)]
         features: Vec<String>,
@@ -175,48 +202,140 @@ pub struct ProfileSection {
     pub panic: Option<String>,
 }
 
+/// Install behaviour configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstallSection {
+    /// How package files are imported from the store into node_modules
+    #[serde(default, rename = "package-import-method")]
+    pub package_import_method: PackageImportMethod,
+    
+    /// node_modules layout
+    #[serde(default, rename = "node-linker")]
+    pub node_linker: NodeLinker,
+    
+    /// Packages that may be hoisted (`!` excludes)
+    #[serde(default = "default_hoist_pattern", rename = "hoist-pattern")]
+    pub hoist_pattern: Vec<String>,
+    
+    /// Packages hoisted to the top of node_modules in every layout
+    #[serde(default, rename = "public-hoist-pattern")]
+    pub public_hoist_pattern: Vec<String>,
+    
+    /// Dependencies allowed to run lifecycle scripts
+    #[serde(default, rename = "trusted-dependencies")]
+    pub trusted_dependencies: Vec<String>,
+    
+    /// Largest unpacked tarball accepted, in bytes
+    #[serde(default, rename = "max-unpacked-size", skip_serializing_if = "Option::is_none")]
+    pub max_unpacked_size: Option<u64>,
+    
+    /// Most entries a tarball may contain
+    #[serde(default, rename = "max-tarball-entries", skip_serializing_if = "Option::is_none")]
+    pub max_tarball_entries: Option<usize>,
+    
+    /// Highest unpacked / packed size ratio accepted
+    #[serde(default, rename = "max-compression-ratio", skip_serializing_if = "Option::is_none")]
+    pub max_compression_ratio: Option<u64>,
+    
+    /// Fail installs whose peer dependencies are missing or out of range
+    #[serde(default, rename = "strict-peer-dependencies")]
+    pub strict_peer_dependencies: bool,
+}
+
+impl Default for InstallSection {
+    fn default() -> Self {
+        Self {
+            package_import_method: PackageImportMethod::default(),
+            node_linker: NodeLinker::default(),
+            hoist_pattern: default_hoist_pattern(),
+            public_hoist_pattern: Vec::new(),
+            trusted_dependencies: Vec::new(),
+            max_unpacked_size: None,
+            max_tarball_entries: None,
+            max_compression_ratio: None,
+            strict_peer_dependencies: false,
+        }
+    }
+}
+
+/// Default value for hoist-pattern (everything)
+fn default_hoist_pattern() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
 /// Default value for default-features (true)
 fn default_features_true() -> bool {
     true
 }
 
+impl PeaToml {
+    /// The overrides to resolve with, sorted by selector, with `$name`
+    /// references replaced by the project's own specifier for `name`
+    pub fn resolved_overrides(&self) -> ConfigResult<BTreeMap<String, String>> {
+        self.overrides.iter()
+            .map(|(selector, specifier)| Ok((selector.clone(), resolve_override(self, selector, specifier)?)))
+            .collect()
+    }
+}
+
 impl DependencySpec {
     /// Get the version requirement string
+    ///
+    /// `None` for dependencies that don't come from the registry; workspace
+    /// dependencies only have one when they name an explicit range.
     pub fn version_req(&self) -> ConfigResult<Option<VersionReq>> {
+        let range = match self.specifier() {
+            Specifier::Range(range) | Specifier::Alias { range, .. } => range,
+            Specifier::Workspace(range) if !matches!(range.as_str(), "*" | "^" | "~") => range,
+            _ => return Ok(None),
+        };
+        let req = VersionReq::parse(&range)
+            .map_err(|e| PeaError::ConfigValidation {
+                field: "version".to_string(),
+                reason: format!("Invalid version requirement '{}': {}", range, e),
+            })?;
+        Ok(Some(req))
+    }
+    
+    /// The dependency's source, in either form, as an npm-style specifier
+    pub fn specifier(&self) -> Specifier {
         match self {
-            DependencySpec::Simple(version) => {
-                let req = VersionReq::parse(version)
-                    .map_err(|e| PeaError::ConfigValidation {
-                        field: "version".to_string(),
-                        reason: format!("Invalid version requirement '{}': {}", version, e),
-                    })?;
-                Ok(Some(req))
+            DependencySpec::Simple(spec) => Specifier::parse(spec),
+            DependencySpec::Detailed { git: Some(url), branch, tag, rev, .. } => Specifier::Git {
+                url: url.clone(),
+                committish: rev.clone().or_else(|| tag.clone()).or_else(|| branch.clone()),
+            },
+            DependencySpec::Detailed { path: Some(path), link, .. } => Specifier::Path {
+                path: path.clone(),
+                link: link.unwrap_or(false),
+            },
+            DependencySpec::Detailed { tarball: Some(url), .. } => Specifier::Tarball(url.clone()),
+            DependencySpec::Detailed { version, workspace: Some(true), .. } => {
+                Specifier::Workspace(version.clone().unwrap_or_else(|| "*".to_string()))
             }
-            DependencySpec::Detailed { version: Some(version), .. } => {
-                let req = VersionReq::parse(version)
-                    .map_err(|e| PeaError::ConfigValidation {
-                        field: "version".to_string(),
-                        reason: format!("Invalid version requirement '{}': {}", version, e),
-                    })?;
-                Ok(Some(req))
+            DependencySpec::Detailed { version, package: Some(package), .. } => Specifier::Alias {
+                package: package.clone(),
+                range: version.clone().unwrap_or_else(|| "*".to_string()),
+            },
+            DependencySpec::Detailed { version, .. } => {
+                Specifier::Range(version.clone().unwrap_or_else(|| "*".to_string()))
             }
-            DependencySpec::Detailed { version: None, .. } => Ok(None),
         }
     }
     
     /// Check if this is a workspace dependency
     pub fn is_workspace(&self) -> bool {
-        matches!(self, DependencySpec::Detailed { workspace: Some(true), .. })
+        matches!(self.specifier(), Specifier::Workspace(_))
     }
     
     /// Check if this is a path dependency
     pub fn is_path(&self) -> bool {
-        matches!(self, DependencySpec::Detailed { path: Some(_), .. })
+        matches!(self.specifier(), Specifier::Path { .. })
     }
     
     /// Check if this is a git dependency
     pub fn is_git(&self) -> bool {
-        matches!(self, DependencySpec::Detailed { git: Some(_), .. })
+        matches!(self.specifier(), Specifier::Git { .. })
     }
 }
 
@@ -289,6 +408,8 @@ pub fn validate_config(config: &PeaToml) -> ConfigResult<()> {
         validate_dependency_spec(name, spec)?;
     }
     
+    config.resolved_overrides()?;
+    
     // Validate workspace configuration
     if let Some(workspace) = &config.workspace {
         if workspace.members.is_empty() {
@@ -344,20 +465,37 @@ fn validate_dependency_spec(name: &str, spec: &DependencySpec) -> ConfigResult<(
         DependencySpec::Simple(_) => {
             // Simple version spec is always valid
         }
-        DependencySpec::Detailed { version, git, path, workspace, .. } => {
-            let source_count = [version.is_some(), git.is_some(), path.is_some(), workspace.unwrap_or(false)].iter().filter(|&&x| x).count();
+        DependencySpec::Detailed { version, git, path, workspace, package, tarball, link, .. } => {
+            // A version narrows a workspace dependency rather than being a source of its own
+            let workspace = workspace.unwrap_or(false);
+            let registry = version.is_some() && !workspace;
+            let source_count = [registry, git.is_some(), path.is_some(), workspace, tarball.is_some()].iter().filter(|&&x| x).count();
             
             if source_count == 0 {
                 return Err(PeaError::ConfigValidation {
                     field: format!("dependencies.{}", name),
-                    reason: format!("Dependency '{}' must specify at least one source (version, git, path, or workspace)", name),
+                    reason: format!("Dependency '{}' must specify at least one source (version, git, path, tarball, or workspace)", name),
                 });
             }
             
             if source_count > 1 {
                 return Err(PeaError::ConfigValidation {
                     field: format!("dependencies.{}", name),
-                    reason: format!("Dependency '{}' can only specify one source (version, git, path, or workspace)", name),
+                    reason: format!("Dependency '{}' can only specify one source (version, git, path, tarball, or workspace)", name),
+                });
+            }
+            
+            if package.is_some() && !registry {
+                return Err(PeaError::ConfigValidation {
+                    field: format!("dependencies.{}", name),
+                    reason: format!("Dependency '{}' can only alias a registry package; 'package' needs a version", name),
+                });
+            }
+            
+            if link.unwrap_or(false) && path.is_none() {
+                return Err(PeaError::ConfigValidation {
+                    field: format!("dependencies.{}", name),
+                    reason: format!("Dependency '{}' sets 'link' without a path", name),
                 });
             }
         }
@@ -366,6 +504,27 @@ fn validate_dependency_spec(name: &str, spec: &DependencySpec) -> ConfigResult<(
     Ok(())
 }
 
+/// Validate an override's selector and specifier, returning the specifier
+/// with a `$name` reference resolved
+fn resolve_override(config: &PeaToml, selector: &str, specifier: &str) -> ConfigResult<String> {
+    let invalid = |reason: String| PeaError::ConfigValidation {
+        field: format!("overrides.{}", selector),
+        reason,
+    };
+    OverrideSelector::parse(selector)
+        .map_err(|reason| invalid(format!("Invalid override selector '{}': {}", selector, reason)))?;
+    if specifier.trim().is_empty() {
+        return Err(invalid(format!("Override '{}' has an empty specifier", selector)));
+    }
+    match specifier.strip_prefix('$') {
+        Some(reference) => config.dependencies.get(reference)
+            .or_else(|| config.dev_dependencies.get(reference))
+            .map(|spec| spec.specifier().to_string())
+            .ok_or_else(|| invalid(format!("Override '{}' refers to ${}, which is not a dependency of the project", selector, reference))),
+        None => Ok(specifier.to_string()),
+    }
+}
+
 /// Check if a package name is valid (npm-compatible)
 fn is_valid_package_name(name: &str) -> bool {
     if name.is_empty() || name.len() > 214 {
@@ -433,6 +592,90 @@ workspace-pkg = { workspace = true }
         // Test workspace dependency
         assert!(config.dependencies.get("workspace-pkg").unwrap().is_workspace());
     }
+
+    #[test]
+    fn test_parse_aliases_and_specifier_strings() {
+        let toml = r#"
+[package]
+name = "test-package"
+version = "1.0.0"
+
+[dependencies]
+lodash = { version = "^4.17.0", package = "lodash-es" }
+legacy = "npm:left-pad@^1.3.0"
+shared = { workspace = true, version = "^" }
+tools = "workspace:*"
+vendored = "link:../vendored"
+"#;
+
+        let config = parse_pea_toml(toml).unwrap();
+        assert_eq!(config.dependencies["lodash"].specifier().to_string(), "npm:lodash-es@^4.17.0");
+        assert_eq!(config.dependencies["legacy"].version_req().unwrap().unwrap().to_string(), "^1.3.0");
+        assert_eq!(config.dependencies["shared"].specifier().to_string(), "workspace:^");
+        assert!(config.dependencies["tools"].is_workspace());
+        assert!(config.dependencies["vendored"].is_path());
+
+        let aliased_git = toml.replace("version = \"^4.17.0\"", "git = \"https://github.com/user/lodash.git\"");
+        assert!(parse_pea_toml(&aliased_git).is_err());
+    }
+
+    #[test]
+    fn test_parse_overrides() {
+        let toml = r#"
+[package]
+name = "app"
+version = "1.0.0"
+
+[dependencies]
+react = "^18.2.0"
+
+[overrides]
+minimist = "1.2.8"
+"webpack>terser" = "npm:@swc/terser@1.0.0"
+"lodash@<4.17.21" = "4.17.21"
+react-dom = "$react"
+"#;
+        
+        let config = parse_pea_toml(toml).unwrap();
+        assert_eq!(config.overrides.len(), 4);
+        assert_eq!(config.overrides["webpack>terser"], "npm:@swc/terser@1.0.0");
+        
+        let missing_reference = toml.replace("\"$react\"", "\"$vue\"");
+        assert!(parse_pea_toml(&missing_reference).is_err());
+        let bad_selector = toml.replace("\"lodash@<4.17.21\"", "\"lodash@not-a-range\"");
+        assert!(parse_pea_toml(&bad_selector).is_err());
+    }
+
+    #[test]
+    fn test_parse_install_section() {
+        let toml = r#"
+[package]
+name = "test-package"
+version = "1.0.0"
+
+[install]
+package-import-method = "clone-or-copy"
+node-linker = "isolated"
+public-hoist-pattern = ["*eslint*", "!eslint-plugin-*"]
+trusted-dependencies = ["esbuild"]
+max-unpacked-size = 1048576
+strict-peer-dependencies = true
+"#;
+        
+        let config = parse_pea_toml(toml).unwrap();
+        let install = config.install.unwrap();
+        assert_eq!(install.package_import_method, PackageImportMethod::CloneOrCopy);
+        assert_eq!(install.node_linker, NodeLinker::Isolated);
+        assert_eq!(install.hoist_pattern, vec!["*"]);
+        assert_eq!(install.public_hoist_pattern, vec!["*eslint*", "!eslint-plugin-*"]);
+        assert_eq!(install.trusted_dependencies, vec!["esbuild"]);
+        assert_eq!(install.max_unpacked_size, Some(1048576));
+        assert_eq!(install.max_tarball_entries, None);
+        assert!(install.strict_peer_dependencies);
+        
+        let invalid = toml.replace("clone-or-copy", "symlink");
+        assert!(parse_pea_toml(&invalid).is_err());
+    }
     
     #[test]
     fn test_invalid_package_name() {
@@ -547,6 +790,8 @@ mod property_tests {
                 features: HashMap::new(),
                 workspace: None,
                 profile: HashMap::new(),
+                install: None,
+                overrides: HashMap::new(),
             }
         }
     }
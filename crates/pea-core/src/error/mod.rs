@@ -55,6 +55,12 @@ pub enum PeaError {
         actual: String,
     },
 
+    #[error("Package '{package}' is not in the cache")]
+    NotInCache { package: String },
+
+    #[error("Tarball exceeds extraction limits: {actual} (allowed: {limit})")]
+    ExtractLimitExceeded { limit: String, actual: String },
+
     // Runtime errors
     #[error("JavaScript error: {message}\n{stack}")]
     JavaScript { message: String, stack: String },
@@ -68,6 +74,13 @@ pub enum PeaError {
     #[error("Module not found: {specifier}")]
     ModuleNotFound { specifier: String },
 
+    #[error("{package}: '{script}' script failed with exit code {code}")]
+    ScriptFailed {
+        package: String,
+        script: String,
+        code: i32,
+    },
+
     // IO errors
     #[error("IO error: {message}")]
     Io {
@@ -102,6 +115,16 @@ impl PeaError {
         matches!(self, PeaError::Network { .. } | PeaError::Io { .. })
     }
 
+    /// Process exit code to report for this error
+    ///
+    /// Failed scripts pass their own exit code through; everything else is 1.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PeaError::ScriptFailed { code, .. } if (1..=255).contains(code) => *code,
+            _ => 1,
+        }
+    }
+
     /// Get a user-friendly suggestion for fixing this error
     pub fn suggestion(&self) -> Option<&'static str> {
         match self {
@@ -118,6 +141,15 @@ impl PeaError {
             PeaError::PermissionDenied { .. } => {
                 Some("Run with appropriate permissions or use --allow-* flags")
             },
+            PeaError::NotInCache { .. } => {
+                Some("Run 'pea cache ls' to see cached packages, or 'pea install' to fetch it")
+            },
+            PeaError::ExtractLimitExceeded { .. } => {
+                Some("If you trust the package, raise max-unpacked-size, max-tarball-entries or max-compression-ratio under [install] in pea.toml")
+            },
+            PeaError::ScriptFailed { .. } => {
+                Some("Check the script output above, or remove the package from trusted-dependencies to skip its scripts")
+            },
             _ => None,
         }
     }
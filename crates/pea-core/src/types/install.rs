@@ -0,0 +1,131 @@
+//! Install behaviour settings.
+//!
+//! Settings shared between configuration parsing and the linker that
+//! control how packages are materialised into node_modules.
+
+use rkyv::{Archive, Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How package files are imported from the store into node_modules
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Archive, Deserialize, Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackageImportMethod {
+    /// Reflink if supported, otherwise hardlink, otherwise copy
+    #[default]
+    Auto,
+    /// Hardlink only; edits in node_modules are visible in the store
+    Hardlink,
+    /// Copy-on-write reflink only
+    Reflink,
+    /// Plain copy
+    Copy,
+    /// Reflink if supported, otherwise copy (never hardlink)
+    CloneOrCopy,
+}
+
+impl PackageImportMethod {
+    /// All methods, in the order they are documented
+    pub const ALL: [PackageImportMethod; 5] = [
+        Self::Auto,
+        Self::Hardlink,
+        Self::Reflink,
+        Self::Copy,
+        Self::CloneOrCopy,
+    ];
+
+    /// Setting value as written in configuration
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Hardlink => "hardlink",
+            Self::Reflink => "reflink",
+            Self::Copy => "copy",
+            Self::CloneOrCopy => "clone-or-copy",
+        }
+    }
+}
+
+impl fmt::Display for PackageImportMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PackageImportMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|method| method.as_str() == s)
+            .ok_or_else(|| format!(
+                "unknown package import method '{}' (expected one of: {})",
+                s,
+                Self::ALL.map(|m| m.as_str()).join(", ")
+            ))
+    }
+}
+
+/// How node_modules is laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Archive, Deserialize, Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NodeLinker {
+    /// Every package placed directly under node_modules
+    #[default]
+    Hoisted,
+    /// Virtual store under node_modules/.pea with symlinked dependency edges
+    Isolated,
+}
+
+impl NodeLinker {
+    /// Setting value as written in configuration
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hoisted => "hoisted",
+            Self::Isolated => "isolated",
+        }
+    }
+}
+
+impl fmt::Display for NodeLinker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for NodeLinker {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hoisted" => Ok(Self::Hoisted),
+            "isolated" => Ok(Self::Isolated),
+            other => Err(format!("unknown node linker '{}' (expected hoisted or isolated)", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_method_roundtrip() {
+        for method in PackageImportMethod::ALL {
+            assert_eq!(method.to_string().parse::<PackageImportMethod>(), Ok(method));
+        }
+        assert!("symlink".parse::<PackageImportMethod>().is_err());
+        assert_eq!(PackageImportMethod::default(), PackageImportMethod::Auto);
+    }
+
+    #[test]
+    fn test_node_linker_roundtrip() {
+        for linker in [NodeLinker::Hoisted, NodeLinker::Isolated] {
+            assert_eq!(linker.to_string().parse::<NodeLinker>(), Ok(linker));
+        }
+        assert!("pnp".parse::<NodeLinker>().is_err());
+    }
+}
@@ -3,13 +3,24 @@
 //! This module provides the fundamental types used throughout the Pea ecosystem:
 //! - Version types for semantic versioning
 //! - Package metadata structures
-//! - Dependency specifications
+//! - Dependency specifications and specifier strings
+//! - Override selectors
+//! - Install behaviour settings
+//! - Install platforms and the platforms packages support
 
 pub mod dependency;
+pub mod install;
 pub mod package;
+pub mod platform;
+pub mod selector;
+pub mod specifier;
 pub mod version;
 
 // Re-export all public types
 pub use dependency::{Dependency, DependencyKind};
+pub use install::{NodeLinker, PackageImportMethod};
 pub use package::{PackageMetadata, Repository};
+pub use platform::{Platform, PlatformSupport};
+pub use selector::{OverrideSelector, SelectorStep};
+pub use specifier::Specifier;
 pub use version::{Comparator, Op, PartialVersion, Version, VersionError, VersionReq};
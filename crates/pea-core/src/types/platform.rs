@@ -0,0 +1,187 @@
+//! Install platforms.
+//!
+//! Packages limit where they install with the package.json `os`, `cpu` and
+//! `libc` lists, written with Node's `process.platform`/`process.arch`
+//! names; an entry starting with `!` excludes that value instead.
+
+use rkyv::{Archive, Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A platform packages are installed for
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Archive, Deserialize, Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Platform {
+    /// Operating system (`linux`, `darwin`, `win32`, ...)
+    pub os: String,
+    /// CPU architecture (`x64`, `arm64`, `ia32`, ...)
+    pub cpu: String,
+    /// C library on Linux (`glibc` or `musl`)
+    pub libc: Option<String>,
+}
+
+/// Platforms a package supports, from its `os`, `cpu` and `libc` lists;
+/// empty lists allow everything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Archive, Deserialize, Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PlatformSupport {
+    /// Operating systems, matched against [`Platform::os`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<String>,
+    /// CPU architectures, matched against [`Platform::cpu`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpu: Vec<String>,
+    /// C libraries, matched against [`Platform::libc`]; platforms without
+    /// one don't meet a restriction
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub libc: Vec<String>,
+}
+
+impl Platform {
+    /// The platform pea itself runs on
+    pub fn current() -> Self {
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            "windows" => "win32",
+            "solaris" | "illumos" => "sunos",
+            os => os,
+        };
+        let cpu = match std::env::consts::ARCH {
+            "x86_64" => "x64",
+            "x86" => "ia32",
+            "aarch64" => "arm64",
+            "powerpc64" => "ppc64",
+            "powerpc" => "ppc",
+            "loongarch64" => "loong64",
+            arch => arch,
+        };
+        let libc = match os {
+            "linux" if cfg!(target_env = "musl") => Some("musl"),
+            "linux" => Some("glibc"),
+            _ => None,
+        };
+        Self { os: os.to_string(), cpu: cpu.to_string(), libc: libc.map(str::to_string) }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.os, self.cpu)?;
+        if let Some(libc) = &self.libc {
+            write!(f, "-{}", libc)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    /// Parse `<os>-<cpu>[-<libc>]`, e.g. `linux-arm64-musl` or `darwin-x64`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('-').collect();
+        match parts[..] {
+            [os, cpu] if !os.is_empty() && !cpu.is_empty() => {
+                Ok(Self { os: os.to_string(), cpu: cpu.to_string(), libc: None })
+            }
+            [os, cpu, libc] if !os.is_empty() && !cpu.is_empty() && !libc.is_empty() => {
+                Ok(Self { os: os.to_string(), cpu: cpu.to_string(), libc: Some(libc.to_string()) })
+            }
+            _ => Err(format!("invalid platform '{}' (expected <os>-<cpu>[-<libc>], e.g. linux-x64-glibc)", s)),
+        }
+    }
+}
+
+impl PlatformSupport {
+    /// Whether the package declares no platform restrictions
+    pub fn is_empty(&self) -> bool {
+        self.os.is_empty() && self.cpu.is_empty() && self.libc.is_empty()
+    }
+
+    /// Whether the package can be installed on `platform`
+    ///
+    /// A `libc` restriction is never met by a platform without a known C
+    /// library, as npm does.
+    pub fn supports(&self, platform: &Platform) -> bool {
+        allows(&self.os, Some(&platform.os))
+            && allows(&self.cpu, Some(&platform.cpu))
+            && allows(&self.libc, platform.libc.as_ref())
+    }
+}
+
+impl fmt::Display for PlatformSupport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lists = [("os", &self.os), ("cpu", &self.cpu), ("libc", &self.libc)];
+        let described: Vec<String> = lists.iter()
+            .filter(|(_, list)| !list.is_empty())
+            .map(|(field, list)| format!("{}: {}", field, list.join(", ")))
+            .collect();
+        f.write_str(&described.join("; "))
+    }
+}
+
+/// Whether `value` passes an `os`/`cpu`/`libc` list: it must not be
+/// excluded with `!`, and must be listed unless the list only excludes
+fn allows(list: &[String], value: Option<&String>) -> bool {
+    if list.is_empty() || list == ["any"] {
+        return true;
+    }
+    let Some(value) = value else {
+        return false;
+    };
+    let mut listed = false;
+    let mut excluding = 0;
+    for entry in list {
+        match entry.strip_prefix('!') {
+            Some(excluded) if excluded == value => return false,
+            Some(_) => excluding += 1,
+            None => listed |= entry == value,
+        }
+    }
+    listed || excluding == list.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn support(os: &[&str], cpu: &[&str], libc: &[&str]) -> PlatformSupport {
+        let list = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+        PlatformSupport { os: list(os), cpu: list(cpu), libc: list(libc) }
+    }
+
+    #[test]
+    fn test_platform_roundtrip() {
+        for platform in ["linux-x64-glibc", "linux-arm64-musl", "darwin-arm64", "win32-ia32"] {
+            assert_eq!(platform.parse::<Platform>().unwrap().to_string(), platform);
+        }
+        assert!("linux".parse::<Platform>().is_err());
+        assert!("linux--musl".parse::<Platform>().is_err());
+        assert!("linux-x64-musl-extra".parse::<Platform>().is_err());
+
+        let current = Platform::current();
+        assert_eq!(current.libc.is_some(), current.os == "linux");
+    }
+
+    #[test]
+    fn test_platform_support() {
+        let linux: Platform = "linux-x64-glibc".parse().unwrap();
+        let darwin: Platform = "darwin-arm64".parse().unwrap();
+
+        assert!(PlatformSupport::default().supports(&linux));
+        assert!(support(&["linux"], &["x64", "arm64"], &[]).supports(&linux));
+        assert!(!support(&["darwin"], &[], &[]).supports(&linux));
+        assert!(!support(&[], &["arm64"], &[]).supports(&linux));
+
+        // Exclusions alone allow everything else
+        assert!(support(&["!win32"], &[], &[]).supports(&linux));
+        assert!(!support(&["!darwin"], &[], &[]).supports(&darwin));
+        assert!(support(&["any"], &["any"], &[]).supports(&darwin));
+
+        assert!(support(&[], &[], &["glibc"]).supports(&linux));
+        assert!(!support(&[], &[], &["musl"]).supports(&linux));
+        assert!(!support(&[], &[], &["glibc"]).supports(&darwin));
+
+        assert_eq!(support(&["darwin"], &["arm64", "x64"], &[]).to_string(), "os: darwin; cpu: arm64, x64");
+    }
+}
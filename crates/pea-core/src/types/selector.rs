@@ -0,0 +1,148 @@
+//! Override selectors.
+//!
+//! An override forces the specifier of a dependency wherever its selector
+//! matches. Selectors name the dependency last and, optionally, the chain
+//! of packages that depend on it before it, separated by `>`:
+//! `lodash`, `lodash@<4.17.21`, `webpack>terser` or `a@^1.0.0>b>c`.
+
+use super::version::{Version, VersionReq};
+
+/// One `name` or `name@range` step of a selector
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorStep {
+    /// Package name, with its scope
+    pub name: String,
+    /// Versions of `name` the step applies to; any version when `None`
+    pub range: Option<VersionReq>,
+}
+
+/// A parsed override selector
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverrideSelector {
+    /// Dependents of the target, outermost first; each depends directly on the next
+    pub parents: Vec<SelectorStep>,
+    /// The dependency whose specifier is overridden
+    pub target: SelectorStep,
+}
+
+impl SelectorStep {
+    /// Whether this step names `name` at `version`
+    pub fn matches(&self, name: &str, version: &Version) -> bool {
+        self.name == name && self.range.as_ref().map_or(true, |range| range.matches(version))
+    }
+}
+
+impl OverrideSelector {
+    /// Parse a selector, reporting what's wrong with it
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        let mut steps = split_steps(selector.trim())
+            .into_iter()
+            .map(parse_step)
+            .collect::<Result<Vec<_>, _>>()?;
+        let target = steps.pop().ok_or_else(|| "selector is empty".to_string())?;
+        Ok(Self { parents: steps, target })
+    }
+
+    /// Whether a dependency whose dependents are `dependents` (outermost
+    /// first, direct dependent last) is below this selector's parents
+    pub fn matches_dependents(&self, dependents: &[(&str, &Version)]) -> bool {
+        self.matches_parents(dependents, self.parents.len())
+    }
+
+    /// How many leading parents (at least one) the last of `dependents`
+    /// match, in increasing order; packages below those dependents may
+    /// match the rest of the selector
+    pub fn matched_parents(&self, dependents: &[(&str, &Version)]) -> Vec<usize> {
+        (1..=self.parents.len())
+            .filter(|&count| self.matches_parents(dependents, count))
+            .collect()
+    }
+
+    /// Whether the last `count` of `dependents` are the first `count` parents
+    fn matches_parents(&self, dependents: &[(&str, &Version)], count: usize) -> bool {
+        dependents.len() >= count
+            && dependents[dependents.len() - count..].iter()
+                .zip(&self.parents[..count])
+                .all(|((name, version), step)| step.matches(name, version))
+    }
+}
+
+/// Split at `>` separators, leaving `>` inside ranges (`a@>=1.0.0>b`) alone:
+/// a separator follows a name or range and is followed by a package name
+fn split_steps(selector: &str) -> Vec<&str> {
+    let bytes = selector.as_bytes();
+    let mut steps = Vec::new();
+    let mut start = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let separator = byte == b'>'
+            && i > start
+            && !matches!(bytes[i - 1], b'@' | b' ' | b'|' | b'<' | b'>' | b'=')
+            && bytes.get(i + 1).is_some_and(|next| next.is_ascii_alphabetic() || *next == b'@');
+        if separator {
+            steps.push(&selector[start..i]);
+            start = i + 1;
+        }
+    }
+    if start < selector.len() {
+        steps.push(&selector[start..]);
+    }
+    steps
+}
+
+fn parse_step(step: &str) -> Result<SelectorStep, String> {
+    let step = step.trim();
+    // The range separator is the first `@` after a possible scope
+    let (name, range) = match step.get(1..).and_then(|rest| rest.find('@')) {
+        Some(at) => (&step[..at + 1], Some(&step[at + 2..])),
+        None => (step, None),
+    };
+    if name.is_empty() || name.contains(' ') {
+        return Err(format!("'{}' is not a package name", name));
+    }
+    let range = range
+        .map(|range| VersionReq::parse(range).map_err(|e| format!("invalid range '{}' for {}: {}", range, name, e)))
+        .transpose()?;
+    Ok(SelectorStep { name: name.to_string(), range })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> Version {
+        version.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_selectors() {
+        let plain = OverrideSelector::parse("lodash").unwrap();
+        assert!(plain.parents.is_empty());
+        assert_eq!(plain.target.name, "lodash");
+
+        let nested = OverrideSelector::parse("@scope/a@^1.0.0>b>@types/c@>=2.0.0").unwrap();
+        assert_eq!(nested.parents.iter().map(|step| step.name.as_str()).collect::<Vec<_>>(), ["@scope/a", "b"]);
+        assert_eq!(nested.target.name, "@types/c");
+        assert!(nested.target.matches("@types/c", &version("2.5.0")));
+        assert!(!nested.target.matches("@types/c", &version("1.9.0")));
+        assert_eq!(OverrideSelector::parse("a@>1.0.0>b").unwrap().parents[0].name, "a");
+
+        assert!(OverrideSelector::parse("").is_err());
+        assert!(OverrideSelector::parse("a@not a range").is_err());
+    }
+
+    #[test]
+    fn test_match_dependents() {
+        let selector = OverrideSelector::parse("a@^1.0.0>b>c").unwrap();
+        let (one, two) = (version("1.0.0"), version("2.0.0"));
+        assert!(selector.matches_dependents(&[("root", &one), ("a", &one), ("b", &two)]));
+        assert!(!selector.matches_dependents(&[("a", &two), ("b", &two)]));
+        assert!(!selector.matches_dependents(&[("a", &one), ("x", &one), ("b", &two)]));
+        assert!(!selector.matches_dependents(&[("b", &two)]));
+        assert!(OverrideSelector::parse("c").unwrap().matches_dependents(&[]));
+
+        assert_eq!(selector.matched_parents(&[("x", &one), ("a", &one)]), [1]);
+        assert_eq!(selector.matched_parents(&[("a", &one), ("b", &two)]), [2]);
+        assert!(selector.matched_parents(&[("a", &two), ("b", &two)]).is_empty());
+        assert!(OverrideSelector::parse("c").unwrap().matched_parents(&[("a", &one)]).is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+//! Dependency specifier strings.
+//!
+//! Parses the right-hand side of a package.json dependency entry: plain
+//! ranges, `npm:` aliases, `workspace:`, `file:`/`link:` paths, tarball URLs
+//! and the git URL forms and hosted shorthands npm accepts.
+
+use std::fmt;
+
+/// Where a dependency comes from, as written in a manifest
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Specifier {
+    /// Registry version range or dist-tag, e.g. `^1.2.0`
+    Range(String),
+    /// `npm:package@range`: `package` from the registry, installed under the
+    /// dependency's own name
+    Alias { package: String, range: String },
+    /// `workspace:<range>`; the range is `*`, `^`, `~` or a version range
+    Workspace(String),
+    /// `file:` or relative path; `link` is set for `link:`, which is
+    /// symlinked without installing its dependencies
+    Path { path: String, link: bool },
+    /// Remote tarball URL
+    Tarball(String),
+    /// Git repository URL (as given to `git clone`) and an optional
+    /// committish from the `#` fragment
+    Git { url: String, committish: Option<String> },
+}
+
+/// Hosted git shorthands and the clone URL prefix they expand to
+const GIT_HOSTS: [(&str, &str); 3] = [
+    ("github:", "https://github.com/"),
+    ("gitlab:", "https://gitlab.com/"),
+    ("bitbucket:", "https://bitbucket.org/"),
+];
+
+impl Specifier {
+    /// Parse a specifier; anything not recognised as another form is a range
+    pub fn parse(spec: &str) -> Self {
+        let spec = spec.trim();
+        if let Some(aliased) = spec.strip_prefix("npm:") {
+            // The version separator is the last `@` after a possible scope
+            let (package, range) = match aliased[1.min(aliased.len())..].rfind('@') {
+                Some(at) => (&aliased[..at + 1], &aliased[at + 2..]),
+                None => (aliased, ""),
+            };
+            return Specifier::Alias {
+                package: package.to_string(),
+                range: if range.is_empty() { "*" } else { range }.to_string(),
+            };
+        }
+        if let Some(range) = spec.strip_prefix("workspace:") {
+            return Specifier::Workspace(if range.is_empty() { "*" } else { range }.to_string());
+        }
+        if let Some(path) = spec.strip_prefix("file:") {
+            return Specifier::Path { path: path.to_string(), link: false };
+        }
+        if let Some(path) = spec.strip_prefix("link:") {
+            return Specifier::Path { path: path.to_string(), link: true };
+        }
+        if spec == "." || spec == ".." || ["./", "../", "/", "~/"].iter().any(|prefix| spec.starts_with(prefix)) {
+            return Specifier::Path { path: spec.to_string(), link: false };
+        }
+
+        let (location, committish) = match spec.split_once('#') {
+            Some((location, committish)) if !committish.is_empty() => (location, Some(committish.to_string())),
+            Some((location, _)) => (location, None),
+            None => (spec, None),
+        };
+        if let Some(url) = git_url(location) {
+            return Specifier::Git { url, committish };
+        }
+        if spec.starts_with("https://") || spec.starts_with("http://") {
+            return Specifier::Tarball(spec.to_string());
+        }
+        Specifier::Range(spec.to_string())
+    }
+
+    /// Version range the registry is asked for, if the dependency comes from
+    /// the registry
+    pub fn registry_range(&self) -> Option<&str> {
+        match self {
+            Specifier::Range(range) | Specifier::Alias { range, .. } => Some(range),
+            _ => None,
+        }
+    }
+}
+
+/// Clone URL for a git specifier without its `#` fragment, or `None` if
+/// `location` isn't one
+fn git_url(location: &str) -> Option<String> {
+    for (prefix, base) in GIT_HOSTS {
+        if let Some(repo) = location.strip_prefix(prefix) {
+            return Some(format!("{}{}.git", base, repo.trim_end_matches(".git")));
+        }
+    }
+    if let Some(url) = location.strip_prefix("git+") {
+        // `git+ssh://git@host:user/repo` is scp-style, which git only
+        // understands without the scheme
+        if let Some((host, path)) = url.strip_prefix("ssh://").and_then(|rest| rest.split_once(':')) {
+            if !host.contains('/') && !path.starts_with(|c: char| c.is_ascii_digit()) {
+                return Some(format!("{}:{}", host, path));
+            }
+        }
+        return Some(url.to_string());
+    }
+    if location.starts_with("git://") || location.starts_with("git@") {
+        return Some(location.to_string());
+    }
+    if location.starts_with("https://") || location.starts_with("http://") {
+        let host = location.split("://").nth(1)?.split('/').next()?;
+        let hosted = GIT_HOSTS.iter().any(|(_, base)| *base == format!("https://{}/", host));
+        let tarball = [".tgz", ".tar.gz", ".tar"].iter().any(|ext| location.ends_with(ext));
+        return (location.ends_with(".git") || (hosted && !tarball)).then(|| location.to_string());
+    }
+    // `user/repo` is a GitHub shorthand; ranges never contain `/`
+    let (user, repo) = location.split_once('/')?;
+    let part = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    (part(user) && part(repo) && !user.starts_with('.')).then(|| format!("https://github.com/{}/{}.git", user, repo.trim_end_matches(".git")))
+}
+
+impl fmt::Display for Specifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Specifier::Range(range) => write!(f, "{}", range),
+            Specifier::Alias { package, range } => write!(f, "npm:{}@{}", package, range),
+            Specifier::Workspace(range) => write!(f, "workspace:{}", range),
+            Specifier::Path { path, link: false } => write!(f, "file:{}", path),
+            Specifier::Path { path, link: true } => write!(f, "link:{}", path),
+            Specifier::Tarball(url) => write!(f, "{}", url),
+            Specifier::Git { url, committish } => {
+                if url.starts_with("git://") {
+                    write!(f, "{}", url)?;
+                } else if url.starts_with("git@") {
+                    write!(f, "git+ssh://{}", url)?;
+                } else {
+                    write!(f, "git+{}", url)?;
+                }
+                match committish {
+                    Some(committish) => write!(f, "#{}", committish),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_and_tags() {
+        assert_eq!(Specifier::parse("^1.2.0"), Specifier::Range("^1.2.0".to_string()));
+        assert_eq!(Specifier::parse(">=1 <2 || 3.x"), Specifier::Range(">=1 <2 || 3.x".to_string()));
+        assert_eq!(Specifier::parse("latest"), Specifier::Range("latest".to_string()));
+    }
+
+    #[test]
+    fn test_aliases() {
+        assert_eq!(Specifier::parse("npm:lodash-es@^4.17.0"), Specifier::Alias {
+            package: "lodash-es".to_string(),
+            range: "^4.17.0".to_string(),
+        });
+        assert_eq!(Specifier::parse("npm:@types/node@20"), Specifier::Alias {
+            package: "@types/node".to_string(),
+            range: "20".to_string(),
+        });
+        assert_eq!(Specifier::parse("npm:@scope/pkg"), Specifier::Alias {
+            package: "@scope/pkg".to_string(),
+            range: "*".to_string(),
+        });
+        assert_eq!(Specifier::parse("npm:@scope/pkg").registry_range(), Some("*"));
+    }
+
+    #[test]
+    fn test_local_specifiers() {
+        assert_eq!(Specifier::parse("workspace:^"), Specifier::Workspace("^".to_string()));
+        assert_eq!(Specifier::parse("workspace:"), Specifier::Workspace("*".to_string()));
+        assert_eq!(Specifier::parse("file:../lib"), Specifier::Path { path: "../lib".to_string(), link: false });
+        assert_eq!(Specifier::parse("link:vendor/a"), Specifier::Path { path: "vendor/a".to_string(), link: true });
+        assert_eq!(Specifier::parse("./pkg.tgz"), Specifier::Path { path: "./pkg.tgz".to_string(), link: false });
+        assert_eq!(Specifier::parse("file:../lib").registry_range(), None);
+    }
+
+    #[test]
+    fn test_git_and_tarballs() {
+        let git = |url: &str, committish: Option<&str>| Specifier::Git {
+            url: url.to_string(),
+            committish: committish.map(str::to_string),
+        };
+        assert_eq!(Specifier::parse("github:user/repo#v1.0.0"), git("https://github.com/user/repo.git", Some("v1.0.0")));
+        assert_eq!(Specifier::parse("user/repo"), git("https://github.com/user/repo.git", None));
+        assert_eq!(Specifier::parse("git+ssh://git@github.com:user/repo.git#main"), git("git@github.com:user/repo.git", Some("main")));
+        assert_eq!(Specifier::parse("git+ssh://git@host.com/user/repo.git"), git("ssh://git@host.com/user/repo.git", None));
+        assert_eq!(Specifier::parse("git+https://github.com/user/repo.git"), git("https://github.com/user/repo.git", None));
+        assert_eq!(Specifier::parse("git://example.com/repo.git#abc123"), git("git://example.com/repo.git", Some("abc123")));
+        assert_eq!(Specifier::parse("https://github.com/user/repo"), git("https://github.com/user/repo", None));
+        assert_eq!(
+            Specifier::parse("https://example.com/pkg-1.0.0.tgz"),
+            Specifier::Tarball("https://example.com/pkg-1.0.0.tgz".to_string())
+        );
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for spec in [
+            "^1.0.0",
+            "npm:@scope/pkg@^2.0.0",
+            "workspace:~",
+            "file:../lib",
+            "link:../lib",
+            "https://example.com/pkg.tgz",
+            "git+ssh://git@github.com:user/repo.git#main",
+            "git+https://github.com/user/repo.git",
+            "git://example.com/repo.git#v1",
+        ] {
+            assert_eq!(Specifier::parse(spec).to_string(), spec);
+        }
+    }
+}
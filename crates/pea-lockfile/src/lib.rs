@@ -0,0 +1,16 @@
+//! Lockfile support for Pea
+//!
+//! `pea.lock` records the exact version, tarball URL and integrity of every
+//! installed package, so repeated installs reproduce the same tree without
+//! resolving against the registry again.
+
+pub mod lockfile;
+pub mod prune;
+
+// Re-export main types
+pub use lockfile::{Lockfile, LockedPackage, RootDependency, LOCKFILE_NAME, LOCKFILE_VERSION};
+
+use pea_core::error::PeaError;
+
+/// Result type for lockfile operations
+pub type LockfileResult<T> = Result<T, PeaError>;
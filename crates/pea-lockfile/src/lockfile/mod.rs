@@ -0,0 +1,269 @@
+//! The `pea.lock` data model
+//!
+//! A lockfile maps each root dependency's declared range to the version it
+//! resolved to, and lists every package in the tree with the exact versions
+//! and declared ranges of its own dependencies, noting which of them
+//! overrides replaced. Everything is kept sorted so
+//! the file diffs well.
+
+use pea_core::error::PeaError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::LockfileResult;
+
+mod paths;
+
+/// File name of the lockfile, next to the project manifest
+pub const LOCKFILE_NAME: &str = "pea.lock";
+
+/// Current lockfile format version
+pub const LOCKFILE_VERSION: u32 = 1;
+
+/// Header written above the lockfile contents
+const HEADER: &str = "# This file is generated by pea. Do not edit it by hand.\n\n";
+
+/// A resolved dependency tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// Format version
+    pub version: u32,
+    /// Root dependencies by name
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, RootDependency>,
+    /// Overrides the tree was resolved with (selector -> specifier)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, String>,
+    /// Every package in the tree
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+/// A dependency declared by the project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootDependency {
+    /// Range as written in the manifest
+    pub range: String,
+    /// Version the range resolved to
+    pub version: String,
+}
+
+/// One resolved package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// Package name, with its scope
+    pub name: String,
+    /// Exact version, followed by the peers it was resolved with when it
+    /// has any (`1.0.0(react@18.2.0)`) and a tag for copies that nested
+    /// overrides apply below
+    pub version: String,
+    /// Tarball URL; path dependencies and workspace packages are
+    /// `file://<absolute path>`, stored relative to the lockfile on disk
+    pub resolved: String,
+    /// Subresource integrity of the tarball
+    pub integrity: String,
+    /// Operating systems the package installs on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<String>,
+    /// CPU architectures the package installs on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpu: Vec<String>,
+    /// C libraries the package installs with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub libc: Vec<String>,
+    /// Which of this package's dependencies are optional
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub optional: BTreeSet<String>,
+    /// Exact versions of this package's dependencies
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, String>,
+    /// Ranges this package declares for those dependencies
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub specifiers: BTreeMap<String, String>,
+    /// Selectors of the overrides applied to those dependencies
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, String>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            dependencies: BTreeMap::new(),
+            overrides: BTreeMap::new(),
+            packages: Vec::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Read the lockfile at `path`, `None` if there isn't one
+    pub fn load(path: &Path) -> LockfileResult<Option<Self>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(PeaError::io(format!("Failed to read {}", path.display()), e)),
+        };
+        let mut lockfile: Self = toml::from_str(&content).map_err(|e| invalid(e.message()))?;
+        if lockfile.version > LOCKFILE_VERSION {
+            return Err(invalid(&format!(
+                "format version {} is newer than this pea supports ({}); upgrade pea",
+                lockfile.version, LOCKFILE_VERSION
+            )));
+        }
+        let dir = lockfile_dir(path);
+        for package in &mut lockfile.packages {
+            package.resolved = paths::absolute_resolved(&package.resolved, &dir);
+        }
+        Ok(Some(lockfile))
+    }
+
+    /// Write the lockfile to `path`, returning whether the file changed
+    ///
+    /// The new contents go to a temporary file that is renamed over the old
+    /// one, so an interrupted save never leaves a truncated lockfile.
+    pub fn save(&self, path: &Path) -> LockfileResult<bool> {
+        let mut lockfile = self.clone();
+        lockfile.packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        let dir = lockfile_dir(path);
+        for package in &mut lockfile.packages {
+            package.resolved = paths::relative_resolved(&package.resolved, &dir);
+        }
+        let body = toml::to_string(&lockfile).map_err(|e| invalid(&e.to_string()))?;
+        let content = format!("{}{}", HEADER, body);
+
+        if std::fs::read_to_string(path).is_ok_and(|existing| existing == content) {
+            return Ok(false);
+        }
+        let staging = path.with_extension(format!("lock.{}.tmp", std::process::id()));
+        std::fs::write(&staging, &content)
+            .map_err(|e| PeaError::io(format!("Failed to write {}", staging.display()), e))?;
+        std::fs::rename(&staging, path).map_err(|e| {
+            let _ = std::fs::remove_file(&staging);
+            PeaError::io(format!("Failed to replace {}", path.display()), e)
+        })?;
+        Ok(true)
+    }
+
+    /// Whether the locked roots are exactly `roots` (`(name, range)` pairs)
+    ///
+    /// A lockfile whose roots differ from the manifest must be re-resolved.
+    pub fn is_up_to_date(&self, roots: &[(String, String)]) -> bool {
+        roots.len() == self.dependencies.len()
+            && roots.iter().all(|(name, range)| {
+                self.dependencies.get(name).is_some_and(|root| &root.range == range)
+            })
+    }
+
+    /// The locked package `name@version`
+    pub fn find(&self, name: &str, version: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name && package.version == version)
+    }
+}
+
+/// Directory local paths in the lockfile at `path` are relative to,
+/// resolved the way path dependencies are
+fn lockfile_dir(path: &Path) -> std::path::PathBuf {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())
+}
+
+/// Error for a lockfile that can't be read or written
+fn invalid(reason: &str) -> PeaError {
+    PeaError::ConfigValidation {
+        field: LOCKFILE_NAME.to_string(),
+        reason: reason.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Lockfile {
+        let mut lockfile = Lockfile::default();
+        lockfile.dependencies.insert("a".to_string(), RootDependency {
+            range: "^1.0.0".to_string(),
+            version: "1.2.0".to_string(),
+        });
+        lockfile.packages.push(LockedPackage {
+            name: "b".to_string(),
+            version: "2.0.0".to_string(),
+            resolved: "https://registry.npmjs.org/b/-/b-2.0.0.tgz".to_string(),
+            integrity: "sha512-bbb".to_string(),
+            os: vec!["darwin".to_string()],
+            cpu: vec!["arm64".to_string()],
+            libc: Vec::new(),
+            optional: BTreeSet::new(),
+            dependencies: BTreeMap::new(),
+            specifiers: BTreeMap::new(),
+            overrides: BTreeMap::new(),
+        });
+        lockfile.packages.push(LockedPackage {
+            name: "a".to_string(),
+            version: "1.2.0".to_string(),
+            resolved: "https://registry.npmjs.org/a/-/a-1.2.0.tgz".to_string(),
+            integrity: "sha512-aaa".to_string(),
+            os: Vec::new(),
+            cpu: Vec::new(),
+            libc: Vec::new(),
+            optional: BTreeSet::from(["b".to_string()]),
+            dependencies: BTreeMap::from([("b".to_string(), "2.0.0".to_string())]),
+            specifiers: BTreeMap::from([("b".to_string(), "^2.0.0".to_string())]),
+            overrides: BTreeMap::new(),
+        });
+        lockfile
+    }
+
+    #[test]
+    fn test_round_trip_is_sorted_and_stable() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(LOCKFILE_NAME);
+        assert_eq!(Lockfile::load(&path).unwrap(), None);
+
+        assert!(sample().save(&path).unwrap());
+        assert!(!sample().save(&path).unwrap());
+
+        let loaded = Lockfile::load(&path).unwrap().unwrap();
+        let names: Vec<&str> = loaded.packages.iter().map(|package| package.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(loaded.find("a", "1.2.0").unwrap().dependencies["b"], "2.0.0");
+        assert_eq!(loaded.find("a", "1.2.0").unwrap().specifiers["b"], "^2.0.0");
+        assert!(loaded.find("a", "1.0.0").is_none());
+        let mut sorted = sample();
+        sorted.packages.reverse();
+        assert_eq!(loaded, sorted);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(HEADER));
+        assert!(content.contains("[[package]]\nname = \"a\""));
+    }
+
+    #[test]
+    fn test_up_to_date_compares_root_ranges() {
+        let lockfile = sample();
+        assert!(lockfile.is_up_to_date(&[("a".to_string(), "^1.0.0".to_string())]));
+        assert!(!lockfile.is_up_to_date(&[("a".to_string(), "^1.1.0".to_string())]));
+        assert!(!lockfile.is_up_to_date(&[]));
+        assert!(!lockfile.is_up_to_date(&[
+            ("a".to_string(), "^1.0.0".to_string()),
+            ("b".to_string(), "^2.0.0".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn test_rejects_newer_and_malformed_lockfiles() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(LOCKFILE_NAME);
+
+        std::fs::write(&path, "version = 99\n").unwrap();
+        assert!(matches!(Lockfile::load(&path), Err(PeaError::ConfigValidation { ref reason, .. }) if reason.contains("upgrade")));
+
+        std::fs::write(&path, "version = \"one\"\n").unwrap();
+        assert!(Lockfile::load(&path).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! Local paths in `pea.lock`
+//!
+//! Path dependencies and workspace packages resolve to
+//! `file://<absolute path>`. The lockfile stores them as `file:<path>`
+//! relative to its own directory instead, so it stays valid when the
+//! project is checked out somewhere else, and turns them back into absolute
+//! paths when it is read.
+
+use std::path::{Component, Path, PathBuf};
+
+/// `resolved` as written to a lockfile in `dir`
+pub(super) fn relative_resolved(resolved: &str, dir: &Path) -> String {
+    let Some(absolute) = resolved.strip_prefix("file://").map(Path::new) else {
+        return resolved.to_string();
+    };
+    let mut base = dir.components().peekable();
+    let mut target = absolute.components().peekable();
+    // Paths on different roots (or Windows drives) can only be absolute
+    if !absolute.is_absolute() || base.peek() != target.peek() {
+        return resolved.to_string();
+    }
+    while base.peek().is_some() && base.peek() == target.peek() {
+        base.next();
+        target.next();
+    }
+    let relative: PathBuf = base.map(|_| Component::ParentDir)
+        .chain(target)
+        .collect();
+    match relative.to_str() {
+        Some("") => "file:.".to_string(),
+        Some(relative) => format!("file:{}", relative.replace('\\', "/")),
+        None => resolved.to_string(),
+    }
+}
+
+/// `resolved` as read from a lockfile in `dir`
+pub(super) fn absolute_resolved(resolved: &str, dir: &Path) -> String {
+    let relative = match resolved.strip_prefix("file:") {
+        Some(relative) if !relative.starts_with("//") => relative,
+        _ => return resolved.to_string(),
+    };
+    let mut absolute = PathBuf::new();
+    for component in dir.join(relative).components() {
+        match component {
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            Component::CurDir => {}
+            component => absolute.push(component),
+        }
+    }
+    format!("file://{}", absolute.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_paths_are_relative_to_the_lockfile() {
+        let dir = Path::new("/work/app");
+        for (resolved, stored) in [
+            ("file:///work/lib", "file:../lib"),
+            ("file:///work/app/packages/a", "file:packages/a"),
+            ("file:///work/app", "file:."),
+            ("file:///other/vendor/pkg.tgz", "file:../../other/vendor/pkg.tgz"),
+            ("https://registry.npmjs.org/a/-/a-1.0.0.tgz", "https://registry.npmjs.org/a/-/a-1.0.0.tgz"),
+            ("git+https://example.com/lib.git#0123", "git+https://example.com/lib.git#0123"),
+        ] {
+            assert_eq!(relative_resolved(resolved, dir), stored);
+            assert_eq!(absolute_resolved(stored, dir), resolved);
+        }
+        // Lockfiles written with absolute paths still load
+        assert_eq!(absolute_resolved("file:///work/lib", dir), "file:///work/lib");
+    }
+}
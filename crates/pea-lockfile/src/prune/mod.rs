@@ -0,0 +1,88 @@
+//! Dropping packages that are no longer reachable
+//!
+//! After root dependencies are removed, every package that can't be reached
+//! from the remaining roots through locked dependency edges is dropped too.
+
+use std::collections::HashSet;
+
+use crate::lockfile::{LockedPackage, Lockfile};
+
+impl Lockfile {
+    /// Keep only the root dependencies named in `roots` and the packages they reach
+    ///
+    /// Returns the packages that were dropped.
+    pub fn retain_roots(&mut self, roots: &[&str]) -> Vec<LockedPackage> {
+        self.dependencies.retain(|name, _| roots.contains(&name.as_str()));
+
+        let mut reachable: HashSet<(&str, &str)> = HashSet::new();
+        let mut pending: Vec<(&str, &str)> = self.dependencies.iter()
+            .map(|(name, root)| (name.as_str(), root.version.as_str()))
+            .collect();
+        while let Some(id) = pending.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            if let Some(package) = self.find(id.0, id.1) {
+                pending.extend(package.dependencies.iter().map(|(name, version)| (name.as_str(), version.as_str())));
+            }
+        }
+
+        let reachable: HashSet<(String, String)> = reachable.into_iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect();
+        let (kept, dropped) = std::mem::take(&mut self.packages)
+            .into_iter()
+            .partition(|package| reachable.contains(&(package.name.clone(), package.version.clone())));
+        self.packages = kept;
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lockfile::RootDependency;
+
+    fn package(name: &str, version: &str, dependencies: &[(&str, &str)]) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            resolved: format!("https://registry.npmjs.org/{0}/-/{0}-{1}.tgz", name, version),
+            integrity: format!("sha512-{}", name),
+            os: Vec::new(),
+            cpu: Vec::new(),
+            libc: Vec::new(),
+            optional: Default::default(),
+            dependencies: dependencies.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect(),
+            specifiers: dependencies.iter().map(|(n, v)| (n.to_string(), format!("^{}", v))).collect(),
+            overrides: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_retain_roots_drops_unreachable_packages() {
+        let mut lockfile = Lockfile::default();
+        for (name, version) in [("app-a", "1.0.0"), ("app-b", "1.0.0")] {
+            lockfile.dependencies.insert(name.to_string(), RootDependency {
+                range: format!("^{}", version),
+                version: version.to_string(),
+            });
+        }
+        lockfile.packages = vec![
+            package("app-a", "1.0.0", &[("shared", "2.0.0"), ("only-a", "1.0.0")]),
+            package("app-b", "1.0.0", &[("shared", "2.0.0")]),
+            package("only-a", "1.0.0", &[("shared", "1.0.0")]),
+            package("shared", "1.0.0", &[]),
+            package("shared", "2.0.0", &[]),
+        ];
+
+        let dropped = lockfile.retain_roots(&["app-b"]);
+
+        let dropped: Vec<String> = dropped.iter().map(|p| format!("{}@{}", p.name, p.version)).collect();
+        assert_eq!(dropped, ["app-a@1.0.0", "only-a@1.0.0", "shared@1.0.0"]);
+        assert_eq!(lockfile.dependencies.keys().collect::<Vec<_>>(), ["app-b"]);
+        assert!(lockfile.find("shared", "2.0.0").is_some());
+        assert_eq!(lockfile.packages.len(), 2);
+        assert_eq!(lockfile.retain_roots(&["app-b"]), Vec::new());
+    }
+}
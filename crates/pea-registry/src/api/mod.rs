@@ -43,10 +43,30 @@ pub struct VersionMetadata {
     /// Peer dependencies
     #[serde(rename = "peerDependencies")]
     pub peer_dependencies: Option<HashMap<String, String>>,
+    /// Settings for peer dependencies, such as which are optional
+    #[serde(rename = "peerDependenciesMeta")]
+    pub peer_dependencies_meta: Option<HashMap<String, PeerDependencyMeta>>,
+    /// Optional dependencies, installed when possible
+    #[serde(rename = "optionalDependencies")]
+    pub optional_dependencies: Option<HashMap<String, String>>,
+    /// Operating systems the package installs on (`!` excludes one)
+    pub os: Option<Vec<String>>,
+    /// CPU architectures the package installs on (`!` excludes one)
+    pub cpu: Option<Vec<String>>,
+    /// C libraries the package installs with on Linux
+    pub libc: Option<Vec<String>>,
     /// Distribution information
     pub dist: DistInfo,
 }
 
+/// `peerDependenciesMeta` entry for one peer dependency
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PeerDependencyMeta {
+    /// Whether the dependent works without the peer installed
+    #[serde(default)]
+    pub optional: bool,
+}
+
 /// Repository information
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RepositoryInfo {
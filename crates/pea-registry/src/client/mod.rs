@@ -45,11 +45,11 @@ pub struct AuthConfig {
 #[derive(Debug, Clone)]
 pub struct RegistryClient {
     /// Underlying HTTP client with connection pooling
-    client: Client,
+    pub(crate) client: Client,
     /// Retry configuration
     retry_config: RetryConfig,
     /// Base registry URL
-    base_url: String,
+    pub(crate) base_url: String,
 }
 impl RegistryClient {
     /// Create new registry client with connection pooling
@@ -215,9 +215,17 @@ impl RegistryClient {
 
     /// Download package tarball with integrity verification
     pub async fn download_tarball(&self, dist_info: &crate::api::DistInfo) -> RegistryResult<Vec<u8>> {
+        let bytes = self.fetch_tarball(&dist_info.tarball).await?;
+        self.verify_integrity(&bytes, dist_info)?;
+        Ok(bytes)
+    }
+
+    /// Download the tarball at `url` without verifying it, for tarballs
+    /// named by URL whose integrity isn't known until they are read
+    pub async fn fetch_tarball(&self, url: &str) -> RegistryResult<Vec<u8>> {
         self.with_retry(|| async {
             let response = self.client
-                .get(&dist_info.tarball)
+                .get(url)
                 .send()
                 .await
                 .map_err(|e| PeaError::Network { 
@@ -240,15 +248,12 @@ impl RegistryClient {
                 })?
                 .to_vec();
 
-            // Verify integrity
-            self.verify_integrity(&bytes, dist_info)?;
-
             Ok(bytes)
         }).await
     }
 
     /// Encode package name for URL (handle scoped packages)
-    fn encode_package_name(&self, name: &str) -> String {
+    pub(crate) fn encode_package_name(&self, name: &str) -> String {
         if name.starts_with('@') {
             // Scoped package: @org/pkg → @org%2fpkg
             name.replace('/', "%2f")
@@ -263,9 +268,9 @@ impl RegistryClient {
         if let Some(integrity) = &dist_info.integrity {
             if integrity.starts_with("sha512-") {
                 let expected = &integrity[7..]; // Remove "sha512-" prefix
-                let computed = blake3::hash(bytes);
+                use sha2::{Digest, Sha512};
                 use base64::{Engine as _, engine::general_purpose};
-                let computed_b64 = general_purpose::STANDARD.encode(computed.as_bytes());
+                let computed_b64 = general_purpose::STANDARD.encode(Sha512::digest(bytes));
                 
                 if computed_b64 != expected {
                     return Err(PeaError::IntegrityFailure {
@@ -137,10 +137,10 @@ async fn test_integrity_verification_sha512() {
     let client = RegistryClient::new().unwrap();
     let content = b"test content";
     
-    // Compute expected blake3 hash
-    let hash = blake3::hash(content);
+    // Compute expected SHA-512 hash
+    use sha2::{Digest, Sha512};
     use base64::{Engine as _, engine::general_purpose};
-    let expected_b64 = general_purpose::STANDARD.encode(hash.as_bytes());
+    let expected_b64 = general_purpose::STANDARD.encode(Sha512::digest(content));
     
     let dist_info = crate::api::DistInfo {
         tarball: "https://example.com/test.tgz".to_string(),
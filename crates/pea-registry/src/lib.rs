@@ -6,11 +6,15 @@
 pub mod client;
 pub mod api;
 pub mod cache;
+pub mod npmrc;
+pub mod publish;
 
 // Re-export main types
 pub use client::{RegistryClient, RetryConfig, AuthConfig};
-pub use api::{PackageMetadataResponse, VersionMetadata, DistInfo, RepositoryInfo};
+pub use api::{PackageMetadataResponse, VersionMetadata, DistInfo, PeerDependencyMeta, RepositoryInfo};
 pub use cache::{MetadataCache, CacheEntry, CacheStats};
+pub use npmrc::{RegistryConfig, DEFAULT_REGISTRY};
+pub use publish::{publish_document, Access};
 
 use pea_core::error::PeaError;
 
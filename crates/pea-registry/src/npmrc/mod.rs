@@ -0,0 +1,187 @@
+//! Registry settings from `.npmrc` files
+//!
+//! Reads the keys npm uses to pick a registry and authenticate against it:
+//! `registry`, `@scope:registry`, and per-registry `//host/path/:_authToken`
+//! or `:_auth` credentials. `${VAR}` references are expanded from the
+//! environment, so tokens can stay out of the file.
+
+use base64::{engine::general_purpose, Engine as _};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::client::{AuthConfig, RegistryClient};
+
+/// Registry used when nothing is configured
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org";
+
+/// Registry URLs and credentials merged from one or more `.npmrc` files
+#[derive(Debug, Clone, Default)]
+pub struct RegistryConfig {
+    /// Default registry, if set
+    pub registry: Option<String>,
+    /// Registry per `@scope`
+    pub scopes: HashMap<String, String>,
+    /// Credentials keyed by registry prefix without scheme (`//host/path/`)
+    pub credentials: HashMap<String, AuthConfig>,
+}
+
+impl RegistryConfig {
+    /// Load `.npmrc` files, earlier paths taking precedence; missing files are skipped
+    pub fn load(paths: &[&Path]) -> Self {
+        let mut config = Self::default();
+        for path in paths.iter().rev() {
+            if let Ok(content) = std::fs::read_to_string(path) {
+                config.apply(&content);
+            }
+        }
+        config
+    }
+
+    /// Registry URL for `package`, honouring scoped registries
+    pub fn registry_for(&self, package: &str) -> String {
+        let scoped = package.strip_prefix('@')
+            .and_then(|rest| rest.split('/').next())
+            .and_then(|scope| self.scopes.get(&format!("@{}", scope)));
+        scoped.or(self.registry.as_ref())
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| DEFAULT_REGISTRY.to_string())
+    }
+
+    /// Credentials for `registry_url`, from the longest matching `//host/path/` key
+    pub fn auth_for(&self, registry_url: &str) -> Option<AuthConfig> {
+        let target = format!("{}/", nerf_dart(registry_url).trim_end_matches('/'));
+        self.credentials.iter()
+            .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, auth)| auth.clone())
+    }
+
+    /// Merge one file's settings over the current ones
+    fn apply(&mut self, content: &str) {
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim();
+            let value = expand_env(value.trim().trim_matches('"'));
+
+            if key == "registry" {
+                self.registry = Some(value);
+            } else if let Some(scope) = key.strip_suffix(":registry").filter(|scope| scope.starts_with('@')) {
+                self.scopes.insert(scope.to_string(), value);
+            } else if let Some((prefix, setting)) = key.rsplit_once(':').filter(|(prefix, _)| prefix.starts_with("//")) {
+                let prefix = format!("{}/", prefix.trim_end_matches('/'));
+                let auth = self.credentials.entry(prefix).or_insert(AuthConfig {
+                    token: None,
+                    username: None,
+                    password: None,
+                });
+                match setting {
+                    "_authToken" => auth.token = Some(value),
+                    "username" => auth.username = Some(value),
+                    "_password" => {
+                        // npm stores the password base64 encoded
+                        let decoded = general_purpose::STANDARD.decode(&value).ok()
+                            .and_then(|bytes| String::from_utf8(bytes).ok());
+                        auth.password = Some(decoded.unwrap_or(value));
+                    }
+                    "_auth" => {
+                        let decoded = general_purpose::STANDARD.decode(&value).ok()
+                            .and_then(|bytes| String::from_utf8(bytes).ok());
+                        if let Some((username, password)) = decoded.as_deref().and_then(|pair| pair.split_once(':')) {
+                            auth.username = Some(username.to_string());
+                            auth.password = Some(password.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl RegistryClient {
+    /// Point the client at `registry_url` instead of the public npm registry
+    pub fn with_registry(mut self, registry_url: &str) -> Self {
+        self.base_url = registry_url.trim_end_matches('/').to_string();
+        self
+    }
+}
+
+/// Registry URL without its scheme, the `//host/path` form npm uses for credential keys
+fn nerf_dart(url: &str) -> String {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    format!("//{}", rest.trim_start_matches('/'))
+}
+
+/// Replace `${VAR}` with the variable's value (empty when unset)
+fn expand_env(value: &str) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&std::env::var(&rest[start + 2..start + end]).unwrap_or_default());
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(content: &str) -> RegistryConfig {
+        let mut config = RegistryConfig::default();
+        config.apply(content);
+        config
+    }
+
+    #[test]
+    fn test_registry_selection() {
+        let config = config("registry=https://npm.example.com/\n@acme:registry=https://acme.example.com/npm/\n");
+        assert_eq!(config.registry_for("lodash"), "https://npm.example.com");
+        assert_eq!(config.registry_for("@acme/widgets"), "https://acme.example.com/npm");
+        assert_eq!(config.registry_for("@other/thing"), "https://npm.example.com");
+        assert_eq!(RegistryConfig::default().registry_for("lodash"), DEFAULT_REGISTRY);
+    }
+
+    #[test]
+    fn test_credentials_use_longest_prefix() {
+        std::env::set_var("PEA_NPMRC_TEST_TOKEN", "from-env");
+        let config = config(concat!(
+            "//registry.example.com/:_authToken=host-token\n",
+            "//registry.example.com/private/:_authToken=${PEA_NPMRC_TEST_TOKEN}\n",
+            "; comment\n",
+            "//basic.example.com/:_auth=dXNlcjpwYXNz\n",
+        ));
+
+        let auth = config.auth_for("https://registry.example.com/private").unwrap();
+        assert_eq!(auth.token.as_deref(), Some("from-env"));
+        let auth = config.auth_for("https://registry.example.com").unwrap();
+        assert_eq!(auth.token.as_deref(), Some("host-token"));
+        let auth = config.auth_for("https://basic.example.com/").unwrap();
+        assert_eq!((auth.username.as_deref(), auth.password.as_deref()), (Some("user"), Some("pass")));
+        assert!(config.auth_for("https://elsewhere.example.com").is_none());
+    }
+
+    #[test]
+    fn test_earlier_files_take_precedence() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project = temp_dir.path().join("project.npmrc");
+        let user = temp_dir.path().join("user.npmrc");
+        std::fs::write(&project, "registry=https://project.example.com\n").unwrap();
+        std::fs::write(&user, "registry=https://user.example.com\n@acme:registry=https://acme.example.com\n").unwrap();
+
+        let config = RegistryConfig::load(&[&project, &user, &temp_dir.path().join("missing")]);
+        assert_eq!(config.registry_for("pkg"), "https://project.example.com");
+        assert_eq!(config.registry_for("@acme/pkg"), "https://acme.example.com");
+    }
+}
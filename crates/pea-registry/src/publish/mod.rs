@@ -0,0 +1,224 @@
+//! Publishing packages to npm-compatible registries
+//!
+//! A publish is a single `PUT /<name>` carrying the packument fragment for
+//! the new version: the manifest with its `dist` info, the dist-tag to move
+//! and the tarball as a base64 attachment, as npm's libnpmpublish sends it.
+
+use base64::{engine::general_purpose, Engine as _};
+use pea_core::error::PeaError;
+use serde_json::{json, Value};
+
+use crate::client::RegistryClient;
+use crate::RegistryResult;
+
+/// Who may install a published scoped package
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Public,
+    Restricted,
+}
+
+impl std::str::FromStr for Access {
+    type Err = PeaError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "public" => Ok(Access::Public),
+            "restricted" => Ok(Access::Restricted),
+            other => Err(PeaError::ConfigValidation {
+                field: "access".to_string(),
+                reason: format!("Expected 'public' or 'restricted', got '{}'", other),
+            }),
+        }
+    }
+}
+
+impl Access {
+    fn as_str(self) -> &'static str {
+        match self {
+            Access::Public => "public",
+            Access::Restricted => "restricted",
+        }
+    }
+}
+
+/// Build the publish document for `manifest` and its packed `tarball`
+///
+/// `integrity` and `shasum` describe the tarball; `registry` is the URL the
+/// tarball will be served from.
+pub fn publish_document(
+    manifest: &Value,
+    tarball: &[u8],
+    integrity: &str,
+    shasum: &str,
+    registry: &str,
+    tag: &str,
+    access: Option<Access>,
+) -> RegistryResult<Value> {
+    let field = |key: &str| {
+        manifest.get(key).and_then(Value::as_str).map(str::to_string)
+            .ok_or_else(|| PeaError::ConfigValidation {
+                field: key.to_string(),
+                reason: format!("package.json needs a '{}' to publish", key),
+            })
+    };
+    let name = field("name")?;
+    let version = field("version")?;
+    if access == Some(Access::Restricted) && !name.starts_with('@') {
+        return Err(PeaError::ConfigValidation {
+            field: "access".to_string(),
+            reason: format!("Unscoped package '{}' can't be restricted", name),
+        });
+    }
+
+    let unscoped = name.rsplit('/').next().unwrap_or(&name);
+    let tarball_url = format!("{}/{}/-/{}-{}.tgz", registry.trim_end_matches('/'), name, unscoped, version);
+    let mut version_manifest = manifest.clone();
+    if let Some(object) = version_manifest.as_object_mut() {
+        object.insert("_id".into(), json!(format!("{}@{}", name, version)));
+        object.insert("dist".into(), json!({
+            "integrity": integrity,
+            "shasum": shasum,
+            "tarball": tarball_url,
+        }));
+    }
+
+    let mut document = json!({
+        "_id": name,
+        "name": name,
+        "dist-tags": { tag: version },
+        "versions": { version.as_str(): version_manifest },
+        "_attachments": {
+            format!("{}-{}.tgz", name, version): {
+                "content_type": "application/octet-stream",
+                "data": general_purpose::STANDARD.encode(tarball),
+                "length": tarball.len(),
+            }
+        },
+    });
+    if let Some(description) = manifest.get("description") {
+        document["description"] = description.clone();
+    }
+    if let Some(access) = access {
+        document["access"] = json!(access.as_str());
+    }
+    Ok(document)
+}
+
+impl RegistryClient {
+    /// Upload a publish document built by [`publish_document`]
+    ///
+    /// Publishing isn't retried: a retry after a lost response would fail
+    /// with a conflict for the version that did get published.
+    pub async fn publish(&self, name: &str, document: &Value) -> RegistryResult<()> {
+        let url = format!("{}/{}", self.base_url, self.encode_package_name(name));
+        let response = self.client
+            .put(&url)
+            .header("npm-command", "publish")
+            .json(document)
+            .send()
+            .await
+            .map_err(|e| PeaError::network(format!("Failed to publish {}", name), e))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let reason = serde_json::from_str::<Value>(&body).ok()
+            .and_then(|json| json.get("error").and_then(Value::as_str).map(str::to_string))
+            .unwrap_or(body);
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+                Err(PeaError::PermissionDenied {
+                    permission: "publish".to_string(),
+                    resource: format!("{} on {} ({})", name, self.base_url, reason.trim()),
+                })
+            }
+            reqwest::StatusCode::CONFLICT => Err(PeaError::ConfigValidation {
+                field: "version".to_string(),
+                reason: format!("This version of {} is already published ({})", name, reason.trim()),
+            }),
+            status => Err(PeaError::Network {
+                message: format!("Registry returned status {} publishing {}: {}", status, name, reason.trim()),
+                source: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn document(name: &str, access: Option<Access>) -> RegistryResult<Value> {
+        let manifest = json!({ "name": name, "version": "1.0.0", "description": "demo" });
+        publish_document(&manifest, b"tarball", "sha512-abc", "0123", "https://registry.example.com/", "beta", access)
+    }
+
+    #[test]
+    fn test_publish_document_layout() {
+        let document = document("@acme/widgets", Some(Access::Public)).unwrap();
+
+        assert_eq!(document["_id"], "@acme/widgets");
+        assert_eq!(document["dist-tags"]["beta"], "1.0.0");
+        assert_eq!(document["access"], "public");
+        let version = &document["versions"]["1.0.0"];
+        assert_eq!(version["_id"], "@acme/widgets@1.0.0");
+        assert_eq!(version["dist"]["integrity"], "sha512-abc");
+        assert_eq!(version["dist"]["tarball"], "https://registry.example.com/@acme/widgets/-/widgets-1.0.0.tgz");
+
+        let attachment = &document["_attachments"]["@acme/widgets-1.0.0.tgz"];
+        assert_eq!(attachment["length"], 7);
+        let data = general_purpose::STANDARD.decode(attachment["data"].as_str().unwrap()).unwrap();
+        assert_eq!(data, b"tarball");
+    }
+
+    #[test]
+    fn test_restricted_requires_scope() {
+        assert!(document("widgets", Some(Access::Restricted)).is_err());
+        assert!(document("@acme/widgets", Some(Access::Restricted)).is_ok());
+        assert!("private".parse::<Access>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_publish_puts_document_with_auth() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/@acme%2fwidgets"))
+            .and(header("authorization", "Bearer secret"))
+            .and(body_partial_json(json!({ "dist-tags": { "beta": "1.0.0" } })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({ "ok": true })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let auth = crate::AuthConfig { token: Some("secret".to_string()), username: None, password: None };
+        let client = RegistryClient::with_auth(auth).unwrap().with_registry(&mock_server.uri());
+        client.publish("@acme/widgets", &document("@acme/widgets", None).unwrap()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_publish_errors() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/denied"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(json!({ "error": "no permission" })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/taken"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(json!({ "error": "cannot modify pre-existing version" })))
+            .mount(&mock_server)
+            .await;
+
+        let client = RegistryClient::new().unwrap().with_registry(&mock_server.uri());
+        let denied = client.publish("denied", &document("denied", None).unwrap()).await.unwrap_err();
+        assert!(matches!(denied, PeaError::PermissionDenied { ref resource, .. } if resource.contains("no permission")));
+        let taken = client.publish("taken", &document("taken", None).unwrap()).await.unwrap_err();
+        assert!(matches!(taken, PeaError::ConfigValidation { ref field, .. } if field == "version"));
+    }
+}
@@ -8,7 +8,7 @@ use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::EdgeRef;
 use serde::{Deserialize, Serialize};
 
-use pea_core::types::{Version, VersionReq, DependencyKind};
+use pea_core::types::{PlatformSupport, Version, VersionReq, DependencyKind};
 
 /// Unique identifier for a package
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -17,6 +17,13 @@ pub struct PackageId {
     pub name: String,
     /// Resolved version
     pub version: Version,
+    /// Peers this copy is resolved with, as `(name@version)` groups sorted
+    /// by name, then an `(overrides-<hash>)` group when nested overrides
+    /// apply below it because of where it is, then `(name@version)` groups
+    /// for the peers its dependencies take from above it; packages that
+    /// differ here are different copies
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub peers: String,
 }
 
 /// Node in the dependency graph representing a resolved package
@@ -32,6 +39,9 @@ pub struct PackageNode {
     pub resolved_url: String,
     /// Integrity hash for verification
     pub integrity: String,
+    /// Platforms the package installs on (package.json `os`/`cpu`/`libc`)
+    #[serde(default)]
+    pub platform: PlatformSupport,
 }
 
 /// Edge in the dependency graph representing a dependency relationship
@@ -43,6 +53,9 @@ pub struct DependencyEdge {
     pub kind: DependencyKind,
     /// Whether this dependency is optional
     pub optional: bool,
+    /// Selector of the override that replaced the declared specifier
+    #[serde(default)]
+    pub overridden_by: Option<String>,
 }
 
 /// Thread-safe dependency graph using petgraph
@@ -107,6 +120,17 @@ impl DependencyGraph {
         Ok(())
     }
 
+    /// Remove a package and its edges
+    pub fn remove_package(&mut self, package_id: &PackageId) -> Option<PackageNode> {
+        let (_, node_index) = self.node_map.remove(package_id)?;
+        let removed = self.graph.remove_node(node_index);
+        // The last node takes over the removed one's index
+        if let Some(moved) = self.graph.node_weight(node_index) {
+            self.node_map.insert(moved.id.clone(), node_index);
+        }
+        removed
+    }
+
     /// Get package node by ID
     pub fn get_package(&self, package_id: &PackageId) -> Option<&PackageNode> {
         let node_index = self.node_map.get(package_id)?;
@@ -118,6 +142,19 @@ impl DependencyGraph {
         self.graph.node_weights()
     }
 
+    /// Get the direct dependencies of a package with their edges
+    pub fn dependencies_of(&self, package_id: &PackageId) -> Vec<(&PackageNode, &DependencyEdge)> {
+        let Some(node_index) = self.node_map.get(package_id).map(|index| *index) else {
+            return Vec::new();
+        };
+        self.graph
+            .edges(node_index)
+            .filter_map(|edge| {
+                self.graph.node_weight(edge.target()).map(|node| (node, edge.weight()))
+            })
+            .collect()
+    }
+
     /// Detect cycles in the dependency graph
     pub fn detect_cycles(&self) -> Result<Vec<PackageId>, Vec<PackageId>> {
         use petgraph::algo::toposort;
@@ -233,21 +270,29 @@ impl DependencyGraph {
 impl PackageId {
     /// Create a new package ID
     pub fn new(name: String, version: Version) -> Self {
-        Self { name, version }
+        Self { name, version, peers: String::new() }
     }
 
-    /// Create package ID from name and version string
+    /// Create package ID from name and version string, which may carry
+    /// peers as written by [`PackageId::version_key`]
     pub fn from_name_version(name: &str, version: &str) -> Result<Self, String> {
         use std::str::FromStr;
-        let version = Version::from_str(version)
+        let (plain, peers) = version.split_at(version.find('(').unwrap_or(version.len()));
+        let parsed = Version::from_str(plain)
             .map_err(|e| format!("Invalid version '{}': {}", version, e))?;
-        Ok(Self::new(name.to_string(), version))
+        Ok(Self { peers: peers.to_string(), ..Self::new(name.to_string(), parsed) })
+    }
+
+    /// Version with peers appended (`18.2.0(react@18.2.0)`), which tells
+    /// copies of a package apart in lockfiles and the virtual store
+    pub fn version_key(&self) -> String {
+        format!("{}{}", self.version, self.peers)
     }
 }
 
 impl std::fmt::Display for PackageId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}@{}", self.name, self.version)
+        write!(f, "{}@{}{}", self.name, self.version, self.peers)
     }
 }
 
@@ -266,8 +311,19 @@ impl PackageNode {
             version,
             resolved_url,
             integrity,
+            platform: PlatformSupport::default(),
         }
     }
+
+    /// This package as resolved with `peers` (name -> version)
+    pub fn with_peers<'a>(mut self, peers: impl IntoIterator<Item = (&'a str, &'a Version)>) -> Self {
+        let mut peers: Vec<_> = peers.into_iter().collect();
+        peers.sort();
+        self.id.peers = peers.into_iter()
+            .map(|(name, version)| format!("({}@{})", name, version))
+            .collect();
+        self
+    }
 }
 
 impl DependencyEdge {
@@ -277,6 +333,7 @@ impl DependencyEdge {
             version_req,
             kind,
             optional,
+            overridden_by: None,
         }
     }
 
@@ -331,6 +388,45 @@ mod tests {
         assert_eq!(graph.package_count(), 1); // Should not duplicate
     }
 
+    #[test]
+    fn test_dependencies_of() {
+        let mut graph = DependencyGraph::new();
+        let app = PackageNode::new("app".to_string(), Version::from_str("1.0.0").unwrap(), String::new(), String::new());
+        let lib = PackageNode::new("lib".to_string(), Version::from_str("2.0.0").unwrap(), String::new(), String::new());
+        graph.add_package(app.clone());
+        graph.add_package(lib.clone());
+        graph.add_dependency(&app.id, &lib.id, DependencyEdge::normal(VersionReq::parse("^2.0.0").unwrap())).unwrap();
+        
+        let deps = graph.dependencies_of(&app.id);
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].0.id, lib.id);
+        assert!(graph.dependencies_of(&lib.id).is_empty());
+        
+        let unknown = PackageId::from_name_version("missing", "1.0.0").unwrap();
+        assert!(graph.dependencies_of(&unknown).is_empty());
+    }
+
+    #[test]
+    fn test_remove_package() {
+        let mut graph = DependencyGraph::new();
+        let package = |name: &str| PackageNode::new(name.to_string(), Version::from_str("1.0.0").unwrap(), String::new(), String::new());
+        let (app, lib, util) = (package("app"), package("lib"), package("util"));
+        for node in [&app, &lib, &util] {
+            graph.add_package(node.clone());
+        }
+        graph.add_dependency(&app.id, &lib.id, DependencyEdge::normal(VersionReq::parse("^1.0.0").unwrap())).unwrap();
+        graph.add_dependency(&app.id, &util.id, DependencyEdge::normal(VersionReq::parse("^1.0.0").unwrap())).unwrap();
+
+        // util moves into lib's place and is still found by its id
+        assert_eq!(graph.remove_package(&lib.id).unwrap().id, lib.id);
+        assert!(graph.get_package(&lib.id).is_none());
+        assert_eq!(graph.get_package(&util.id).unwrap().id, util.id);
+        let deps = graph.dependencies_of(&app.id);
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].0.id, util.id);
+        assert!(graph.remove_package(&lib.id).is_none());
+    }
+
     #[test]
     fn test_add_dependency() {
         let mut graph = DependencyGraph::new();
@@ -4,12 +4,16 @@
 //! parallel processing, and comprehensive conflict resolution for JavaScript packages.
 
 pub mod graph;
+pub mod local;
+pub mod peers;
 pub mod sat;
 pub mod semver;
 
 // Re-export main types
 pub use graph::{DependencyGraph, PackageNode, DependencyEdge, PackageId};
 pub use sat::{Resolver, ResolutionResult, ConflictError};
+pub use local::LocalPackage;
+pub use peers::{PeerDependency, PeerIssue};
 
 use pea_core::error::PeaError;
 
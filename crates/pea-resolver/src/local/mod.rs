@@ -0,0 +1,128 @@
+//! Path dependencies on local directories and tarball files
+//!
+//! A `file:` dependency names either a package directory or a `.tgz` file.
+//! Both resolve to `file://<absolute path>`; the integrity recorded for them
+//! is that of the tarball, or of a directory's package.json, so a reinstall
+//! can tell when what the lockfile describes has changed on disk. pea.lock
+//! stores these paths relative to the project.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use pea_core::error::{PeaError, PeaResult};
+use pea_core::types::{PlatformSupport, Specifier};
+use std::collections::HashMap;
+
+/// A package read from a local directory or tarball file
+#[derive(Debug, Clone)]
+pub struct LocalPackage {
+    /// Absolute path of the directory or tarball
+    pub path: Utf8PathBuf,
+    /// Raw package.json
+    pub manifest: Vec<u8>,
+    /// sha512 SRI of the tarball, or of a directory's package.json
+    pub integrity: String,
+}
+
+impl LocalPackage {
+    /// Read the package at `path` (a directory or tarball), relative to `base`
+    pub fn read(base: &Utf8Path, path: &str) -> PeaResult<Self> {
+        let expanded = match path.strip_prefix("~/") {
+            Some(rest) => Utf8PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest),
+            None => base.join(path),
+        };
+        let path = expanded.canonicalize_utf8()
+            .map_err(|e| PeaError::io(format!("Failed to find {}", expanded), e))?;
+
+        if path.is_file() {
+            let tarball = std::fs::read(&path)
+                .map_err(|e| PeaError::io(format!("Failed to read {}", path), e))?;
+            let manifest = pea_cache::tarball::package_manifest(tarball.as_slice())?;
+            return Ok(Self { integrity: pea_cache::tarball::integrity(&tarball), path, manifest });
+        }
+        let manifest_path = path.join("package.json");
+        let manifest = std::fs::read(&manifest_path)
+            .map_err(|e| PeaError::io(format!("Failed to read {}", manifest_path), e))?;
+        Ok(Self { integrity: pea_cache::tarball::integrity(&manifest), path, manifest })
+    }
+
+    /// Read what a lockfile resolved as `file://<path>`
+    pub fn read_resolved(resolved: &str) -> Option<PeaResult<Self>> {
+        let path = resolved.strip_prefix("file://")?;
+        Some(Self::read(Utf8Path::new("/"), path))
+    }
+
+    /// Directory the package's own relative path dependencies are relative to
+    pub fn base_dir(&self) -> &Utf8Path {
+        if self.path.is_file() {
+            self.path.parent().unwrap_or(&self.path)
+        } else {
+            &self.path
+        }
+    }
+}
+
+/// Dependencies under `field` (`dependencies`, `optionalDependencies`) of a
+/// package.json as name -> specifier, with relative paths joined onto
+/// `base` so they resolve the same from anywhere
+pub(crate) fn manifest_dependencies(manifest: &serde_json::Value, field: &str, base: &Utf8Path) -> HashMap<String, String> {
+    let Some(dependencies) = manifest.get(field).and_then(serde_json::Value::as_object) else {
+        return HashMap::new();
+    };
+    dependencies.iter()
+        .filter_map(|(name, spec)| {
+            let spec = spec.as_str()?;
+            let spec = match Specifier::parse(spec) {
+                Specifier::Path { path, link } if Utf8Path::new(&path).is_relative() && !path.starts_with("~/") => {
+                    Specifier::Path { path: base.join(path).to_string(), link }.to_string()
+                }
+                _ => spec.to_string(),
+            };
+            Some((name.clone(), spec))
+        })
+        .collect()
+}
+
+/// Platforms a package.json says the package installs on
+pub(crate) fn manifest_platform(manifest: &serde_json::Value) -> PlatformSupport {
+    let list = |field: &str| manifest.get(field)
+        .and_then(serde_json::Value::as_array)
+        .map(|values| values.iter().filter_map(|value| Some(value.as_str()?.to_string())).collect())
+        .unwrap_or_default();
+    PlatformSupport { os: list("os"), cpu: list("cpu"), libc: list("libc") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_directory_and_tarball_packages() {
+        let temp = TempDir::new().unwrap();
+        let root = Utf8PathBuf::from_path_buf(temp.path().canonicalize().unwrap()).unwrap();
+        std::fs::create_dir_all(root.join("lib")).unwrap();
+        std::fs::create_dir_all(root.join("app")).unwrap();
+        std::fs::write(root.join("lib/package.json"),
+            r#"{"name": "lib", "dependencies": {"a": "^1.0.0", "b": "file:../b"}, "optionalDependencies": {"c": "file:c"}, "os": ["!win32"]}"#).unwrap();
+
+        let lib = LocalPackage::read(&root.join("app"), "../lib").unwrap();
+        assert_eq!(lib.path, root.join("lib"));
+        assert_eq!(lib.integrity, pea_cache::tarball::integrity(&std::fs::read(root.join("lib/package.json")).unwrap()));
+        let manifest = serde_json::from_slice(&lib.manifest).unwrap();
+        let dependencies = manifest_dependencies(&manifest, "dependencies", lib.base_dir());
+        assert_eq!(dependencies["a"], "^1.0.0");
+        assert_eq!(dependencies["b"], format!("file:{}/../b", root.join("lib")));
+        assert_eq!(manifest_dependencies(&manifest, "optionalDependencies", lib.base_dir())["c"], format!("file:{}/c", root.join("lib")));
+        assert_eq!(manifest_platform(&manifest).os, ["!win32"]);
+        assert!(LocalPackage::read(&root, "missing").is_err());
+
+        let mut tarball = Vec::new();
+        pea_cache::tarball::write_package_tarball(&mut tarball, root.join("lib").as_std_path(),
+            &["package.json".to_string()], None).unwrap();
+        std::fs::write(root.join("lib.tgz"), &tarball).unwrap();
+        let packed = LocalPackage::read_resolved(&format!("file://{}", root.join("lib.tgz"))).unwrap().unwrap();
+        assert_eq!(packed.integrity, pea_cache::tarball::integrity(&tarball));
+        assert_eq!(packed.base_dir(), root);
+        let manifest = serde_json::from_slice(&packed.manifest).unwrap();
+        assert_eq!(manifest_dependencies(&manifest, "dependencies", packed.base_dir())["b"], format!("file:{}/../b", root));
+    }
+}
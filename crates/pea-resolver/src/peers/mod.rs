@@ -0,0 +1,137 @@
+//! Peer dependencies
+//!
+//! A peer isn't a package's own dependency: it resolves to whatever the
+//! package's dependent can see, first among the dependent's other
+//! dependencies, then further up the tree. Required peers nothing provides
+//! are installed beside the package, as npm 7+ does; optional ones
+//! (`peerDependenciesMeta`) are left out. A package reached from places
+//! that provide different peers becomes a different copy for each, named
+//! by its peers (`react-dom@18.2.0(react@18.2.0)`). Peers its dependencies
+//! take from above it name it too, so a package that only reaches react
+//! through such a dependency is also copied for each react it's placed
+//! under (`form@1.0.0(react@18.2.0)`).
+
+use pea_core::types::{DependencyKind, Version, VersionReq};
+use pea_registry::VersionMetadata;
+
+use crate::graph::{DependencyEdge, PackageId};
+
+/// A peer dependency a package declares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerDependency {
+    /// Name of the package expected beside the dependent
+    pub name: String,
+    /// Range as declared
+    pub range: String,
+    /// Whether the package works without it
+    pub optional: bool,
+}
+
+/// A peer dependency that isn't met
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIssue {
+    /// Package that declares the peer
+    pub package: PackageId,
+    /// Name of the peer
+    pub peer: String,
+    /// Range the package declares for it
+    pub range: String,
+    /// Version the package resolved the peer to, outside `range`; `None`
+    /// when nothing provides it and it couldn't be installed
+    pub found: Option<Version>,
+}
+
+impl PeerDependency {
+    /// Whether `version` satisfies the declared range; ranges pea can't
+    /// parse accept any version
+    pub fn accepts(&self, version: &Version) -> bool {
+        VersionReq::parse(&self.range).map_or(true, |range| range.matches(version))
+    }
+
+    /// Edge from the dependent to the package providing this peer
+    pub(crate) fn edge(&self) -> DependencyEdge {
+        let version_req = VersionReq::parse(&self.range)
+            .unwrap_or_else(|_| VersionReq::parse("*").expect("* is a valid range"));
+        DependencyEdge::new(version_req, DependencyKind::Peer, self.optional)
+    }
+}
+
+impl std::fmt::Display for PeerIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.found {
+            Some(found) => write!(f, "{}: invalid peer {}@{} (found {})", self.package, self.peer, self.range, found),
+            None => write!(f, "{}: missing peer {}@{}", self.package, self.peer, self.range),
+        }
+    }
+}
+
+/// Peers declared in registry metadata, except those that are also
+/// regular dependencies
+pub(crate) fn registry_peers(metadata: &VersionMetadata) -> Vec<PeerDependency> {
+    let Some(peers) = &metadata.peer_dependencies else {
+        return Vec::new();
+    };
+    let meta = metadata.peer_dependencies_meta.clone().unwrap_or_default();
+    let dependencies = metadata.dependencies.clone().unwrap_or_default();
+    collect(peers.iter().map(|(name, range)| (name, range.as_str())),
+        |name| meta.get(name).is_some_and(|meta| meta.optional),
+        |name| dependencies.contains_key(name))
+}
+
+/// Peers declared in a package.json, except those that are also regular
+/// dependencies
+pub(crate) fn manifest_peers(manifest: &serde_json::Value) -> Vec<PeerDependency> {
+    let Some(peers) = manifest.get("peerDependencies").and_then(serde_json::Value::as_object) else {
+        return Vec::new();
+    };
+    let dependencies = manifest.get("dependencies").and_then(serde_json::Value::as_object);
+    collect(peers.iter().filter_map(|(name, range)| Some((name, range.as_str()?))),
+        |name| manifest.pointer(&format!("/peerDependenciesMeta/{}/optional", name.replace('/', "~1")))
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false),
+        |name| dependencies.is_some_and(|dependencies| dependencies.contains_key(name)))
+}
+
+fn collect<'a>(
+    peers: impl Iterator<Item = (&'a String, &'a str)>,
+    optional: impl Fn(&str) -> bool,
+    is_dependency: impl Fn(&str) -> bool,
+) -> Vec<PeerDependency> {
+    let mut peers: Vec<PeerDependency> = peers
+        .filter(|(name, _)| !is_dependency(name))
+        .map(|(name, range)| PeerDependency { name: name.clone(), range: range.to_string(), optional: optional(name) })
+        .collect();
+    peers.sort_by(|a, b| a.name.cmp(&b.name));
+    peers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_peers() {
+        let manifest = serde_json::json!({
+            "dependencies": {"tslib": "^2.0.0"},
+            "peerDependencies": {"react": "^18.0.0", "@types/react": "^18.0.0", "tslib": "^2.0.0", "vue": ">=2 <4"},
+            "peerDependenciesMeta": {"@types/react": {"optional": true}},
+        });
+        let peers = manifest_peers(&manifest);
+        assert_eq!(peers.iter().map(|peer| peer.name.as_str()).collect::<Vec<_>>(), ["@types/react", "react", "vue"]);
+        assert!(peers[0].optional && !peers[1].optional);
+
+        let version = |version: &str| version.parse::<Version>().unwrap();
+        assert!(peers[1].accepts(&version("18.2.0")));
+        assert!(!peers[1].accepts(&version("17.0.2")));
+        // Ranges pea can't parse don't produce false alarms
+        assert!(peers[2].accepts(&version("1.0.0")));
+
+        let issue = PeerIssue {
+            package: PackageId::from_name_version("ui", "1.0.0(react@17.0.2)").unwrap(),
+            peer: "react".to_string(),
+            range: "^18.0.0".to_string(),
+            found: Some(version("17.0.2")),
+        };
+        assert_eq!(issue.to_string(), "ui@1.0.0(react@17.0.2): invalid peer react@^18.0.0 (found 17.0.2)");
+    }
+}
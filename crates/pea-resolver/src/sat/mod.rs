@@ -5,12 +5,16 @@
 
 use std::sync::Arc;
 use std::str::FromStr;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use dashmap::DashMap;
 
+use pea_cache::GitCache;
+use pea_core::types::{DependencyKind, OverrideSelector, PlatformSupport, Specifier, Version};
 use pea_registry::{RegistryClient, MetadataCache};
-use crate::graph::{DependencyGraph, PackageId, PackageNode};
+use crate::graph::{DependencyEdge, DependencyGraph, PackageId, PackageNode};
+use crate::local::{manifest_dependencies, manifest_platform, LocalPackage};
+use crate::peers::{manifest_peers, registry_peers, PeerDependency, PeerIssue};
 
 /// Main dependency resolver with parallel processing
 #[derive(Debug)]
@@ -21,6 +25,32 @@ pub struct Resolver {
     metadata_cache: Arc<MetadataCache>,
     /// Resolved packages cache (name@version_req -> PackageNode)
     resolved_packages: DashMap<String, PackageNode>,
+    /// Versions to keep when they still satisfy a range (name -> versions)
+    preferred_versions: HashMap<String, Vec<pea_core::types::Version>>,
+    /// Registry package behind each `npm:` alias (alias id -> real name)
+    aliases: DashMap<PackageId, String>,
+    /// Checkouts for git dependencies; without one they can't be resolved
+    git_cache: Option<Arc<GitCache>>,
+    /// Dependencies of packages that don't come from the registry, read
+    /// from their own package.json (id -> name -> specifier)
+    local_dependencies: DashMap<PackageId, HashMap<String, String>>,
+    /// Optional dependencies of those packages (id -> name -> specifier)
+    local_optional_dependencies: DashMap<PackageId, HashMap<String, String>>,
+    /// Directory the roots' relative `file:` paths are relative to
+    project_dir: Option<camino::Utf8PathBuf>,
+    /// Overrides as (selector, parsed selector, specifier), most specific first
+    overrides: Vec<(String, OverrideSelector, String)>,
+    /// Peer dependencies of resolved packages (id without peers -> peers)
+    peer_dependencies: DashMap<PackageId, Vec<PeerDependency>>,
+    /// Unmet peers found by the current resolution, by (package, peer)
+    peer_issues: DashMap<(PackageId, String), PeerIssue>,
+    /// Peers the dependencies of packages take from above them
+    /// (id without peers -> peer names)
+    transitive_peers: DashMap<PackageId, BTreeSet<String>>,
+    /// Root dependencies that may be left out, like optional dependencies
+    optional_roots: HashSet<String>,
+    /// Optional dependencies the current resolution left out, by (name, specifier)
+    skipped_optional: DashMap<(String, String), ConflictError>,
 }
 
 /// Result of dependency resolution
@@ -34,6 +64,28 @@ pub struct ResolutionResult {
     pub package_count: usize,
     /// Resolution time in milliseconds
     pub resolution_time_ms: u64,
+    /// Peer dependencies that aren't met, sorted by package
+    pub peer_issues: Vec<PeerIssue>,
+    /// Optional dependencies left out because they couldn't be resolved,
+    /// sorted by package
+    pub skipped_optional: Vec<ConflictError>,
+}
+
+/// A package placed beside others, resolved with its peers
+struct PlacedPackage {
+    package: PackageNode,
+    /// Packages its peers resolved to, with the edges to them
+    peers: Vec<(PackageNode, DependencyEdge)>,
+}
+
+/// Where a peer dependency is provided from
+enum PeerSource {
+    /// The package placed beside the dependent under this name
+    Beside(String),
+    /// A package elsewhere in the graph
+    Package(Box<PackageNode>),
+    /// One of the dependent's own dependents, which it doesn't link to
+    Dependent(Version),
 }
 
 /// Conflict error when dependencies cannot be satisfied
@@ -57,9 +109,61 @@ impl Resolver {
             registry_client,
             metadata_cache,
             resolved_packages: DashMap::new(),
+            preferred_versions: HashMap::new(),
+            aliases: DashMap::new(),
+            git_cache: None,
+            local_dependencies: DashMap::new(),
+            local_optional_dependencies: DashMap::new(),
+            project_dir: None,
+            overrides: Vec::new(),
+            peer_dependencies: DashMap::new(),
+            peer_issues: DashMap::new(),
+            transitive_peers: DashMap::new(),
+            optional_roots: HashSet::new(),
+            skipped_optional: DashMap::new(),
         }
     }
 
+    /// Resolve git dependencies to commits, checked out through `git_cache`
+    pub fn with_git_cache(mut self, git_cache: Arc<GitCache>) -> Self {
+        self.git_cache = Some(git_cache);
+        self
+    }
+
+    /// Resolve the roots' relative `file:` paths against `project_dir`
+    /// (the current directory otherwise)
+    pub fn with_project_dir(mut self, project_dir: impl Into<camino::Utf8PathBuf>) -> Self {
+        self.project_dir = Some(project_dir.into());
+        self
+    }
+
+    /// Force the specifiers of transitive dependencies (selector -> specifier)
+    ///
+    /// Where several selectors match, the one with the longest chain of
+    /// dependents wins. Selectors that don't parse are ignored, so validate
+    /// them first.
+    pub fn with_overrides(mut self, overrides: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.overrides = overrides.into_iter()
+            .filter_map(|(selector, specifier)| Some((selector.clone(), OverrideSelector::parse(&selector).ok()?, specifier)))
+            .collect();
+        self.overrides.sort_by(|a, b| b.1.parents.len().cmp(&a.1.parents.len()).then_with(|| a.0.cmp(&b.0)));
+        self
+    }
+
+    /// Treat these root dependencies like optional dependencies: when one
+    /// can't be resolved it's left out instead of failing the resolution
+    pub fn with_optional_roots(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.optional_roots = names.into_iter().collect();
+        self
+    }
+
+    /// Prefer these versions (e.g. from a lockfile) over newer ones whenever
+    /// they satisfy the requested range
+    pub fn with_preferred_versions(mut self, preferred_versions: HashMap<String, Vec<pea_core::types::Version>>) -> Self {
+        self.preferred_versions = preferred_versions;
+        self
+    }
+
     /// Resolve dependencies for a set of root packages
     pub async fn resolve(
         &self,
@@ -86,24 +190,39 @@ impl Resolver {
     ) -> Result<ResolutionResult, ConflictError> {
         let start_time = std::time::Instant::now();
         let mut graph = DependencyGraph::new();
-        let mut roots = Vec::new();
+        self.peer_issues.clear();
+        self.transitive_peers.clear();
+        self.skipped_optional.clear();
 
         // Process root dependencies
-        for (name, version_req_str) in root_dependencies {
-            let version_req = pea_core::types::VersionReq::parse(&version_req_str)
-                .map_err(|_| ConflictError {
-                    package: name.clone(),
-                    required: version_req_str.clone(),
-                    conflicting: "root".to_string(),
-                    conflict: "invalid version requirement".to_string(),
-                })?;
-
+        let mut root_packages = Vec::new();
+        for (name, specifier) in root_dependencies {
             // Resolve the package with workspace context and features
-            let resolved_package = self.resolve_package_with_workspace(&name, &version_req, workspace_members.as_ref()).await?;
-            roots.push(resolved_package.id.clone());
-            
-            // Add to graph and resolve dependencies recursively
-            self.resolve_recursive_with_features(&mut graph, resolved_package, workspace_members.as_ref(), enabled_features.as_ref()).await?;
+            match self.resolve_specifier(&name, &specifier, "root", workspace_members.as_ref()).await {
+                Ok((resolved_package, _)) => root_packages.push(resolved_package),
+                Err(e) if self.optional_roots.contains(&name) => self.skip_optional(e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Roots sit side by side, so their peers resolve among each other
+        let root_count = root_packages.len();
+        let placed = self.place_with_peers(&graph, root_packages, &[], None, workspace_members.as_ref()).await?;
+        let mut roots: Vec<PackageId> = placed[..root_count].iter().map(|placed| placed.package.id.clone()).collect();
+        let mut root_scope: HashMap<String, PackageNode> = placed.iter()
+            .map(|placed| (placed.package.name.clone(), placed.package.clone()))
+            .collect();
+
+        // Resolve dependencies recursively, expanding each package once
+        for package in add_placed(&mut graph, &placed)? {
+            if !self.optional_roots.contains(&package.name) || !roots.contains(&package.id) {
+                self.resolve_recursive_with_features(&mut graph, package, workspace_members.as_ref(), enabled_features.as_ref(), &[], Some(&root_scope)).await?;
+                continue;
+            }
+            if !self.resolve_optional(&mut graph, package.clone(), workspace_members.as_ref(), enabled_features.as_ref(), &[], Some(&root_scope)).await? {
+                roots.retain(|root| *root != package.id);
+                root_scope.remove(&package.name);
+            }
         }
 
         // Validate no cycles
@@ -115,15 +234,224 @@ impl Resolver {
         })?;
 
         let resolution_time_ms = start_time.elapsed().as_millis() as u64;
+        let mut peer_issues: Vec<PeerIssue> = self.peer_issues.iter().map(|issue| issue.value().clone()).collect();
+        peer_issues.sort_by_key(|issue| (issue.package.to_string(), issue.peer.clone()));
+        let mut skipped_optional: Vec<ConflictError> = self.skipped_optional.iter().map(|skipped| skipped.value().clone()).collect();
+        skipped_optional.sort_by(|a, b| (&a.package, &a.required).cmp(&(&b.package, &b.required)));
 
         Ok(ResolutionResult {
             package_count: graph.package_count(),
             resolution_time_ms,
             graph,
             roots,
+            peer_issues,
+            skipped_optional,
         })
     }
 
+    /// Resolve `name` as declared by `dependent` with an npm-style specifier,
+    /// returning the package and the range the dependency edge records
+    ///
+    /// Registry ranges and `npm:` aliases are looked up in the registry,
+    /// `workspace:` must name a workspace member, git specifiers are pinned
+    /// to a commit (`git+<url>#<sha>`) and paths to a directory or tarball
+    /// file become `file://<absolute path>`. Remote tarballs are downloaded
+    /// and locked at their URL with the integrity of what was downloaded.
+    async fn resolve_specifier(
+        &self,
+        name: &str,
+        specifier: &str,
+        dependent: &str,
+        workspace_members: Option<&std::collections::HashMap<String, String>>, // name -> path
+    ) -> Result<(PackageNode, pea_core::types::VersionReq), ConflictError> {
+        let parse = |range: &str| pea_core::types::VersionReq::parse(range)
+            .map_err(|_| ConflictError {
+                package: name.to_string(),
+                required: specifier.to_string(),
+                conflicting: dependent.to_string(),
+                conflict: "invalid version requirement".to_string(),
+            });
+
+        match Specifier::parse(specifier) {
+            Specifier::Range(range) => {
+                let version_req = parse(&range)?;
+                let package = self.resolve_package_with_workspace(name, &version_req, workspace_members).await?;
+                Ok((package, version_req))
+            }
+            Specifier::Alias { package: real_name, range } => {
+                let version_req = parse(&range)?;
+                let real = self.resolve_package_with_workspace(&real_name, &version_req, None).await?;
+                // Installed under the alias, fetched and expanded as the real package
+                let mut package = PackageNode::new(name.to_string(), real.version.clone(), real.resolved_url, real.integrity);
+                package.platform = real.platform;
+                self.aliases.insert(package.id.clone(), real_name);
+                let peers = self.peer_dependencies.get(&real.id).map(|peers| peers.clone());
+                if let Some(peers) = peers {
+                    self.peer_dependencies.insert(package.id.clone(), peers);
+                }
+                Ok((package, version_req))
+            }
+            Specifier::Workspace(range) => {
+                if !workspace_members.is_some_and(|members| members.contains_key(name)) {
+                    return Err(ConflictError {
+                        package: name.to_string(),
+                        required: specifier.to_string(),
+                        conflicting: dependent.to_string(),
+                        conflict: "not a workspace member".to_string(),
+                    });
+                }
+                let version_req = parse(if matches!(range.as_str(), "^" | "~") { "*" } else { &range })?;
+                let package = self.resolve_package_with_workspace(name, &version_req, workspace_members).await?;
+                Ok((package, version_req))
+            }
+            Specifier::Git { url, committish } if self.git_cache.is_some() => {
+                let package_key = format!("{}@{}", name, specifier);
+                if let Some(cached) = self.resolved_packages.get(&package_key) {
+                    return Ok((cached.clone(), parse("*")?));
+                }
+                let git_error = |e: pea_core::error::PeaError| ConflictError {
+                    package: name.to_string(),
+                    required: specifier.to_string(),
+                    conflicting: dependent.to_string(),
+                    conflict: e.to_string(),
+                };
+                let git_cache = self.git_cache.as_ref().expect("checked by the match guard");
+                let sha = git_cache.resolve(&url, committish.as_deref()).map_err(git_error)?;
+                let checkout = git_cache.checkout(&url, &sha).map_err(git_error)?;
+                let package = LocalPackage::read(&checkout, ".")
+                    .and_then(|local| self.local_package(name, format!("git+{}#{}", url, sha), "git", &local.manifest, local.base_dir()))
+                    .map_err(git_error)?;
+                self.resolved_packages.insert(package_key, package.clone());
+                Ok((package, parse("*")?))
+            }
+            Specifier::Path { path, link } => {
+                let package_key = format!("{}@{}", name, specifier);
+                if let Some(cached) = self.resolved_packages.get(&package_key) {
+                    return Ok((cached.clone(), parse("*")?));
+                }
+                let package = LocalPackage::read(&self.base_dir(), &path)
+                    .and_then(|local| self.local_package(name, format!("file://{}", local.path), &local.integrity, &local.manifest, local.base_dir()))
+                    .map_err(|e| ConflictError {
+                        package: name.to_string(),
+                        required: specifier.to_string(),
+                        conflicting: dependent.to_string(),
+                        conflict: e.to_string(),
+                    })?;
+                if link {
+                    // `link:` only symlinks the directory; its dependencies are its own business
+                    self.local_dependencies.insert(package.id.clone(), HashMap::new());
+                    self.local_optional_dependencies.insert(package.id.clone(), HashMap::new());
+                    self.peer_dependencies.insert(package.id.clone(), Vec::new());
+                }
+                self.resolved_packages.insert(package_key, package.clone());
+                Ok((package, parse("*")?))
+            }
+            Specifier::Tarball(url) => {
+                let package_key = format!("{}@{}", name, specifier);
+                if let Some(cached) = self.resolved_packages.get(&package_key) {
+                    return Ok((cached.clone(), parse("*")?));
+                }
+                let tarball_error = |e: pea_core::error::PeaError| ConflictError {
+                    package: name.to_string(),
+                    required: specifier.to_string(),
+                    conflicting: dependent.to_string(),
+                    conflict: e.to_string(),
+                };
+                let tarball = self.registry_client.fetch_tarball(&url).await.map_err(tarball_error)?;
+                let package = pea_cache::tarball::package_manifest(tarball.as_slice())
+                    .and_then(|manifest| self.local_package(name, url.clone(), &pea_cache::tarball::integrity(&tarball), &manifest, &self.base_dir()))
+                    .map_err(tarball_error)?;
+                self.resolved_packages.insert(package_key, package.clone());
+                Ok((package, parse("*")?))
+            }
+            Specifier::Git { .. } => Err(ConflictError {
+                package: name.to_string(),
+                required: specifier.to_string(),
+                conflicting: dependent.to_string(),
+                conflict: "git dependencies are not supported by the resolver".to_string(),
+            }),
+        }
+    }
+
+    /// Resolve a dependency of the last package in `dependents` (outermost
+    /// first), applying
+    /// the first matching override; also returns that override's selector
+    ///
+    /// A target range (`name@<2.0.0`) is checked against the version the
+    /// declared specifier resolves to. The returned range is always the
+    /// declared one, even when an override picked the package.
+    async fn resolve_dependency(
+        &self,
+        name: &str,
+        specifier: &str,
+        dependents: &[PackageId],
+        workspace_members: Option<&std::collections::HashMap<String, String>>, // name -> path
+    ) -> Result<(PackageNode, pea_core::types::VersionReq, Option<String>), ConflictError> {
+        let dependent = dependents.last().map_or("root", |id| id.name.as_str());
+        let chain: Vec<(&str, &Version)> = dependents.iter().map(|id| (id.name.as_str(), &id.version)).collect();
+        let mut declared = None;
+        for (selector, parsed, forced) in &self.overrides {
+            if parsed.target.name != name || !parsed.matches_dependents(&chain) {
+                continue;
+            }
+            if let Some(range) = &parsed.target.range {
+                if declared.is_none() {
+                    declared = Some(self.resolve_specifier(name, specifier, dependent, workspace_members).await?);
+                }
+                if !declared.as_ref().is_some_and(|(package, _)| range.matches(&package.version)) {
+                    continue;
+                }
+            }
+            let (package, forced_req) = self.resolve_specifier(name, forced, dependent, workspace_members).await?;
+            let version_req = match &declared {
+                Some((_, declared_req)) => declared_req.clone(),
+                None => declared_range(specifier).unwrap_or(forced_req),
+            };
+            return Ok((package, version_req, Some(selector.clone())));
+        }
+        let (package, version_req) = match declared {
+            Some(declared) => declared,
+            None => self.resolve_specifier(name, specifier, dependent, workspace_members).await?,
+        };
+        Ok((package, version_req, None))
+    }
+
+    /// Directory the roots' relative `file:` paths are relative to
+    fn base_dir(&self) -> camino::Utf8PathBuf {
+        match &self.project_dir {
+            Some(project_dir) => project_dir.clone(),
+            None => std::env::current_dir().ok()
+                .and_then(|dir| camino::Utf8PathBuf::from_path_buf(dir).ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Package node for a package read from disk or a tarball, remembering
+    /// the dependencies its package.json declares; relative paths among them
+    /// are relative to `base`
+    fn local_package(
+        &self,
+        name: &str,
+        resolved_url: String,
+        integrity: &str,
+        manifest: &[u8],
+        base: &camino::Utf8Path,
+    ) -> Result<PackageNode, pea_core::error::PeaError> {
+        let manifest: serde_json::Value = serde_json::from_slice(manifest)
+            .map_err(|e| pea_core::error::PeaError::JsonParse { message: format!("In package.json of {}: {}", resolved_url, e) })?;
+        let version = manifest.get("version")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|version| version.parse().ok())
+            .unwrap_or_else(|| pea_core::types::Version::new(0, 0, 0));
+
+        let mut package = PackageNode::new(name.to_string(), version, resolved_url, integrity.to_string());
+        package.platform = manifest_platform(&manifest);
+        self.local_dependencies.insert(package.id.clone(), manifest_dependencies(&manifest, "dependencies", base));
+        self.local_optional_dependencies.insert(package.id.clone(), manifest_dependencies(&manifest, "optionalDependencies", base));
+        self.peer_dependencies.insert(package.id.clone(), manifest_peers(&manifest));
+        Ok(package)
+    }
+
     /// Resolve a single package to a specific version
     async fn resolve_package(
         &self,
@@ -180,8 +508,14 @@ impl Resolver {
             .filter_map(|v| pea_core::types::Version::from_str(v).ok())
             .collect();
 
+        let preferred = self.preferred_versions.get(name)
+            .and_then(|preferred| preferred.iter()
+                .filter(|version| version_req.matches(version) && available_versions.contains(version))
+                .max()
+                .cloned());
         let selector = crate::semver::VersionSelector::new(available_versions.clone());
-        let selected_version = selector.select_preferred(&[version_req.clone()], false)
+        let selected_version = preferred
+            .or_else(|| selector.select_preferred(&[version_req.clone()], false))
             .ok_or_else(|| {
                 let available_str = available_versions.iter()
                     .map(|v| v.to_string())
@@ -206,7 +540,7 @@ impl Resolver {
             })?;
 
         // Create package node
-        let package_node = PackageNode::new(
+        let mut package_node = PackageNode::new(
             name.to_string(),
             selected_version,
             version_metadata.dist.tarball.clone(),
@@ -214,8 +548,14 @@ impl Resolver {
                 version_metadata.dist.shasum.clone()
             }),
         );
+        package_node.platform = PlatformSupport {
+            os: version_metadata.os.clone().unwrap_or_default(),
+            cpu: version_metadata.cpu.clone().unwrap_or_default(),
+            libc: version_metadata.libc.clone().unwrap_or_default(),
+        };
 
         // Cache the resolved package
+        self.peer_dependencies.insert(package_node.id.clone(), registry_peers(version_metadata));
         self.resolved_packages.insert(package_key, package_node.clone());
 
         Ok(package_node)
@@ -237,29 +577,174 @@ impl Resolver {
         package: PackageNode,
         workspace_members: Option<&'a std::collections::HashMap<String, String>>,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ConflictError>> + 'a>> {
-        self.resolve_recursive_with_features(graph, package, workspace_members, None)
+        self.resolve_recursive_with_features(graph, package, workspace_members, None, &[], None)
     }
 
     /// Recursively resolve dependencies for a package with workspace context and features
+    ///
+    /// `dependents` is the chain of packages that led to `package`, outermost
+    /// first, for matching override selectors and resolving peers; peers
+    /// nothing along it provides resolve to `roots` (name -> package).
     fn resolve_recursive_with_features<'a>(
         &'a self,
         graph: &'a mut DependencyGraph,
         package: PackageNode,
         workspace_members: Option<&'a std::collections::HashMap<String, String>>,
         enabled_features: Option<&'a std::collections::HashSet<String>>,
+        dependents: &'a [PackageId],
+        roots: Option<&'a HashMap<String, PackageNode>>,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ConflictError>> + 'a>> {
         Box::pin(async move {
         // Add package to graph
         graph.add_package(package.clone());
 
-        // Skip dependency resolution for workspace packages (they're handled locally)
-        if package.resolved_url.starts_with("file://") {
+        // Workspace packages are handled locally
+        let Some((dependencies, optional_dependencies)) = self.declared_dependencies(&package).await? else {
             return Ok(());
+        };
+
+        // Process dependencies in parallel batches
+        let mut dep_packages = Vec::new();
+        let mut dep_edges = Vec::new();
+
+        let chain: Vec<PackageId> = dependents.iter().cloned()
+            .chain(std::iter::once(package.id.clone()))
+            .collect();
+        // An optional dependency also listed as a regular one is still optional
+        let mut deps: Vec<_> = dependencies.iter()
+            .filter(|(name, _)| !optional_dependencies.contains_key(*name))
+            .chain(&optional_dependencies)
+            .collect();
+        deps.sort();
+        for (dep_name, dep_version_req) in deps {
+            // Resolve dependency with workspace context and overrides
+            let optional = optional_dependencies.contains_key(dep_name);
+            let (dep_package, version_req, overridden_by) = match self.resolve_dependency(dep_name, dep_version_req, &chain, workspace_members).await {
+                Ok(resolved) => resolved,
+                Err(e) if optional => {
+                    self.skip_optional(e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            // Create dependency edge
+            let mut edge = if optional {
+                crate::graph::DependencyEdge::optional(version_req, DependencyKind::Normal)
+            } else {
+                crate::graph::DependencyEdge::normal(version_req)
+            };
+            edge.overridden_by = overridden_by;
+
+            dep_packages.push(dep_package);
+            dep_edges.push((dep_name.clone(), dep_version_req.clone(), edge));
+        }
+
+        // Dependencies sit side by side, so their peers resolve among
+        // each other before looking further up
+        let placed = self.place_with_peers(graph, dep_packages, &chain, roots, workspace_members).await?;
+        let new_packages = add_placed(graph, &placed)?;
+        let optional: HashSet<PackageId> = dep_edges.iter().zip(&placed)
+            .filter(|((_, _, edge), _)| edge.optional)
+            .map(|(_, dep_package)| dep_package.package.id.clone())
+            .collect();
+
+        // Add all dependency edges to graph
+        for ((dep_name, dep_version_req, edge), dep_package) in dep_edges.into_iter().zip(&placed) {
+            graph.add_dependency(&package.id, &dep_package.package.id, edge)
+                .map_err(|e| ConflictError {
+                    package: package.name.clone(),
+                    required: dep_version_req,
+                    conflicting: dep_name,
+                    conflict: e,
+                })?;
+        }
+
+        // Recursively resolve dependencies not already expanded
+        for dep_package in new_packages {
+            if optional.contains(&dep_package.id) {
+                self.resolve_optional(graph, dep_package, workspace_members, enabled_features, &chain, roots).await?;
+            } else {
+                self.resolve_recursive_with_features(graph, dep_package, workspace_members, enabled_features, &chain, roots).await?;
+            }
+        }
+
+        Ok(())
+        })
+    }
+
+    /// Expand `package`, which the last of `dependents` (or the project,
+    /// for a root) depends on optionally, and return whether it was kept
+    ///
+    /// If anything below it fails, the packages expanding it added are
+    /// removed along with the package itself, and it's reported as skipped
+    /// (`name@version`) like an optional dependency that doesn't resolve.
+    /// Packages added before it are never touched, so this holds as long as
+    /// nothing else links to it; otherwise the error stands.
+    async fn resolve_optional(
+        &self,
+        graph: &mut DependencyGraph,
+        package: PackageNode,
+        workspace_members: Option<&std::collections::HashMap<String, String>>,
+        enabled_features: Option<&std::collections::HashSet<String>>,
+        dependents: &[PackageId],
+        roots: Option<&HashMap<String, PackageNode>>,
+    ) -> Result<bool, ConflictError> {
+        let package_count = graph.package_count();
+        let peer_issues: HashSet<(PackageId, String)> = self.peer_issues.iter().map(|issue| issue.key().clone()).collect();
+        let skipped: HashSet<(String, String)> = self.skipped_optional.iter().map(|skipped| skipped.key().clone()).collect();
+        let error = match self.resolve_recursive_with_features(graph, package.clone(), workspace_members, enabled_features, dependents, roots).await {
+            Ok(()) => return Ok(true),
+            Err(error) => error,
+        };
+
+        // Nodes are only ever appended, so what it added comes last
+        let added: Vec<PackageId> = graph.packages().skip(package_count).map(|added| added.id.clone()).collect();
+        for id in added.iter().rev() {
+            graph.remove_package(id);
+        }
+        let dependent = dependents.last();
+        let needed = graph.packages()
+            .filter(|other| Some(&other.id) != dependent)
+            .any(|other| graph.dependencies_of(&other.id).iter().any(|(dependency, _)| dependency.id == package.id));
+        if needed {
+            return Err(error);
+        }
+        graph.remove_package(&package.id);
+        self.peer_issues.retain(|key, _| peer_issues.contains(key) && key.0 != package.id);
+        self.skipped_optional.retain(|key, _| skipped.contains(key));
+        self.skip_optional(ConflictError {
+            package: package.name.clone(),
+            required: package.version.to_string(),
+            conflicting: error.package.clone(),
+            conflict: error.to_string(),
+        });
+        Ok(false)
+    }
+
+    /// Dependencies and optional dependencies (name -> specifier) `package`
+    /// declares; `None` for workspace packages, which are handled locally
+    async fn declared_dependencies(
+        &self,
+        package: &PackageNode,
+    ) -> Result<Option<(HashMap<String, String>, HashMap<String, String>)>, ConflictError> {
+        // Copies resolved with different peers share everything else
+        let base_id = PackageId::new(package.name.clone(), package.version.clone());
+        if let Some(dependencies) = self.local_dependencies.get(&base_id) {
+            // Git checkouts and path dependencies declare their dependencies in their own package.json
+            let optional = self.local_optional_dependencies.get(&base_id).map(|optional| optional.clone());
+            return Ok(Some((dependencies.clone(), optional.unwrap_or_default())));
+        }
+        if package.resolved_url.starts_with("file://") {
+            return Ok(None);
         }
 
         // Fetch package metadata to get dependencies
+        let registry_name = self.aliases.get(&base_id)
+            .map(|real_name| real_name.clone())
+            .unwrap_or_else(|| package.name.clone());
         let metadata = self.registry_client
-            .fetch_metadata(&package.name)
+            .fetch_metadata(&registry_name)
             .await
             .map_err(|_| ConflictError {
                 package: package.name.clone(),
@@ -276,71 +761,230 @@ impl Resolver {
                 conflicting: "metadata".to_string(),
                 conflict: "version metadata missing".to_string(),
             })?;
+        Ok(Some((version_metadata.dependencies.clone().unwrap_or_default(),
+            version_metadata.optional_dependencies.clone().unwrap_or_default())))
+    }
 
-        // Process dependencies in parallel batches
-        if let Some(deps) = &version_metadata.dependencies {
-            // Collect dependency resolution tasks
-            let mut dep_packages = Vec::new();
-            let mut dep_edges = Vec::new();
-            
-            for (dep_name, dep_version_req) in deps {
-                let version_req = pea_core::types::VersionReq::parse(dep_version_req)
-                    .map_err(|_| ConflictError {
-                        package: package.name.clone(),
-                        required: dep_version_req.clone(),
-                        conflicting: dep_name.clone(),
-                        conflict: "invalid version requirement".to_string(),
-                    })?;
-
-                // Resolve dependency with workspace context
-                let dep_package = self.resolve_package_with_workspace(dep_name, &version_req, workspace_members).await?;
-                
-                // Create dependency edge
-                let edge = crate::graph::DependencyEdge::normal(version_req);
-                
-                dep_packages.push(dep_package);
-                dep_edges.push((dep_name.clone(), dep_version_req.clone(), edge));
+    /// Tag for the nested overrides that may apply below `package`, whose
+    /// dependents are `chain`, because of where it is in the tree
+    ///
+    /// Packages are expanded once per id, so a package reached both from
+    /// inside and from outside an `a>b>c` selector needs a separate copy for
+    /// each. Only selectors whose parents the chain matches above `package`
+    /// and whose next step `package` depends on count; what depends on
+    /// `package` alone is the same for every copy.
+    async fn override_context(&self, package: &PackageNode, chain: &[PackageId]) -> Option<String> {
+        let dependents: Vec<(&str, &Version)> = chain.iter()
+            .map(|id| (id.name.as_str(), &id.version))
+            .chain(std::iter::once((package.name.as_str(), &package.version)))
+            .collect();
+        let mut matched: Vec<(&str, usize, &str)> = Vec::new();
+        for (selector, parsed, _) in &self.overrides {
+            for count in parsed.matched_parents(&dependents).into_iter().filter(|&count| count > 1) {
+                let next = parsed.parents.get(count).unwrap_or(&parsed.target);
+                matched.push((selector, count, &next.name));
             }
+        }
+        if matched.is_empty() {
+            return None;
+        }
 
-            // Process peer dependencies - validate but don't resolve
-            if let Some(peer_deps) = &version_metadata.peer_dependencies {
-                for (peer_name, peer_version_req) in peer_deps {
-                    let _version_req = pea_core::types::VersionReq::parse(peer_version_req)
-                        .map_err(|_| ConflictError {
-                            package: package.name.clone(),
-                            required: peer_version_req.clone(),
-                            conflicting: peer_name.clone(),
-                            conflict: "invalid peer dependency version requirement".to_string(),
-                        })?;
+        // Without its dependencies the package fails to expand anyway
+        let (dependencies, optional) = self.declared_dependencies(package).await.ok()??;
+        let mut context: Vec<String> = matched.into_iter()
+            .filter(|(_, _, next)| dependencies.contains_key(*next) || optional.contains_key(*next))
+            .map(|(selector, count, _)| format!("{}#{}", selector, count))
+            .collect();
+        if context.is_empty() {
+            return None;
+        }
+        context.sort();
+        Some(format!("(overrides-{})", &blake3::hash(context.join("\n").as_bytes()).to_hex()[..12]))
+    }
 
-                    // Note: Peer dependencies are not resolved here - they're expected to be 
-                    // provided by the consumer and validated separately in validate_peer_dependencies
-                }
+    /// Resolve the peers of packages placed side by side (a package's
+    /// dependencies, or the roots) and make each the copy for its peers
+    ///
+    /// A peer resolves to a package placed beside it, then to its dependents
+    /// (`chain`, innermost last) and what they sit beside, then to `roots`.
+    /// Required peers nothing provides are resolved from their range and
+    /// placed beside the others, so their own peers resolve there too. The
+    /// result starts with `packages`, in order, followed by those peers.
+    async fn place_with_peers(
+        &self,
+        graph: &DependencyGraph,
+        packages: Vec<PackageNode>,
+        chain: &[PackageId],
+        roots: Option<&HashMap<String, PackageNode>>,
+        workspace_members: Option<&std::collections::HashMap<String, String>>, // name -> path
+    ) -> Result<Vec<PlacedPackage>, ConflictError> {
+        let mut placed = packages;
+        let mut beside: HashMap<String, usize> = placed.iter().enumerate()
+            .map(|(i, package)| (package.name.clone(), i))
+            .collect();
+        let mut sources = Vec::new();
+        let mut missing = Vec::new();
+        let mut i = 0;
+        while i < placed.len() {
+            let package = placed[i].clone();
+            let declared = self.peer_dependencies.get(&package.id).map(|peers| peers.clone()).unwrap_or_default();
+            let mut found = Vec::new();
+            for peer in declared {
+                let source = match beside.get(&peer.name) {
+                    Some(&j) if j != i => Some(PeerSource::Beside(peer.name.clone())),
+                    _ => provided_peer(graph, chain, roots, &peer.name),
+                };
+                let source = match source {
+                    Some(source) => source,
+                    None if peer.optional => continue,
+                    None => {
+                        let dependents: Vec<PackageId> = chain.iter().cloned().chain(std::iter::once(package.id.clone())).collect();
+                        match self.resolve_dependency(&peer.name, &peer.range, &dependents, workspace_members).await {
+                            Ok((installed, _, _)) => {
+                                beside.insert(peer.name.clone(), placed.len());
+                                placed.push(installed);
+                                PeerSource::Beside(peer.name.clone())
+                            }
+                            Err(_) => {
+                                missing.push((i, peer));
+                                continue;
+                            }
+                        }
+                    }
+                };
+                found.push((peer, source));
             }
-            
-            // Add all dependency edges to graph
-            for (i, (dep_name, dep_version_req, edge)) in dep_edges.into_iter().enumerate() {
-                let dep_package = &dep_packages[i];
-                
-                graph.add_dependency(&package.id, &dep_package.id, edge)
-                    .map_err(|e| ConflictError {
-                        package: package.name.clone(),
-                        required: dep_version_req,
-                        conflicting: dep_name,
-                        conflict: e,
-                    })?;
+            sources.push(found);
+            i += 1;
+        }
+
+        // A package's identity includes the versions of its peers
+        let versions: Vec<Version> = placed.iter().map(|package| package.version.clone()).collect();
+        let version_of = |source: &PeerSource| match source {
+            PeerSource::Beside(name) => versions[beside[name]].clone(),
+            PeerSource::Package(package) => package.version.clone(),
+            PeerSource::Dependent(version) => version.clone(),
+        };
+        let mut variants: Vec<PackageNode> = placed.into_iter().zip(&sources)
+            .map(|(package, found)| {
+                let versions: Vec<(&str, Version)> = found.iter()
+                    .filter(|(_, source)| !matches!(source, PeerSource::Dependent(_)))
+                    .map(|(peer, source)| (peer.name.as_str(), version_of(source)))
+                    .collect();
+                package.with_peers(versions.iter().map(|(name, version)| (*name, version)))
+            })
+            .collect();
+        // As does where nested overrides put it
+        for variant in &mut variants {
+            if let Some(context) = self.override_context(variant, chain).await {
+                variant.id.peers.push_str(&context);
             }
-            
-            // Recursively resolve all dependencies
-            for dep_package in dep_packages {
-                self.resolve_recursive_with_features(graph, dep_package, workspace_members, enabled_features).await?;
+        }
+        // And the peers its dependencies take from above it, as what
+        // provides them differs between places
+        for (i, variant) in variants.iter_mut().enumerate() {
+            let own = self.peer_dependencies.get(&base_id(variant)).map(|peers| peers.clone()).unwrap_or_default();
+            let names = self.peers_from_above(variant, chain, workspace_members, &mut HashSet::new()).await;
+            for name in names.iter().filter(|name| !own.iter().any(|peer| peer.name == **name)) {
+                let version = match beside.get(name) {
+                    Some(&j) if j != i => Some(versions[j].clone()),
+                    _ => match provided_peer(graph, chain, roots, name) {
+                        Some(PeerSource::Package(provider)) => Some(provider.version),
+                        // Like its own peers, dependents don't make copies
+                        _ => None,
+                    },
+                };
+                if let Some(version) = version {
+                    variant.id.peers.push_str(&format!("({}@{})", name, version));
+                }
             }
         }
+        for (i, peer) in missing {
+            self.record_peer_issue(&variants[i].id, &peer, None);
+        }
 
-        Ok(())
+        Ok(variants.iter().zip(sources)
+            .map(|(package, found)| {
+                let mut peers = Vec::new();
+                for (peer, source) in found {
+                    let version = version_of(&source);
+                    if !peer.accepts(&version) {
+                        self.record_peer_issue(&package.id, &peer, Some(version));
+                    }
+                    let provider = match source {
+                        PeerSource::Beside(name) => variants[beside[&name]].clone(),
+                        PeerSource::Package(provider) => *provider,
+                        // Depending on a dependent would be a cycle, and it's visible anyway
+                        PeerSource::Dependent(_) => continue,
+                    };
+                    peers.push((provider, peer.edge()));
+                }
+                PlacedPackage { package: package.clone(), peers }
+            })
+            .collect())
+    }
+
+    /// Names of the peers the dependencies of `package`, whose dependents
+    /// are `chain`, take from above it: those that aren't among its own
+    /// dependencies or `package` itself, down to the bottom of its tree
+    ///
+    /// Worked out from metadata before the package is placed, so its id is
+    /// final wherever it's reached from first. Dependencies that fail to
+    /// resolve are left out; `visiting` breaks cycles.
+    fn peers_from_above<'a>(
+        &'a self,
+        package: &'a PackageNode,
+        chain: &'a [PackageId],
+        workspace_members: Option<&'a std::collections::HashMap<String, String>>,
+        visiting: &'a mut HashSet<PackageId>,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = BTreeSet<String>> + 'a>> {
+        Box::pin(async move {
+        let id = base_id(package);
+        if let Some(names) = self.transitive_peers.get(&id) {
+            return names.clone();
+        }
+        if !visiting.insert(id.clone()) {
+            return BTreeSet::new();
+        }
+
+        let (dependencies, optional) = self.declared_dependencies(package).await.ok().flatten().unwrap_or_default();
+        let chain: Vec<PackageId> = chain.iter().cloned()
+            .chain(std::iter::once(package.id.clone()))
+            .collect();
+        let mut deps: Vec<_> = dependencies.iter().chain(&optional).collect();
+        deps.sort();
+        let mut names = BTreeSet::new();
+        for (name, specifier) in deps {
+            let Ok((dependency, _, _)) = self.resolve_dependency(name, specifier, &chain, workspace_members).await else {
+                continue;
+            };
+            if let Some(peers) = self.peer_dependencies.get(&base_id(&dependency)) {
+                names.extend(peers.iter().map(|peer| peer.name.clone()));
+            }
+            names.extend(self.peers_from_above(&dependency, &chain, workspace_members, visiting).await);
+        }
+        names.retain(|name| *name != package.name && !dependencies.contains_key(name) && !optional.contains_key(name));
+
+        visiting.remove(&id);
+        self.transitive_peers.insert(id, names.clone());
+        names
         })
     }
 
+    /// Leave out an optional dependency that couldn't be resolved
+    fn skip_optional(&self, error: ConflictError) {
+        self.skipped_optional.insert((error.package.clone(), error.required.clone()), error);
+    }
+
+    fn record_peer_issue(&self, package: &PackageId, peer: &PeerDependency, found: Option<Version>) {
+        self.peer_issues.insert((package.clone(), peer.name.clone()), PeerIssue {
+            package: package.clone(),
+            peer: peer.name.clone(),
+            range: peer.range.clone(),
+            found,
+        });
+    }
+
     /// Check if a dependency should be included based on features
     fn should_include_dependency(
         &self,
@@ -392,73 +1036,56 @@ impl Resolver {
         conflicts
     }
 
-    /// Validate peer dependencies against resolved packages
-    pub async fn validate_peer_dependencies(&self, graph: &DependencyGraph) -> Result<Vec<String>, ConflictError> {
-        let mut warnings = Vec::new();
-        let resolved_packages: HashMap<String, &PackageNode> = graph.packages()
-            .map(|pkg| (pkg.name.clone(), pkg))
-            .collect();
-
+    /// Check each package's peers against what it resolved them to
+    ///
+    /// A peer outside its declared range is invalid; a required one with no
+    /// package of its name anywhere in the graph is missing. Peers of
+    /// packages this resolver didn't resolve are read from the registry.
+    pub async fn validate_peer_dependencies(&self, graph: &DependencyGraph) -> Result<Vec<PeerIssue>, ConflictError> {
+        let mut issues = Vec::new();
         for package in graph.packages() {
-            // Skip workspace packages for peer dependency validation
-            if package.resolved_url.starts_with("file://") {
-                continue;
-            }
-
-            // Fetch metadata to check peer dependencies
-            let metadata = self.registry_client
-                .fetch_metadata(&package.name)
-                .await
-                .map_err(|_| ConflictError {
-                    package: package.name.clone(),
-                    required: "peer dependency validation".to_string(),
-                    conflicting: "registry".to_string(),
-                    conflict: "failed to fetch metadata for peer dependency validation".to_string(),
-                })?;
-
-            let version_metadata = metadata.versions
-                .get(&package.version.to_string())
-                .ok_or_else(|| ConflictError {
-                    package: package.name.clone(),
-                    required: "peer dependency validation".to_string(),
-                    conflicting: "metadata".to_string(),
-                    conflict: "version metadata missing for peer dependency validation".to_string(),
-                })?;
-
-            // Check peer dependencies
-            if let Some(peer_deps) = &version_metadata.peer_dependencies {
-                for (peer_name, peer_version_req) in peer_deps {
-                    if let Ok(version_req) = pea_core::types::VersionReq::parse(peer_version_req) {
-                        match resolved_packages.get(peer_name) {
-                            Some(peer_package) => {
-                                // Peer dependency is resolved, check if version satisfies requirement
-                                if !version_req.matches(&peer_package.version) {
-                                    warnings.push(format!(
-                                        "Peer dependency mismatch: {} requires {}@{} but found {}@{}",
-                                        package.name,
-                                        peer_name,
-                                        peer_version_req,
-                                        peer_name,
-                                        peer_package.version
-                                    ));
-                                }
-                            }
-                            None => {
-                                // Peer dependency is missing
-                                warnings.push(format!(
-                                    "Unmet peer dependency: {} requires {}@{} but it was not found",
-                                    package.name,
-                                    peer_name,
-                                    peer_version_req
-                                ));
-                            }
-                        }
-                    }
+            let base_id = PackageId::new(package.name.clone(), package.version.clone());
+            let known = self.peer_dependencies.get(&base_id).map(|peers| peers.clone());
+            let peers = match known {
+                Some(peers) => peers,
+                // Workspace members and unknown local packages have no registry metadata
+                None if package.resolved_url.starts_with("file://") || package.resolved_url.starts_with("git+") => continue,
+                None => {
+                    let metadata = self.registry_client
+                        .fetch_metadata(&package.name)
+                        .await
+                        .map_err(|_| ConflictError {
+                            package: package.name.clone(),
+                            required: "peer dependency validation".to_string(),
+                            conflicting: "registry".to_string(),
+                            conflict: "failed to fetch metadata for peer dependency validation".to_string(),
+                        })?;
+                    let version_metadata = metadata.versions
+                        .get(&package.version.to_string())
+                        .ok_or_else(|| ConflictError {
+                            package: package.name.clone(),
+                            required: "peer dependency validation".to_string(),
+                            conflicting: "metadata".to_string(),
+                            conflict: "version metadata missing for peer dependency validation".to_string(),
+                        })?;
+                    registry_peers(version_metadata)
                 }
+            };
+
+            let dependencies = graph.dependencies_of(&package.id);
+            for peer in peers {
+                let found = match dependencies.iter().find(|(dependency, _)| dependency.name == peer.name) {
+                    Some((provider, _)) if !peer.accepts(&provider.version) => Some(provider.version.clone()),
+                    Some(_) => continue,
+                    // Peers provided by a dependent have no edge
+                    None if peer.optional || graph.packages().any(|other| other.name == peer.name) => continue,
+                    None => None,
+                };
+                issues.push(PeerIssue { package: package.id.clone(), peer: peer.name, range: peer.range, found });
             }
         }
 
-        Ok(warnings)
+        Ok(issues)
     }
 
     /// Validate that all resolved packages satisfy their constraints
@@ -477,14 +1104,76 @@ impl Resolver {
             conflict: cycle_msg,
         })?;
 
-        // Validate peer dependencies (warnings only)
-        let _peer_warnings = self.validate_peer_dependencies(graph).await?;
-        // Note: Peer dependency warnings are returned but don't fail the resolution
+        // Validate peer dependencies (issues are reported, not fatal)
+        let _peer_issues = self.validate_peer_dependencies(graph).await?;
 
         Ok(())
     }
 }
 
+/// The range an edge declared with `specifier` records, without resolving it
+fn declared_range(specifier: &str) -> Option<pea_core::types::VersionReq> {
+    let range = match Specifier::parse(specifier) {
+        Specifier::Range(range) | Specifier::Alias { range, .. } => range,
+        Specifier::Workspace(range) if !matches!(range.as_str(), "^" | "~") => range,
+        _ => "*".to_string(),
+    };
+    pea_core::types::VersionReq::parse(&range).ok()
+}
+
+/// Add placed packages and the edges to their peers, returning those not
+/// already in the graph, whose dependencies still need resolving
+fn add_placed(graph: &mut DependencyGraph, placed: &[PlacedPackage]) -> Result<Vec<PackageNode>, ConflictError> {
+    let new_packages: Vec<&PlacedPackage> = placed.iter()
+        .filter(|placed| graph.get_package(&placed.package.id).is_none())
+        .collect();
+    for placed in &new_packages {
+        graph.add_package(placed.package.clone());
+    }
+    for placed in &new_packages {
+        for (provider, edge) in &placed.peers {
+            graph.add_dependency(&placed.package.id, &provider.id, edge.clone())
+                .map_err(|e| ConflictError {
+                    package: placed.package.name.clone(),
+                    required: edge.version_req.to_string(),
+                    conflicting: provider.name.clone(),
+                    conflict: e,
+                })?;
+        }
+    }
+    Ok(new_packages.into_iter().map(|placed| placed.package.clone()).collect())
+}
+
+/// Where peer `name` comes from for a package whose dependents are `chain`:
+/// a dependent itself or a package beside it, innermost first, then `roots`
+fn provided_peer(
+    graph: &DependencyGraph,
+    chain: &[PackageId],
+    roots: Option<&HashMap<String, PackageNode>>,
+    name: &str,
+) -> Option<PeerSource> {
+    for (level, dependent) in chain.iter().enumerate().rev() {
+        if dependent.name == name {
+            return Some(PeerSource::Dependent(dependent.version.clone()));
+        }
+        let beside = match level {
+            0 => roots.and_then(|roots| roots.get(name)).cloned(),
+            _ => graph.dependencies_of(&chain[level - 1]).into_iter()
+                .find(|(dependency, _)| dependency.name == name)
+                .map(|(dependency, _)| dependency.clone()),
+        };
+        if let Some(package) = beside {
+            return Some(PeerSource::Package(Box::new(package)));
+        }
+    }
+    None
+}
+
+/// Id of `package` without its peers, which its copies share
+fn base_id(package: &PackageNode) -> PackageId {
+    PackageId::new(package.name.clone(), package.version.clone())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -525,6 +1214,8 @@ mod tests {
             roots: vec![],
             package_count: 0,
             resolution_time_ms: 100,
+            peer_issues: Vec::new(),
+            skipped_optional: Vec::new(),
         };
         
         assert_eq!(result.package_count, 0);
@@ -561,6 +1252,44 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_path_dependencies_resolve_from_disk() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = camino::Utf8PathBuf::from_path_buf(temp.path().canonicalize().unwrap()).unwrap();
+        for (dir, manifest) in [
+            ("app", r#"{"name": "app"}"#),
+            ("lib", r#"{"name": "lib", "version": "1.2.0", "dependencies": {"inner": "file:../inner.tgz"}}"#),
+            ("inner", r#"{"name": "inner", "version": "0.3.0"}"#),
+            ("linked", r#"{"name": "linked", "dependencies": {"not-on-any-registry": "^1.0.0"}}"#),
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("package.json"), manifest).unwrap();
+        }
+        let mut tarball = Vec::new();
+        pea_cache::tarball::write_package_tarball(&mut tarball, root.join("inner").as_std_path(),
+            &["package.json".to_string()], None).unwrap();
+        std::fs::write(root.join("inner.tgz"), &tarball).unwrap();
+
+        let client = Arc::new(RegistryClient::new().unwrap());
+        let resolver = Resolver::new(client, Arc::new(MetadataCache::new())).with_project_dir(root.join("app"));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(resolver.resolve(vec![
+            ("lib".to_string(), "file:../lib".to_string()),
+            ("linked".to_string(), "link:../linked".to_string()),
+        ])).unwrap();
+
+        assert_eq!(result.package_count, 3);
+        let lib = result.graph.packages().into_iter().find(|package| package.name == "lib").unwrap();
+        assert_eq!(lib.version.to_string(), "1.2.0");
+        assert_eq!(lib.resolved_url, format!("file://{}", root.join("lib")));
+        let inner = result.graph.packages().into_iter().find(|package| package.name == "inner").unwrap();
+        assert_eq!(inner.resolved_url, format!("file://{}", root.join("inner.tgz")));
+        assert_eq!(inner.integrity, pea_cache::tarball::integrity(&tarball));
+
+        let missing = rt.block_on(resolver.resolve(vec![("gone".to_string(), "file:../gone".to_string())]));
+        assert!(missing.unwrap_err().conflict.contains("Failed to find"));
+    }
+
     #[test]
     fn test_workspace_vs_registry_resolution() {
         let client = Arc::new(RegistryClient::new().unwrap());
@@ -742,6 +1471,8 @@ mod tests {
             roots: roots.clone(),
             package_count: 5,
             resolution_time_ms: 250,
+            peer_issues: Vec::new(),
+            skipped_optional: Vec::new(),
         };
         
         assert_eq!(result.package_count, 5);